@@ -0,0 +1,279 @@
+//! Benchmarks of the per frame loops which scale with entity count
+//!
+//! Run with `cargo bench -p core`. Benchmarks which need a device create one without a
+//! surface and are skipped, with a message, when no adapter is available. Recording and
+//! submitting render passes needs a surface, see the `stress` example for whole frame timings.
+
+use core::{
+    bindings::SharedBindings,
+    camera::Camera,
+    entity::{EntityDrawInstruction, RenderProperties},
+    material::{Material, MaterialId},
+    mesh::{Mesh, MeshId},
+    scene::Scene,
+    shader::{BlendMode, EntityUniforms, PipelineOptions, Shader, ShaderId},
+    shader_library::ShaderLibrary,
+    texture::Texture,
+    transform::Transform,
+    transform_hierarchy::{TransformHierarchy, TransformId},
+    Color, DrawCommand, Resources,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use glam::*;
+
+const ENTITY_COUNT: usize = 50_000;
+/// Children per root in the hierarchy benchmarks
+const CHILD_COUNT: usize = 9;
+
+/// Position on a square grid, spreading entities out so depth sorting has work to do
+fn grid_position(index: usize) -> Vec3 {
+    let side = (ENTITY_COUNT as f32).sqrt() as usize;
+    Vec3::new((index % side) as f32, 0.0, (index / side) as f32)
+}
+
+fn bench_hierarchy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hierarchy");
+
+    let mut hierarchy = TransformHierarchy::new();
+    let roots = (0..ENTITY_COUNT)
+        .map(|index| hierarchy.insert(Transform::from_position(grid_position(index)), None))
+        .collect::<Vec<_>>();
+    group.bench_function("set_transform_flat_50k", |b| {
+        b.iter(|| {
+            for (index, id) in roots.iter().enumerate() {
+                let position = grid_position(index) + Vec3::Y;
+                hierarchy.set_transform(*id, Transform::from_position(position));
+            }
+        })
+    });
+
+    // Moving each root propagates to its children
+    let mut hierarchy = TransformHierarchy::new();
+    let mut roots = Vec::new();
+    for index in 0..ENTITY_COUNT / (CHILD_COUNT + 1) {
+        let root = hierarchy.insert(Transform::from_position(grid_position(index)), None);
+        for child in 0..CHILD_COUNT {
+            let offset = Vec3::new(0.0, child as f32, 0.0);
+            hierarchy.insert(Transform::from_position(offset), Some(root));
+        }
+        roots.push(root);
+    }
+    let mut angle = 0.0f32;
+    group.bench_function("propagate_50k", |b| {
+        b.iter(|| {
+            angle += 0.01;
+            for (index, id) in roots.iter().enumerate() {
+                let mut transform = Transform::from_position(grid_position(index));
+                transform.rotation = Quat::from_rotation_y(angle);
+                hierarchy.set_transform(*id, transform);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// Resources for drawing a quad with the unlit textured shader, created without a surface
+struct Fixture {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    resources: Resources,
+    bindings: SharedBindings,
+    shader: ShaderId,
+    mesh: MeshId,
+    materials: [MaterialId; 2],
+}
+
+impl Fixture {
+    fn new() -> Option<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+                .ok()?;
+
+        let mut resources = Resources::new();
+        let mut bindings = SharedBindings::new(&device);
+        let texture_bind_group_layout = Material::create_bind_group_layout(&device);
+        let descriptor = ShaderLibrary::with_builtins()
+            .descriptor(
+                "unlit_textured.wgsl",
+                include_str!("../src/shaders/unlit_textured.wgsl"),
+            )
+            .expect("Built-in shader includes should resolve");
+        let shader = resources.shaders.insert(Shader::new(
+            &device,
+            &mut bindings,
+            descriptor,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            &texture_bind_group_layout,
+            BlendMode::Opaque,
+            wgpu::CompareFunction::Less,
+            std::mem::size_of::<EntityUniforms>(),
+            EntityUniforms::write_bytes,
+        ));
+
+        let texture = Texture::from_color(&device, &queue, [255, 255, 255, 255]).ok()?;
+        let texture_id = resources.textures.insert(texture);
+        let texture = &resources.textures[texture_id];
+        let material = |options| Material {
+            shader,
+            texture: texture_id,
+            textures: vec![texture_id],
+            diffuse_bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &texture_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                    },
+                ],
+                label: Some("diffuse_bind_group"),
+            }),
+            options,
+            blend_constant: Color::WHITE,
+            uniforms_buffer: None,
+        };
+        let opaque = material(None);
+        // Alpha blended so the scene has depth ordered entities to sort
+        let blended = material(Some(PipelineOptions::new(
+            BlendMode::Alpha,
+            wgpu::CompareFunction::Less,
+        )));
+        let materials = [
+            resources.materials.insert(opaque),
+            resources.materials.insert(blended),
+        ];
+
+        let mesh = resources.meshes.insert(Mesh::from_arrays(
+            &[
+                Vec3::new(-0.5, -0.5, 0.0),
+                Vec3::new(0.5, -0.5, 0.0),
+                Vec3::new(0.5, 0.5, 0.0),
+                Vec3::new(-0.5, 0.5, 0.0),
+            ],
+            &[
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, 0.0),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+            &device,
+        ));
+
+        Some(Self {
+            device,
+            queue,
+            resources,
+            bindings,
+            shader,
+            mesh,
+            materials,
+        })
+    }
+
+    /// Scene of quads, one in ten alpha blended
+    fn scene(&self) -> (Scene, Vec<TransformId>) {
+        let mut scene = Scene::new();
+        let ids = (0..ENTITY_COUNT)
+            .map(|index| {
+                let material = self.materials[(index % 10 == 0) as usize];
+                let transform = Transform::from_position(grid_position(index));
+                scene.add(
+                    self.mesh,
+                    material,
+                    transform,
+                    RenderProperties::from_transform(transform),
+                )
+            })
+            .collect();
+        (scene, ids)
+    }
+}
+
+fn bench_renderer(c: &mut Criterion) {
+    let Some(mut fixture) = Fixture::new() else {
+        eprintln!("No graphics adapter available, skipping scene and uniform benchmarks");
+        return;
+    };
+    let camera = Camera {
+        eye: Vec3::new(100.0, 50.0, 300.0),
+        target: Vec3::new(100.0, 0.0, 100.0),
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("scene");
+    let (mut scene, ids) = fixture.scene();
+    group.bench_function("update_50k", |b| {
+        b.iter(|| scene.update(&camera, &fixture.resources, 0.0))
+    });
+    let mut angle = 0.0f32;
+    group.bench_function("move_and_update_50k", |b| {
+        b.iter(|| {
+            angle += 0.01;
+            for (index, id) in ids.iter().enumerate() {
+                let mut transform = Transform::from_position(grid_position(index));
+                transform.rotation = Quat::from_rotation_z(angle);
+                scene.hierarchy.set_transform(*id, transform);
+            }
+            scene.update(&camera, &fixture.resources, 0.0);
+        })
+    });
+    group.finish();
+
+    // Building the draw command list the renderer consumes each frame
+    let mut group = c.benchmark_group("draw_commands");
+    scene.update(&camera, &fixture.resources, 0.0);
+    let mut commands = Vec::with_capacity(ENTITY_COUNT);
+    group.bench_function("encode_50k", |b| {
+        b.iter(|| {
+            commands.clear();
+            scene.render(&mut commands);
+        })
+    });
+    group.finish();
+
+    // Writing each entity's uniforms to the shader's dynamic offset buffer
+    let mut group = c.benchmark_group("uniforms");
+    // One write per entity makes this far slower than the other benchmarks
+    group.sample_size(10);
+    let mut entities = commands
+        .iter()
+        .filter_map(|command| match command {
+            DrawCommand::Draw(mesh, material, properties) => {
+                Some(EntityDrawInstruction::new(*mesh, *material, *properties))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let shader = &mut fixture.resources.shaders[fixture.shader];
+    let bindings = &mut fixture.bindings;
+    bindings.reserve_entities(shader.entity_buffer(), ENTITY_COUNT as u64, &fixture.device);
+    group.bench_function("upload_50k", |b| {
+        b.iter(|| {
+            bindings
+                .entity_bind_group_mut(shader.entity_buffer(), &fixture.device)
+                .reset_offset();
+            for entity in entities.iter_mut() {
+                shader.write_entity_uniforms(entity, bindings);
+            }
+            bindings.flush_entities(&fixture.queue);
+            // Submitting flushes the queued writes to the buffer
+            fixture.queue.submit([]);
+            fixture.device.poll(wgpu::Maintain::Wait);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_hierarchy, bench_renderer);
+criterion_main!(benches);
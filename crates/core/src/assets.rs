@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use glam::*;
+
+use crate::{
+    camera::{Camera, OrthographicSize, Projection},
+    entity::RenderProperties,
+    material::{Material, MaterialId},
+    mesh::MeshId,
+    texture::Texture,
+    Color, DrawCommand, State,
+};
+
+// Assets are currently only loaded as raw bytes, it is up to game code to turn these into
+// textures, meshes etc during init. On native files are read on a background thread, on the
+// web they are fetched relative to the page.
+
+type LoadResult = (String, Result<Vec<u8>, String>);
+
+/// Identifies a group of requested assets, returned from `Game::load`
+#[derive(Debug, Clone, Default)]
+pub struct LoadHandle {
+    paths: Vec<String>,
+}
+
+impl LoadHandle {
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+pub struct AssetLoader {
+    requested: Vec<String>,
+    loaded: HashMap<String, Vec<u8>>,
+    failed: HashMap<String, String>,
+    sender: Sender<LoadResult>,
+    receiver: Receiver<LoadResult>,
+}
+
+impl AssetLoader {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            requested: Vec::new(),
+            loaded: HashMap::new(),
+            failed: HashMap::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Request the bytes at path be loaded, paths are relative to the working directory
+    /// on native and relative to the page on the web
+    pub fn load<T: Into<String>>(&mut self, path: T) {
+        let path = path.into();
+        if self.requested.contains(&path) {
+            return;
+        }
+        self.requested.push(path.clone());
+        Self::start_load(path, self.sender.clone());
+    }
+
+    /// Request each path, returning a handle covering only these
+    pub fn load_all<I, T>(&mut self, paths: I) -> LoadHandle
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut handle = LoadHandle::default();
+        for path in paths {
+            let path = path.into();
+            self.load(path.clone());
+            if !handle.paths.contains(&path) {
+                handle.paths.push(path);
+            }
+        }
+        handle
+    }
+
+    /// Handle covering every asset requested so far
+    pub fn handle(&self) -> LoadHandle {
+        LoadHandle {
+            paths: self.requested.clone(),
+        }
+    }
+
+    /// Receive any assets which have finished loading
+    pub fn poll(&mut self) {
+        while let Ok((path, result)) = self.receiver.try_recv() {
+            match result {
+                Ok(bytes) => {
+                    self.loaded.insert(path, bytes);
+                }
+                Err(error) => {
+                    log::error!("Failed to load asset {path}: {error}");
+                    self.failed.insert(path, error);
+                }
+            }
+        }
+    }
+
+    /// Fraction of the assets in the handle which have finished loading (or failed)
+    pub fn progress(&self, handle: &LoadHandle) -> f32 {
+        if handle.paths.is_empty() {
+            return 1.0;
+        }
+        let complete = handle
+            .paths
+            .iter()
+            .filter(|path| self.loaded.contains_key(*path) || self.failed.contains_key(*path))
+            .count();
+        complete as f32 / handle.paths.len() as f32
+    }
+
+    pub fn is_complete(&self, handle: &LoadHandle) -> bool {
+        self.progress(handle) >= 1.0
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.loaded.get(path).map(|bytes| bytes.as_slice())
+    }
+
+    /// Take ownership of loaded bytes, releasing the loader's copy
+    pub fn take(&mut self, path: &str) -> Option<Vec<u8>> {
+        self.loaded.remove(path)
+    }
+
+    pub fn get_error(&self, path: &str) -> Option<&str> {
+        self.failed.get(path).map(|error| error.as_str())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_load(path: String, sender: Sender<LoadResult>) {
+        std::thread::spawn(move || {
+            let result = std::fs::read(&path).map_err(|error| error.to_string());
+            sender.send((path, result)).ok();
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start_load(path: String, sender: Sender<LoadResult>) {
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = Self::fetch(&path).await;
+            sender.send((path, result)).ok();
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn fetch(path: &str) -> Result<Vec<u8>, String> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+
+        let window = web_sys::window().ok_or("No window available")?;
+        let response = JsFuture::from(window.fetch_with_str(path))
+            .await
+            .map_err(|error| format!("{error:?}"))?;
+        let response: web_sys::Response = response
+            .dyn_into()
+            .map_err(|error| format!("{error:?}"))?;
+        if !response.ok() {
+            return Err(format!("{} {}", response.status(), response.status_text()));
+        }
+        let promise = response.array_buffer().map_err(|error| format!("{error:?}"))?;
+        let buffer = JsFuture::from(promise)
+            .await
+            .map_err(|error| format!("{error:?}"))?;
+        Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Default screen shown while the assets requested in `Game::load` are loading
+pub(crate) struct LoadingScreen {
+    mesh: MeshId,
+    material: MaterialId,
+}
+
+impl LoadingScreen {
+    const BAR_SIZE: Vec2 = Vec2::new(1.0, 0.05);
+
+    pub fn new(state: &mut State) -> Self {
+        let positions = [
+            Vec3::new(-0.5, -0.5, 0.0),
+            Vec3::new(0.5, -0.5, 0.0),
+            Vec3::new(0.5, 0.5, 0.0),
+            Vec3::new(-0.5, 0.5, 0.0),
+        ];
+        let uvs = [Vec2::ZERO; 4];
+        let mesh = state.mesh_pool.create_from_arrays(
+            &positions,
+            &uvs,
+            &[0, 1, 2, 0, 2, 3],
+            &state.device,
+            &state.queue,
+        );
+        let mesh = state.resources.meshes.insert(mesh);
+        let texture = Texture::from_color(&state.device, &state.queue, [255; 4]).unwrap();
+        let texture = state.resources.textures.insert(texture);
+        let material = Material::new(state.shaders.overlay, texture, state);
+        let material = state.resources.materials.insert(material);
+        Self { mesh, material }
+    }
+
+    pub fn camera() -> Camera {
+        Camera {
+            projection: Projection::Orthographic,
+            size: OrthographicSize::from_width_height(2.0, 2.0),
+            ..Camera::default()
+        }
+    }
+
+    pub fn render(&self, progress: f32, commands: &mut Vec<DrawCommand>) {
+        let background = Mat4::from_scale(Self::BAR_SIZE.extend(1.0));
+        commands.push(DrawCommand::Draw(
+            self.mesh,
+            self.material,
+            RenderProperties::builder()
+                .with_matrix(background)
+                .with_color(Color { r: 0.2, g: 0.2, b: 0.2, a: 1.0 })
+                .build(),
+        ));
+
+        let width = progress.clamp(0.0, 1.0) * Self::BAR_SIZE.x;
+        let fill = Mat4::from_scale_rotation_translation(
+            Vec3::new(width, Self::BAR_SIZE.y, 1.0),
+            Quat::IDENTITY,
+            Vec3::new(0.5 * (width - Self::BAR_SIZE.x), 0.0, 0.0),
+        );
+        commands.push(DrawCommand::Draw(
+            self.mesh,
+            self.material,
+            RenderProperties::builder()
+                .with_matrix(fill)
+                .with_color(Color::WHITE)
+                .build(),
+        ));
+    }
+
+    pub fn release(self, state: &mut State) {
+        if let Some(material) = state.resources.materials.remove(self.material) {
+            state.resources.textures.remove(material.texture);
+        }
+        state.resources.meshes.remove(self.mesh);
+    }
+}
@@ -0,0 +1,387 @@
+use glam::*;
+use slotmap::SlotMap;
+
+use crate::{
+    camera::Camera,
+    transform_hierarchy::{TransformHierarchy, TransformId},
+};
+
+slotmap::new_key_type! { pub struct EmitterId; }
+
+/// How an emitter's gain falls off between its min and max distance
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Rolloff {
+    /// Constant gain regardless of distance, e.g. for ambience
+    None,
+    /// Gain falls linearly to zero at the max distance
+    Linear,
+    /// Gain is min distance / distance, the physically based falloff, silent beyond the max distance
+    #[default]
+    Inverse,
+}
+
+/// Sound source positioned at a transform
+#[derive(Debug, Clone, Copy)]
+pub struct AudioEmitter {
+    pub transform: TransformId,
+    pub volume: f32,
+    /// Within this distance the emitter plays at full volume
+    pub min_distance: f32,
+    /// Beyond this distance the emitter is silent
+    pub max_distance: f32,
+    pub rolloff: Rolloff,
+    /// Scales panning, 0.0 for sounds which should stay centered
+    pub spread: f32,
+}
+
+impl AudioEmitter {
+    pub fn new(transform: TransformId) -> Self {
+        Self {
+            transform,
+            volume: 1.0,
+            min_distance: 1.0,
+            max_distance: 50.0,
+            rolloff: Rolloff::default(),
+            spread: 1.0,
+        }
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_distances(mut self, min_distance: f32, max_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_rolloff(mut self, rolloff: Rolloff) -> Self {
+        self.rolloff = rolloff;
+        self
+    }
+
+    fn attenuation(&self, distance: f32) -> f32 {
+        if distance >= self.max_distance {
+            return 0.0;
+        }
+        if distance <= self.min_distance {
+            return 1.0;
+        }
+        match self.rolloff {
+            Rolloff::None => 1.0,
+            Rolloff::Linear => {
+                1.0 - (distance - self.min_distance) / (self.max_distance - self.min_distance)
+            }
+            Rolloff::Inverse => self.min_distance.max(f32::EPSILON) / distance,
+        }
+    }
+}
+
+/// Gain and stereo pan of an emitter relative to the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialParams {
+    /// Volume multiplied by distance attenuation, 0.0 to 1.0 for volumes up to 1.0
+    pub gain: f32,
+    /// -1.0 fully left to 1.0 fully right
+    pub pan: f32,
+    pub distance: f32,
+}
+
+impl SpatialParams {
+    /// Gains of the left and right channels using a constant power pan law
+    pub fn stereo_gains(&self) -> (f32, f32) {
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+        (self.gain * angle.cos(), self.gain * angle.sin())
+    }
+}
+
+/// Where sounds are heard from
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AudioListener {
+    /// The camera passed to `SpatialAudio::update`, usually `state.camera`
+    #[default]
+    Camera,
+    /// A transform, e.g. the player character in a top down game, facing down its -z axis
+    Transform(TransformId),
+}
+
+/// Pan and distance attenuation of sound emitters attached to transforms
+///
+/// There is no playback here, feed the computed `SpatialParams` to the game's audio
+/// library each frame. Call `update` from `Game::update` once entities have moved,
+/// emitters whose transforms have been removed from the hierarchy are removed.
+#[derive(Debug, Default)]
+pub struct SpatialAudio {
+    pub listener: AudioListener,
+    emitters: SlotMap<EmitterId, (AudioEmitter, SpatialParams)>,
+}
+
+impl SpatialAudio {
+    pub fn add_emitter(&mut self, emitter: AudioEmitter) -> EmitterId {
+        let params = SpatialParams {
+            gain: 0.0,
+            pan: 0.0,
+            distance: f32::INFINITY,
+        };
+        self.emitters.insert((emitter, params))
+    }
+
+    pub fn remove_emitter(&mut self, id: EmitterId) -> Option<AudioEmitter> {
+        self.emitters.remove(id).map(|(emitter, _)| emitter)
+    }
+
+    pub fn get_emitter_mut(&mut self, id: EmitterId) -> Option<&mut AudioEmitter> {
+        self.emitters.get_mut(id).map(|(emitter, _)| emitter)
+    }
+
+    /// Parameters as of the last update, silent until then
+    pub fn params(&self, id: EmitterId) -> Option<SpatialParams> {
+        self.emitters.get(id).map(|(_, params)| *params)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EmitterId, &AudioEmitter, SpatialParams)> {
+        self.emitters
+            .iter()
+            .map(|(id, (emitter, params))| (id, emitter, *params))
+    }
+
+    /// Recompute the parameters of each emitter from the listener's position and orientation
+    pub fn update(&mut self, hierarchy: &TransformHierarchy, camera: &Camera) {
+        let (position, right) = match self.listener {
+            AudioListener::Camera => {
+                let forward = (camera.target - camera.eye).normalize_or_zero();
+                (camera.eye, forward.cross(camera.up).normalize_or_zero())
+            }
+            AudioListener::Transform(id) => match hierarchy.get_world_matrix(id) {
+                Some(matrix) => (
+                    matrix.w_axis.truncate(),
+                    matrix.x_axis.truncate().normalize_or_zero(),
+                ),
+                None => (camera.eye, Vec3::X),
+            },
+        };
+
+        self.emitters
+            .retain(|_, (emitter, _)| hierarchy.contains(emitter.transform));
+        for (emitter, params) in self.emitters.values_mut() {
+            let Some(matrix) = hierarchy.get_world_matrix(emitter.transform) else {
+                continue;
+            };
+            let offset = matrix.w_axis.truncate() - position;
+            let distance = offset.length();
+            let pan = if distance > f32::EPSILON {
+                offset.dot(right) / distance
+            } else {
+                0.0
+            };
+            *params = SpatialParams {
+                gain: emitter.volume * emitter.attenuation(distance),
+                pan: (pan * emitter.spread).clamp(-1.0, 1.0),
+                distance,
+            };
+        }
+    }
+}
+
+/// Mixer channel sounds play through, each with its own volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+impl AudioBus {
+    pub const ALL: [AudioBus; 3] = [AudioBus::Music, AudioBus::Sfx, AudioBus::Ui];
+
+    fn index(&self) -> usize {
+        match self {
+            AudioBus::Music => 0,
+            AudioBus::Sfx => 1,
+            AudioBus::Ui => 2,
+        }
+    }
+}
+
+/// Volumes as set by the player, e.g. from a settings menu, from 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixerSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub ui: f32,
+}
+
+impl MixerSettings {
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        match bus {
+            AudioBus::Music => self.music,
+            AudioBus::Sfx => self.sfx,
+            AudioBus::Ui => self.ui,
+        }
+    }
+
+    pub fn set_volume(&mut self, bus: AudioBus, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        match bus {
+            AudioBus::Music => self.music = volume,
+            AudioBus::Sfx => self.sfx = volume,
+            AudioBus::Ui => self.ui = volume,
+        }
+    }
+}
+
+impl Default for MixerSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+            ui: 1.0,
+        }
+    }
+}
+
+/// Value moving linearly towards a target over a duration
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    value: f32,
+    target: f32,
+    /// Change per second
+    rate: f32,
+}
+
+impl Fade {
+    fn new(value: f32) -> Self {
+        Self {
+            value,
+            target: value,
+            rate: 0.0,
+        }
+    }
+
+    fn to(&mut self, target: f32, duration: f32) {
+        self.target = target;
+        if duration > 0.0 {
+            self.rate = (target - self.value).abs() / duration;
+        } else {
+            self.value = target;
+            self.rate = 0.0;
+        }
+    }
+
+    fn update(&mut self, elapsed: f32) {
+        let step = self.rate * elapsed;
+        if (self.target - self.value).abs() <= step {
+            self.value = self.target;
+        } else {
+            self.value += step.copysign(self.target - self.value);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MusicTrack {
+    name: String,
+    fade: Fade,
+}
+
+/// Bus volumes, ducking and music crossfades, updated each frame by the engine
+///
+/// As with `SpatialAudio` there is no playback here, each frame apply `bus_gain` to sounds
+/// playing through each bus and start, stop and set the volume of music tracks to match
+/// `music_tracks`. Fades use real time, so pausing the game doesn't stall them.
+#[derive(Debug, Clone)]
+pub struct AudioMixer {
+    pub settings: MixerSettings,
+    ducking: [Fade; 3],
+    music: Vec<MusicTrack>,
+}
+
+impl AudioMixer {
+    pub fn new(settings: MixerSettings) -> Self {
+        Self {
+            settings,
+            ducking: [Fade::new(1.0); 3],
+            music: Vec::new(),
+        }
+    }
+
+    /// Volume of sounds on the bus, combining the master and bus volumes and any ducking
+    pub fn bus_gain(&self, bus: AudioBus) -> f32 {
+        self.settings.master * self.settings.volume(bus) * self.ducking[bus.index()].value
+    }
+
+    /// Lower the bus to a fraction of its volume over the duration, e.g. music under dialogue
+    pub fn duck(&mut self, bus: AudioBus, level: f32, duration: f32) {
+        self.ducking[bus.index()].to(level.clamp(0.0, 1.0), duration);
+    }
+
+    /// Return the bus to full volume over the duration
+    pub fn release_duck(&mut self, bus: AudioBus, duration: f32) {
+        self.ducking[bus.index()].to(1.0, duration);
+    }
+
+    /// Fade out the current music while fading in the track over the duration, no-op if
+    /// the track is already the current track
+    pub fn crossfade_to<T: Into<String>>(&mut self, track: T, duration: f32) {
+        let track = track.into();
+        if self.current_music() == Some(track.as_str()) {
+            return;
+        }
+        for music in self.music.iter_mut() {
+            music.fade.to(0.0, duration);
+        }
+        // A track still fading out resumes from its current volume
+        let fade = match self.music.iter().position(|music| music.name == track) {
+            Some(index) => self.music.remove(index).fade,
+            None => Fade::new(0.0),
+        };
+        let mut music = MusicTrack { name: track, fade };
+        music.fade.to(1.0, duration);
+        self.music.push(music);
+    }
+
+    /// Fade out all music over the duration
+    pub fn stop_music(&mut self, duration: f32) {
+        for music in self.music.iter_mut() {
+            music.fade.to(0.0, duration);
+        }
+    }
+
+    /// Track most recently crossfaded to, None once stopped
+    pub fn current_music(&self) -> Option<&str> {
+        self.music
+            .last()
+            .filter(|music| music.fade.target > 0.0)
+            .map(|music| music.name.as_str())
+    }
+
+    /// Tracks which should be playing and their volumes including the music bus gain,
+    /// tracks are dropped once faded out
+    pub fn music_tracks(&self) -> impl Iterator<Item = (&str, f32)> {
+        let gain = self.bus_gain(AudioBus::Music);
+        self.music
+            .iter()
+            .map(move |music| (music.name.as_str(), music.fade.value * gain))
+    }
+
+    pub(crate) fn update(&mut self, elapsed: f32) {
+        for ducking in self.ducking.iter_mut() {
+            ducking.update(elapsed);
+        }
+        for music in self.music.iter_mut() {
+            music.fade.update(elapsed);
+        }
+        self.music
+            .retain(|music| music.fade.value > 0.0 || music.fade.target > 0.0);
+    }
+}
+
+impl Default for AudioMixer {
+    fn default() -> Self {
+        Self::new(MixerSettings::default())
+    }
+}
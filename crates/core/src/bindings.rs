@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    camera::{Camera, CameraBindGroup, CameraBinding, CameraId},
+    entity::{EntityBindGroup, EntityBuffer, EntityPagePool},
+    resource_map::ResourceMap,
+};
+
+/// Camera and entity bindings owned by the renderer and shared between shaders
+///
+/// Every shader binds the camera at `@group(0)`, so each camera has a single uniform
+/// buffer written once per frame however many shaders draw with it. Entity uniforms at
+/// `@group(1)` are written to a buffer shared by all shaders with the same uniform size,
+/// or for shaders reading entities from storage, the same size in a storage buffer. These are
+/// split into pages, which buffers using fewer entities return to a pool shared by all.
+pub struct SharedBindings {
+    camera: CameraBindGroup,
+    /// Bindings for cameras used through `DrawCommand::SetCamera`, created on first use
+    cameras: slotmap::SecondaryMap<CameraId, CameraBinding>,
+    entities: HashMap<EntityBuffer, EntityBindGroup>,
+    entity_pages: EntityPagePool,
+    storage_supported: bool,
+}
+
+impl SharedBindings {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            camera: CameraBindGroup::new(device),
+            cameras: slotmap::SecondaryMap::new(),
+            entities: HashMap::new(),
+            entity_pages: EntityPagePool::default(),
+            // WebGL2 has no storage buffers
+            storage_supported: device.limits().max_storage_buffers_per_shader_stage > 0,
+        }
+    }
+
+    /// Whether entities can be read from storage buffers, see `helia/entity_instance.wgsl`
+    pub fn storage_supported(&self) -> bool {
+        self.storage_supported
+    }
+
+    pub fn camera_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.camera.layout
+    }
+
+    /// Layout of the entity buffer, creating the buffer on first use
+    pub fn entity_layout(
+        &mut self,
+        entity_buffer: EntityBuffer,
+        device: &wgpu::Device,
+    ) -> &wgpu::BindGroupLayout {
+        &self.entity_bind_group_mut(entity_buffer, device).layout
+    }
+
+    /// Buffer and bind group for shaders using the entity buffer, if any exist
+    pub fn entity_bind_group(&self, entity_buffer: EntityBuffer) -> Option<&EntityBindGroup> {
+        self.entities.get(&entity_buffer)
+    }
+
+    pub fn entity_bind_group_mut(
+        &mut self,
+        entity_buffer: EntityBuffer,
+        device: &wgpu::Device,
+    ) -> &mut EntityBindGroup {
+        self.entities
+            .entry(entity_buffer)
+            .or_insert_with(|| EntityBindGroup::new(entity_buffer, device))
+    }
+
+    /// Number of entity buffers, one per distinct uniform size and binding type in use
+    pub fn entity_buffer_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Number of entity pages not in use by any entity buffer
+    pub fn free_entity_page_count(&self) -> usize {
+        self.entity_pages.len()
+    }
+
+    /// Ensure the entity buffer has capacity for the entities, e.g. before writing uniforms
+    /// outside of rendering
+    pub fn reserve_entities(
+        &mut self,
+        entity_buffer: EntityBuffer,
+        capacity: u64,
+        device: &wgpu::Device,
+    ) {
+        self.entities
+            .entry(entity_buffer)
+            .or_insert_with(|| EntityBindGroup::new(entity_buffer, device))
+            .reserve(capacity, &mut self.entity_pages, device);
+    }
+
+    /// Write the view projection of the camera to the binding for the id, None being the main
+    /// camera
+    pub fn update_camera(
+        &mut self,
+        id: Option<CameraId>,
+        camera: &Camera,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        match id {
+            None => self.camera.update(camera, queue),
+            Some(id) => {
+                if !self.cameras.contains_key(id) {
+                    let label = format!("Camera {:?}", id);
+                    let binding = CameraBinding::with_label(device, &self.camera.layout, &label);
+                    self.cameras.insert(id, binding);
+                }
+                self.cameras[id].update(camera, queue);
+            }
+        }
+    }
+
+    /// Write each camera used by draws, None being the main camera
+    pub(crate) fn update_cameras(
+        &mut self,
+        used: &HashSet<Option<CameraId>>,
+        main_camera: &Camera,
+        cameras: &ResourceMap<CameraId, Camera>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        for id in used.iter() {
+            let camera = match id {
+                Some(id) => &cameras[*id],
+                None => main_camera,
+            };
+            self.update_camera(*id, camera, device, queue);
+        }
+    }
+
+    /// Bind group for the camera, falls back to the main camera if the binding has not been
+    /// created with `update_camera`
+    pub fn camera_bind_group(&self, id: Option<CameraId>) -> &wgpu::BindGroup {
+        id.and_then(|id| self.cameras.get(id))
+            .map(|binding| &binding.bind_group)
+            .unwrap_or(&self.camera.bind_group)
+    }
+
+    /// Write uniforms to the next free slot of the entity buffer, returning the offset to
+    /// bind them at, they are uploaded by `flush_entities`
+    pub fn write_entity_uniforms(
+        &mut self,
+        entity_buffer: EntityBuffer,
+        bytes: &[u8],
+    ) -> wgpu::BufferAddress {
+        self.entities
+            .get_mut(&entity_buffer)
+            .expect("Entity bind group is created with the shader")
+            .write(bytes)
+    }
+
+    /// Upload the uniforms written to each entity buffer this frame, one write per buffer
+    pub fn flush_entities(&mut self, queue: &wgpu::Queue) {
+        for bind_group in self.entities.values_mut() {
+            bind_group.flush(queue);
+        }
+    }
+
+    /// Start writing entity uniforms from the beginning of each buffer, adding pages to hold
+    /// the number of entities using each
+    ///
+    /// Buffers using less than half their pages return the rest to the pool first, so they
+    /// can be taken by the buffers that need them.
+    pub(crate) fn reset_entities(
+        &mut self,
+        counts_by_buffer: &HashMap<EntityBuffer, u64>,
+        device: &wgpu::Device,
+    ) {
+        for (entity_buffer, bind_group) in self.entities.iter_mut() {
+            bind_group.reset_offset();
+            let entity_count = counts_by_buffer.get(entity_buffer).copied().unwrap_or(0);
+            if 2 * entity_count < bind_group.entity_capacity() {
+                bind_group.release(entity_count, &mut self.entity_pages);
+            }
+        }
+        for (entity_buffer, entity_count) in counts_by_buffer.iter() {
+            self.reserve_entities(*entity_buffer, *entity_count, device);
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use winit::dpi::PhysicalSize;
+
+use crate::{shader_library::ShaderLibrary, texture::Texture};
+
+/// Glow around the bright parts of the scene, e.g. projectiles and effects drawn with bright
+/// colors, set on `State::camera`
+///
+/// The scene is rendered in the surface format, so brightness is clamped to 1.0 and the
+/// threshold selects which colors glow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// Brightness above which colors glow, 0.0 to 1.0
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold, avoids a hard edge to the glow
+    pub knee: f32,
+    /// Strength of the glow added to the scene
+    pub intensity: f32,
+    /// Number of times the bright colors are halved in resolution and blurred, more levels
+    /// spread the glow wider
+    pub levels: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            knee: 0.2,
+            intensity: 0.8,
+            levels: 5,
+        }
+    }
+}
+
+impl BloomSettings {
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_levels(mut self, levels: u32) -> Self {
+        self.levels = levels;
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomUniforms {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _padding: f32,
+}
+
+struct BloomTargets {
+    size: PhysicalSize<u32>,
+    levels: u32,
+    /// The scene is rendered here rather than to the surface
+    input: Texture,
+    /// Each half the size of the previous, the first half the size of the input
+    chain: Vec<Texture>,
+    prefilter: wgpu::BindGroup,
+    /// Reading level i, writing level i + 1
+    downsample: Vec<wgpu::BindGroup>,
+    /// Reading level i + 1, adding to level i
+    upsample: Vec<wgpu::BindGroup>,
+    composite: wgpu::BindGroup,
+}
+
+/// Post process pass which blurs the bright parts of the scene over it
+pub(crate) struct Bloom {
+    format: wgpu::TextureFormat,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    targets: Option<BloomTargets>,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom_bind_group_layout"),
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(3),
+            ],
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Bloom Uniform Buffer"),
+            size: std::mem::size_of::<BloomUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Bilinear filtering averages four texels per sample, halving the taps needed
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("bloom_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let [prefilter, downsample, upsample, composite] =
+            Self::create_pipelines(device, library, &bind_group_layout, format);
+
+        Self {
+            format,
+            bind_group_layout,
+            sampler,
+            uniform_buffer,
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+            targets: None,
+        }
+    }
+
+    /// Ensure the targets match the surface, the scene should then be rendered to
+    /// `input_view` rather than the surface
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        library: &ShaderLibrary,
+        surface_size: PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+        settings: &BloomSettings,
+    ) {
+        if format != self.format {
+            self.format = format;
+            [
+                self.prefilter,
+                self.downsample,
+                self.upsample,
+                self.composite,
+            ] = Self::create_pipelines(device, library, &self.bind_group_layout, format);
+            self.targets = None;
+        }
+
+        // Stop halving before the smallest level is under a pixel
+        let max_levels = surface_size.width.min(surface_size.height).max(1).ilog2();
+        let levels = settings.levels.clamp(1, max_levels.max(1));
+        if self
+            .targets
+            .as_ref()
+            .is_none_or(|targets| targets.size != surface_size || targets.levels != levels)
+        {
+            self.targets = Some(self.create_targets(device, surface_size, levels));
+        }
+
+        let uniforms = BloomUniforms {
+            threshold: settings.threshold,
+            knee: settings.knee.max(0.0),
+            intensity: settings.intensity.max(0.0),
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// View to render the scene to, valid after `prepare`
+    pub fn input_view(&self) -> Option<&wgpu::TextureView> {
+        self.targets.as_ref().map(|targets| &targets.input.view)
+    }
+
+    /// Blur the bright parts of the input and draw it with the glow to the view, covering
+    /// it entirely
+    pub fn apply(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        let mut pass = |label, view, pipeline, bind_group, load| {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        };
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        pass(
+            "Bloom Prefilter Pass",
+            &targets.chain[0].view,
+            &self.prefilter,
+            &targets.prefilter,
+            clear,
+        );
+        for (level, bind_group) in targets.downsample.iter().enumerate() {
+            pass(
+                "Bloom Downsample Pass",
+                &targets.chain[level + 1].view,
+                &self.downsample,
+                bind_group,
+                clear,
+            );
+        }
+        // Each level adds the blurred level below it, from the smallest up
+        for (level, bind_group) in targets.upsample.iter().enumerate().rev() {
+            pass(
+                "Bloom Upsample Pass",
+                &targets.chain[level].view,
+                &self.upsample,
+                bind_group,
+                wgpu::LoadOp::Load,
+            );
+        }
+        pass(
+            "Bloom Composite Pass",
+            view,
+            &self.composite,
+            &targets.composite,
+            clear,
+        );
+    }
+
+    fn create_targets(
+        &self,
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        levels: u32,
+    ) -> BloomTargets {
+        let input = Texture::create_render_target(
+            device,
+            size.width,
+            size.height,
+            self.format,
+            "bloom_input_texture",
+        );
+        let chain = (1..=levels)
+            .map(|level| {
+                Texture::create_render_target(
+                    device,
+                    (size.width >> level).max(1),
+                    (size.height >> level).max(1),
+                    self.format,
+                    "bloom_chain_texture",
+                )
+            })
+            .collect::<Vec<_>>();
+        // The second texture is only read by the composite pass
+        let bind_group = |source: &Texture, scene: &Texture| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bloom_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&scene.view),
+                    },
+                ],
+            })
+        };
+        let prefilter = bind_group(&input, &input);
+        let downsample = chain
+            .windows(2)
+            .map(|pair| bind_group(&pair[0], &pair[0]))
+            .collect();
+        let upsample = chain
+            .windows(2)
+            .map(|pair| bind_group(&pair[1], &pair[1]))
+            .collect();
+        let composite = bind_group(&chain[0], &input);
+
+        BloomTargets {
+            size,
+            levels,
+            input,
+            chain,
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+        }
+    }
+
+    /// Prefilter, downsample, upsample and composite pipelines
+    fn create_pipelines(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> [wgpu::RenderPipeline; 4] {
+        let descriptor = library
+            .descriptor("bloom.wgsl", include_str!("shaders/bloom.wgsl"))
+            .expect("Built-in shader includes should resolve");
+        let shader_module = device.create_shader_module(descriptor);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+        let pipeline = |entry_point, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Bloom Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_main"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: Some(entry_point),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        [
+            pipeline("fs_prefilter", wgpu::BlendState::REPLACE),
+            pipeline("fs_downsample", wgpu::BlendState::REPLACE),
+            pipeline("fs_upsample", additive),
+            pipeline("fs_composite", wgpu::BlendState::REPLACE),
+        ]
+    }
+}
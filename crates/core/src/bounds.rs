@@ -0,0 +1,63 @@
+use glam::*;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    /// Smallest box containing the points, a zero sized box at the origin if there are none
+    pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Self::new(Vec3::ZERO, Vec3::ZERO);
+        };
+        points.fold(Self::new(first, first), |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    pub fn center(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        0.5 * (self.max - self.min)
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Box containing this box after transformation, e.g. by an entity's world matrix
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        // Each axis of the new box spans the absolute contributions of the rotated axes
+        let half_extents = self.half_extents();
+        let half_extents = matrix.x_axis.truncate().abs() * half_extents.x
+            + matrix.y_axis.truncate().abs() * half_extents.y
+            + matrix.z_axis.truncate().abs() * half_extents.z;
+        Self::from_center_half_extents(center, half_extents)
+    }
+}
@@ -2,6 +2,8 @@ use glam::*;
 use wgpu::util::DeviceExt;
 use winit::dpi::PhysicalSize;
 
+use crate::picking::Ray;
+
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: Mat4 = Mat4::from_cols_array(&[
     1.0, 0.0, 0.0, 0.0,
@@ -13,11 +15,101 @@ pub const OPENGL_TO_WGPU_MATRIX: Mat4 = Mat4::from_cols_array(&[
 // without this models centered on 0,0,0 halfway inside the clipping
 // area arguably this is fine.
 
+slotmap::new_key_type! { pub struct CameraId; }
+
 pub enum Projection {
     Orthographic,
     Perspective,
 }
 
+/// How the engine updates `State::camera` when the window is resized
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AspectPolicy {
+    /// Leave the camera unchanged, the view stretches to fill the window
+    Stretch,
+    /// Keep the visible width, the visible height follows the window's aspect ratio
+    /// Perspective cameras keep their horizontal field of view
+    KeepWidth,
+    /// Keep the visible height, the visible width follows the window's aspect ratio
+    #[default]
+    KeepHeight,
+    /// Orthographic size in physical pixels divided by the ratio, so world units stay the
+    /// same size on screen and more of the world is visible in larger windows
+    KeepPixel(f32),
+}
+
+/// What is cleared when draw commands switch to a camera with `DrawCommand::SetCamera`, or
+/// move on to a layer, see `DrawCommand::SetLayerClear`
+///
+/// Clears apply to the whole target, not just the camera's viewport. The frame always
+/// starts cleared to the main camera's clear color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraClear {
+    /// Draw over and depth test against the previous camera's output
+    None,
+    /// Keep the color but clear depth, e.g. for a UI camera drawn over the world
+    #[default]
+    Depth,
+    /// Clear to the camera's clear color, or that set with `DrawCommand::SetClearColor`, and
+    /// clear depth
+    ColorAndDepth,
+}
+
+/// Region of the render target a camera draws to, as fractions of the target size with
+/// the origin at the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub const FULL: Self = Self::new(0.0, 0.0, 1.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Region in pixels of a target with the provided size, as x, y, width, height
+    pub fn to_pixels(&self, size: PhysicalSize<u32>) -> (f32, f32, f32, f32) {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let x = (self.x * width).clamp(0.0, width);
+        let y = (self.y * height).clamp(0.0, height);
+        (
+            x,
+            y,
+            (self.width * width).clamp(0.0, width - x),
+            (self.height * height).clamp(0.0, height - y),
+        )
+    }
+}
+
+/// Elevation of the camera for axonometric presets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxonometricAngle {
+    /// True isometric, all three axes foreshortened equally
+    Isometric,
+    /// 2:1 dimetric, as commonly used for pixel art so tile edges are clean pixel steps
+    Dimetric,
+}
+
+impl AxonometricAngle {
+    /// Angle of the camera above the horizontal in radians
+    pub fn elevation(&self) -> f32 {
+        match self {
+            AxonometricAngle::Isometric => (1.0 / std::f32::consts::SQRT_2).atan(),
+            AxonometricAngle::Dimetric => 0.5f32.asin(),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct OrthographicSize {
     pub left: f32,
@@ -63,7 +155,13 @@ impl OrthographicSize {
     /// Create orthographic viewport from physical size, scaled by a pixel ratio, ensuring integer boundary values
     /// Use for upscaled pixel perfect alignment
     pub fn from_size_scale(size: PhysicalSize<u32>, pixel_ratio: u32) -> Self {
-        let half_size = 0.5 * (pixel_ratio as f32).recip();
+        Self::from_size_scale_factor(size, pixel_ratio as f32)
+    }
+
+    /// Create orthographic viewport from physical size, scaled by a potentially fractional
+    /// scale factor (e.g. a window's DPI scale), ensuring integer boundary values
+    pub fn from_size_scale_factor(size: PhysicalSize<u32>, scale_factor: f32) -> Self {
+        let half_size = 0.5 * scale_factor.recip();
         Self {
             left: (-half_size * size.width as f32).ceil(),
             right: (half_size * size.width as f32).ceil(),
@@ -90,13 +188,102 @@ pub struct Camera {
     pub size: OrthographicSize,
     pub clear_color: wgpu::Color,
     pub projection: Projection,
+    /// What is cleared when draws switch to this camera, ignored for `State::camera`
+    pub clear: CameraClear,
+    /// Region of the target drawn to, None for the whole target
+    pub viewport: Option<Viewport>,
+    /// Cubemap drawn behind the scene when this camera clears color, see
+    /// `Texture::cubemap_from_bytes`, best suited to perspective cameras
+    pub skybox: Option<crate::texture::TextureId>,
+    /// Visibility layers drawn by this camera, entities sharing no bit with it are skipped,
+    /// see `entity::VISIBILITY_DEFAULT`
+    pub cull_mask: u32,
+    /// Glow around bright colors, only applied for `State::camera`
+    #[cfg(feature = "post-processing")]
+    pub bloom: Option<crate::bloom::BloomSettings>,
 }
 
 impl Camera {
     // todo: provide functions for orthographic and perspective camera create methods
 
+    /// Orthographic camera looking down at the target from 45 degrees around the y axis
+    /// at the elevation of the angle preset, for 3D scenes viewed isometrically
+    pub fn axonometric(
+        target: Vec3,
+        distance: f32,
+        angle: AxonometricAngle,
+        size: OrthographicSize,
+    ) -> Self {
+        let elevation = angle.elevation();
+        let azimuth = std::f32::consts::FRAC_PI_4;
+        let direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        );
+        Self {
+            eye: target + distance * direction,
+            target,
+            up: Vec3::Y,
+            aspect_ratio: (size.right - size.left) / (size.top - size.bottom),
+            near: 0.01,
+            far: 2.0 * distance,
+            size,
+            projection: Projection::Orthographic,
+            ..Self::default()
+        }
+    }
+
+    /// Update the aspect ratio and orthographic size for the new target size per the policy,
+    /// orthographic sizes stay centred where they were
+    pub fn apply_aspect_policy(&mut self, policy: AspectPolicy, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        let aspect_ratio = size.width as f32 / size.height as f32;
+        let center = Vec2::new(
+            0.5 * (self.size.left + self.size.right),
+            0.5 * (self.size.bottom + self.size.top),
+        );
+        let (width, height) = (
+            self.size.right - self.size.left,
+            self.size.top - self.size.bottom,
+        );
+        let extents = match policy {
+            AspectPolicy::Stretch => return,
+            AspectPolicy::KeepWidth => {
+                let horizontal_fov = 2.0 * ((0.5 * self.fov).tan() * self.aspect_ratio).atan();
+                self.fov = 2.0 * ((0.5 * horizontal_fov).tan() / aspect_ratio).atan();
+                Vec2::new(width, width / aspect_ratio)
+            }
+            AspectPolicy::KeepHeight => Vec2::new(height * aspect_ratio, height),
+            AspectPolicy::KeepPixel(pixel_ratio) => {
+                let pixel_size = OrthographicSize::from_size_scale_factor(size, pixel_ratio);
+                Vec2::new(
+                    pixel_size.right - pixel_size.left,
+                    pixel_size.top - pixel_size.bottom,
+                )
+            }
+        };
+        self.aspect_ratio = aspect_ratio;
+        self.size = OrthographicSize::new(
+            center.x - 0.5 * extents.x,
+            center.x + 0.5 * extents.x,
+            center.y + 0.5 * extents.y,
+            center.y - 0.5 * extents.y,
+        );
+    }
+
     pub fn build_view_projection_matrix(&self) -> Mat4 {
-        let view = Mat4::look_at_rh(self.eye, self.target, self.up);
+        self.build_projection_matrix() * self.build_view_matrix()
+    }
+
+    pub fn build_view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    /// Projection to wgpu's clip space
+    pub fn build_projection_matrix(&self) -> Mat4 {
         let proj = match self.projection {
             Projection::Perspective => {
                 Mat4::perspective_rh(self.fov, self.aspect_ratio, self.near, self.far)
@@ -110,7 +297,33 @@ impl Camera {
                 self.far,
             ),
         };
-        OPENGL_TO_WGPU_MATRIX * proj * view
+        OPENGL_TO_WGPU_MATRIX * proj
+    }
+
+    /// Create a ray from the camera through the provided screen position (in physical pixels, origin top left)
+    pub fn screen_to_ray(&self, position: Vec2, size: PhysicalSize<u32>) -> Ray {
+        let ndc = Vec2::new(
+            2.0 * position.x / size.width as f32 - 1.0,
+            1.0 - 2.0 * position.y / size.height as f32,
+        );
+        let inverse_view_projection = self.build_view_projection_matrix().inverse();
+        let near = inverse_view_projection.project_point3(ndc.extend(0.0));
+        let far = inverse_view_projection.project_point3(ndc.extend(1.0));
+        Ray::new(near, far - near)
+    }
+
+    /// Project a world position to screen position (in physical pixels, origin top left)
+    /// Returns None if the position is behind the camera
+    pub fn world_to_screen(&self, position: Vec3, size: PhysicalSize<u32>) -> Option<Vec2> {
+        let clip = self.build_view_projection_matrix() * position.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(Vec2::new(
+            0.5 * (ndc.x + 1.0) * size.width as f32,
+            0.5 * (1.0 - ndc.y) * size.height as f32,
+        ))
     }
 }
 
@@ -127,6 +340,12 @@ impl Default for Camera {
             size: OrthographicSize::default(),
             clear_color: wgpu::Color::BLACK,
             projection: Projection::Perspective,
+            clear: CameraClear::default(),
+            viewport: None,
+            skybox: None,
+            cull_mask: crate::entity::VISIBILITY_ALL,
+            #[cfg(feature = "post-processing")]
+            bloom: None,
         }
     }
 }
@@ -155,14 +374,12 @@ impl CameraUniform {
 pub struct CameraBindGroup {
     // This depends on the shader
     pub layout: wgpu::BindGroupLayout,
-    // These should be per camera, if we want to be able to use multiple cameras
-    // Assuming we can rebind bind_group mid render pass
+    // Bound for the main camera, additional cameras use a `CameraBinding` with this layout
     pub bind_group: wgpu::BindGroup,
     buffer: wgpu::Buffer,
     uniform: CameraUniform,
 }
 // todo: a better name would be nice
-// only one camera supported currently
 
 impl CameraBindGroup {
     pub fn new(device: &wgpu::Device) -> Self {
@@ -213,3 +430,44 @@ impl CameraBindGroup {
         // See just above - https://sotrh.github.io/learn-wgpu/beginner/tutorial6-uniforms/#a-controller-for-our-camera
     }
 }
+
+/// Buffer and bind group for an additional camera, using the layout of a `CameraBindGroup`
+pub struct CameraBinding {
+    pub bind_group: wgpu::BindGroup,
+    buffer: wgpu::Buffer,
+    uniform: CameraUniform,
+}
+
+impl CameraBinding {
+    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
+        Self::with_label(device, layout, "Camera")
+    }
+
+    /// Binding whose buffer and bind group labels start with the label, e.g. the camera's id
+    pub fn with_label(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, label: &str) -> Self {
+        let uniform = CameraUniform::new();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Buffer", label)),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some(&format!("{} Bind Group", label)),
+        });
+        Self {
+            bind_group,
+            buffer,
+            uniform,
+        }
+    }
+
+    pub fn update(&mut self, camera: &Camera, queue: &wgpu::Queue) {
+        self.uniform.update_view_proj(camera);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+}
@@ -0,0 +1,98 @@
+use glam::*;
+
+use crate::camera::Camera;
+
+/// Moves an orthographic camera in the xy plane to follow a target
+///
+/// The target can move within the deadzone without the camera moving, outside of it the
+/// camera eases towards the target, and the visible area is clamped to the world bounds
+/// so the camera never shows outside the level.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraFollow {
+    /// Half extents of the area around the camera center the target can move freely within
+    pub deadzone: Vec2,
+    /// Approximate time in seconds for the camera to catch up with the target, zero snaps
+    pub smoothing: f32,
+    /// Offset from the target the camera centers on, e.g. to show more ahead of the player
+    pub offset: Vec2,
+    /// Minimum and maximum world positions which may be visible
+    pub bounds: Option<(Vec2, Vec2)>,
+}
+
+impl CameraFollow {
+    pub fn new() -> Self {
+        Self {
+            deadzone: Vec2::ZERO,
+            smoothing: 0.0,
+            offset: Vec2::ZERO,
+            bounds: None,
+        }
+    }
+
+    pub fn with_deadzone(mut self, deadzone: Vec2) -> Self {
+        self.deadzone = deadzone;
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_bounds(mut self, min: Vec2, max: Vec2) -> Self {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    /// Move the camera towards the target, call once per frame with the elapsed time
+    pub fn update(&self, camera: &mut Camera, target: Vec3, elapsed: f32) {
+        let center = camera.target.truncate();
+        let delta = target.truncate() + self.offset - center;
+        let outside_deadzone = delta - delta.clamp(-self.deadzone, self.deadzone);
+
+        let t = if self.smoothing > 0.0 {
+            1.0 - (-elapsed / self.smoothing).exp()
+        } else {
+            1.0
+        };
+        let position = self.clamp_to_bounds(camera, center + t * outside_deadzone);
+        Self::move_camera(camera, position);
+    }
+
+    /// Center the camera on the target immediately, e.g. on level load
+    pub fn snap(&self, camera: &mut Camera, target: Vec3) {
+        let position = self.clamp_to_bounds(camera, target.truncate() + self.offset);
+        Self::move_camera(camera, position);
+    }
+
+    fn clamp_to_bounds(&self, camera: &Camera, position: Vec2) -> Vec2 {
+        let Some((min, max)) = self.bounds else {
+            return position;
+        };
+        let size = &camera.size;
+        let min = min - Vec2::new(size.left, size.bottom);
+        let max = max - Vec2::new(size.right, size.top);
+        // Center on the bounds along any axis smaller than the view
+        Vec2::new(
+            if min.x > max.x { 0.5 * (min.x + max.x) } else { position.x.clamp(min.x, max.x) },
+            if min.y > max.y { 0.5 * (min.y + max.y) } else { position.y.clamp(min.y, max.y) },
+        )
+    }
+
+    fn move_camera(camera: &mut Camera, position: Vec2) {
+        let translation = (position - camera.target.truncate()).extend(0.0);
+        camera.eye += translation;
+        camera.target += translation;
+    }
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    fixed_point::DeterministicRng,
+    scene::Scene,
+    snapshot::{SceneDiff, SceneSnapshot},
+    time::TimeSnapshot,
+    State,
+};
+
+/// Game data captured mid-play, restore it after tweaking parameters to retry from the
+/// same point without replaying
+///
+/// Only covers data the engine can see, the scene (hierarchy and entities), game time with
+/// its frame and tick counters and optionally the simulation RNG. Resources are not
+/// captured, so meshes and materials referenced by the scene must still exist on restore.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub scene: SceneSnapshot,
+    pub rng: Option<DeterministicRng>,
+    time: TimeSnapshot,
+}
+
+impl Checkpoint {
+    /// Include the RNG, restore it from `rng` as it's owned by the game
+    pub fn with_rng(mut self, rng: &DeterministicRng) -> Self {
+        self.rng = Some(rng.clone());
+        self
+    }
+}
+
+impl State {
+    pub fn checkpoint(&self, scene: &Scene) -> Checkpoint {
+        Checkpoint {
+            scene: scene.snapshot(),
+            rng: None,
+            time: self.time.snapshot(),
+        }
+    }
+
+    /// Return the scene and game time to the checkpoint, the returned diff maps ids of
+    /// entities removed since the checkpoint to their re-added ids, see `SceneDiff::remapped`
+    pub fn restore(&mut self, checkpoint: &Checkpoint, scene: &mut Scene) -> SceneDiff {
+        let diff = scene.apply(&checkpoint.scene);
+        // Applying a snapshot only restores the hierarchy, so reset entity state, names and
+        // tags as well
+        for (id, entry) in checkpoint.scene.entries.iter() {
+            let id = diff.resolve(id);
+            scene.set_node_metadata(id, entry.name.as_deref(), &entry.tags);
+            if let Some(entity) = entry.entity {
+                if scene.get_properties_mut(id).is_some() {
+                    *scene.get_mut(id) = entity;
+                    scene.set_visible(id, entity.visible());
+                }
+            }
+        }
+        self.time.restore(&checkpoint.time);
+        diff
+    }
+}
@@ -0,0 +1,391 @@
+use anyhow::*;
+use winit::dpi::PhysicalSize;
+
+use crate::{shader_library::ShaderLibrary, texture::Texture};
+
+/// Color lookup table used to grade the rendered scene
+///
+/// Stored as a `size`³ volume of RGBA8 texels indexed by the input color, red varying
+/// fastest then green then blue. Strip images are `size * size` wide and `size` tall,
+/// with one `size` wide square per blue slice, red increasing to the right and green
+/// increasing down the image.
+#[derive(Clone)]
+pub struct ColorLut {
+    size: u32,
+    data: Vec<u8>,
+}
+
+impl ColorLut {
+    pub const DEFAULT_SIZE: u32 = 16;
+
+    /// LUT which leaves colors unchanged, a starting point for authoring grades
+    pub fn neutral(size: u32) -> Self {
+        let size = size.max(2);
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((4 * size * size * size) as usize);
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    data.extend_from_slice(&[
+                        (255.0 * red as f32 / max).round() as u8,
+                        (255.0 * green as f32 / max).round() as u8,
+                        (255.0 * blue as f32 / max).round() as u8,
+                        255,
+                    ]);
+                }
+            }
+        }
+        Self { size, data }
+    }
+
+    /// Create from RGBA8 volume data, see `ColorLut` for the expected layout
+    pub fn from_volume(size: u32, data: Vec<u8>) -> Result<Self> {
+        if size < 2 {
+            bail!("LUT size must be at least 2, got {}", size);
+        }
+        let expected = (4 * size * size * size) as usize;
+        if data.len() != expected {
+            bail!(
+                "LUT of size {} requires {} bytes of RGBA8 data, got {}",
+                size,
+                expected,
+                data.len()
+            );
+        }
+        Ok(Self { size, data })
+    }
+
+    /// Create from a strip image, see `ColorLut` for the expected layout
+    pub fn from_strip_image(image: &image::DynamicImage) -> Result<Self> {
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let size = height;
+        if size < 2 || width != size * size {
+            bail!(
+                "LUT strip must be size * size wide and size tall, got {}x{}",
+                width,
+                height
+            );
+        }
+        let mut data = Vec::with_capacity((4 * size * size * size) as usize);
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    data.extend_from_slice(&rgba.get_pixel(blue * size + red, green).0);
+                }
+            }
+        }
+        Ok(Self { size, data })
+    }
+
+    /// Create from an encoded strip image, e.g. the bytes of a png
+    pub fn from_strip_bytes(bytes: &[u8]) -> Result<Self> {
+        let image = image::load_from_memory(bytes)?;
+        Self::from_strip_image(&image)
+    }
+
+    /// Number of entries along each axis
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Default for ColorLut {
+    fn default() -> Self {
+        Self::neutral(Self::DEFAULT_SIZE)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColorGradingUniforms {
+    intensity: f32,
+    lut_size: f32,
+    srgb_target: u32,
+    _padding: f32,
+}
+
+struct GradingTarget {
+    size: PhysicalSize<u32>,
+    color: Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Post process pass which remaps the colors of the rendered scene through a LUT
+pub(crate) struct ColorGrading {
+    /// Blend between the ungraded (0.0) and graded (1.0) scene
+    pub intensity: f32,
+    lut_size: u32,
+    lut_view: wgpu::TextureView,
+    lut_sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    target: Option<GradingTarget>,
+}
+
+impl ColorGrading {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        library: &ShaderLibrary,
+        format: wgpu::TextureFormat,
+        lut: &ColorLut,
+    ) -> Self {
+        let texture_entry = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("color_grading_bind_group_layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::D2),
+                sampler_entry(1),
+                texture_entry(2, wgpu::TextureViewDimension::D3),
+                sampler_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Color Grading Uniform Buffer"),
+            size: std::mem::size_of::<ColorGradingUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Trilinear filtering interpolates between the LUT entries
+        let lut_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("color_grading_lut_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let pipeline = Self::create_pipeline(device, library, &bind_group_layout, format);
+
+        Self {
+            intensity: 1.0,
+            lut_size: lut.size,
+            lut_view: Self::create_lut_view(device, queue, lut),
+            lut_sampler,
+            format,
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            target: None,
+        }
+    }
+
+    /// Replace the LUT used for grading, takes effect from the next frame
+    pub fn set_lut(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lut: &ColorLut) {
+        self.lut_size = lut.size;
+        self.lut_view = Self::create_lut_view(device, queue, lut);
+        // The bind group references the previous LUT
+        self.target = None;
+    }
+
+    /// Ensure the grading input target matches the surface, the scene should then be
+    /// rendered to `input_view` rather than the surface
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        library: &ShaderLibrary,
+        surface_size: PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) {
+        if format != self.format {
+            self.format = format;
+            self.pipeline = Self::create_pipeline(device, library, &self.bind_group_layout, format);
+            self.target = None;
+        }
+
+        if self.target.as_ref().is_none_or(|target| target.size != surface_size) {
+            let color = Texture::create_render_target(
+                device,
+                surface_size.width,
+                surface_size.height,
+                format,
+                "color_grading_texture",
+            );
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("color_grading_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&color.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&color.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&self.lut_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(&self.lut_sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            self.target = Some(GradingTarget {
+                size: surface_size,
+                color,
+                bind_group,
+            });
+        }
+
+        let uniforms = ColorGradingUniforms {
+            intensity: self.intensity.clamp(0.0, 1.0),
+            lut_size: self.lut_size as f32,
+            srgb_target: format.is_srgb() as u32,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// View to render the ungraded scene to, valid after `prepare`
+    pub fn input_view(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|target| &target.color.view)
+    }
+
+    /// Draw the graded scene to the view, covering it entirely
+    pub fn apply(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Color Grading Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_lut_view(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lut: &ColorLut,
+    ) -> wgpu::TextureView {
+        let size = wgpu::Extent3d {
+            width: lut.size,
+            height: lut.size,
+            depth_or_array_layers: lut.size,
+        };
+        // Not an sRGB format, LUT entries are looked up and returned as authored
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("color_grading_lut"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &lut.data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * lut.size),
+                rows_per_image: Some(lut.size),
+            },
+            size,
+        );
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let descriptor = library
+            .descriptor(
+                "color_grading.wgsl",
+                include_str!("shaders/color_grading.wgsl"),
+            )
+            .expect("Built-in shader includes should resolve");
+        let shader_module = device.create_shader_module(descriptor);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Color Grading Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Color Grading Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
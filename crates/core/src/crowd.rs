@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+
+use anyhow::*;
+use glam::*;
+use slotmap::DenseSlotMap;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::{Camera, CameraBindGroup},
+    material::MaterialId,
+    mesh::MeshId,
+    shader::Vertex,
+    shader_library::ShaderLibrary,
+    texture::Texture,
+    Color, PassTarget, Resources,
+};
+
+slotmap::new_key_type! { pub struct CrowdId; }
+
+/// Vertex positions of a mesh for each frame of its animations, baked into a texture the
+/// crowd shader reads by vertex index, e.g. exported from skinned animations
+pub struct BakedAnimation {
+    view: wgpu::TextureView,
+    vertex_count: u32,
+    frame_count: u32,
+}
+
+impl BakedAnimation {
+    /// Texels per row of the baked positions, rows wrap so long animations fit within the
+    /// texture size limits
+    const WIDTH: u32 = 1024;
+
+    /// Frames of positions in the order of the mesh's vertices, every frame must have the
+    /// same number of positions as the mesh has vertices
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, frames: &[Vec<Vec3>]) -> Result<Self> {
+        let vertex_count = frames.first().map_or(0, Vec::len);
+        if vertex_count == 0 {
+            bail!("Baked animation has no vertices");
+        }
+        if let Some(index) = frames.iter().position(|frame| frame.len() != vertex_count) {
+            bail!(
+                "Frame {} has {} positions, expected {}",
+                index,
+                frames[index].len(),
+                vertex_count
+            );
+        }
+        let texel_count = vertex_count * frames.len();
+        let height = texel_count.div_ceil(Self::WIDTH as usize) as u32;
+        let max_height = device.limits().max_texture_dimension_2d;
+        if height > max_height {
+            bail!(
+                "Baked animation needs {} rows of positions, the device supports {}",
+                height,
+                max_height
+            );
+        }
+
+        let mut texels = frames
+            .iter()
+            .flatten()
+            .map(|position| position.extend(1.0).to_array())
+            .collect::<Vec<_>>();
+        texels.resize((Self::WIDTH * height) as usize, [0.0; 4]);
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Baked Animation Texture"),
+                size: wgpu::Extent3d {
+                    width: Self::WIDTH,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&texels),
+        );
+        Ok(Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            vertex_count: vertex_count as u32,
+            frame_count: frames.len() as u32,
+        })
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+}
+
+/// Range of frames of a baked animation played at a fixed rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationClip {
+    pub first_frame: u32,
+    pub frame_count: u32,
+    pub frames_per_second: f32,
+    pub looping: bool,
+}
+
+impl AnimationClip {
+    pub fn new(first_frame: u32, frame_count: u32, frames_per_second: f32) -> Self {
+        Self {
+            first_frame,
+            frame_count,
+            frames_per_second,
+            looping: true,
+        }
+    }
+
+    /// Fractional frame `time` seconds into the clip, for `CrowdInstance::frame`, offset
+    /// the time per instance so crowds don't move in lockstep
+    ///
+    /// The last frame is held rather than blended, so frames of the next clip never show.
+    pub fn frame(&self, time: f32) -> f32 {
+        let frame = (time * self.frames_per_second).max(0.0);
+        let count = self.frame_count.max(1) as f32;
+        let frame = if self.looping { frame % count } else { frame };
+        self.first_frame as f32 + frame.min(count - 1.0)
+    }
+}
+
+/// Transform, tint and animation frame of one member of a crowd
+#[derive(Debug, Clone, Copy)]
+pub struct CrowdInstance {
+    pub world_matrix: Mat4,
+    pub color: Color,
+    /// Frame of the baked animation, fractional frames blend with the next
+    pub frame: f32,
+}
+
+impl CrowdInstance {
+    pub fn new(world_matrix: Mat4, frame: f32) -> Self {
+        Self {
+            world_matrix,
+            color: Color::WHITE,
+            frame,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+    frame: f32,
+}
+
+impl InstanceRaw {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+        10 => Float32,
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct AnimationUniforms {
+    base_vertex: u32,
+    vertex_count: u32,
+    frame_count: u32,
+    width: u32,
+}
+
+/// Many copies of an animated mesh drawn in a single instanced draw call, each with its own
+/// transform, tint and frame of a baked animation
+pub struct Crowd {
+    pub mesh: MeshId,
+    /// Only the material's texture is used, materials whose shader declares uniforms or binds
+    /// a texture array are not supported
+    pub material: MaterialId,
+    pub instances: Vec<CrowdInstance>,
+    pub visible: bool,
+    animation: BakedAnimation,
+    uniforms_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: Option<wgpu::Buffer>,
+    instance_capacity: usize,
+}
+
+impl Crowd {
+    pub fn animation(&self) -> &BakedAnimation {
+        &self.animation
+    }
+}
+
+/// Instanced crowds of animated characters, see `State::crowds`
+///
+/// Crowds are drawn with the main camera after the frame's draw commands, depth tested
+/// against the last layer drawn, one draw call each.
+pub struct CrowdRenderer {
+    crowds: DenseSlotMap<CrowdId, Crowd>,
+    camera_bind_group: CameraBindGroup,
+    animation_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader_module: wgpu::ShaderModule,
+    /// Keyed by target format and sample count
+    pipelines: HashMap<(wgpu::TextureFormat, u32), wgpu::RenderPipeline>,
+    draw_calls: usize,
+}
+
+impl CrowdRenderer {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        texture_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let camera_bind_group = CameraBindGroup::new(device);
+        let animation_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("crowd_animation_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Crowd Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group.layout, &animation_layout, texture_layout],
+            push_constant_ranges: &[],
+        });
+        let descriptor = library
+            .descriptor("crowd.wgsl", include_str!("shaders/crowd.wgsl"))
+            .expect("Built-in shader includes should resolve");
+        Self {
+            crowds: DenseSlotMap::with_key(),
+            camera_bind_group,
+            animation_layout,
+            pipeline_layout,
+            shader_module: device.create_shader_module(descriptor),
+            pipelines: HashMap::new(),
+            draw_calls: 0,
+        }
+    }
+
+    /// Create an empty crowd of the mesh, the animation must have been baked for its vertices
+    pub fn create(
+        &mut self,
+        device: &wgpu::Device,
+        mesh: MeshId,
+        material: MaterialId,
+        animation: BakedAnimation,
+    ) -> CrowdId {
+        let uniforms_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Crowd Animation Buffer"),
+            size: std::mem::size_of::<AnimationUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("crowd_animation_bind_group"),
+            layout: &self.animation_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&animation.view),
+                },
+            ],
+        });
+        self.crowds.insert(Crowd {
+            mesh,
+            material,
+            instances: Vec::new(),
+            visible: true,
+            animation,
+            uniforms_buffer,
+            bind_group,
+            instance_buffer: None,
+            instance_capacity: 0,
+        })
+    }
+
+    pub fn get(&self, id: CrowdId) -> Option<&Crowd> {
+        self.crowds.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: CrowdId) -> Option<&mut Crowd> {
+        self.crowds.get_mut(id)
+    }
+
+    pub fn remove(&mut self, id: CrowdId) -> Option<Crowd> {
+        self.crowds.remove(id)
+    }
+
+    /// Draw calls made for crowds in the last frame
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    pub(crate) fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &PassTarget,
+        camera: &Camera,
+        resources: &Resources,
+    ) {
+        self.draw_calls = 0;
+        let drawable = |crowd: &Crowd| {
+            crowd.visible
+                && !crowd.instances.is_empty()
+                && resources.meshes.contains_key(crowd.mesh)
+                && resources
+                    .materials
+                    .get(crowd.material)
+                    .and_then(|material| resources.shaders.get(material.shader))
+                    .is_some_and(|shader| shader.material_layout().is_none())
+        };
+        if !self.crowds.values().any(drawable) {
+            return;
+        }
+        let key = (target.format, target.sample_count);
+        if !self.pipelines.contains_key(&key) {
+            let pipeline = self.create_pipeline(device, target.format, target.sample_count);
+            self.pipelines.insert(key, pipeline);
+        }
+        self.camera_bind_group.update(camera, queue);
+        for crowd in self.crowds.values_mut().filter(|crowd| drawable(crowd)) {
+            let mesh = &resources.meshes[crowd.mesh];
+            let uniforms = AnimationUniforms {
+                base_vertex: mesh.base_vertex as u32,
+                vertex_count: crowd.animation.vertex_count,
+                frame_count: crowd.animation.frame_count,
+                width: BakedAnimation::WIDTH,
+            };
+            queue.write_buffer(&crowd.uniforms_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+            let instances = crowd
+                .instances
+                .iter()
+                .map(|instance| InstanceRaw {
+                    model: instance.world_matrix.to_cols_array_2d(),
+                    color: [
+                        instance.color.r as f32,
+                        instance.color.g as f32,
+                        instance.color.b as f32,
+                        instance.color.a as f32,
+                    ],
+                    frame: instance.frame,
+                })
+                .collect::<Vec<_>>();
+            if crowd.instance_buffer.is_none() || instances.len() > crowd.instance_capacity {
+                crowd.instance_capacity = instances.len().next_power_of_two();
+                crowd.instance_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Crowd Instance Buffer"),
+                    size: (crowd.instance_capacity * std::mem::size_of::<InstanceRaw>())
+                        as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }));
+            }
+            if let Some(buffer) = &crowd.instance_buffer {
+                queue.write_buffer(buffer, 0, bytemuck::cast_slice(&instances));
+            }
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Crowd Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target.view,
+                resolve_target: target.resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipelines[&key]);
+        render_pass.set_bind_group(0, &self.camera_bind_group.bind_group, &[]);
+        for crowd in self.crowds.values().filter(|crowd| drawable(crowd)) {
+            let (mesh, material) = (
+                &resources.meshes[crowd.mesh],
+                &resources.materials[crowd.material],
+            );
+            let Some(instance_buffer) = &crowd.instance_buffer else {
+                continue;
+            };
+            render_pass.set_bind_group(1, &crowd.bind_group, &[]);
+            render_pass.set_bind_group(2, &material.diffuse_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, mesh.buffers.vertex.slice(..));
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.buffers.index.slice(..), mesh.index_format);
+            render_pass.draw_indexed(
+                mesh.first_index..mesh.first_index + mesh.index_count,
+                mesh.base_vertex,
+                0..crowd.instances.len() as u32,
+            );
+            self.draw_calls += 1;
+        }
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Crowd Pipeline"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
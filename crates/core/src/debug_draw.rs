@@ -0,0 +1,306 @@
+use glam::*;
+
+use crate::{
+    bounds::Aabb,
+    camera::{Camera, CameraBindGroup},
+    shader_library::ShaderLibrary,
+    Color, RenderContext,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+impl LineVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Immediate mode debug lines, drawn over the scene with the main camera and cleared each frame
+///
+/// Lines added while `enabled` is false are ignored, so debug visuals can be left in game code
+/// and toggled at runtime.
+pub struct DebugDraw {
+    pub enabled: bool,
+    vertices: Vec<LineVertex>,
+    format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    camera_bind_group: CameraBindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+}
+
+impl DebugDraw {
+    const INITIAL_VERTEX_CAPACITY: usize = 1024;
+    const CIRCLE_SEGMENTS: usize = 32;
+
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let camera_bind_group = CameraBindGroup::new(device);
+        let pipeline = Self::create_pipeline(device, library, &camera_bind_group.layout, format);
+        Self {
+            enabled: true,
+            vertices: Vec::new(),
+            format,
+            pipeline,
+            camera_bind_group,
+            vertex_buffer: Self::create_vertex_buffer(device, Self::INITIAL_VERTEX_CAPACITY),
+            vertex_capacity: Self::INITIAL_VERTEX_CAPACITY,
+        }
+    }
+
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: Color) {
+        if !self.enabled {
+            return;
+        }
+        let color = [
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ];
+        self.vertices.push(LineVertex {
+            position: from.to_array(),
+            color,
+        });
+        self.vertices.push(LineVertex {
+            position: to.to_array(),
+            color,
+        });
+    }
+
+    /// Outline of a rect in the xy plane, the plane sprites are drawn in
+    pub fn rect(&mut self, center: Vec3, size: Vec2, color: Color) {
+        let half = (size / 2.0).extend(0.0);
+        let corners = [
+            center + Vec3::new(-half.x, -half.y, 0.0),
+            center + Vec3::new(half.x, -half.y, 0.0),
+            center + Vec3::new(half.x, half.y, 0.0),
+            center + Vec3::new(-half.x, half.y, 0.0),
+        ];
+        for i in 0..4 {
+            self.line(corners[i], corners[(i + 1) % 4], color);
+        }
+    }
+
+    /// Outline of a circle in the xy plane
+    pub fn circle(&mut self, center: Vec3, radius: f32, color: Color) {
+        let point = |i: usize| {
+            let angle = i as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + radius * Vec3::new(angle.cos(), angle.sin(), 0.0)
+        };
+        for i in 0..Self::CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Axes of the transform from its origin, x red, y green and z blue, scaled by the
+    /// transform, e.g. a node's world matrix from a `TransformHierarchy`
+    pub fn axes(&mut self, transform: &Mat4, length: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        let axes = [
+            (Vec3::X, Color::RED),
+            (Vec3::Y, Color::GREEN),
+            (Vec3::Z, Color::BLUE),
+        ];
+        for (axis, color) in axes {
+            let end = transform.transform_point3(axis * length);
+            self.line(origin, end, color);
+        }
+    }
+
+    /// Edges of the box, e.g. an entity's `RenderProperties::world_bounds` to outline it
+    pub fn aabb(&mut self, bounds: &Aabb, color: Color) {
+        let corner = |i: usize| {
+            Vec3::select(
+                BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                bounds.max,
+                bounds.min,
+            )
+        };
+        for i in 0..8 {
+            // Each edge joins corners differing in one axis, drawn from the lower corner
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Outline of the volume the camera renders, a box for orthographic cameras
+    ///
+    /// Perspective frustums extend to the camera's far plane, reduce `far` on a copy of the
+    /// camera to visualise a shorter range.
+    pub fn camera_frustum(&mut self, camera: &Camera, color: Color) {
+        let inverse = camera.build_view_projection_matrix().inverse();
+        let corners = |z: f32| {
+            [
+                Vec2::new(-1.0, -1.0),
+                Vec2::new(1.0, -1.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(-1.0, 1.0),
+            ]
+            .map(|ndc| inverse.project_point3(ndc.extend(z)))
+        };
+        let near = corners(0.0);
+        let far = corners(1.0);
+        for i in 0..4 {
+            let next = (i + 1) % 4;
+            self.line(near[i], near[next], color);
+            self.line(far[i], far[next], color);
+            self.line(near[i], far[i], color);
+        }
+    }
+
+    /// Rect the camera sees on the plane through its target facing the camera, i.e. the
+    /// visible area of a 2D scene
+    pub fn camera_bounds(&mut self, camera: &Camera, color: Color) {
+        let inverse = camera.build_view_projection_matrix().inverse();
+        let normal = (camera.eye - camera.target).normalize_or_zero();
+        let corners = [
+            Vec2::new(-1.0, -1.0),
+            Vec2::new(1.0, -1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(-1.0, 1.0),
+        ]
+        .map(|ndc| {
+            let near = inverse.project_point3(ndc.extend(0.0));
+            let far = inverse.project_point3(ndc.extend(1.0));
+            let direction = far - near;
+            let denominator = direction.dot(normal);
+            if denominator.abs() < f32::EPSILON {
+                return near;
+            }
+            near + direction * ((camera.target - near).dot(normal) / denominator)
+        });
+        for i in 0..4 {
+            self.line(corners[i], corners[(i + 1) % 4], color);
+        }
+    }
+
+    /// Number of lines queued for the next frame
+    pub fn line_count(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Draw and clear the queued lines
+    pub(crate) fn render(
+        &mut self,
+        context: &mut RenderContext,
+        camera: &Camera,
+        library: &ShaderLibrary,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let (device, queue, format) = (context.device, context.queue, context.format);
+        if format != self.format {
+            self.format = format;
+            self.pipeline = Self::create_pipeline(
+                device,
+                library,
+                &self.camera_bind_group.layout,
+                format,
+            );
+        }
+        if self.vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.vertex_capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.camera_bind_group.update(camera, queue);
+
+        {
+            let mut render_pass = context.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug Lines Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..self.vertices.len() as u32, 0..1);
+        }
+        self.vertices.clear();
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Lines Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        camera_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let descriptor = library
+            .descriptor("debug_lines.wgsl", include_str!("shaders/debug_lines.wgsl"))
+            .expect("Built-in shader includes should resolve");
+        let shader_module = device.create_shader_module(descriptor);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Lines Pipeline Layout"),
+            bind_group_layouts: &[camera_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug Lines Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[LineVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
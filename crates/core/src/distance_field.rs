@@ -0,0 +1,223 @@
+use anyhow::*;
+use image::{Rgba, RgbaImage};
+
+/// Squared distance used for texels with no nearby edge, large enough to never be nearest
+const FAR: f64 = 1e20;
+
+/// Options for generating a signed distance field atlas from a bitmap atlas
+///
+/// Distances are measured separately within each tile, so glyphs need transparent margin in
+/// their tiles for outlines and glow to extend into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceFieldSettings {
+    /// Size of each tile of the source atlas in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Distance in output texels mapped to the full range of the field, larger spreads allow
+    /// wider outlines and glow at the cost of precision
+    pub spread: f32,
+    /// Factor the source is reduced by, generating from a high resolution source gives
+    /// sharper corners, tile sizes must be divisible by it
+    pub downscale: u32,
+}
+
+impl DistanceFieldSettings {
+    pub fn new(tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            tile_width,
+            tile_height,
+            spread: 4.0,
+            downscale: 1,
+        }
+    }
+
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_downscale(mut self, downscale: u32) -> Self {
+        self.downscale = downscale.max(1);
+        self
+    }
+
+    /// Size of each tile of the generated atlas
+    pub fn output_tile_size(&self) -> (u32, u32) {
+        (
+            self.tile_width / self.downscale,
+            self.tile_height / self.downscale,
+        )
+    }
+}
+
+/// Generate a signed distance field atlas from the alpha of a bitmap atlas
+///
+/// Each texel stores 0.5 at the glyph's edge, rising to 1.0 a spread inside it and falling
+/// to 0.0 a spread outside, in every channel, for use with the SDF text shader.
+pub fn generate(source: &RgbaImage, settings: &DistanceFieldSettings) -> Result<RgbaImage> {
+    let DistanceFieldSettings {
+        tile_width,
+        tile_height,
+        downscale,
+        ..
+    } = *settings;
+    if tile_width == 0
+        || tile_height == 0
+        || source.width() % tile_width != 0
+        || source.height() % tile_height != 0
+    {
+        bail!(
+            "Atlas of {}x{} is not a whole number of {}x{} tiles",
+            source.width(),
+            source.height(),
+            tile_width,
+            tile_height
+        );
+    }
+    if downscale == 0 || tile_width % downscale != 0 || tile_height % downscale != 0 {
+        bail!(
+            "Tile size {}x{} is not divisible by the downscale {}",
+            tile_width,
+            tile_height,
+            downscale
+        );
+    }
+
+    let columns = source.width() / tile_width;
+    let rows = source.height() / tile_height;
+    let (output_width, output_height) = settings.output_tile_size();
+    let mut output = RgbaImage::new(columns * output_width, rows * output_height);
+    let spread = settings.spread.max(f32::EPSILON);
+    for row in 0..rows {
+        for column in 0..columns {
+            let distances = signed_distances(
+                source,
+                column * tile_width,
+                row * tile_height,
+                tile_width,
+                tile_height,
+            );
+            for y in 0..output_height {
+                for x in 0..output_width {
+                    // Average the block of source texels covered by the output texel
+                    let mut sum = 0.0;
+                    for source_y in y * downscale..(y + 1) * downscale {
+                        for source_x in x * downscale..(x + 1) * downscale {
+                            sum += distances[(source_y * tile_width + source_x) as usize];
+                        }
+                    }
+                    // Averaged over the block, then converted from source to output texels
+                    let distance = sum / (downscale * downscale * downscale) as f32;
+                    let value = (0.5 + 0.5 * distance / spread).clamp(0.0, 1.0);
+                    let value = (255.0 * value).round() as u8;
+                    output.put_pixel(
+                        column * output_width + x,
+                        row * output_height + y,
+                        Rgba([value; 4]),
+                    );
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Distance in source pixels from each texel of the tile to the glyph's edge, positive inside
+fn signed_distances(source: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Vec<f32> {
+    let inside = (0..height)
+        .flat_map(|row| (0..width).map(move |column| (column, row)))
+        .map(|(column, row)| source.get_pixel(x + column, y + row)[3] >= 128)
+        .collect::<Vec<_>>();
+    let to_inside = squared_distances(&inside, width as usize, height as usize, true);
+    let to_outside = squared_distances(&inside, width as usize, height as usize, false);
+    // Both are whole texels from the nearest texel of the other kind, so the edge between
+    // neighbouring inside and outside texels lands halfway at zero
+    to_outside
+        .iter()
+        .zip(to_inside.iter())
+        .map(|(outside, inside)| (outside.sqrt() - inside.sqrt()) as f32)
+        .collect()
+}
+
+/// Squared distance from each texel to the nearest texel matching `target`
+///
+/// Felzenszwalb and Huttenlocher's exact euclidean distance transform, run over columns
+/// then rows.
+fn squared_distances(inside: &[bool], width: usize, height: usize, target: bool) -> Vec<f64> {
+    let mut grid = inside
+        .iter()
+        .map(|inside| if *inside == target { 0.0 } else { FAR })
+        .collect::<Vec<_>>();
+    let length = width.max(height);
+    let mut line = vec![0.0; length];
+    let mut result = vec![0.0; length];
+    let mut parabolas = vec![0; length];
+    let mut boundaries = vec![0.0; length + 1];
+
+    for column in 0..width {
+        for row in 0..height {
+            line[row] = grid[row * width + column];
+        }
+        transform_line(
+            &line[..height],
+            &mut result[..height],
+            &mut parabolas,
+            &mut boundaries,
+        );
+        for row in 0..height {
+            grid[row * width + column] = result[row];
+        }
+    }
+    for row in 0..height {
+        let start = row * width;
+        line[..width].copy_from_slice(&grid[start..start + width]);
+        transform_line(
+            &line[..width],
+            &mut result[..width],
+            &mut parabolas,
+            &mut boundaries,
+        );
+        grid[start..start + width].copy_from_slice(&result[..width]);
+    }
+    grid
+}
+
+/// One dimensional squared distance transform, the lower envelope of the parabolas rooted
+/// at each sample
+fn transform_line(
+    samples: &[f64],
+    result: &mut [f64],
+    parabolas: &mut [usize],
+    boundaries: &mut [f64],
+) {
+    let intersection = |q: usize, p: usize| {
+        let (q_position, p_position) = (q as f64, p as f64);
+        ((samples[q] + q_position * q_position) - (samples[p] + p_position * p_position))
+            / (2.0 * q_position - 2.0 * p_position)
+    };
+
+    let mut k = 0;
+    parabolas[0] = 0;
+    boundaries[0] = f64::NEG_INFINITY;
+    boundaries[1] = f64::INFINITY;
+    for q in 1..samples.len() {
+        let mut s = intersection(q, parabolas[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = f64::INFINITY;
+    }
+
+    k = 0;
+    for (q, value) in result.iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - parabolas[k] as f64;
+        *value = offset * offset + samples[parabolas[k]];
+    }
+}
@@ -0,0 +1,140 @@
+use glam::*;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    camera::Camera,
+    input::{InputState, MouseButton},
+    picking::Ray,
+    scene::Scene,
+    transform::GridSnap,
+    transform_hierarchy::TransformId,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveDrag {
+    id: TransformId,
+    /// Point on the drag plane where the entity was grabbed
+    plane_point: Vec3,
+    /// From the grabbed point to the entity's world position
+    offset: Vec3,
+}
+
+/// Grab scene entities with the left mouse button and move them with the cursor
+///
+/// Movement is constrained to the plane through the grabbed point with the provided normal,
+/// optionally snapped to a grid. Call `update` each frame with the current input, it returns
+/// the entity moved that frame so the game can respond, e.g. checking a puzzle solution.
+#[derive(Debug, Clone)]
+pub struct EntityDrag {
+    /// Normal of the plane entities move in, e.g. `Vec3::Y` to slide over the ground
+    pub plane_normal: Vec3,
+    /// World space cell size to snap positions to, axes with a size of zero move freely
+    pub grid: Option<Vec3>,
+    /// Only entities with the tag can be grabbed, any visible entity if None
+    pub tag: Option<String>,
+    /// Local space box used to pick entities, see `Scene::pick`
+    pub pick_bounds: (Vec3, Vec3),
+    active: Option<ActiveDrag>,
+}
+
+impl EntityDrag {
+    pub fn new(plane_normal: Vec3) -> Self {
+        Self {
+            plane_normal: plane_normal.normalize_or_zero(),
+            grid: None,
+            tag: None,
+            pick_bounds: (Vec3::splat(-0.5), Vec3::splat(0.5)),
+            active: None,
+        }
+    }
+
+    pub fn with_grid(mut self, cell_size: Vec3) -> Self {
+        self.grid = Some(cell_size);
+        self
+    }
+
+    pub fn with_tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    pub fn with_pick_bounds(mut self, min: Vec3, max: Vec3) -> Self {
+        self.pick_bounds = (min, max);
+        self
+    }
+
+    /// Entity currently held
+    pub fn dragging(&self) -> Option<TransformId> {
+        self.active.map(|drag| drag.id)
+    }
+
+    /// Release the held entity where it is
+    pub fn release(&mut self) {
+        self.active = None;
+    }
+
+    /// Grab on left mouse down and move the held entity while the button is pressed
+    /// Returns the entity if its transform was changed
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        camera: &Camera,
+        input: &InputState,
+        size: PhysicalSize<u32>,
+    ) -> Option<TransformId> {
+        let mouse_position =
+            Vec2::new(input.mouse_position.x as f32, input.mouse_position.y as f32);
+        let ray = camera.screen_to_ray(mouse_position, size);
+
+        let Some(drag) = self.active.filter(|drag| scene.hierarchy.contains(drag.id)) else {
+            self.active = None;
+            if input.mouse_button_down(MouseButton::Left) {
+                self.grab(scene, &ray);
+            }
+            return None;
+        };
+        if !input.mouse_button_pressed(MouseButton::Left) {
+            self.active = None;
+            return None;
+        }
+
+        let distance = ray.intersect_plane(drag.plane_point, self.plane_normal)?;
+        let mut position = ray.at(distance) + drag.offset;
+        if let Some(grid) = self.grid {
+            position = GridSnap::new(grid).snap_position(position);
+        }
+
+        let hierarchy = &mut scene.hierarchy;
+        let mut transform = hierarchy.get_transform(drag.id)?;
+        let parent_matrix = hierarchy
+            .get_parent(drag.id)
+            .and_then(|parent| hierarchy.get_world_matrix(parent))
+            .unwrap_or(Mat4::IDENTITY);
+        let local_position = parent_matrix.inverse().transform_point3(position);
+        if local_position == transform.position {
+            return None;
+        }
+        transform.position = local_position;
+        hierarchy.set_transform(drag.id, transform);
+        Some(drag.id)
+    }
+
+    fn grab(&mut self, scene: &Scene, ray: &Ray) {
+        let (min, max) = self.pick_bounds;
+        let tag = self.tag.as_deref();
+        let Some((id, distance)) = scene.pick(ray, min, max, |id| {
+            tag.is_none_or(|tag| scene.has_tag(id, tag))
+        }) else {
+            return;
+        };
+        let Some(matrix) = scene.hierarchy.get_world_matrix(id) else {
+            return;
+        };
+        let plane_point = ray.at(distance);
+        self.active = Some(ActiveDrag {
+            id,
+            plane_point,
+            offset: matrix.w_axis.truncate() - plane_point,
+        });
+    }
+}
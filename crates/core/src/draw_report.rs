@@ -0,0 +1,87 @@
+use crate::{camera::CameraId, material::MaterialId, mesh::MeshId, shader::ShaderId};
+
+/// Distinct invalid ids logged before further ones are only reported, churning stale ids
+/// would otherwise grow the set of logged ids without bound
+pub(crate) const MAX_LOGGED_DROPS: usize = 64;
+
+/// Why a draw command was skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    MissingMesh(MeshId),
+    MissingMaterial(MaterialId),
+    /// The material exists but references a shader which has been removed
+    MissingShader(MaterialId, ShaderId),
+    /// Drawn after switching to a camera which does not exist
+    MissingCamera(CameraId),
+}
+
+impl std::fmt::Display for DropReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DropReason::MissingMesh(id) => write!(f, "mesh {:?} does not exist", id),
+            DropReason::MissingMaterial(id) => write!(f, "material {:?} does not exist", id),
+            DropReason::MissingShader(material, shader) => write!(
+                f,
+                "shader {:?} used by material {:?} does not exist",
+                shader, material
+            ),
+            DropReason::MissingCamera(id) => write!(f, "camera {:?} does not exist", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DroppedDraw {
+    /// Index of the command in the frame's draw commands
+    pub index: usize,
+    pub reason: DropReason,
+}
+
+/// Number of state changes recorded while encoding draws, fewer changes are cheaper
+/// for the GPU driver, see `State::sort_draws`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BindCounts {
+    /// `set_pipeline` calls, each also rebinding the camera
+    pub pipelines: usize,
+    /// Texture bind group changes between materials
+    pub materials: usize,
+    /// Vertex and index buffer changes between meshes, meshes in the same `MeshPool` page
+    /// share buffers
+    pub meshes: usize,
+    /// Draw calls, not counted in `total`
+    pub draws: usize,
+}
+
+impl BindCounts {
+    pub fn total(&self) -> usize {
+        self.pipelines + self.materials + self.meshes
+    }
+}
+
+/// Summary of the draw commands processed in a frame
+#[derive(Debug, Clone, Default)]
+pub struct DrawReport {
+    pub submitted: usize,
+    pub drawn: usize,
+    /// Draws skipped as they were outside the camera's view, see `State::frustum_culling`
+    pub culled: usize,
+    /// Draws skipped as none of their visibility layers are in the camera's cull mask
+    pub masked: usize,
+    pub dropped: Vec<DroppedDraw>,
+    pub binds: BindCounts,
+}
+
+impl DrawReport {
+    pub fn has_dropped(&self) -> bool {
+        !self.dropped.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.submitted = 0;
+        self.drawn = 0;
+        self.culled = 0;
+        self.masked = 0;
+        self.dropped.clear();
+        self.binds = BindCounts::default();
+    }
+}
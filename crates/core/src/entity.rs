@@ -2,7 +2,12 @@
 
 use glam::{Mat4, Vec2};
 
-use crate::{material::MaterialId, mesh::MeshId, shader::EntityUniforms, transform::Transform};
+use crate::{
+    bounds::Aabb,
+    material::MaterialId,
+    mesh::{Mesh, MeshId},
+    transform::Transform,
+};
 
 // This specifically and intentionally only refers to entity / instance data for rendering objects
 // Currently it is game codes responsibility to define and track any broader concept of entity
@@ -16,6 +21,17 @@ use crate::{material::MaterialId, mesh::MeshId, shader::EntityUniforms, transfor
 // the game to be able to extend the properties a shader can act upon
 // and if want to avoid properties that have no effect for certain entities
 
+/// Render layers draw in ascending order, later layers over earlier ones regardless of depth
+pub const BACKGROUND_LAYER: u8 = 0;
+pub const DEFAULT_LAYER: u8 = 128;
+pub const FOREGROUND_LAYER: u8 = 192;
+
+/// Visibility layers are bits of `RenderProperties::visibility`, entities are only drawn by
+/// cameras whose `cull_mask` shares a bit with them, what other bits mean is up to the game,
+/// e.g. map only or UI entities
+pub const VISIBILITY_DEFAULT: u32 = 1;
+pub const VISIBILITY_ALL: u32 = u32::MAX;
+
 pub struct RenderPropertiesBuilder {
     properties: RenderProperties,
 }
@@ -56,6 +72,39 @@ impl RenderPropertiesBuilder {
         self.properties.uv_scale = uv_scale;
         self
     }
+
+    /// Outline drawn by the sprite outline shader, thickness is in texels, or by the SDF text
+    /// shader, thickness is in normalized distance where 0.5 is the field's spread
+    pub fn with_outline(&mut self, color: wgpu::Color, thickness: f32) -> &mut Self {
+        self.properties.outline_color = color;
+        self.properties.outline_thickness = thickness;
+        self
+    }
+
+    pub fn with_layer(&mut self, layer: u8) -> &mut Self {
+        self.properties.layer = layer;
+        self
+    }
+
+    pub fn with_order_in_layer(&mut self, order: i32) -> &mut Self {
+        self.properties.order_in_layer = order;
+        self
+    }
+
+    pub fn with_visibility(&mut self, visibility: u32) -> &mut Self {
+        self.properties.visibility = visibility;
+        self
+    }
+
+    pub fn with_texture_layer(&mut self, layer: u32) -> &mut Self {
+        self.properties.texture_layer = layer;
+        self
+    }
+
+    pub fn with_bounds(&mut self, bounds: Aabb) -> &mut Self {
+        self.properties.bounds = Some(bounds);
+        self
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -64,6 +113,21 @@ pub struct RenderProperties {
     pub color: wgpu::Color,
     pub uv_offset: Vec2,
     pub uv_scale: Vec2,
+    /// Only used by the sprite outline and SDF text shaders
+    pub outline_color: wgpu::Color,
+    /// Outline thickness, 0.0 for no outline, see `RenderPropertiesBuilder::with_outline`
+    pub outline_thickness: f32,
+    /// Layers draw in ascending order, depth is cleared between them
+    pub layer: u8,
+    /// Draw order within a layer, ascending, before batching by material
+    pub order_in_layer: i32,
+    /// Visibility layers, see `VISIBILITY_DEFAULT`
+    pub visibility: u32,
+    /// Layer of the material's texture array sampled by the sprite array shader
+    pub texture_layer: u32,
+    /// Model space bounds used in place of the mesh's, for meshes whose vertices move beyond
+    /// them, such as skinned or procedurally displaced meshes, see `local_bounds`
+    pub bounds: Option<Aabb>,
 }
 
 impl Default for RenderProperties {
@@ -73,6 +137,13 @@ impl Default for RenderProperties {
             color: wgpu::Color::WHITE,
             uv_offset: Vec2::ZERO,
             uv_scale: Vec2::ONE,
+            outline_color: wgpu::Color::WHITE,
+            outline_thickness: 0.0,
+            layer: DEFAULT_LAYER,
+            order_in_layer: 0,
+            visibility: VISIBILITY_DEFAULT,
+            texture_layer: 0,
+            bounds: None,
         }
     }
 }
@@ -85,11 +156,20 @@ impl RenderProperties {
     pub fn from_transform(transform: Transform) -> Self {
         Self {
             world_matrix: transform.into(),
-            color: wgpu::Color::WHITE,
-            uv_offset: Vec2::ZERO,
-            uv_scale: Vec2::ONE,
+            ..Default::default()
         }
     }
+
+    /// Bounds in model space, the override if set, otherwise the mesh's, used for culling,
+    /// picking and debug drawing
+    pub fn local_bounds(&self, mesh: &Mesh) -> Aabb {
+        self.bounds.unwrap_or(mesh.bounds)
+    }
+
+    /// Bounds in world space, `local_bounds` transformed by the world matrix
+    pub fn world_bounds(&self, mesh: &Mesh) -> Aabb {
+        self.local_bounds(mesh).transformed(&self.world_matrix)
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -111,74 +191,259 @@ impl EntityDrawInstruction {
     }
 }
 
+/// Identifies a buffer of per entity data shared by shaders, see `SharedBindings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityBuffer {
+    /// Size in bytes of an entity's data
+    pub uniforms_size: usize,
+    /// Read from a storage buffer indexed by instance rather than a uniform bound at a dynamic
+    /// offset per entity, see `helia/entity_instance.wgsl`
+    pub storage: bool,
+}
+
+/// Size in bytes of each buffer entity data is written to, the buffers of an `EntityBindGroup`
+/// hold as many entities as fit
+pub const ENTITY_PAGE_SIZE: wgpu::BufferAddress = 64 * 1024;
+
+/// Buffer of entity data and the bind group it's bound with
+pub struct EntityPage {
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// Unused entity page buffers, shared between entity bind groups so a shader drawing fewer
+/// entities frees pages for those drawing more
+#[derive(Default)]
+pub struct EntityPagePool {
+    uniform: Vec<wgpu::Buffer>,
+    storage: Vec<wgpu::Buffer>,
+}
+
+impl EntityPagePool {
+    fn free(&mut self, storage: bool) -> &mut Vec<wgpu::Buffer> {
+        if storage {
+            &mut self.storage
+        } else {
+            &mut self.uniform
+        }
+    }
+
+    /// Reuse a free buffer of at least the size, or create one
+    fn take(
+        &mut self,
+        size: wgpu::BufferAddress,
+        storage: bool,
+        device: &wgpu::Device,
+    ) -> wgpu::Buffer {
+        let free = self.free(storage);
+        match free.iter().position(|buffer| buffer.size() >= size) {
+            Some(index) => free.swap_remove(index),
+            None => Self::create_buffer(size, storage, device),
+        }
+    }
+
+    fn give(&mut self, buffer: wgpu::Buffer, storage: bool) {
+        self.free(storage).push(buffer);
+    }
+
+    /// Number of free buffers
+    pub fn len(&self) -> usize {
+        self.uniform.len() + self.storage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn create_buffer(
+        size: wgpu::BufferAddress,
+        storage: bool,
+        device: &wgpu::Device,
+    ) -> wgpu::Buffer {
+        let usage = if storage {
+            wgpu::BufferUsages::STORAGE
+        } else {
+            wgpu::BufferUsages::UNIFORM
+        };
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Entity Page"),
+            size,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+/// Entity data for shaders sharing an `EntityBuffer`, written to a list of fixed size pages
+///
+/// Growing appends pages, so buffers and bind groups already in use are never replaced.
+/// Offsets returned by `write` span the pages, see `page_of` and `page_offset` to bind them.
 pub struct EntityBindGroup {
+    label: String,
     pub layout: wgpu::BindGroupLayout,
-    pub bind_group: wgpu::BindGroup,
-    pub buffer: wgpu::Buffer,
+    pub pages: Vec<EntityPage>,
+    /// Distance between entities in a page, the array stride for storage buffers
     pub alignment: wgpu::BufferAddress,
-    pub entity_capacity: u64,
+    pub entities_per_page: u64,
+    uniforms_size: wgpu::BufferAddress,
+    storage: bool,
+    next_offset: u64,
+    /// Entities written since the last flush, uploaded in a single `write_buffer` per page
+    staging: Vec<u8>,
 }
 
 impl EntityBindGroup {
-    pub fn new(entity_uniforms_size: usize, device: &wgpu::Device) -> Self {
-        let entity_uniforms_size = entity_uniforms_size as wgpu::BufferAddress;
+    pub fn new(entity_buffer: EntityBuffer, device: &wgpu::Device) -> Self {
+        let EntityBuffer {
+            uniforms_size,
+            storage,
+        } = entity_buffer;
+        let entity_uniforms_size = uniforms_size as wgpu::BufferAddress;
+        let ty = if storage {
+            wgpu::BufferBindingType::Storage { read_only: true }
+        } else {
+            wgpu::BufferBindingType::Uniform
+        };
         let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
                 visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: true,
+                    ty,
+                    has_dynamic_offset: !storage,
                     min_binding_size: wgpu::BufferSize::new(entity_uniforms_size),
                 },
                 count: None,
             }],
-            label: None,
+            label: Some(&format!("Entity Layout {}", Self::label(entity_buffer))),
         });
 
-        let alignment = wgpu::util::align_to(
-            entity_uniforms_size,
-            device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress,
-        );
-
-        const INITIAL_ENTITY_CAPACITY: u64 = 32;
-        let buffer = Self::create_buffer(INITIAL_ENTITY_CAPACITY, alignment, device);
-        let bind_group = Self::create_bind_group(&layout, &buffer, device);
+        // Storage entities are bound once per page and indexed, so are tightly packed
+        let alignment = if storage {
+            entity_uniforms_size
+        } else {
+            wgpu::util::align_to(
+                entity_uniforms_size,
+                device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress,
+            )
+        };
 
         Self {
+            label: Self::label(entity_buffer),
             layout,
-            bind_group,
-            buffer,
+            pages: Vec::new(),
             alignment,
-            entity_capacity: INITIAL_ENTITY_CAPACITY,
+            entities_per_page: (ENTITY_PAGE_SIZE / alignment).max(1),
+            uniforms_size: entity_uniforms_size,
+            storage,
+            next_offset: 0,
+            staging: Vec::new(),
         }
     }
 
-    pub fn recreate_entity_buffer(&mut self, capacity: u64, device: &wgpu::Device) {
-        self.entity_capacity = capacity;
-        self.buffer = Self::create_buffer(self.entity_capacity, self.alignment, device);
-        self.bind_group = Self::create_bind_group(&self.layout, &self.buffer, device);
+    /// Describes the entity buffer in labels, e.g. `(144 byte uniforms)`
+    fn label(entity_buffer: EntityBuffer) -> String {
+        let binding = if entity_buffer.storage {
+            "storage"
+        } else {
+            "uniforms"
+        };
+        format!("({} byte {})", entity_buffer.uniforms_size, binding)
     }
 
-    fn create_buffer(
-        entity_capacity: u64,
-        alignment: wgpu::BufferAddress,
-        device: &wgpu::Device,
-    ) -> wgpu::Buffer {
-        device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: entity_capacity * alignment,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        })
+    pub fn entity_capacity(&self) -> u64 {
+        self.pages.len() as u64 * self.entities_per_page
+    }
+
+    /// Bytes between the start of each page in the offsets `write` returns
+    fn page_stride(&self) -> wgpu::BufferAddress {
+        self.entities_per_page * self.alignment
+    }
+
+    pub fn reset_offset(&mut self) {
+        self.next_offset = 0;
+        self.staging.clear();
+    }
+
+    /// Write uniforms to the next free slot, returning its offset across the pages
+    ///
+    /// Data is kept on the CPU until `flush`, the pages must have capacity for every entity
+    /// written since the offset was reset.
+    pub fn write(&mut self, bytes: &[u8]) -> wgpu::BufferAddress {
+        let offset = self.next_offset * self.alignment;
+        self.next_offset += 1;
+        self.staging.resize(offset as usize, 0);
+        self.staging.extend_from_slice(bytes);
+        offset
+    }
+
+    /// Upload the entities written since the offset was reset to the pages
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        if self.staging.is_empty() {
+            return;
+        }
+        let size = wgpu::util::align_to(self.staging.len(), wgpu::COPY_BUFFER_ALIGNMENT as usize);
+        self.staging.resize(size, 0);
+        let stride = self.page_stride() as usize;
+        for (page, bytes) in self.pages.iter().zip(self.staging.chunks(stride)) {
+            queue.write_buffer(&page.buffer, 0, bytes);
+        }
+        self.staging.clear();
+    }
+
+    /// Index of the page holding the entity written at the offset
+    pub fn page_of(&self, offset: wgpu::BufferAddress) -> usize {
+        (offset / self.page_stride()) as usize
+    }
+
+    /// Offset of the entity within its page, the dynamic offset for uniform buffers
+    pub fn page_offset(&self, offset: wgpu::BufferAddress) -> wgpu::BufferAddress {
+        offset % self.page_stride()
+    }
+
+    /// Index of the entity written at the offset within its page, drawn as the instance index
+    /// when the pages are storage buffers
+    pub fn instance_index(&self, offset: wgpu::BufferAddress) -> u32 {
+        (self.page_offset(offset) / self.alignment) as u32
+    }
+
+    pub fn is_storage(&self) -> bool {
+        self.storage
+    }
+
+    /// Append pages until there is capacity for the entities, taking buffers from the pool
+    pub fn reserve(&mut self, capacity: u64, pool: &mut EntityPagePool, device: &wgpu::Device) {
+        let page_size = self.page_stride();
+        while self.entity_capacity() < capacity {
+            let buffer = pool.take(page_size, self.storage, device);
+            let bind_group = Self::create_bind_group(
+                &self.layout,
+                &buffer,
+                self.uniforms_size,
+                self.storage,
+                &format!("Entity Page {} {}", self.pages.len(), self.label),
+                device,
+            );
+            self.pages.push(EntityPage { buffer, bind_group });
+        }
+    }
+
+    /// Return pages beyond those needed for the entities to the pool
+    pub fn release(&mut self, capacity: u64, pool: &mut EntityPagePool) {
+        let needed = capacity.div_ceil(self.entities_per_page) as usize;
+        for page in self.pages.drain(needed.min(self.pages.len())..) {
+            pool.give(page.buffer, self.storage);
+        }
     }
 
     fn create_bind_group(
         layout: &wgpu::BindGroupLayout,
         buffer: &wgpu::Buffer,
+        entity_uniforms_size: wgpu::BufferAddress,
+        storage: bool,
+        label: &str,
         device: &wgpu::Device,
     ) -> wgpu::BindGroup {
-        let entity_uniforms_size = std::mem::size_of::<EntityUniforms>() as wgpu::BufferAddress;
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout,
             entries: &[wgpu::BindGroupEntry {
@@ -186,10 +451,15 @@ impl EntityBindGroup {
                 resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
                     buffer,
                     offset: 0,
-                    size: wgpu::BufferSize::new(entity_uniforms_size),
+                    // Uniforms are bound an entity at a time, storage as a whole page
+                    size: if storage {
+                        None
+                    } else {
+                        wgpu::BufferSize::new(entity_uniforms_size)
+                    },
                 }),
             }],
-            label: None,
+            label: Some(label),
         })
     }
 }
@@ -0,0 +1,57 @@
+use crate::{
+    property_animation::{Easing, PropertyAnimation, Track},
+    Color,
+};
+
+/// Built in visual effect on a scene entity, played as a `PropertyAnimation`, see
+/// `PropertyAnimations::start_effect`
+///
+/// Periods and durations are in seconds, effects with a period of 0.0 do nothing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntityEffect {
+    /// Hide the entity for the second half of each period, e.g. while invulnerable
+    Blink { period: f32 },
+    /// Blend the color to the color and back each period
+    PulseColor { color: Color, period: f32 },
+    /// Scale the entity by the factor and back each period
+    PulseScale { scale: f32, period: f32 },
+    /// Replace the color, fading back to the entity's own over the duration, e.g. when hit
+    Flash { color: Color, duration: f32 },
+}
+
+impl EntityEffect {
+    /// Animation playing the effect, looping for all but `Flash`
+    pub fn animation(&self) -> PropertyAnimation {
+        match *self {
+            EntityEffect::Blink { period } if period > 0.0 => PropertyAnimation::new()
+                .with_visible(
+                    Track::new()
+                        .with_eased_key(0.0, true, Easing::Step)
+                        .with_eased_key(period * 0.5, false, Easing::Step)
+                        .with_eased_key(period, true, Easing::Step),
+                )
+                .looping(),
+            EntityEffect::PulseColor { color, period } if period > 0.0 => PropertyAnimation::new()
+                .with_color_blend(color, pulse(0.0, 1.0, period))
+                .looping(),
+            EntityEffect::PulseScale { scale, period } if period > 0.0 => PropertyAnimation::new()
+                .with_scale(pulse(1.0, scale, period))
+                .looping(),
+            EntityEffect::Flash { color, duration } => PropertyAnimation::new().with_color_blend(
+                color,
+                Track::new()
+                    .with_key(0.0, 1.0)
+                    .with_key(duration.max(0.0), 0.0),
+            ),
+            _ => PropertyAnimation::new(),
+        }
+    }
+}
+
+/// Eases from the value to the peak and back over the period
+fn pulse(from: f32, peak: f32, period: f32) -> Track<f32> {
+    Track::new()
+        .with_eased_key(0.0, from, Easing::EaseInOut)
+        .with_eased_key(period * 0.5, peak, Easing::EaseInOut)
+        .with_key(period, from)
+}
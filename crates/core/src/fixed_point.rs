@@ -0,0 +1,379 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use glam::{Vec2, Vec3};
+
+// Simulation types for lockstep games, where every peer must produce bit identical results.
+// f32 results can differ across platforms and compilers, so simulation state is kept in
+// fixed point and only converted to f32 when building render transforms.
+
+const FRACTIONAL_BITS: u32 = 16;
+
+/// Fixed point number with 16 fractional bits, arithmetic is integer only so deterministic
+///
+/// Operations wrap on overflow, the range is roughly ±1.4e14 with a precision of 1/65536.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1 << FRACTIONAL_BITS);
+    pub const HALF: Self = Self(1 << (FRACTIONAL_BITS - 1));
+    /// Smallest representable step
+    pub const EPSILON: Self = Self(1);
+
+    pub const fn from_raw(raw: i64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+
+    pub const fn from_int(value: i32) -> Self {
+        Self((value as i64) << FRACTIONAL_BITS)
+    }
+
+    /// Exact ratio of two integers, e.g. `from_ratio(1, 3)`, prefer to `from_f32` in simulation code
+    pub const fn from_ratio(numerator: i32, denominator: i32) -> Self {
+        Self(((numerator as i64) << FRACTIONAL_BITS) / denominator as i64)
+    }
+
+    /// Convert from f32, only deterministic for values that are exactly representable,
+    /// intended for authored data rather than values computed at runtime
+    pub fn from_f32(value: f32) -> Self {
+        Self((value as f64 * Self::ONE.0 as f64).round() as i64)
+    }
+
+    /// Convert for rendering, the result should not be fed back into the simulation
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / Self::ONE.0 as f32
+    }
+
+    /// Rounds towards negative infinity
+    pub const fn to_int(self) -> i64 {
+        self.0 >> FRACTIONAL_BITS
+    }
+
+    pub const fn floor(self) -> Self {
+        Self(self.0 & !(Self::ONE.0 - 1))
+    }
+
+    pub const fn abs(self) -> Self {
+        Self(self.0.wrapping_abs())
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        Ord::min(self, other)
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        Ord::max(self, other)
+    }
+
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        Ord::clamp(self, min, max)
+    }
+
+    /// Square root, zero for negative values
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+        Self(isqrt((self.0 as u128) << FRACTIONAL_BITS) as i64)
+    }
+
+    pub fn lerp(self, to: Self, t: Self) -> Self {
+        self + (to - self) * t
+    }
+}
+
+// Integer square root by bitwise digit extraction
+fn isqrt(value: u128) -> u128 {
+    let mut remainder = value;
+    let mut result = 0u128;
+    let mut bit = 1u128 << ((127 - value.leading_zeros()) & !1);
+    while bit != 0 {
+        if remainder >= result + bit {
+            remainder -= result + bit;
+            result = (result >> 1) + bit;
+        } else {
+            result >>= 1;
+        }
+        bit >>= 2;
+    }
+    result
+}
+
+impl From<i32> for Fixed {
+    fn from(value: i32) -> Self {
+        Self::from_int(value)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self(((self.0 as i128 * rhs.0 as i128) >> FRACTIONAL_BITS) as i64)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Self;
+    /// Panics on division by zero, as with integer division
+    fn div(self, rhs: Self) -> Self {
+        Self((((self.0 as i128) << FRACTIONAL_BITS) / rhs.0 as i128) as i64)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FixedVec2 {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+impl FixedVec2 {
+    pub const ZERO: Self = Self::new(Fixed::ZERO, Fixed::ZERO);
+
+    pub const fn new(x: Fixed, y: Fixed) -> Self {
+        Self { x, y }
+    }
+
+    pub const fn from_ints(x: i32, y: i32) -> Self {
+        Self::new(Fixed::from_int(x), Fixed::from_int(y))
+    }
+
+    pub fn dot(self, other: Self) -> Fixed {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length_squared(self) -> Fixed {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> Fixed {
+        self.length_squared().sqrt()
+    }
+
+    /// Unit vector in the same direction, zero for a zero vector
+    pub fn normalize_or_zero(self) -> Self {
+        let length = self.length();
+        if length == Fixed::ZERO {
+            Self::ZERO
+        } else {
+            Self::new(self.x / length, self.y / length)
+        }
+    }
+
+    pub fn to_vec2(self) -> Vec2 {
+        Vec2::new(self.x.to_f32(), self.y.to_f32())
+    }
+}
+
+impl Add for FixedVec2 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for FixedVec2 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for FixedVec2 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign for FixedVec2 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<Fixed> for FixedVec2 {
+    type Output = Self;
+    fn mul(self, rhs: Fixed) -> Self {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Neg for FixedVec2 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FixedVec3 {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub z: Fixed,
+}
+
+impl FixedVec3 {
+    pub const ZERO: Self = Self::new(Fixed::ZERO, Fixed::ZERO, Fixed::ZERO);
+
+    pub const fn new(x: Fixed, y: Fixed, z: Fixed) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn from_ints(x: i32, y: i32, z: i32) -> Self {
+        Self::new(Fixed::from_int(x), Fixed::from_int(y), Fixed::from_int(z))
+    }
+
+    pub fn dot(self, other: Self) -> Fixed {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length_squared(self) -> Fixed {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> Fixed {
+        self.length_squared().sqrt()
+    }
+
+    /// Unit vector in the same direction, zero for a zero vector
+    pub fn normalize_or_zero(self) -> Self {
+        let length = self.length();
+        if length == Fixed::ZERO {
+            Self::ZERO
+        } else {
+            Self::new(self.x / length, self.y / length, self.z / length)
+        }
+    }
+
+    pub fn truncate(self) -> FixedVec2 {
+        FixedVec2::new(self.x, self.y)
+    }
+
+    /// Convert for rendering, e.g. as the position of a `Transform`
+    pub fn to_vec3(self) -> Vec3 {
+        Vec3::new(self.x.to_f32(), self.y.to_f32(), self.z.to_f32())
+    }
+}
+
+impl Add for FixedVec3 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl AddAssign for FixedVec3 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for FixedVec3 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl SubAssign for FixedVec3 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<Fixed> for FixedVec3 {
+    type Output = Self;
+    fn mul(self, rhs: Fixed) -> Self {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Neg for FixedVec3 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+/// Seeded random number generator (SplitMix64) giving the same sequence on every platform
+///
+/// Peers should share the seed when a match starts and only draw from it in simulation code.
+#[derive(Debug, Clone)]
+pub struct DeterministicRng {
+    state: u64,
+}
+
+impl DeterministicRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Integer in the range [min, max), max must be greater than min
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        let span = (max as i64 - min as i64) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    /// Value in the range [0, 1)
+    pub fn next_fixed(&mut self) -> Fixed {
+        Fixed::from_raw((self.next_u64() >> (64 - FRACTIONAL_BITS)) as i64)
+    }
+
+    /// Current state, store with simulation snapshots to resume the same sequence
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+
+use crate::draw_report::DrawReport;
+
+/// Rolling history of frame times, for tracking down intermittent hitches
+///
+/// Times are real time in seconds. When `spike_threshold` is set any frame exceeding it is
+/// logged along with the number of draws that frame.
+pub struct FrameStats {
+    history: VecDeque<f32>,
+    capacity: usize,
+    /// Frame time in seconds above which a frame is reported as a spike
+    pub spike_threshold: Option<f32>,
+    spike_count: usize,
+    frame_count: u64,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            spike_threshold: None,
+            spike_count: 0,
+            frame_count: 0,
+        }
+    }
+
+    pub fn with_spike_threshold(mut self, threshold: f32) -> Self {
+        self.spike_threshold = Some(threshold);
+        self
+    }
+
+    pub fn record(&mut self, frame_time: f32, draw_report: &DrawReport) {
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(frame_time);
+        self.frame_count += 1;
+
+        if let Some(threshold) = self.spike_threshold {
+            // The first frame includes start up work, so is not worth reporting
+            if frame_time > threshold && self.frame_count > 1 {
+                self.spike_count += 1;
+                log::warn!(
+                    "Frame spike: {:.2}ms (threshold {:.2}ms, median {:.2}ms), {} draws submitted, {} drawn, {} dropped",
+                    1000.0 * frame_time,
+                    1000.0 * threshold,
+                    1000.0 * self.percentile(0.5),
+                    draw_report.submitted,
+                    draw_report.drawn,
+                    draw_report.dropped.len(),
+                );
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// Most recent frame time
+    pub fn latest(&self) -> f32 {
+        self.history.back().copied().unwrap_or(0.0)
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        self.history.iter().sum::<f32>() / self.history.len() as f32
+    }
+
+    pub fn max(&self) -> f32 {
+        self.history.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Frame time at the percentile of the history, e.g. 0.99 for the 99th percentile
+    pub fn percentile(&self, percentile: f32) -> f32 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<f32> = self.history.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let index = (percentile.clamp(0.0, 1.0) * (sorted.len() - 1) as f32).round() as usize;
+        sorted[index]
+    }
+
+    /// Number of spikes reported since creation or the last reset
+    pub fn spike_count(&self) -> usize {
+        self.spike_count
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &f32> {
+        self.history.iter()
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.spike_count = 0;
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(240)
+    }
+}
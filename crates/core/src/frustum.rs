@@ -0,0 +1,48 @@
+use glam::*;
+
+use crate::{bounds::Aabb, camera::Camera};
+
+/// Volume visible to a camera, bounded by six planes facing inwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes, xyz is the normal and w the distance
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from a view projection matrix with wgpu's 0 to 1 depth range
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|index| view_projection.row(index));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            let length = plane.truncate().length();
+            if length > f32::EPSILON {
+                plane / length
+            } else {
+                plane
+            }
+        });
+        Self { planes }
+    }
+
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self::from_view_projection(camera.build_view_projection_matrix())
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+    }
+
+    /// Whether any part of the box may be visible, boxes near the frustum's corners can
+    /// pass without intersecting, which is fine for culling
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // Distance of the box corner furthest along the plane's normal
+            normal.dot(center) + normal.abs().dot(half_extents) + plane.w >= 0.0
+        })
+    }
+}
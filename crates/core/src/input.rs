@@ -8,11 +8,165 @@ use std::{
 use winit::{
     dpi::PhysicalPosition,
     event::{ElementState, KeyEvent, MouseScrollDelta, WindowEvent},
-    keyboard::PhysicalKey,
+    keyboard::{Key, PhysicalKey},
 };
 
 pub type KeyCode = winit::keyboard::KeyCode;
 pub type MouseButton = winit::event::MouseButton;
+/// Key as produced by the keyboard layout, e.g. `Key::Character("z")`, see `key_down_logical`
+pub type LogicalKey = winit::keyboard::Key;
+pub use winit::keyboard::NamedKey;
+
+/// Buttons of a gamepad in the standard mapping of the web Gamepad API, in index order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// A on Xbox controllers, cross on PlayStation controllers
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Home,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 17] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+        GamepadButton::Home,
+    ];
+}
+
+/// Source of input a player can be assigned, gamepads are identified by their index as
+/// reported by the platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputDevice {
+    Keyboard,
+    Gamepad(usize),
+}
+
+/// Device connection changes this frame, see `InputState::device_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Connected(InputDevice),
+    Disconnected(InputDevice),
+}
+
+/// Input an action is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(GamepadButton),
+}
+
+/// Named actions of a player and what triggers them, key bindings only apply to players
+/// assigned the keyboard, so players sharing a keyboard bind separate regions of it
+#[derive(Debug, Clone, Default)]
+pub struct ActionBindings {
+    actions: HashMap<String, Vec<Binding>>,
+}
+
+impl ActionBindings {
+    pub fn bind<S: Into<String>>(&mut self, action: S, binding: Binding) {
+        self.actions.entry(action.into()).or_default().push(binding);
+    }
+
+    pub fn with<S: Into<String>>(mut self, action: S, binding: Binding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Devices and bindings of a local multiplayer player slot
+#[derive(Debug, Clone, Default)]
+pub struct PlayerSlot {
+    pub devices: Vec<InputDevice>,
+    pub bindings: ActionBindings,
+}
+
+#[derive(Clone, Copy)]
+enum ButtonState {
+    Pressed,
+    Down,
+    Up,
+}
+
+impl ButtonState {
+    fn of<T: Eq + Hash + Clone>(self, map: &InputMap<T>, key: T) -> bool {
+        match self {
+            ButtonState::Pressed => map.is_pressed(key),
+            ButtonState::Down => map.down(key),
+            ButtonState::Up => map.up(key),
+        }
+    }
+}
+
+/// Actions of a player slot, from the devices assigned to it, see `InputState::player`
+pub struct PlayerInput<'a> {
+    input: &'a InputState,
+    slot: Option<&'a PlayerSlot>,
+}
+
+impl PlayerInput<'_> {
+    /// Is a binding of the action currently held
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.any_binding(action, ButtonState::Pressed)
+    }
+
+    /// Was a binding of the action pressed this frame
+    pub fn action_down(&self, action: &str) -> bool {
+        self.any_binding(action, ButtonState::Down)
+    }
+
+    /// Was a binding of the action released this frame
+    pub fn action_up(&self, action: &str) -> bool {
+        self.any_binding(action, ButtonState::Up)
+    }
+
+    fn any_binding(&self, action: &str, state: ButtonState) -> bool {
+        let Some(slot) = self.slot else {
+            return false;
+        };
+        slot.bindings.bindings(action).iter().any(|binding| {
+            slot.devices
+                .iter()
+                .any(|device| self.input.binding_state(*device, *binding, state))
+        })
+    }
+}
 
 pub struct InputState {
     pub mouse_position: PhysicalPosition<f64>,
@@ -21,10 +175,18 @@ pub struct InputState {
     pub pixel_scroll_ratio: f32,
     last_mouse_position: PhysicalPosition<f64>,
     key_map: InputMap<KeyCode>,
+    logical_key_map: InputMap<LogicalKey>,
+    /// Logical key each physical key last produced, learnt from key presses as the layout
+    /// can't be queried up front
+    logical_keys: HashMap<KeyCode, LogicalKey>,
     mouse_button_map: InputMap<MouseButton>,
+    /// Buttons of each connected gamepad
+    gamepads: HashMap<usize, InputMap<GamepadButton>>,
+    device_events: Vec<DeviceEvent>,
+    players: Vec<PlayerSlot>,
 }
 
-struct InputMap<T: Eq + Hash + Copy> {
+struct InputMap<T: Eq + Hash + Clone> {
     pressed: HashSet<T>,
     down: HashSet<T>,
     up: HashSet<T>,
@@ -32,7 +194,7 @@ struct InputMap<T: Eq + Hash + Copy> {
     up_times: HashMap<T, Instant>,
 }
 
-impl<T: Eq + Hash + Copy> InputMap<T> {
+impl<T: Eq + Hash + Clone> InputMap<T> {
     pub fn new() -> Self {
         Self {
             pressed: HashSet::new(),
@@ -45,15 +207,15 @@ impl<T: Eq + Hash + Copy> InputMap<T> {
 
     pub fn pressed(&mut self, key: T) {
         if !self.pressed.contains(&key) {
-            self.down.insert(key);
-            self.down_times.insert(key, Instant::now());
+            self.down.insert(key.clone());
+            self.down_times.insert(key.clone(), Instant::now());
         }
         self.pressed.insert(key);
     }
 
     pub fn released(&mut self, key: T) {
         self.pressed.remove(&key);
-        self.up.insert(key);
+        self.up.insert(key.clone());
         self.up_times.insert(key, Instant::now());
     }
 
@@ -109,12 +271,27 @@ impl InputState {
                     KeyEvent {
                         state,
                         physical_key: PhysicalKey::Code(keycode),
+                        logical_key,
                         ..
                     },
                 ..
             } => match *state {
-                ElementState::Pressed => self.key_map.pressed(*keycode),
-                ElementState::Released => self.key_map.released(*keycode),
+                ElementState::Pressed => {
+                    self.key_map.pressed(*keycode);
+                    let logical_key = Self::normalize(logical_key.as_ref());
+                    self.logical_keys.insert(*keycode, logical_key.clone());
+                    self.logical_key_map.pressed(logical_key);
+                }
+                ElementState::Released => {
+                    self.key_map.released(*keycode);
+                    // Release what the key produced when pressed, modifiers may have changed since
+                    let logical_key = self
+                        .logical_keys
+                        .get(keycode)
+                        .cloned()
+                        .unwrap_or_else(|| Self::normalize(logical_key.as_ref()));
+                    self.logical_key_map.released(logical_key);
+                }
             },
             _ => {}
         }
@@ -122,7 +299,12 @@ impl InputState {
 
     pub fn frame_finished(&mut self) {
         self.key_map.frame_finished();
+        self.logical_key_map.frame_finished();
         self.mouse_button_map.frame_finished();
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.frame_finished();
+        }
+        self.device_events.clear();
         self.mouse_delta = Vec2::ZERO;
         self.mouse_scroll_delta = Vec2::ZERO;
         self.last_mouse_position = self.mouse_position;
@@ -153,6 +335,144 @@ impl InputState {
         self.key_map.down_elapsed(keycode)
     }
 
+    /// Is the key produced by the keyboard layout currently pressed, characters are
+    /// compared ignoring case, e.g. `Key::Character("z")` is the key labelled Z on any layout
+    pub fn key_pressed_logical(&self, key: Key<&str>) -> bool {
+        self.logical_key_map.is_pressed(Self::normalize(key))
+    }
+
+    /// Was the key produced by the keyboard layout pressed this frame
+    pub fn key_down_logical(&self, key: Key<&str>) -> bool {
+        self.logical_key_map.down(Self::normalize(key))
+    }
+
+    /// Was the key produced by the keyboard layout released this frame
+    pub fn key_up_logical(&self, key: Key<&str>) -> bool {
+        self.logical_key_map.up(Self::normalize(key))
+    }
+
+    /// Name of the physical key on the user's keyboard layout for prompts, e.g. "A" for
+    /// `KeyCode::KeyQ` on AZERTY
+    ///
+    /// The layout is learnt from key presses, keys not yet pressed are named as on a US
+    /// layout.
+    pub fn display_name(&self, keycode: KeyCode) -> String {
+        match self.logical_keys.get(&keycode) {
+            Some(Key::Character(character)) => character.to_uppercase(),
+            Some(Key::Named(named)) => format!("{:?}", named),
+            _ => Self::us_layout_name(keycode),
+        }
+    }
+
+    fn us_layout_name(keycode: KeyCode) -> String {
+        let name = format!("{:?}", keycode);
+        ["Key", "Digit", "Arrow"]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix))
+            .filter(|stripped| !stripped.is_empty())
+            .map_or(name.clone(), str::to_string)
+    }
+
+    /// Owned key with characters lower cased, so held modifiers don't change the key
+    fn normalize(key: Key<&str>) -> LogicalKey {
+        match key {
+            Key::Character(character) => Key::Character(character.to_lowercase().into()),
+            Key::Named(named) => Key::Named(named),
+            Key::Unidentified(native) => Key::Unidentified(native),
+            Key::Dead(character) => Key::Dead(character),
+        }
+    }
+
+    /// Gamepads connected and disconnected this frame
+    pub fn device_events(&self) -> &[DeviceEvent] {
+        &self.device_events
+    }
+
+    /// Indices of the connected gamepads
+    pub fn gamepads(&self) -> impl Iterator<Item = usize> + '_ {
+        self.gamepads.keys().copied()
+    }
+
+    /// Is the gamepad button currently pressed
+    pub fn gamepad_button_pressed(&self, gamepad: usize, button: GamepadButton) -> bool {
+        self.gamepads
+            .get(&gamepad)
+            .is_some_and(|buttons| buttons.is_pressed(button))
+    }
+
+    /// Was the gamepad button pressed this frame
+    pub fn gamepad_button_down(&self, gamepad: usize, button: GamepadButton) -> bool {
+        self.gamepads
+            .get(&gamepad)
+            .is_some_and(|buttons| buttons.down(button))
+    }
+
+    /// Was the gamepad button released this frame
+    pub fn gamepad_button_up(&self, gamepad: usize, button: GamepadButton) -> bool {
+        self.gamepads
+            .get(&gamepad)
+            .is_some_and(|buttons| buttons.up(button))
+    }
+
+    /// Replace the devices and bindings of the player slot, adding slots up to it as needed
+    pub fn set_player(&mut self, player: usize, slot: PlayerSlot) {
+        if self.players.len() <= player {
+            self.players.resize_with(player + 1, PlayerSlot::default);
+        }
+        self.players[player] = slot;
+    }
+
+    pub fn player_slot(&self, player: usize) -> Option<&PlayerSlot> {
+        self.players.get(player)
+    }
+
+    pub fn player_slot_mut(&mut self, player: usize) -> Option<&mut PlayerSlot> {
+        self.players.get_mut(player)
+    }
+
+    /// Assign the device to the player, removing it from any other player, e.g. when a
+    /// newly connected gamepad presses start
+    pub fn assign_device(&mut self, player: usize, device: InputDevice) {
+        self.unassign_device(device);
+        if self.players.len() <= player {
+            self.players.resize_with(player + 1, PlayerSlot::default);
+        }
+        self.players[player].devices.push(device);
+    }
+
+    pub fn unassign_device(&mut self, device: InputDevice) {
+        for slot in self.players.iter_mut() {
+            slot.devices.retain(|assigned| *assigned != device);
+        }
+    }
+
+    /// Player the device is assigned to, if any
+    pub fn device_player(&self, device: InputDevice) -> Option<usize> {
+        self.players
+            .iter()
+            .position(|slot| slot.devices.contains(&device))
+    }
+
+    /// Actions of the player from their assigned devices, e.g.
+    /// `input.player(0).action_down("jump")`, players without a slot have no input
+    pub fn player(&self, player: usize) -> PlayerInput<'_> {
+        PlayerInput {
+            input: self,
+            slot: self.players.get(player),
+        }
+    }
+
+    fn binding_state(&self, device: InputDevice, binding: Binding, state: ButtonState) -> bool {
+        match (device, binding) {
+            (InputDevice::Keyboard, Binding::Key(keycode)) => state.of(&self.key_map, keycode),
+            (InputDevice::Gamepad(gamepad), Binding::Gamepad(button)) => self
+                .gamepads
+                .get(&gamepad)
+                .is_some_and(|buttons| state.of(buttons, button)),
+            _ => false,
+        }
+    }
+
     /// Is mouse button currently pressed
     pub fn mouse_button_pressed(&self, mouse_button: MouseButton) -> bool {
         self.mouse_button_map.is_pressed(mouse_button)
@@ -177,6 +497,147 @@ impl InputState {
     pub fn mouse_button_down_elapsed(&self, mouse_button: MouseButton) -> Option<f32> {
         self.mouse_button_map.down_elapsed(mouse_button)
     }
+
+    /// Simulate a key press, as if received from the window, e.g. for scripted tests
+    pub fn press_key(&mut self, keycode: KeyCode) {
+        self.key_map.pressed(keycode);
+    }
+
+    /// Simulate a key release, as if received from the window
+    pub fn release_key(&mut self, keycode: KeyCode) {
+        self.key_map.released(keycode);
+    }
+
+    /// Simulate a mouse button press, as if received from the window
+    pub fn press_mouse_button(&mut self, mouse_button: MouseButton) {
+        self.mouse_button_map.pressed(mouse_button);
+    }
+
+    /// Simulate a mouse button release, as if received from the window
+    pub fn release_mouse_button(&mut self, mouse_button: MouseButton) {
+        self.mouse_button_map.released(mouse_button);
+    }
+
+    /// Simulate the cursor moving to the position in physical pixels
+    pub fn move_mouse(&mut self, position: PhysicalPosition<f64>) {
+        self.process_events(&WindowEvent::CursorMoved {
+            device_id: winit::event::DeviceId::dummy(),
+            position,
+        });
+    }
+
+    /// Simulate a gamepad connecting, or feed one from a platform gamepad library, does
+    /// nothing if already connected
+    pub fn connect_gamepad(&mut self, gamepad: usize) {
+        if let std::collections::hash_map::Entry::Vacant(entry) = self.gamepads.entry(gamepad) {
+            entry.insert(InputMap::new());
+            let event = DeviceEvent::Connected(InputDevice::Gamepad(gamepad));
+            self.device_events.push(event);
+        }
+    }
+
+    /// Simulate a gamepad disconnecting, forgetting its held buttons, the gamepad stays
+    /// assigned to its player so it resumes control if reconnected
+    pub fn disconnect_gamepad(&mut self, gamepad: usize) {
+        if self.gamepads.remove(&gamepad).is_some() {
+            let event = DeviceEvent::Disconnected(InputDevice::Gamepad(gamepad));
+            self.device_events.push(event);
+        }
+    }
+
+    /// Simulate a gamepad button press, connecting the gamepad if needed
+    pub fn press_gamepad_button(&mut self, gamepad: usize, button: GamepadButton) {
+        self.connect_gamepad(gamepad);
+        if let Some(buttons) = self.gamepads.get_mut(&gamepad) {
+            buttons.pressed(button);
+        }
+    }
+
+    /// Simulate a gamepad button release
+    pub fn release_gamepad_button(&mut self, gamepad: usize, button: GamepadButton) {
+        if let Some(buttons) = self.gamepads.get_mut(&gamepad) {
+            buttons.released(button);
+        }
+    }
+
+    /// Whether gamepads are read from the platform by `poll_gamepads`, otherwise they must be
+    /// fed through `press_gamepad_button` and friends, as winit has no gamepad support
+    pub fn gamepads_supported() -> bool {
+        cfg!(target_arch = "wasm32")
+    }
+
+    /// Read the connected gamepads and their buttons, called by the event loop at the start
+    /// of each frame
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn poll_gamepads(&mut self) {}
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn poll_gamepads(&mut self) {
+        // Buttons pressed on each connected gamepad, by index
+        let polled = match Self::read_gamepads() {
+            Ok(polled) => polled,
+            Err(error) => {
+                log::debug!("Unable to read gamepads: {:?}", error);
+                return;
+            }
+        };
+        let disconnected = self
+            .gamepads
+            .keys()
+            .filter(|gamepad| !polled.contains_key(gamepad))
+            .copied()
+            .collect::<Vec<_>>();
+        for gamepad in disconnected {
+            self.disconnect_gamepad(gamepad);
+        }
+        for (gamepad, pressed) in polled {
+            self.connect_gamepad(gamepad);
+            for button in GamepadButton::ALL {
+                let held = self.gamepad_button_pressed(gamepad, button);
+                if pressed.contains(&button) && !held {
+                    self.press_gamepad_button(gamepad, button);
+                } else if !pressed.contains(&button) && held {
+                    self.release_gamepad_button(gamepad, button);
+                }
+            }
+        }
+    }
+
+    /// Accessed dynamically as web-sys's gamepad API requires its own set of features
+    #[cfg(target_arch = "wasm32")]
+    fn read_gamepads() -> Result<HashMap<usize, HashSet<GamepadButton>>, wasm_bindgen::JsValue> {
+        use js_sys::Reflect;
+        use wasm_bindgen::JsCast;
+
+        let window = web_sys::window().ok_or("No window available")?;
+        let navigator = Reflect::get(&window, &"navigator".into())?;
+        let get_gamepads: js_sys::Function =
+            Reflect::get(&navigator, &"getGamepads".into())?.dyn_into()?;
+        // Older browsers return an array-like GamepadList rather than an array
+        let gamepads = js_sys::Array::from(&get_gamepads.call0(&navigator)?);
+        let mut polled = HashMap::new();
+        for (index, gamepad) in gamepads.iter().enumerate() {
+            if gamepad.is_null()
+                || gamepad.is_undefined()
+                || Reflect::get(&gamepad, &"connected".into())?.as_bool() == Some(false)
+            {
+                continue;
+            }
+            let buttons = Reflect::get(&gamepad, &"buttons".into())?;
+            let pressed = GamepadButton::ALL
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| {
+                    Reflect::get_u32(&buttons, *i as u32)
+                        .and_then(|button| Reflect::get(&button, &"pressed".into()))
+                        .is_ok_and(|pressed| pressed.as_bool() == Some(true))
+                })
+                .map(|(_, button)| button)
+                .collect();
+            polled.insert(index, pressed);
+        }
+        Ok(polled)
+    }
 }
 
 impl Default for InputState {
@@ -186,9 +647,14 @@ impl Default for InputState {
             last_mouse_position: PhysicalPosition { x: 0.0, y: 0.0 },
             mouse_delta: Vec2::ZERO,
             key_map: InputMap::new(),
+            logical_key_map: InputMap::new(),
+            logical_keys: HashMap::new(),
             mouse_button_map: InputMap::new(),
             pixel_scroll_ratio: 1.0,
             mouse_scroll_delta: Vec2::ZERO,
+            gamepads: HashMap::new(),
+            device_events: Vec::new(),
+            players: Vec::new(),
         }
     }
 }
@@ -0,0 +1,68 @@
+use glam::*;
+
+/// Maps between grid coordinates and world positions for isometric, dimetric and other
+/// skewed tile layouts
+///
+/// Each axis is the world space offset for a step of one cell along the grid axis, the
+/// z component can be used to depth sort rows for 2D sprites.
+#[derive(Debug, Clone, Copy)]
+pub struct IsometricGrid {
+    pub origin: Vec3,
+    pub x_axis: Vec3,
+    pub y_axis: Vec3,
+}
+
+impl IsometricGrid {
+    pub fn new(origin: Vec3, x_axis: Vec3, y_axis: Vec3) -> Self {
+        Self {
+            origin,
+            x_axis,
+            y_axis,
+        }
+    }
+
+    /// Diamond layout for tiles of the given size in world units, e.g. 64 x 32 for 2:1
+    /// pixel art, grid x runs down-right and grid y runs up-right on screen
+    ///
+    /// Rows further up the screen are pushed back by `depth_step` so sprites overlap correctly
+    pub fn diamond(tile_width: f32, tile_height: f32, depth_step: f32) -> Self {
+        let half_width = 0.5 * tile_width;
+        let half_height = 0.5 * tile_height;
+        Self::new(
+            Vec3::ZERO,
+            Vec3::new(half_width, -half_height, depth_step),
+            Vec3::new(half_width, half_height, -depth_step),
+        )
+    }
+
+    pub fn with_origin(mut self, origin: Vec3) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// World position of the center of the cell
+    pub fn grid_to_world(&self, position: IVec2) -> Vec3 {
+        self.grid_to_world_f32(position.as_vec2())
+    }
+
+    /// World position of a fractional grid position, for movement between cells
+    pub fn grid_to_world_f32(&self, position: Vec2) -> Vec3 {
+        self.origin + position.x * self.x_axis + position.y * self.y_axis
+    }
+
+    /// Fractional grid position under the world position, ignoring depth
+    /// Returns None if the axes are parallel on screen
+    pub fn world_to_grid(&self, position: Vec3) -> Option<Vec2> {
+        let basis = Mat2::from_cols(self.x_axis.truncate(), self.y_axis.truncate());
+        if basis.determinant().abs() < f32::EPSILON {
+            return None;
+        }
+        Some(basis.inverse() * (position - self.origin).truncate())
+    }
+
+    /// Cell containing the world position, ignoring depth
+    pub fn world_to_cell(&self, position: Vec3) -> Option<IVec2> {
+        self.world_to_grid(position)
+            .map(|position| position.round().as_ivec2())
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::*;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+/// Texture in a KTX2 container, holding either block compressed or RGBA8 data per mip
+/// level, see `Texture::from_ktx2_bytes`
+///
+/// Only single 2D images are supported, Basis Universal and zstd supercompressed files are
+/// rejected, write them with e.g. `ktx create --format BC7_SRGB_BLOCK`.
+#[derive(Debug, Clone)]
+pub struct Ktx2<'a> {
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    /// Largest first
+    levels: Vec<&'a [u8]>,
+}
+
+impl<'a> Ktx2<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE || bytes[..12] != IDENTIFIER {
+            bail!("Not a KTX2 file");
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        let vk_format = u32_at(12);
+        let format = texture_format(vk_format)
+            .ok_or_else(|| anyhow!("Unsupported KTX2 Vulkan format {}", vk_format))?;
+        let (width, height, depth) = (u32_at(20), u32_at(24), u32_at(28));
+        let (layer_count, face_count, level_count) = (u32_at(32), u32_at(36), u32_at(40));
+        let supercompression = u32_at(44);
+        if width == 0 || height == 0 || depth != 0 {
+            bail!("Only 2D KTX2 textures are supported");
+        }
+        if layer_count > 1 || face_count != 1 {
+            bail!("KTX2 arrays and cubemaps are not supported");
+        }
+        if supercompression != 0 {
+            bail!(
+                "KTX2 supercompression scheme {} is not supported",
+                supercompression
+            );
+        }
+        let (block_width, block_height) = format.block_dimensions();
+        if width % block_width != 0 || height % block_height != 0 {
+            bail!(
+                "KTX2 texture size {}x{} is not a multiple of the {}x{} block size",
+                width,
+                height,
+                block_width,
+                block_height
+            );
+        }
+
+        // Zero asks the loader to generate mipmaps, only the base level is stored
+        let level_count = level_count.max(1) as usize;
+        let index_end = HEADER_SIZE + level_count * LEVEL_INDEX_ENTRY_SIZE;
+        if bytes.len() < index_end {
+            bail!("KTX2 level index is truncated");
+        }
+        let levels = (0..level_count)
+            .map(|level| {
+                let entry = HEADER_SIZE + level * LEVEL_INDEX_ENTRY_SIZE;
+                let (offset, length) = (u64_at(entry) as usize, u64_at(entry + 8) as usize);
+                bytes
+                    .get(offset..offset.saturating_add(length))
+                    .ok_or_else(|| anyhow!("KTX2 level {} is out of bounds", level))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    pub fn level(&self, level: u32) -> Option<&'a [u8]> {
+        self.levels.get(level as usize).copied()
+    }
+
+    /// Device features needed to upload the data as is, e.g. `TEXTURE_COMPRESSION_BC`
+    pub fn required_features(&self) -> wgpu::Features {
+        self.format.required_features()
+    }
+
+    /// Decode the base level to RGBA8, for devices without support for the compression,
+    /// only BC1 to BC5 and uncompressed data can be decoded
+    pub fn decompress(&self) -> Result<image::RgbaImage> {
+        use wgpu::TextureFormat::*;
+        let data = self.levels[0];
+        let decode: fn(&[u8]) -> [[u8; 4]; 16] = match self.format {
+            Rgba8Unorm | Rgba8UnormSrgb => {
+                let length = (4 * self.width * self.height) as usize;
+                let pixels = data
+                    .get(..length)
+                    .ok_or_else(|| anyhow!("KTX2 level 0 is truncated"))?;
+                return image::RgbaImage::from_raw(self.width, self.height, pixels.to_vec())
+                    .ok_or_else(|| anyhow!("KTX2 level 0 is truncated"));
+            }
+            Bc1RgbaUnorm | Bc1RgbaUnormSrgb => |block| decode_color(block, true),
+            Bc2RgbaUnorm | Bc2RgbaUnormSrgb => |block| {
+                let mut texels = decode_color(&block[8..], false);
+                for (i, texel) in texels.iter_mut().enumerate() {
+                    let alpha = (block[i / 2] >> (4 * (i % 2))) & 0xF;
+                    texel[3] = alpha * 17;
+                }
+                texels
+            },
+            Bc3RgbaUnorm | Bc3RgbaUnormSrgb => |block| {
+                let mut texels = decode_color(&block[8..], false);
+                for (texel, alpha) in texels.iter_mut().zip(decode_alpha(block)) {
+                    texel[3] = alpha;
+                }
+                texels
+            },
+            Bc4RUnorm => |block| decode_alpha(block).map(|red| [red, 0, 0, 255]),
+            Bc5RgUnorm => |block| {
+                let (red, green) = (decode_alpha(block), decode_alpha(&block[8..]));
+                std::array::from_fn(|i| [red[i], green[i], 0, 255])
+            },
+            format => bail!("No fallback decoder for {:?}", format),
+        };
+
+        let block_size = self.format.block_copy_size(None).unwrap_or(16) as usize;
+        let blocks_wide = self.width.div_ceil(4) as usize;
+        let blocks_high = self.height.div_ceil(4) as usize;
+        if data.len() < blocks_wide * blocks_high * block_size {
+            bail!("KTX2 level 0 is truncated");
+        }
+        let mut image = image::RgbaImage::new(self.width, self.height);
+        for (index, block) in data
+            .chunks_exact(block_size)
+            .take(blocks_wide * blocks_high)
+            .enumerate()
+        {
+            let (block_x, block_y) = (
+                (index % blocks_wide) as u32 * 4,
+                (index / blocks_wide) as u32 * 4,
+            );
+            for (i, texel) in decode(block).into_iter().enumerate() {
+                let (x, y) = (block_x + i as u32 % 4, block_y + i as u32 / 4);
+                if x < self.width && y < self.height {
+                    image.put_pixel(x, y, image::Rgba(texel));
+                }
+            }
+        }
+        Ok(image)
+    }
+}
+
+/// BC1 color block, `punch_through` enables the three color and transparent mode used when
+/// the first endpoint isn't greater, BC2 and BC3 always use four colors
+fn decode_color(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+    let expand = |color: u16| {
+        let (r, g, b) = ((color >> 11) & 0x1F, (color >> 5) & 0x3F, color & 0x1F);
+        [
+            (r << 3 | r >> 2) as u8,
+            (g << 2 | g >> 4) as u8,
+            (b << 3 | b >> 2) as u8,
+        ]
+    };
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (expand(c0), expand(c1));
+    let mix = |a: u8, b: u8, wa: u16, wb: u16| ((a as u16 * wa + b as u16 * wb) / (wa + wb)) as u8;
+    let mut palette = [[0u8; 4]; 4];
+    palette[0] = [e0[0], e0[1], e0[2], 255];
+    palette[1] = [e1[0], e1[1], e1[2], 255];
+    if c0 > c1 || !punch_through {
+        palette[2] = std::array::from_fn(|c| if c == 3 { 255 } else { mix(e0[c], e1[c], 2, 1) });
+        palette[3] = std::array::from_fn(|c| if c == 3 { 255 } else { mix(e0[c], e1[c], 1, 2) });
+    } else {
+        palette[2] = std::array::from_fn(|c| if c == 3 { 255 } else { mix(e0[c], e1[c], 1, 1) });
+        palette[3] = [0, 0, 0, 0];
+    }
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|i| palette[(indices >> (2 * i) & 0x3) as usize])
+}
+
+/// BC3 alpha or BC4 and BC5 channel block, eight values interpolated between two endpoints
+fn decode_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u32) * a0 + i as u32 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u32) * a0 + i as u32 * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    std::array::from_fn(|i| palette[(indices >> (3 * i) & 0x7) as usize])
+}
+
+/// Texture format of a Vulkan format enum value, only formats wgpu can sample are mapped
+fn texture_format(vk_format: u32) -> Option<wgpu::TextureFormat> {
+    use wgpu::{AstcBlock, AstcChannel, TextureFormat::*};
+    const ASTC_BLOCKS: [AstcBlock; 14] = [
+        AstcBlock::B4x4,
+        AstcBlock::B5x4,
+        AstcBlock::B5x5,
+        AstcBlock::B6x5,
+        AstcBlock::B6x6,
+        AstcBlock::B8x5,
+        AstcBlock::B8x6,
+        AstcBlock::B8x8,
+        AstcBlock::B10x5,
+        AstcBlock::B10x6,
+        AstcBlock::B10x8,
+        AstcBlock::B10x10,
+        AstcBlock::B12x10,
+        AstcBlock::B12x12,
+    ];
+    let format = match vk_format {
+        37 => Rgba8Unorm,
+        43 => Rgba8UnormSrgb,
+        // BC1 without alpha is decoded the same, with alpha always one
+        131 | 133 => Bc1RgbaUnorm,
+        132 | 134 => Bc1RgbaUnormSrgb,
+        135 => Bc2RgbaUnorm,
+        136 => Bc2RgbaUnormSrgb,
+        137 => Bc3RgbaUnorm,
+        138 => Bc3RgbaUnormSrgb,
+        139 => Bc4RUnorm,
+        140 => Bc4RSnorm,
+        141 => Bc5RgUnorm,
+        142 => Bc5RgSnorm,
+        143 => Bc6hRgbUfloat,
+        144 => Bc6hRgbFloat,
+        145 => Bc7RgbaUnorm,
+        146 => Bc7RgbaUnormSrgb,
+        147 => Etc2Rgb8Unorm,
+        148 => Etc2Rgb8UnormSrgb,
+        149 => Etc2Rgb8A1Unorm,
+        150 => Etc2Rgb8A1UnormSrgb,
+        151 => Etc2Rgba8Unorm,
+        152 => Etc2Rgba8UnormSrgb,
+        153 => EacR11Unorm,
+        154 => EacR11Snorm,
+        155 => EacRg11Unorm,
+        156 => EacRg11Snorm,
+        // Pairs of unorm and sRGB for each block size
+        157..=184 => {
+            let index = (vk_format - 157) as usize;
+            Astc {
+                block: ASTC_BLOCKS[index / 2],
+                channel: [AstcChannel::Unorm, AstcChannel::UnormSrgb][index % 2],
+            }
+        }
+        _ => return None,
+    };
+    Some(format)
+}
@@ -1,52 +1,130 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
-use entity::{EntityDrawInstruction, RenderProperties};
+use entity::{EntityBuffer, EntityDrawInstruction, RenderProperties};
 use glam::*;
-use slotmap::SlotMap;
 use wgpu::InstanceDescriptor;
 use winit::{
-    application::ApplicationHandler, dpi::PhysicalSize, event::*, event_loop::{EventLoop, EventLoopProxy}, keyboard::{KeyCode, PhysicalKey}, window::Window
+    application::ApplicationHandler, dpi::PhysicalSize, event::*, event_loop::{EventLoop, EventLoopProxy}, keyboard::{KeyCode, PhysicalKey}, window::{Window, WindowAttributes}
 };
 
+use assets::{AssetLoader, LoadHandle, LoadingScreen};
+use draw_report::{BindCounts, DrawReport, DropReason, DroppedDraw, MAX_LOGGED_DROPS};
+use frustum::Frustum;
 use material::*;
+use resolution::{AdaptiveResolution, ResolutionScaling};
+#[cfg(feature = "post-processing")]
+use color_grading::{ColorGrading, ColorLut};
+use resource_map::ResourceMap;
 use mesh::*;
 use shader::*;
+use shader_descriptor::{EntityUniformLayout, UniformField};
+use shader_library::ShaderLibrary;
 use texture::*;
 
 pub type Color = wgpu::Color;
 
+pub mod assets;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod bounds;
+pub mod checkpoint;
+pub mod crowd;
+pub mod debug_draw;
+pub mod drag;
+pub mod draw_report;
 pub mod entity;
+pub mod entity_effects;
+pub mod fixed_point;
+pub mod frame_stats;
+pub mod frustum;
 pub mod game_object;
 pub mod input;
+pub mod isometric;
+pub mod ktx2;
+pub mod lighting;
+pub mod localization;
+pub mod parallax;
+pub mod picking;
+pub mod pool;
 pub mod prefab;
+pub mod property_animation;
+pub mod render_target;
+pub mod resolution;
+pub mod resource_map;
+pub mod rumble;
 pub mod scene;
+pub mod scene_file;
+pub mod scene_validation;
+pub mod screenshot;
+pub mod shapes;
+pub mod snapshot;
+pub mod stats;
+pub mod streaming;
+pub mod taskbar;
+pub mod tasks;
 pub mod time;
 pub mod transform;
 pub mod transform_hierarchy;
+pub mod window_chrome;
+pub mod work_queue;
 
 pub mod orbit_camera;
 
 pub mod atlas;
+pub mod bindings;
+#[cfg(feature = "post-processing")]
+pub mod bloom;
 pub mod camera;
+pub mod camera_follow;
+pub mod distance_field;
+#[cfg(feature = "post-processing")]
+pub mod color_grading;
 pub mod material;
 pub mod mesh;
+pub mod mesh_pool;
+pub mod mipmaps;
+pub mod msaa;
 pub mod shader;
+pub mod shader_descriptor;
+pub mod shader_library;
+pub mod skybox;
 pub mod texture;
 
 pub struct Resources {
-    pub meshes: SlotMap<MeshId, Mesh>,
-    pub materials: SlotMap<MaterialId, Material>,
-    pub shaders: SlotMap<ShaderId, Shader>,
-    pub textures: SlotMap<TextureId, Texture>,
+    pub meshes: ResourceMap<MeshId, Mesh>,
+    pub materials: ResourceMap<MaterialId, Material>,
+    pub shaders: ResourceMap<ShaderId, Shader>,
+    pub textures: ResourceMap<TextureId, Texture>,
+    /// Cameras draw commands can switch to with `DrawCommand::SetCamera`
+    pub cameras: ResourceMap<camera::CameraId, camera::Camera>,
+    /// Lights used by lit shaders, written to the light uniform each frame
+    pub lights: ResourceMap<lighting::LightId, lighting::Light>,
 }
 
 impl Resources {
     pub fn new() -> Self {
         Self {
-            meshes: SlotMap::with_key(),
-            materials: SlotMap::with_key(),
-            shaders: SlotMap::with_key(),
-            textures: SlotMap::with_key(),
+            meshes: ResourceMap::new(),
+            materials: ResourceMap::new(),
+            shaders: ResourceMap::new(),
+            textures: ResourceMap::new(),
+            cameras: ResourceMap::new(),
+            lights: ResourceMap::new(),
+        }
+    }
+
+    /// Human readable reason a draw was dropped, including debug names where still available
+    pub fn describe_drop_reason(&self, reason: &DropReason) -> String {
+        match reason {
+            DropReason::MissingShader(material, shader) => format!(
+                "shader {:?} used by material {} does not exist",
+                shader,
+                self.materials.describe(*material)
+            ),
+            _ => reason.to_string(),
         }
     }
 }
@@ -54,44 +132,219 @@ impl Resources {
 pub struct BuildInShaders {
     pub unlit_textured: ShaderId,
     pub sprite: ShaderId,
+    /// Sprite shader which draws the entity's outline color around opaque texels,
+    /// sprites need transparent padding for the outline to extend into
+    pub sprite_outline: ShaderId,
+    /// Text from signed distance field atlases, sharp at any scale, with outline and glow,
+    /// the material's texture should be created with `Texture::generate_distance_field`,
+    /// glow is set on the material with `SdfTextUniforms`
+    pub sdf_text: ShaderId,
+    pub overlay: ShaderId,
+    /// Unlit textured shaded by the lights in `Resources::lights`
+    pub lit_textured: ShaderId,
+    /// Alpha blended sprite sampling the layer of a texture array set by the entity's
+    /// `texture_layer`, the material's texture should be created with
+    /// `Texture::array_from_images`, so different sprites batch without an atlas
+    pub sprite_array: ShaderId,
+}
+
+/// Where frames are rendered to
+enum RenderOutput {
+    /// Presented to the window
+    Surface(wgpu::Surface<'static>),
+    /// Rendered to a texture, with no window to present to, see `Helia::run_headless`
+    Offscreen(Arc<wgpu::Texture>),
+}
+
+impl RenderOutput {
+    /// Format and usage of textures rendered to when running headless, readable by screenshots
+    const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+    const OFFSCREEN_USAGE: wgpu::TextureUsages =
+        wgpu::TextureUsages::RENDER_ATTACHMENT.union(wgpu::TextureUsages::COPY_SRC);
+
+    fn create_offscreen(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        Self::Offscreen(Arc::new(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Output"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        })))
+    }
+
+    /// Reconfigure the surface, or recreate the offscreen texture at the new size
+    fn configure(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        match self {
+            RenderOutput::Surface(surface) => surface.configure(device, config),
+            RenderOutput::Offscreen(texture) => {
+                if (texture.width(), texture.height()) != (config.width, config.height) {
+                    *self = Self::create_offscreen(device, config);
+                }
+            }
+        }
+    }
+
+    fn current_texture(&self) -> Result<FrameTexture, wgpu::SurfaceError> {
+        match self {
+            RenderOutput::Surface(surface) => {
+                surface.get_current_texture().map(FrameTexture::Surface)
+            }
+            RenderOutput::Offscreen(texture) => Ok(FrameTexture::Offscreen(texture.clone())),
+        }
+    }
+}
+
+/// Texture a frame is rendered to
+enum FrameTexture {
+    Surface(wgpu::SurfaceTexture),
+    Offscreen(Arc<wgpu::Texture>),
+}
+
+impl FrameTexture {
+    fn texture(&self) -> &wgpu::Texture {
+        match self {
+            FrameTexture::Surface(output) => &output.texture,
+            FrameTexture::Offscreen(texture) => texture,
+        }
+    }
+
+    fn present(self) {
+        if let FrameTexture::Surface(output) = self {
+            output.present();
+        }
+    }
 }
 
 pub struct State {
     pub camera: camera::Camera,
     pub time: time::Time,
-    surface: wgpu::Surface<'static>,
+    output: RenderOutput,
+    /// Kept to re-query the surface's capabilities, see `refresh_surface_format`
+    adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    present_modes: Vec<wgpu::PresentMode>,
     pub size: winit::dpi::PhysicalSize<u32>,
     depth_texture: texture::Texture,
     pub input: input::InputState,
+    pub assets: AssetLoader,
     pub resources: Resources,
+    /// Shared buffers for creating many small meshes, e.g. sprites and UI
+    pub mesh_pool: mesh_pool::MeshPool,
+    /// Camera and entity uniforms shared by all shaders
+    pub bindings: bindings::SharedBindings,
     pub shaders: BuildInShaders,
+    /// Modules available to `#include` when composing shader source
+    pub shader_library: ShaderLibrary,
+    /// Panic on draw commands referencing removed resources in debug builds,
+    /// rather than skipping them with a warning
+    pub strict_draw_validation: bool,
+    /// Skip draws whose mesh bounds are outside the view of the camera they're drawn with,
+    /// disable for shaders which move vertices beyond the mesh's bounds
+    pub frustum_culling: bool,
+    /// Group opaque draws by shader, material and mesh to minimise rebinding, disable to
+    /// compare `DrawReport::binds` against drawing in submission order
+    pub sort_draws: bool,
+    /// Wrap passes and batches of draws in debug groups named after their shaders and
+    /// materials, for navigating graphics captures, on by default in debug builds
+    pub debug_groups: bool,
+    /// Drag and button regions for custom title bars on undecorated windows
+    pub window_chrome: window_chrome::WindowChrome,
+    /// How `camera` is updated when the window is resized, before `Game::resize` is called
+    pub aspect_policy: camera::AspectPolicy,
+    /// Frame time history and spike detection
+    pub frame_stats: frame_stats::FrameStats,
+    /// Debug lines drawn over the scene for the current frame
+    pub debug: debug_draw::DebugDraw,
+    /// Flat colored 2D shapes drawn over the scene for the current frame
+    pub shapes: shapes::ShapeDraw,
+    /// Instanced animated crowds, drawn after the scene's draw commands
+    pub crowds: crowd::CrowdRenderer,
+    /// Async tasks, results are received at the start of each frame
+    pub tasks: tasks::Tasks,
+    /// Localized strings for the current language
+    pub localization: localization::Localization,
+    /// Incremental tasks run each frame within a time budget
+    pub work_queue: work_queue::WorkQueue,
+    /// Pan and attenuation of sound emitters relative to the listener
+    #[cfg(feature = "audio")]
+    pub audio: audio::SpatialAudio,
+    /// Bus volumes, ducking and music crossfades
+    #[cfg(feature = "audio")]
+    pub mixer: audio::AudioMixer,
+    /// Gamepad haptics, requests are sent at the end of each update
+    pub rumble: rumble::Rumble,
+    /// Taskbar progress and badge, sent at the end of each update
+    pub taskbar: taskbar::Taskbar,
+    capture_next_frame: bool,
+    screenshot_requested: bool,
+    screenshot: Option<screenshot::Screenshot>,
+    exit_requested: bool,
+    resolution: Option<AdaptiveResolution>,
+    #[cfg(feature = "post-processing")]
+    color_grading: Option<ColorGrading>,
+    #[cfg(feature = "post-processing")]
+    bloom: Option<bloom::Bloom>,
+    msaa: Option<msaa::Multisampling>,
+    debug_view: DebugView,
+    lighting: lighting::LightBindGroup,
+    skybox: skybox::Skybox,
+    draw_report: DrawReport,
+    /// Collected when enabled with `enable_stats`
+    stats: Option<stats::Stats>,
+    gpu_timer: Option<stats::GpuTimer>,
+    /// Buffers for preparing draws, kept between frames to avoid reallocating them
+    frame_arena: FrameArena,
+    /// Drop reasons already logged, up to `MAX_LOGGED_DROPS`
+    logged_drop_reasons: HashSet<DropReason>,
     texture_bind_group_layout: wgpu::BindGroupLayout,
-    pub window: Arc<Window>,
+    /// None when running headless, see `Helia::run_headless`
+    pub window: Option<Arc<Window>>,
 }
 
 impl State {
     // Creating some of the wgpu types requires async code
-    async fn new(window: Arc<Window>, size: PhysicalSize<u32>) -> Self {
+    // Without a window frames are rendered to an offscreen texture
+    async fn new(
+        window: Option<Arc<Window>>,
+        size: PhysicalSize<u32>,
+        settings: RendererSettings,
+    ) -> anyhow::Result<Self> {
         // The instance is a handle to our GPU
         let instance = wgpu::util::new_instance_with_webgpu_detection(InstanceDescriptor::default()).await;
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = window
+            .clone()
+            .map(|window| instance.create_surface(window))
+            .transpose()?;
         log::info!("{:?}", surface);
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
+                compatible_surface: surface.as_ref(),
                 force_fallback_adapter: false,
             })
             .await
-            .unwrap();
+            .ok_or_else(|| anyhow::anyhow!("No compatible graphics adapter found"))?;
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
+                    // Optional, for `DebugView::Wireframe`, `Texture::from_ktx2_bytes` and GPU
+                    // times in `State::stats`
+                    required_features: adapter.features()
+                        & (wgpu::Features::POLYGON_MODE_LINE
+                            | wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                            | wgpu::Features::TEXTURE_COMPRESSION_ASTC
+                            | stats::GpuTimer::FEATURES),
                     // WebGL doesn't support all of wgpu's features, so if
                     // we're building for the web we'll have to disable some.
                     required_limits: if cfg!(target_arch = "wasm32") {
@@ -102,23 +355,46 @@ impl State {
                     label: None,
                     memory_hints: wgpu::MemoryHints::Performance,
                 },
-                None, // Trace path
+                settings.trace_path.as_deref(),
             )
-            .await
-            .unwrap();
-
+            .await?;
+        // Not supported by WebGL, pooled meshes then offset their indices instead
+        let base_vertex = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::BASE_VERTEX);
+
+        let (format, usage, present_modes) = match &surface {
+            Some(surface) => {
+                let capabilities = surface.get_capabilities(&adapter);
+                // Copying from the surface allows screenshots, see `request_screenshot`
+                let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | (capabilities.usages & wgpu::TextureUsages::COPY_SRC);
+                (capabilities.formats[0], usage, capabilities.present_modes)
+            }
+            None => (RenderOutput::OFFSCREEN_FORMAT, RenderOutput::OFFSCREEN_USAGE, Vec::new()),
+        };
+        let present_mode = Self::supported_present_mode(
+            settings.present_mode.unwrap_or(wgpu::PresentMode::AutoNoVsync),
+            &present_modes,
+        );
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_capabilities(&adapter).formats[0],
+            usage,
+            format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::AutoNoVsync, // May want to auto v-sync
+            present_mode,
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![],
-            desired_maximum_frame_latency: 1, // 2 is default
+            desired_maximum_frame_latency: settings.frame_latency.unwrap_or(1), // 2 is default
+        };
+        let output = match surface {
+            Some(surface) => {
+                surface.configure(&device, &config);
+                RenderOutput::Surface(surface)
+            }
+            None => RenderOutput::create_offscreen(&device, &config),
         };
-        // can find valid present modes via: surface.get_supported_modes(&adapter);
-        surface.configure(&device, &config);
 
         let mut resources = Resources::new();
 
@@ -127,48 +403,222 @@ impl State {
             texture::Texture::create_depth_texture(&device, &config, "depth_texture");
 
         let texture_bind_group_layout = Material::create_bind_group_layout(&device);
+        let mut bindings = bindings::SharedBindings::new(&device);
 
         // Makin' shaders
+        let mut shader_library = ShaderLibrary::with_builtins();
+        if bindings.storage_supported() {
+            shader_library.enable_entity_storage();
+        }
+        let unlit_textured_source = include_str!("shaders/unlit_textured.wgsl");
+        let unlit_textured_descriptor = || {
+            shader_library
+                .descriptor("unlit_textured.wgsl", unlit_textured_source)
+                .expect("Built-in shader includes should resolve")
+        };
+
         let shader = Shader::new(
             &device,
-            wgpu::include_wgsl!("shaders/unlit_textured.wgsl"),
+            &mut bindings,
+            unlit_textured_descriptor(),
             config.format,
             &texture_bind_group_layout,
-            false,
+            BlendMode::Opaque,
+            wgpu::CompareFunction::Less,
             std::mem::size_of::<EntityUniforms>(),
             EntityUniforms::write_bytes,
         );
-        let unlit_textured = resources.shaders.insert(shader);
+        let unlit_textured = resources.shaders.insert_named(shader, "unlit_textured");
 
         let sprite_shader = Shader::new(
             &device,
-            wgpu::include_wgsl!("shaders/unlit_textured.wgsl"),
+            &mut bindings,
+            unlit_textured_descriptor(),
             config.format,
             &texture_bind_group_layout,
-            true,
+            BlendMode::Alpha,
+            wgpu::CompareFunction::Less,
             std::mem::size_of::<EntityUniforms>(),
             EntityUniforms::write_bytes,
         );
-        let sprite = resources.shaders.insert(sprite_shader);
+        let sprite = resources.shaders.insert_named(sprite_shader, "sprite");
 
-        Self {
+        let sprite_outline_shader = Shader::new(
+            &device,
+            &mut bindings,
+            shader_library
+                .descriptor(
+                    "sprite_outline.wgsl",
+                    include_str!("shaders/sprite_outline.wgsl"),
+                )
+                .expect("Built-in shader includes should resolve"),
+            config.format,
+            &texture_bind_group_layout,
+            BlendMode::Alpha,
+            wgpu::CompareFunction::Less,
+            std::mem::size_of::<OutlineEntityUniforms>(),
+            OutlineEntityUniforms::write_bytes,
+        );
+        let sprite_outline = resources
+            .shaders
+            .insert_named(sprite_outline_shader, "sprite_outline");
+
+        let sdf_text_shader = Shader::with_layout(
+            &device,
+            &mut bindings,
+            shader_library
+                .descriptor("sdf_text.wgsl", include_str!("shaders/sdf_text.wgsl"))
+                .expect("Built-in shader includes should resolve"),
+            config.format,
+            &texture_bind_group_layout,
+            None,
+            PipelineOptions::new(BlendMode::Alpha, wgpu::CompareFunction::Less),
+            EntityUniformLayout::new(&[
+                UniformField::Model,
+                UniformField::Color,
+                UniformField::UvOffset,
+                UniformField::UvScale,
+                UniformField::OutlineColor,
+                UniformField::OutlineThickness,
+            ]),
+            MaterialTextureLayout::single(),
+            std::mem::size_of::<SdfTextUniforms>(),
+        );
+        let sdf_text = resources.shaders.insert_named(sdf_text_shader, "sdf_text");
+
+        // Alpha blended and ignores depth, for handles and debug visuals drawn over the scene
+        let overlay_shader = Shader::new(
+            &device,
+            &mut bindings,
+            unlit_textured_descriptor(),
+            config.format,
+            &texture_bind_group_layout,
+            BlendMode::Alpha,
+            wgpu::CompareFunction::Always,
+            std::mem::size_of::<EntityUniforms>(),
+            EntityUniforms::write_bytes,
+        );
+        let overlay = resources.shaders.insert_named(overlay_shader, "overlay");
+
+        let lighting = lighting::LightBindGroup::new(&device);
+        let lit_textured_shader = Shader::with_layout(
+            &device,
+            &mut bindings,
+            shader_library
+                .descriptor("lit_textured.wgsl", include_str!("shaders/lit_textured.wgsl"))
+                .expect("Built-in shader includes should resolve"),
+            config.format,
+            &texture_bind_group_layout,
+            Some(&lighting.layout),
+            PipelineOptions::default(),
+            EntityUniformLayout::standard(),
+            MaterialTextureLayout::single(),
+            0,
+        );
+        let lit_textured = resources
+            .shaders
+            .insert_named(lit_textured_shader, "lit_textured");
+
+        let sprite_array_shader = Shader::with_texture_array(
+            &device,
+            &mut bindings,
+            shader_library
+                .descriptor("sprite_array.wgsl", include_str!("shaders/sprite_array.wgsl"))
+                .expect("Built-in shader includes should resolve"),
+            config.format,
+            PipelineOptions::new(BlendMode::Alpha, wgpu::CompareFunction::Less),
+            std::mem::size_of::<ArrayEntityUniforms>(),
+            ArrayEntityUniforms::write_bytes,
+        );
+        let sprite_array = resources
+            .shaders
+            .insert_named(sprite_array_shader, "sprite_array");
+
+        let resolution = settings
+            .resolution_scaling
+            .map(|scaling| AdaptiveResolution::new(&device, config.format, scaling));
+
+        let debug = debug_draw::DebugDraw::new(&device, &shader_library, config.format);
+        let shapes = shapes::ShapeDraw::new(&device, &shader_library, config.format);
+        let crowds =
+            crowd::CrowdRenderer::new(&device, &shader_library, &texture_bind_group_layout);
+        let skybox = skybox::Skybox::new(&device, &shader_library);
+
+        let msaa = settings.msaa_samples.and_then(|requested| {
+            let samples =
+                msaa::Multisampling::supported_sample_count(&adapter, config.format, requested);
+            if samples != requested {
+                log::warn!("{}x MSAA is not supported, using {}x", requested, samples);
+            }
+            (samples > 1).then(|| msaa::Multisampling::new(samples))
+        });
+
+        Ok(Self {
             camera: camera::Camera::default(),
             time: time::Time::default(),
-            surface,
+            output,
+            adapter,
             device,
             queue,
             config,
+            present_modes,
             size,
             depth_texture,
             texture_bind_group_layout,
             resources,
+            mesh_pool: mesh_pool::MeshPool::default().with_base_vertex(base_vertex),
+            bindings,
             input: input::InputState::default(),
+            assets: AssetLoader::new(),
+            shader_library,
+            strict_draw_validation: false,
+            frustum_culling: true,
+            sort_draws: true,
+            debug_groups: cfg!(debug_assertions),
+            window_chrome: window_chrome::WindowChrome::default(),
+            aspect_policy: camera::AspectPolicy::default(),
+            frame_stats: frame_stats::FrameStats::default(),
+            debug,
+            shapes,
+            crowds,
+            tasks: tasks::Tasks::new(),
+            localization: localization::Localization::new(),
+            work_queue: work_queue::WorkQueue::default(),
+            #[cfg(feature = "audio")]
+            audio: audio::SpatialAudio::default(),
+            #[cfg(feature = "audio")]
+            mixer: audio::AudioMixer::default(),
+            rumble: rumble::Rumble::default(),
+            taskbar: taskbar::Taskbar::default(),
+            capture_next_frame: false,
+            screenshot_requested: false,
+            screenshot: None,
+            exit_requested: false,
+            resolution,
+            #[cfg(feature = "post-processing")]
+            color_grading: None,
+            #[cfg(feature = "post-processing")]
+            bloom: None,
+            msaa,
+            debug_view: DebugView::Normal,
+            lighting,
+            skybox,
+            draw_report: DrawReport::default(),
+            stats: None,
+            gpu_timer: None,
+            frame_arena: FrameArena::default(),
+            logged_drop_reasons: HashSet::new(),
             shaders: BuildInShaders {
                 unlit_textured,
                 sprite,
+                sprite_outline,
+                sdf_text,
+                overlay,
+                lit_textured,
+                sprite_array,
             },
             window,
-        }
+        })
     }
 
     // HACK: ideally wouldn't have to have an accessor like this, could probably
@@ -178,39 +628,367 @@ impl State {
         &self.texture_bind_group_layout
     }
 
+    /// Borrow what's needed to create resources, e.g. for helpers which would otherwise
+    /// take the whole `State`
+    pub fn graphics(&mut self) -> GraphicsContext<'_> {
+        self.split_graphics().0
+    }
+
+    /// Borrow what's needed to create resources alongside the camera and input, so game code
+    /// can build assets in response to input during update
+    pub fn split_graphics(
+        &mut self,
+    ) -> (GraphicsContext<'_>, &mut camera::Camera, &mut input::InputState) {
+        let graphics = GraphicsContext {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.config.format,
+            texture_bind_group_layout: &self.texture_bind_group_layout,
+            shaders: &self.shaders,
+            resources: &mut self.resources,
+            mesh_pool: &mut self.mesh_pool,
+        };
+        (graphics, &mut self.camera, &mut self.input)
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) -> bool {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            self.output.configure(&self.device, &self.config);
             self.depth_texture =
                 texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+            self.camera.apply_aspect_policy(self.aspect_policy, new_size);
             return true;
         }
         false
     }
 
     fn update(&mut self) {
-        /* Don't think we need to do anything here? */
+        #[cfg(feature = "audio")]
+        self.mixer.update(self.time.elapsed_real_time);
+        self.rumble.flush();
+        if let Some(window) = &self.window {
+            self.taskbar.flush(window);
+        }
+        self.run_work_queue();
+    }
+
+    /// Physical pixels per logical pixel of the window, 1.0 when running headless
+    pub fn scale_factor(&self) -> f32 {
+        self.window
+            .as_ref()
+            .map_or(1.0, |window| window.scale_factor() as f32)
     }
 
-    fn render(&mut self, draw_commands: &Vec<DrawCommand>) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+    /// Whether frames are rendered offscreen rather than to a window, see `Helia::run_headless`
+    pub fn is_headless(&self) -> bool {
+        matches!(self.output, RenderOutput::Offscreen(_))
+    }
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-        
-        let mut entities = Vec::new();
-        let mut entity_count_by_shader = HashMap::<ShaderId, u64>::new();
-        for command in draw_commands.iter() {
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Present modes the surface supports, the automatic modes are always available, empty
+    /// when running headless
+    pub fn supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        &self.present_modes
+    }
+
+    /// Re-query the surface's capabilities and reconfigure it, picking a new format if the
+    /// current one is no longer supported, e.g. after the window moves between SDR and HDR
+    /// monitors. Returns whether the format changed.
+    ///
+    /// Called when the surface is outdated or lost. Built-in pipelines are rebuilt for the
+    /// new format as they are next used.
+    pub fn refresh_surface_format(&mut self) -> bool {
+        let RenderOutput::Surface(surface) = &self.output else {
+            return false;
+        };
+        let capabilities = surface.get_capabilities(&self.adapter);
+        self.present_modes = capabilities.present_modes;
+        self.config.present_mode =
+            Self::supported_present_mode(self.config.present_mode, &self.present_modes);
+        self.config.usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (capabilities.usages & wgpu::TextureUsages::COPY_SRC);
+        let previous = self.config.format;
+        if !capabilities.formats.is_empty() && !capabilities.formats.contains(&previous) {
+            // Prefer keeping the same gamma, so colors are unchanged
+            let format = capabilities
+                .formats
+                .iter()
+                .copied()
+                .find(|format| format.is_srgb() == previous.is_srgb())
+                .unwrap_or(capabilities.formats[0]);
+            log::info!("Surface format changed from {:?} to {:?}", previous, format);
+            self.config.format = format;
+            for shader in self.resources.shaders.values_mut() {
+                shader.clear_pipelines();
+            }
+            if let Some(msaa) = &self.msaa {
+                let samples = msaa::Multisampling::supported_sample_count(
+                    &self.adapter,
+                    format,
+                    msaa.sample_count,
+                );
+                if samples != msaa.sample_count {
+                    log::warn!("{}x MSAA is not supported, using {}x", msaa.sample_count, samples);
+                    self.msaa = (samples > 1).then(|| msaa::Multisampling::new(samples));
+                }
+            }
+        }
+        self.output.configure(&self.device, &self.config);
+        self.config.format != previous
+    }
+
+    /// Reconfigure the surface with the present mode, falling back to the automatic mode
+    /// with the same vsync behaviour if unsupported, returns the mode used
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let present_mode = Self::supported_present_mode(present_mode, &self.present_modes);
+        if present_mode != self.config.present_mode {
+            self.config.present_mode = present_mode;
+            self.output.configure(&self.device, &self.config);
+        }
+        present_mode
+    }
+
+    /// Wait for the display's vertical blank to present frames, capping the frame rate to
+    /// the refresh rate and preventing tearing
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.set_present_mode(if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        });
+    }
+
+    pub fn vsync(&self) -> bool {
+        matches!(
+            self.config.present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed
+        )
+    }
+
+    fn supported_present_mode(
+        requested: wgpu::PresentMode,
+        supported: &[wgpu::PresentMode],
+    ) -> wgpu::PresentMode {
+        match requested {
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => requested,
+            _ if supported.contains(&requested) => requested,
+            // Keep the cap to the refresh rate, the other modes present as soon as possible
+            wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed => {
+                log::warn!("Present mode {:?} is not supported, using vsync", requested);
+                wgpu::PresentMode::AutoVsync
+            }
+            _ => {
+                log::warn!("Present mode {:?} is not supported, using no vsync", requested);
+                wgpu::PresentMode::AutoNoVsync
+            }
+        }
+    }
+
+    /// Capture the next rendered frame with the graphics debugger the app was launched
+    /// from (e.g. RenderDoc on native), no-op if none is attached
+    pub fn trigger_capture(&mut self) {
+        self.capture_next_frame = true;
+    }
+
+    /// Read back the next rendered frame, including anything drawn in `post_render`,
+    /// available from `take_screenshot` once rendered. Blocks until the GPU has finished
+    /// the frame, so intended for tests and tooling rather than every frame.
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    /// Frame captured since the last call, see `request_screenshot`
+    pub fn take_screenshot(&mut self) -> Option<screenshot::Screenshot> {
+        self.screenshot.take()
+    }
+
+    /// Close the app once the current frame has rendered
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    /// Enable or disable dynamic resolution, see `ResolutionScaling`
+    pub fn set_resolution_scaling(&mut self, scaling: Option<ResolutionScaling>) {
+        match (scaling, &mut self.resolution) {
+            (Some(scaling), Some(resolution)) => resolution.settings = scaling,
+            (Some(scaling), None) => {
+                self.resolution = Some(AdaptiveResolution::new(
+                    &self.device,
+                    self.config.format,
+                    scaling,
+                ));
+            }
+            (None, _) => self.resolution = None,
+        }
+    }
+
+    /// Fraction of the surface resolution the scene is currently rendered at
+    pub fn resolution_scale(&self) -> f32 {
+        self.resolution
+            .as_ref()
+            .map_or(1.0, |resolution| resolution.scale().min(1.0))
+    }
+
+    #[cfg(feature = "post-processing")]
+    /// Grade the rendered scene through the LUT, replacing any current LUT, or disable
+    /// color grading if None. UI drawn in `post_render` is not graded.
+    pub fn set_color_lut(&mut self, lut: Option<&ColorLut>) {
+        match (lut, &mut self.color_grading) {
+            (Some(lut), Some(grading)) => grading.set_lut(&self.device, &self.queue, lut),
+            (Some(lut), None) => {
+                self.color_grading = Some(ColorGrading::new(
+                    &self.device,
+                    &self.queue,
+                    &self.shader_library,
+                    self.config.format,
+                    lut,
+                ));
+            }
+            (None, _) => self.color_grading = None,
+        }
+    }
+
+    #[cfg(feature = "post-processing")]
+    /// Blend between the ungraded (0.0) and graded (1.0) scene, no-op if color grading is disabled
+    pub fn set_color_grading_intensity(&mut self, intensity: f32) {
+        if let Some(grading) = &mut self.color_grading {
+            grading.intensity = intensity;
+        }
+    }
+
+    #[cfg(feature = "post-processing")]
+    pub fn color_grading_intensity(&self) -> Option<f32> {
+        self.color_grading.as_ref().map(|grading| grading.intensity)
+    }
+
+    /// Samples per pixel the scene is rendered with, 1 when MSAA is disabled
+    pub fn msaa_sample_count(&self) -> u32 {
+        self.msaa.as_ref().map_or(1, |msaa| msaa.sample_count)
+    }
+
+    /// Switch how meshes are drawn, e.g. to inspect generated geometry, returns false and
+    /// keeps the current view if the device doesn't support it
+    pub fn set_debug_view(&mut self, view: DebugView) -> bool {
+        if view == DebugView::Wireframe && !self.wireframe_supported() {
+            log::warn!("Wireframe view requires a device with POLYGON_MODE_LINE");
+            return false;
+        }
+        self.debug_view = view;
+        true
+    }
+
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    pub fn wireframe_supported(&self) -> bool {
+        self.device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+    }
+
+    /// Report of the draw commands processed in the last rendered frame
+    pub fn draw_report(&self) -> &DrawReport {
+        &self.draw_report
+    }
+
+    /// Start or stop collecting `stats`, GPU pass times are collected if the device supports
+    /// timestamp queries between passes
+    pub fn enable_stats(&mut self, enabled: bool) {
+        if enabled == self.stats.is_some() {
+            return;
+        }
+        self.stats = enabled.then(stats::Stats::default);
+        self.gpu_timer = enabled
+            .then(|| stats::GpuTimer::new(&self.device, &self.queue))
+            .flatten();
+    }
+
+    /// Timings and counts of the last rendered frame, None unless enabled with `enable_stats`
+    pub fn stats(&self) -> Option<&stats::Stats> {
+        self.stats.as_ref()
+    }
+
+    fn validate_draw(
+        &self,
+        mesh: MeshId,
+        material: MaterialId,
+        camera: Option<camera::CameraId>,
+    ) -> Result<ShaderId, DropReason> {
+        if let Some(camera) = camera.filter(|id| !self.resources.cameras.contains_key(*id)) {
+            return Err(DropReason::MissingCamera(camera));
+        }
+        if !self.resources.meshes.contains_key(mesh) {
+            return Err(DropReason::MissingMesh(mesh));
+        }
+        let shader = self
+            .resources
+            .materials
+            .get(material)
+            .ok_or(DropReason::MissingMaterial(material))?
+            .shader;
+        if !self.resources.shaders.contains_key(shader) {
+            return Err(DropReason::MissingShader(material, shader));
+        }
+        Ok(shader)
+    }
+
+    /// Validate draw commands, recording dropped draws in the report, and write their entity
+    /// uniforms ready for `encode_draws`, the draws are prepared into the arena's
+    /// `prepared`, replacing those of a previous call
+    ///
+    /// Uniforms are written from the start of each shader's buffer, so the draws must be
+    /// submitted before preparing any more.
+    /// The frustum is that of the camera draws without a camera set use, None to disable culling,
+    /// and the cull mask that camera's `cull_mask`
+    ///
+    /// Between camera changes draws are sorted by layer then order in layer, then opaque
+    /// draws are grouped by shader, material and mesh unless `sort_draws` is disabled,
+    /// ordered draws keep their submitted order.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare_draws(
+        &mut self,
+        draw_commands: &[DrawCommand],
+        main_frustum: Option<Frustum>,
+        main_cull_mask: u32,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        report: &mut DrawReport,
+        arena: &mut FrameArena,
+    ) {
+        arena.clear();
+        let FrameArena {
+            prepared:
+                PreparedDraws {
+                    entities,
+                    scissor_changes,
+                    camera_changes,
+                    layer_changes,
+                    cameras,
+                    clear_colors,
+                },
+            unsorted_entities,
+            sort_keys,
+            order,
+            entity_count_by_size,
+            layer_clears,
+        } = arena;
+        let mut scissor = None;
+        let mut segment = 0;
+        let mut camera = None;
+        let mut frustum = main_frustum;
+        let mut cull_mask = main_cull_mask;
+        for (index, command) in draw_commands.iter().enumerate() {
             let entity = 
             match command {
                 DrawCommand::Draw(
@@ -222,134 +1000,692 @@ impl State {
                         *material,
                         *properties,
                     ),
+                DrawCommand::SetScissor(rect) => {
+                    scissor = Some(*rect);
+                    continue;
+                }
+                DrawCommand::ClearScissor => {
+                    scissor = None;
+                    continue;
+                }
+                DrawCommand::SetCamera(id) => {
+                    camera = Some(*id);
+                    frustum = main_frustum.and_then(|_| {
+                        self.resources.cameras.get(*id).map(Frustum::from_camera)
+                    });
+                    cull_mask = self
+                        .resources
+                        .cameras
+                        .get(*id)
+                        .map_or(entity::VISIBILITY_ALL, |camera| camera.cull_mask);
+                    camera_changes.push((unsorted_entities.len(), camera));
+                    segment += 1;
+                    continue;
+                }
+                DrawCommand::ClearCamera => {
+                    camera = None;
+                    frustum = main_frustum;
+                    cull_mask = main_cull_mask;
+                    camera_changes.push((unsorted_entities.len(), camera));
+                    segment += 1;
+                    continue;
+                }
+                DrawCommand::SetClearColor(color) => {
+                    clear_colors.insert(camera, *color);
+                    continue;
+                }
+                DrawCommand::SetLayerClear(layer, clear) => {
+                    layer_clears.insert((camera, *layer), *clear);
+                    continue;
+                }
             };
-            if let Some(shader) = self.resources.materials.get(entity.material).and_then(|material| Some(material.shader)) {
-                if let Some(count) = entity_count_by_shader.get(&shader) {
-                    entity_count_by_shader.insert(shader, count + 1);
-                } else {
-                    entity_count_by_shader.insert(shader, 1);
+            report.submitted += 1;
+            match self.validate_draw(entity.mesh, entity.material, camera) {
+                Ok(shader) => {
+                    if entity.instance.visibility & cull_mask == 0 {
+                        report.masked += 1;
+                        continue;
+                    }
+                    let culled = frustum.is_some_and(|frustum| {
+                        let bounds = entity
+                            .instance
+                            .world_bounds(&self.resources.meshes[entity.mesh]);
+                        !frustum.intersects_aabb(&bounds)
+                    });
+                    if culled {
+                        report.culled += 1;
+                        continue;
+                    }
+                    let entity_buffer = self.resources.shaders[shader].entity_buffer();
+                    *entity_count_by_size.entry(entity_buffer).or_insert(0) += 1;
+                    cameras.insert(camera);
+                    let ordered = self.resources.materials[entity.material]
+                        .requires_ordering(&self.resources.shaders[shader]);
+                    let key = DrawSortKey {
+                        segment,
+                        layer: entity.instance.layer,
+                        order_in_layer: entity.instance.order_in_layer,
+                        ordered,
+                        batch: (self.sort_draws && !ordered)
+                            .then_some((shader, entity.material, entity.mesh)),
+                    };
+                    sort_keys.push((key, scissor));
+                    unsorted_entities.push(entity);
+                }
+                Err(reason) => {
+                    debug_assert!(
+                        !self.strict_draw_validation,
+                        "Invalid draw command {}: {}",
+                        index,
+                        reason
+                    );
+                    // Only log the first occurrence, stale ids tend to be drawn every frame
+                    if self.logged_drop_reasons.len() < MAX_LOGGED_DROPS
+                        && self.logged_drop_reasons.insert(reason)
+                    {
+                        log::warn!(
+                            "Skipping draw command: {}",
+                            self.resources.describe_drop_reason(&reason)
+                        );
+                        if self.logged_drop_reasons.len() == MAX_LOGGED_DROPS {
+                            log::warn!("Further dropped draws are only reported by draw_report");
+                        }
+                    }
+                    report.dropped.push(DroppedDraw { index, reason });
                 }
-                entities.push(entity);
             }
         }
-        
-        for (shader_id, entity_count) in entity_count_by_shader.iter() {
-            let shader = &mut self.resources.shaders[*shader_id];
-
-            shader.reset_offset();
-            // NOTE: camera dependency, see the render pass for more details
-            shader.camera_bind_group.update(&self.camera, &self.queue);
-
-            // Ensure sufficient capacity in each shader to be used for entity uniform data
-            let capacity = shader.entity_bind_group.entity_capacity;
-            if capacity < 2 * entity_count {
-                let mut target_capacity = 2 * capacity;
-                while target_capacity < 2 * entity_count {
-                    target_capacity *= 2;
-                }
-                shader
-                    .entity_bind_group
-                    .recreate_entity_buffer(target_capacity, &self.device);
+        report.drawn = unsorted_entities.len();
+
+        // Stable, so ordered draws keep their submitted order and camera changes stay valid
+        order.extend(0..unsorted_entities.len());
+        order.sort_by_key(|index| sort_keys[*index].0);
+        entities.extend(order.iter().map(|index| unsorted_entities[*index]));
+        let mut previous_scissor = None;
+        let mut previous_key: Option<DrawSortKey> = None;
+        for (index, (key, scissor)) in order.iter().map(|index| sort_keys[*index]).enumerate() {
+            if scissor != previous_scissor {
+                scissor_changes.push((index, scissor));
+                previous_scissor = scissor;
+            }
+            let new_layer = previous_key.is_some_and(|previous| {
+                previous.segment == key.segment && previous.layer != key.layer
+            });
+            // Segments after the first each begin with a camera change
+            let segment_camera = key
+                .segment
+                .checked_sub(1)
+                .and_then(|change| camera_changes[change].1);
+            let clear = layer_clears
+                .get(&(segment_camera, key.layer))
+                .copied()
+                .unwrap_or_default();
+            if new_layer && clear != camera::CameraClear::None {
+                layer_changes.push((index, clear));
             }
+            previous_key = Some(key);
         }
 
-        // Write instance properties to shader
+        self.bindings
+            .reset_entities(entity_count_by_size, &self.device);
+
+        // Write instance properties to shader, and make sure the pipeline variant each
+        // material needs exists before the render pass borrows the shaders
         for entity in entities.iter_mut() {
-           let shader_id = self.resources.materials.get(entity.material).unwrap().shader; 
-           self.resources.shaders[shader_id].write_entity_uniforms(entity, &self.queue);
+            let material = self.resources.materials.get(entity.material).unwrap();
+            let shader = &mut self.resources.shaders[material.shader];
+            shader.write_entity_uniforms(entity, &mut self.bindings);
+            let key = PipelineKey {
+                format,
+                sample_count,
+                options: material.pipeline_options(shader),
+                view: self.debug_view,
+            };
+            shader.prepare_pipeline(&self.device, key);
         }
-        // When we're copying all this entity data around, I'm not sure how much we care about this mut passing
-
-        // This was scene render, but then that was pointless if we want to be able to mix and match draw commands
-        // (though entites was a loop over the scene graph)
-        // Adding scope so render pass is dropped when done
-        {
-            let camera = &self.camera;
-            let view = &view;
-            let depth_view = &self.depth_texture.view;
-            // ^^ Arguably we don't need this and the attachment it's used in if we're rendering 2D
-            // I guess the question is, are these separate render passes? 
-            let resources = &self.resources;
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[
-                    // This is what @location(0) in fragment shader targets
-                    Some(wgpu::RenderPassColorAttachment {
-                        view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(camera.clear_color),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    }),
-                ],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                ..Default::default()
-            });
-
-            let mut currently_bound_shader_id: Option<ShaderId> = None;
-            let mut currently_bound_mesh_id: Option<MeshId> = None;
-            let mut currently_bound_material_id: Option<MaterialId> = None;
-
-            for entity in entities.iter() {
-                let mesh = &resources.meshes[entity.mesh];
-                let material = &resources.materials[entity.material];
-                let shader = &resources.shaders[material.shader];
+        self.bindings.flush_entities(&self.queue);
+    }
 
-                let entity_bind_group = &shader.entity_bind_group.bind_group;
+    /// Record render passes drawing the prepared entities, the bindings of each camera used
+    /// must already be updated, see `SharedBindings::update_cameras`
+    ///
+    /// Returns the number of pipeline, material and mesh changes recorded.
+    pub(crate) fn encode_draws(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        prepared: &PreparedDraws,
+        target: &PassTarget,
+    ) -> BindCounts {
+        let target_size = target.size;
+        // ^^ Arguably we don't need the depth attachment if we're rendering 2D
+        // I guess the question is, are these separate render passes? 
+        let resources = &self.resources;
+
+        // Overdraw accumulates from black, without the sky
+        let overdraw = self.debug_view == DebugView::Overdraw;
+        let main_clear_color = prepared
+            .clear_colors
+            .get(&None)
+            .copied()
+            .unwrap_or(target.clear_color);
+        let main_clear_color = if overdraw { Color::BLACK } else { main_clear_color };
+        let mut clear_color = main_clear_color;
+        let mut render_pass =
+            Self::begin_pass(encoder, target, wgpu::LoadOp::Clear(main_clear_color));
+
+        let mut currently_bound_pipeline: Option<(ShaderId, PipelineKey)> = None;
+        let mut currently_bound_buffers: Option<&mesh::MeshBuffers> = None;
+        let mut currently_bound_material_id: Option<MaterialId> = None;
+        // Storage entity buffers are bound once and indexed by instance
+        let mut currently_bound_entities: Option<(EntityBuffer, usize)> = None;
+        let mut scissor_changes = prepared.scissor_changes.iter().peekable();
+        let mut camera_changes = prepared.camera_changes.iter().peekable();
+        let mut layer_changes = prepared.layer_changes.iter().peekable();
+        let mut scissor: Option<ScissorRect> = None;
+        let mut viewport = target.viewport;
+        let mut camera_id: Option<camera::CameraId> = None;
+        let mut clipped_out = false;
+        let mut binds = BindCounts::default();
+        // Shader and material debug groups open in the render pass
+        let mut debug_depth = 0;
+        let mut outside_viewport = false;
+        if let Some(viewport) = viewport {
+            outside_viewport = Self::set_viewport(&mut render_pass, viewport, target_size);
+        }
+        if !outside_viewport && !overdraw {
+            self.skybox
+                .draw(&mut render_pass, None, target.format, target.sample_count);
+        }
 
-                if currently_bound_material_id != Some(entity.material) {
-                    currently_bound_material_id = Some(entity.material);
+        for (index, entity) in prepared.entities.iter().enumerate() {
+            while let Some((_, id)) = camera_changes.next_if(|(at, _)| *at == index) {
+                camera_id = *id;
+                let (clear, camera_viewport, camera_clear_color) = match id {
+                    // Draws after switching to a missing camera were dropped when prepared
+                    Some(id) => match resources.cameras.get(*id) {
+                        Some(camera) => (camera.clear, camera.viewport, camera.clear_color),
+                        None => continue,
+                    },
+                    None => (camera::CameraClear::None, target.viewport, main_clear_color),
+                };
+                clear_color = prepared
+                    .clear_colors
+                    .get(id)
+                    .copied()
+                    .unwrap_or(camera_clear_color);
+                if overdraw {
+                    clear_color = Color::BLACK;
+                }
+                // Attachments can only be cleared when a pass begins
+                let color_load = match clear {
+                    camera::CameraClear::None => None,
+                    camera::CameraClear::Depth => Some(wgpu::LoadOp::Load),
+                    camera::CameraClear::ColorAndDepth => Some(wgpu::LoadOp::Clear(clear_color)),
+                };
+                if let Some(color_load) = color_load {
+                    Self::pop_debug_groups(&mut render_pass, &mut debug_depth);
+                    drop(render_pass);
+                    render_pass = Self::begin_pass(encoder, target, color_load);
+                    currently_bound_buffers = None;
+                    if let Some(rect) = scissor.filter(|rect| !rect.is_empty()) {
+                        render_pass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+                    }
+                }
+                // Rebind the pipeline and with it the camera bind group
+                currently_bound_pipeline = None;
+                currently_bound_material_id = None;
+                let camera_viewport = camera_viewport.unwrap_or(camera::Viewport::FULL);
+                outside_viewport =
+                    Self::set_viewport(&mut render_pass, camera_viewport, target_size);
+                viewport = Some(camera_viewport);
+                let draw_sky = matches!(clear, camera::CameraClear::ColorAndDepth);
+                if draw_sky && !outside_viewport && !overdraw {
+                    self.skybox
+                        .draw(&mut render_pass, *id, target.format, target.sample_count);
+                }
+            }
+            if let Some((_, clear)) = layer_changes.next_if(|(at, _)| *at == index) {
+                // Clear depth so the layer draws over those before it
+                let color_load = match clear {
+                    camera::CameraClear::ColorAndDepth => wgpu::LoadOp::Clear(clear_color),
+                    _ => wgpu::LoadOp::Load,
+                };
+                Self::pop_debug_groups(&mut render_pass, &mut debug_depth);
+                drop(render_pass);
+                render_pass = Self::begin_pass(encoder, target, color_load);
+                currently_bound_pipeline = None;
+                currently_bound_material_id = None;
+                currently_bound_buffers = None;
+                if let Some(rect) = scissor.filter(|rect| !rect.is_empty()) {
+                    render_pass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+                }
+                if let Some(viewport) = viewport {
+                    outside_viewport = Self::set_viewport(&mut render_pass, viewport, target_size);
+                }
+                if *clear == camera::CameraClear::ColorAndDepth && !outside_viewport && !overdraw {
+                    self.skybox
+                        .draw(&mut render_pass, camera_id, target.format, target.sample_count);
+                }
+            }
+            while let Some((_, rect)) = scissor_changes.next_if(|(at, _)| *at == index) {
+                let rect = rect
+                    .map(|rect| rect.scale(target.scissor_scale))
+                    .unwrap_or(ScissorRect::new(0, 0, target_size.width, target_size.height))
+                    .clamp(target_size);
+                // wgpu rejects empty scissor rects, so skip drawing instead
+                clipped_out = rect.is_empty();
+                if !clipped_out {
+                    render_pass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+                }
+                scissor = Some(rect);
+            }
+            if clipped_out || outside_viewport {
+                continue;
+            }
 
-                    if currently_bound_shader_id != Some(material.shader) {
-                        currently_bound_shader_id = Some(material.shader);
-                        render_pass.set_pipeline(&shader.render_pipeline);
-                        render_pass.set_bind_group(0, &shader.camera_bind_group.bind_group, &[]); 
-                        // TODO: Should be asking shader for camera_bind_group for a particular camera
-                        // This would require the shader to have an updated bind_group / buffer & uniform
-                        // for each camera which it needs to render for
+            let mesh = &resources.meshes[entity.mesh];
+            let material = &resources.materials[entity.material];
+            let shader = &resources.shaders[material.shader];
+
+            let entity_buffer = shader.entity_buffer();
+            let entity_bind_group = self
+                .bindings
+                .entity_bind_group(entity_buffer)
+                .expect("Entity bind group is created with the shader");
+
+            if currently_bound_material_id != Some(entity.material) {
+                currently_bound_material_id = Some(entity.material);
+                binds.materials += 1;
+
+                let key = PipelineKey {
+                    format: target.format,
+                    sample_count: target.sample_count,
+                    options: material.pipeline_options(shader),
+                    view: self.debug_view,
+                };
+                if currently_bound_pipeline != Some((material.shader, key)) {
+                    currently_bound_pipeline = Some((material.shader, key));
+                    binds.pipelines += 1;
+                    if self.debug_groups {
+                        Self::pop_debug_groups(&mut render_pass, &mut debug_depth);
+                        let label = format!("Shader {} {:?}", shader.label(), material.shader);
+                        render_pass.push_debug_group(&label);
+                        debug_depth = 1;
+                    }
+                    render_pass.set_pipeline(shader.get_pipeline(&key).unwrap());
+                    currently_bound_entities = None;
+                    render_pass.set_bind_group(0, self.bindings.camera_bind_group(camera_id), &[]);
+                    if shader.lit {
+                        render_pass.set_bind_group(3, &self.lighting.bind_group, &[]);
                     }
+                }
 
-                    render_pass.set_bind_group(2, &material.diffuse_bind_group, &[]);
-                    // We're presumably going to share the layout for textures across shaders
-                    // therefore we can and should share texture bind groups across materials
-                    // only rebind when appropriate, rather than rebinding per material
-                    // however should only do this if we're bothering to order the scene graph
-                    // to group materials with the same textures
+                if self.debug_groups {
+                    if debug_depth == 2 {
+                        render_pass.pop_debug_group();
+                    }
+                    let material_name = resources.materials.describe(entity.material);
+                    render_pass.push_debug_group(&format!("Material {}", material_name));
+                    debug_depth = 2;
                 }
+                if key.options.blend_constant {
+                    render_pass.set_blend_constant(material.blend_constant);
+                }
+                render_pass.set_bind_group(2, &material.diffuse_bind_group, &[]);
+                // We're presumably going to share the layout for textures across shaders
+                // therefore we can and should share texture bind groups across materials
+                // only rebind when appropriate, rather than rebinding per material
+                // however should only do this if we're bothering to order the scene graph
+                // to group materials with the same textures
+            }
 
-                if currently_bound_mesh_id != Some(entity.mesh) {
-                    currently_bound_mesh_id = Some(entity.mesh);
+            // Meshes sharing the buffers of a mesh pool page draw without rebinding
+            if !currently_bound_buffers.is_some_and(|buffers| buffers.ptr_eq(&mesh.buffers)) {
+                currently_bound_buffers = Some(&mesh.buffers);
+                binds.meshes += 1;
 
-                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                }
+                render_pass.set_vertex_buffer(0, mesh.buffers.vertex.slice(..));
+                render_pass.set_index_buffer(mesh.buffers.index.slice(..), mesh.index_format);
+            }
 
+            let page = entity_bind_group.page_of(entity.uniform_offset);
+            let bind_group = &entity_bind_group.pages[page].bind_group;
+            if entity_bind_group.is_storage() {
+                if currently_bound_entities != Some((entity_buffer, page)) {
+                    currently_bound_entities = Some((entity_buffer, page));
+                    render_pass.set_bind_group(1, bind_group, &[]);
+                }
+                let instance = entity_bind_group.instance_index(entity.uniform_offset);
+                let instances = instance..instance + 1;
+                render_pass.draw_indexed(mesh.index_range(), mesh.base_vertex, instances);
+                binds.draws += 1;
+            } else {
                 // using uniform with offset approach of
                 // https://github.com/gfx-rs/wgpu/tree/master/wgpu/examples/shadow
-                render_pass.set_bind_group(
-                    1,
-                    entity_bind_group,
-                    &[entity.uniform_offset as wgpu::DynamicOffset],
-                );
-                render_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+                currently_bound_entities = None;
+                let offset = entity_bind_group.page_offset(entity.uniform_offset);
+                render_pass.set_bind_group(1, bind_group, &[offset as wgpu::DynamicOffset]);
+                render_pass.draw_indexed(mesh.index_range(), mesh.base_vertex, 0..1);
+                binds.draws += 1;
             }
         }
+        Self::pop_debug_groups(&mut render_pass, &mut debug_depth);
+        binds
+    }
+
+    fn pop_debug_groups(render_pass: &mut wgpu::RenderPass, depth: &mut u32) {
+        for _ in 0..std::mem::take(depth) {
+            render_pass.pop_debug_group();
+        }
+    }
+
+    /// Begin a pass over the target clearing depth, and color if requested
+    fn begin_pass<'a>(
+        encoder: &'a mut wgpu::CommandEncoder,
+        target: &PassTarget,
+        color_load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[
+                // This is what @location(0) in fragment shader targets
+                Some(wgpu::RenderPassColorAttachment {
+                    view: target.view,
+                    resolve_target: target.resolve_target,
+                    ops: wgpu::Operations {
+                        load: color_load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Returns true if the viewport is empty, in which case it isn't set and nothing should
+    /// be drawn, as wgpu rejects empty viewports
+    fn set_viewport(
+        render_pass: &mut wgpu::RenderPass,
+        viewport: camera::Viewport,
+        size: PhysicalSize<u32>,
+    ) -> bool {
+        let (x, y, width, height) = viewport.to_pixels(size);
+        if width <= 0.0 || height <= 0.0 {
+            return true;
+        }
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        false
+    }
+
+    fn render(
+        &mut self,
+        draw_commands: &Vec<DrawCommand>,
+        mut game: Option<&mut dyn Game>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let render_start = instant::Instant::now();
+        let output = self.output.current_texture()?;
+
+        let capturing = std::mem::take(&mut self.capture_next_frame);
+        if capturing {
+            self.device.start_capture();
+        }
+
+        let view = output
+            .texture()
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+        
+        let mut report = std::mem::take(&mut self.draw_report);
+        report.clear();
+        let frustum = self
+            .frustum_culling
+            .then(|| Frustum::from_camera(&self.camera));
+        let mut arena = std::mem::take(&mut self.frame_arena);
+        self.prepare_draws(
+            draw_commands,
+            frustum,
+            self.camera.cull_mask,
+            self.config.format,
+            self.msaa_sample_count(),
+            &mut report,
+            &mut arena,
+        );
+        let prepared = &arena.prepared;
+        self.draw_report = report;
+        self.bindings.update_cameras(
+            &prepared.cameras,
+            &self.camera,
+            &self.resources.cameras,
+            &self.device,
+            &self.queue,
+        );
+        self.lighting.update(&self.resources.lights, &self.queue);
+        self.skybox.prepare(
+            &self.device,
+            &self.queue,
+            &self.camera,
+            &self.resources.cameras,
+            prepared.camera_changes.iter().filter_map(|(_, id)| *id),
+            &self.resources.textures,
+            self.config.format,
+            self.msaa_sample_count(),
+        );
+        if let Some(game) = game.as_mut() {
+            game.pre_render(&mut RenderContext {
+                device: &self.device,
+                queue: &self.queue,
+                encoder: &mut encoder,
+                view: &view,
+                format: self.config.format,
+                size: self.size,
+                scale_factor: self.scale_factor(),
+                resources: &self.resources,
+            });
+        }
+        // When we're copying all this entity data around, I'm not sure how much we care about this mut passing
+
+        // This was scene render, but then that was pointless if we want to be able to mix and match draw commands
+        // (though entites was a loop over the scene graph)
+        let scaled = match &mut self.resolution {
+            Some(resolution) => {
+                resolution.update(self.time.elapsed_real_time);
+                resolution.prepare(&self.device, &self.queue, self.size, self.config.format)
+            }
+            None => false,
+        };
+        #[cfg(feature = "post-processing")]
+        if let Some(grading) = &mut self.color_grading {
+            grading.prepare(
+                &self.device,
+                &self.queue,
+                &self.shader_library,
+                self.size,
+                self.config.format,
+            );
+        }
+        #[cfg(feature = "post-processing")]
+        match self.camera.bloom {
+            Some(settings) => self
+                .bloom
+                .get_or_insert_with(|| {
+                    bloom::Bloom::new(&self.device, &self.shader_library, self.config.format)
+                })
+                .prepare(
+                    &self.device,
+                    &self.queue,
+                    &self.shader_library,
+                    self.size,
+                    self.config.format,
+                    &settings,
+                ),
+            None => self.bloom = None,
+        }
+        // The scene (upscaled if needed) goes to the bloom input when bloom is enabled, and
+        // the result of bloom to the grading input when grading is enabled
+        #[cfg(feature = "post-processing")]
+        let graded_view = self
+            .color_grading
+            .as_ref()
+            .and_then(|grading| grading.input_view())
+            .unwrap_or(&view);
+        #[cfg(feature = "post-processing")]
+        let scene_view = self
+            .bloom
+            .as_ref()
+            .and_then(|bloom| bloom.input_view())
+            .unwrap_or(graded_view);
+        #[cfg(not(feature = "post-processing"))]
+        let scene_view = &view;
+
+        let (pass_view, depth_view, target_size) =
+            match self.resolution.as_ref().filter(|_| scaled) {
+                Some(resolution) => resolution.target_views().unwrap(),
+                None => (scene_view, &self.depth_texture.view, self.size),
+            };
+        if let Some(msaa) = &mut self.msaa {
+            msaa.prepare(&self.device, target_size, self.config.format);
+        }
+        // When multisampling the scene is drawn to the MSAA target and resolved into the view
+        let (pass_view, resolve_target, depth_view) =
+            match self.msaa.as_ref().and_then(|msaa| msaa.views()) {
+                Some((color, depth)) => (color, Some(pass_view), depth),
+                None => (pass_view, None, depth_view),
+            };
+        let pass_target = PassTarget {
+            view: pass_view,
+            resolve_target,
+            depth_view,
+            size: target_size,
+            format: self.config.format,
+            sample_count: self.msaa_sample_count(),
+            clear_color: self.camera.clear_color,
+            viewport: self.camera.viewport,
+            // Scissor rects are specified relative to the surface
+            scissor_scale: target_size.width as f32 / self.size.width as f32,
+        };
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin(&mut encoder);
+        }
+        if self.debug_groups {
+            encoder.push_debug_group("Scene");
+        }
+        self.draw_report.binds = self.encode_draws(&mut encoder, prepared, &pass_target);
+        self.frame_arena = arena;
+        self.crowds.render(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &pass_target,
+            &self.camera,
+            &self.resources,
+        );
+        if self.debug_groups {
+            encoder.pop_debug_group();
+            encoder.push_debug_group("Post Processing");
+        }
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.mark(&mut encoder, "scene");
+        }
+
+        if scaled {
+            if let Some(resolution) = &self.resolution {
+                resolution.upscale(&mut encoder, scene_view);
+            }
+        }
+        #[cfg(feature = "post-processing")]
+        if let Some(bloom) = &self.bloom {
+            bloom.apply(&mut encoder, graded_view);
+        }
+        #[cfg(feature = "post-processing")]
+        if let Some(grading) = &self.color_grading {
+            grading.apply(&mut encoder, &view);
+        }
+        if self.debug_groups {
+            encoder.pop_debug_group();
+            encoder.push_debug_group("Overlays");
+        }
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.mark(&mut encoder, "post processing");
+        }
+        let mut context = RenderContext {
+            device: &self.device,
+            queue: &self.queue,
+            encoder: &mut encoder,
+            view: &view,
+            format: self.config.format,
+            size: self.size,
+            scale_factor: self.scale_factor(),
+            resources: &self.resources,
+        };
+        self.shapes.render(&mut context, &self.camera, &self.shader_library);
+        self.debug.render(&mut context, &self.camera, &self.shader_library);
+        if let Some(game) = game {
+            game.post_render(&mut context);
+        }
+        if self.debug_groups {
+            context.encoder.pop_debug_group();
+        }
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.mark(&mut encoder, "overlays");
+            timer.resolve(&mut encoder);
+        }
+
+        let pending_screenshot = std::mem::take(&mut self.screenshot_requested)
+            .then(|| {
+                if !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC)
+                    || !screenshot::Screenshot::supports_format(self.config.format)
+                {
+                    log::warn!("Screenshots are not supported by the surface");
+                    return None;
+                }
+                Some(screenshot::Screenshot::encode_copy(
+                    &self.device,
+                    &mut encoder,
+                    output.texture(),
+                ))
+            })
+            .flatten();
 
         // submit will accept anything that implements IntoIter
         self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.submitted();
+        }
+        if capturing {
+            self.device.stop_capture();
+        }
+        if let Some(pending) = pending_screenshot {
+            self.screenshot = pending.read(&self.device);
+        }
 
         output.present();
 
+        self.frame_stats
+            .record(self.time.elapsed_real_time, &self.draw_report);
+        if let Some(stats) = &mut self.stats {
+            stats.frame_time = self.time.elapsed_real_time;
+            stats.render_time = render_start.elapsed().as_secs_f32();
+            stats.entities = self.draw_report.drawn;
+            stats.draw_calls = self.draw_report.binds.draws;
+            stats.binds = self.draw_report.binds;
+            if let Some(passes) = self.gpu_timer.as_mut().and_then(|t| t.collect(&self.device)) {
+                stats.gpu_passes = passes;
+            }
+        }
+
         Ok(())
     }
 }
@@ -357,6 +1693,91 @@ impl State {
 // Consider implementing Drop for State 
 // https://github.com/sotrh/learn-wgpu/issues/549#issuecomment-2445330937
 
+/// Runs the game's frames on a `State`, shared by the windowed app and headless runs
+struct GameRunner {
+    game: Box<dyn Game>,
+    loading: Option<(LoadHandle, LoadingScreen)>,
+    /// Cleared and refilled each frame, keeping its capacity
+    draw_commands: Vec<DrawCommand>,
+}
+
+impl GameRunner {
+    fn new(game: Box<dyn Game>) -> Self {
+        Self {
+            game,
+            loading: None,
+            draw_commands: Vec::new(),
+        }
+    }
+
+    /// Start loading the game's assets, initializing it straight away if it has none
+    fn start(&mut self, state: &mut State) {
+        let handle = self.game.load(&mut state.assets);
+        if handle.is_empty() {
+            self.game.init(state);
+        } else {
+            // init is deferred until the requested assets have loaded, see `frame`
+            let loading_screen = LoadingScreen::new(state);
+            state.camera = LoadingScreen::camera();
+            self.loading = Some((handle, loading_screen));
+        }
+    }
+
+    /// Update and render a frame, returns false when the app should exit
+    fn frame(&mut self, state: &mut State) -> bool {
+        let draw_commands = &mut self.draw_commands;
+        draw_commands.clear();
+
+        if let Some((handle, loading_screen)) = &self.loading {
+            state.assets.poll();
+            let progress = state.assets.progress(handle);
+            if progress < 1.0 {
+                state.time.update();
+                state.input.frame_finished();
+                loading_screen.render(progress, draw_commands);
+            } else if let Some((_, loading_screen)) = self.loading.take() {
+                loading_screen.release(state);
+                state.camera = camera::Camera::default();
+                self.game.init(state);
+            }
+        }
+
+        if self.loading.is_none() {
+            if state.localization.take_language_changed() {
+                self.game.language_changed(state);
+            }
+            state.tasks.poll();
+            state.input.poll_gamepads();
+            let elapsed = state.time.update();
+            self.game.update(state, elapsed);
+            state.update();
+            state.input.frame_finished();
+            self.game.render(draw_commands);
+        }
+
+        // The game isn't initialized while the loading screen shows
+        let game = self
+            .loading
+            .is_none()
+            .then_some(&mut *self.game as &mut dyn Game);
+        match state.render(draw_commands, game) {
+            Ok(_) => {}
+            // Reconfigure the surface, its supported formats may have changed if the
+            // window moved to another monitor
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                if state.refresh_surface_format() && self.loading.is_none() {
+                    self.game.surface_format_changed(state);
+                }
+            }
+            // The system is out of memory, we should probably quit
+            Err(wgpu::SurfaceError::OutOfMemory) => return false,
+            // All other errors (Timeout) should be resolved by the next frame
+            Err(e) => eprintln!("{:?}", e),
+        }
+        !state.exit_requested
+    }
+}
+
 // App and enum to support flow necessary to create
 // window for both native and WASM export  
 enum UserEvent {
@@ -364,28 +1785,28 @@ enum UserEvent {
 }
 
 struct App {
-    title: String,
-    resizable: bool,
+    window_attributes: WindowAttributes,
     window_size: PhysicalSize<u32>,
+    settings: RendererSettings,
     state: Option<State>,
     event_loop_proxy: EventLoopProxy<UserEvent>,
-    game: Box<dyn Game>,
+    runner: GameRunner,
 }
 
 impl App {
     fn new(
         game: Box<dyn Game>,
-        title: String,
-        resizable: bool,
+        window_attributes: WindowAttributes,
         window_size: PhysicalSize<u32>,
+        settings: RendererSettings,
         event_loop: &EventLoop<UserEvent>) -> Self {
         Self {
-            game,
-            title,
-            resizable,
+            window_attributes,
             window_size,
+            settings,
             state: None,
             event_loop_proxy: event_loop.create_proxy(),
+            runner: GameRunner::new(game),
         }
     }
 }
@@ -393,9 +1814,7 @@ impl App {
 impl ApplicationHandler<UserEvent> for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         let window = event_loop.create_window(
-            Window::default_attributes().with_title(self.title.clone())
-                .with_resizable(self.resizable)
-                .with_inner_size(self.window_size)
+            self.window_attributes.clone().with_inner_size(self.window_size)
             ).ok().unwrap();
 
         #[cfg(target_arch = "wasm32")]
@@ -414,24 +1833,26 @@ impl ApplicationHandler<UserEvent> for App {
                 })
                 .expect("Couldn't append canvas to document body.");
             
-            let state_future = State::new(Arc::new(window), self.window_size);
+            let state_future =
+                State::new(Some(Arc::new(window)), self.window_size, self.settings.clone());
             let event_loop_proxy = self.event_loop_proxy.clone();
             let future = async move {
-                let state = state_future.await;
+                let state = state_future.await.expect("Unable to create renderer");
                 assert!(event_loop_proxy.send_event(UserEvent::StateReady(state)).is_ok());
             };
             wasm_bindgen_futures::spawn_local(future);
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let state = pollster::block_on(State::new(Arc::new(window), self.window_size));
+            let state = State::new(Some(Arc::new(window)), self.window_size, self.settings.clone());
+            let state = pollster::block_on(state).expect("Unable to create renderer");
             assert!(self.event_loop_proxy.send_event(UserEvent::StateReady(state)).is_ok());
         }
     }
 
     fn user_event(&mut self, _: &winit::event_loop::ActiveEventLoop, event: UserEvent) {
         let UserEvent::StateReady(mut state) = event;
-        self.game.init(&mut state);
+        self.runner.start(&mut state);
         self.state = Some(state);
     }
 
@@ -444,12 +1865,35 @@ impl ApplicationHandler<UserEvent> for App {
         let Some(ref mut state) = self.state else {
             return;
         };
+        let Some(window) = state.window.clone() else {
+            return;
+        };
 
-        if window_id != state.window.id() {
+        if window_id != window.id() {
             return;
         }
 
-        state.input.process_events(&event);
+        let runner = &mut self.runner;
+        let consumed = runner.loading.is_none() && runner.game.window_event(state, &event);
+        if !consumed {
+            if let WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } = event
+            {
+                let position = state.input.mouse_position;
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                if let Some(action) = state.window_chrome.hit_test(position) {
+                    if window_chrome::WindowChrome::perform(action, &window) {
+                        event_loop.exit();
+                    }
+                    return;
+                }
+            }
+
+            state.input.process_events(&event);
+        }
 
         match event {
             WindowEvent::CloseRequested
@@ -463,8 +1907,8 @@ impl ApplicationHandler<UserEvent> for App {
                 ..
             } => event_loop.exit(),
             WindowEvent::Resized(physical_size) => {
-                if state.resize(physical_size) {
-                    self.game.resize(state);
+                if state.resize(physical_size) && runner.loading.is_none() {
+                    runner.game.resize(state);
                 }
             }
             WindowEvent::ScaleFactorChanged { .. } => {
@@ -472,24 +1916,9 @@ impl ApplicationHandler<UserEvent> for App {
                 // although the documentation still refers to it
             }
             WindowEvent::RedrawRequested => {
-                let elapsed = state.time.update();
-                self.game.update(state, elapsed);
-                state.update();
-                state.input.frame_finished();
-
-                let mut draw_commands = Vec::new(); // probably don't want a new one each frame but hey prototyping
-                self.game.render(&mut draw_commands);
-
-                match state.render(&draw_commands) {
-                    Ok(_) => {}
-                    // Reconfigure the surface if lost
-                    Err(wgpu::SurfaceError::Lost) => {
-                        state.resize(state.size);
-                    }
-                    // The system is out of memory, we should probably quit
-                    Err(wgpu::SurfaceError::OutOfMemory) => event_loop.exit(),
-                    // All other errors (Outdated, Timeout) should be resolved by the next frame
-                    Err(e) => eprintln!("{:?}", e),
+                let running = runner.frame(state);
+                if !running {
+                    event_loop.exit();
                 }
             }
             _ => {}
@@ -497,27 +1926,281 @@ impl ApplicationHandler<UserEvent> for App {
     }
 
     fn about_to_wait(&mut self, _: &winit::event_loop::ActiveEventLoop) {
-        if let Some(ref state) = self.state {
-            state.window.request_redraw();
+        if let Some(window) = self.state.as_ref().and_then(|state| state.window.as_ref()) {
+            window.request_redraw();
         }
     }
 }
 
+// Draws are by far the most common command, boxing their properties would allocate per draw
+#[allow(clippy::large_enum_variant)]
 pub enum DrawCommand {
     Draw(MeshId, MaterialId, RenderProperties),
+    /// Clip subsequent draws to the rect, until cleared or replaced
+    SetScissor(ScissorRect),
+    ClearScissor,
+    /// Draw subsequent commands with a camera from `resources.cameras`, applying its clear
+    /// and viewport, until cleared or replaced
+    SetCamera(camera::CameraId),
+    /// Return to drawing with the main camera
+    ClearCamera,
+    /// Clear to the color rather than the current camera's `clear_color` this frame, e.g. for
+    /// a damage flash, the main camera's clear color is that of the whole frame
+    SetClearColor(Color),
+    /// What is cleared when draws of the current camera move on to the layer this frame,
+    /// layers clear depth by default, see `RenderProperties::layer`
+    SetLayerClear(u8, camera::CameraClear),
+}
+
+/// Region of the surface in physical pixels, with the origin at the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScissorRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Rect covering the screen space points, expanded to whole pixels
+    pub fn from_min_max(min: Vec2, max: Vec2) -> Self {
+        let min = min.min(max).max(Vec2::ZERO).floor();
+        let max = max.max(min).ceil();
+        Self::new(
+            min.x as u32,
+            min.y as u32,
+            (max.x - min.x) as u32,
+            (max.y - min.y) as u32,
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.x as f32
+            && point.y >= self.y as f32
+            && point.x < (self.x + self.width) as f32
+            && point.y < (self.y + self.height) as f32
+    }
+
+    /// Rect covering the same region of a target scaled by the factor
+    pub fn scale(&self, factor: f32) -> Self {
+        let min = Vec2::new(self.x as f32, self.y as f32) * factor;
+        let max = Vec2::new((self.x + self.width) as f32, (self.y + self.height) as f32) * factor;
+        Self::from_min_max(min, max)
+    }
+
+    /// Intersection of the rect with a surface of the given size
+    pub fn clamp(&self, size: PhysicalSize<u32>) -> Self {
+        let x = self.x.min(size.width);
+        let y = self.y.min(size.height);
+        Self::new(
+            x,
+            y,
+            self.width.min(size.width - x),
+            self.height.min(size.height - y),
+        )
+    }
+}
+
+/// Order of a draw within a camera change, see `State::prepare_draws`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct DrawSortKey {
+    segment: usize,
+    layer: u8,
+    order_in_layer: i32,
+    ordered: bool,
+    /// None for ordered draws so they keep their submitted order
+    batch: Option<(ShaderId, MaterialId, MeshId)>,
+}
+
+/// Validated draw commands with their entity uniforms written
+#[derive(Default)]
+pub(crate) struct PreparedDraws {
+    entities: Vec<EntityDrawInstruction>,
+    // Scissor changes to apply before drawing the entity at the index
+    scissor_changes: Vec<(usize, Option<ScissorRect>)>,
+    // Camera changes to apply before drawing the entity at the index, None being the main camera
+    camera_changes: Vec<(usize, Option<camera::CameraId>)>,
+    // Indices where a new layer starts, drawn over the previous layers, with what it clears
+    layer_changes: Vec<(usize, camera::CameraClear)>,
+    /// Cameras used by the draws, which need their bindings updating, None being the main camera
+    cameras: HashSet<Option<camera::CameraId>>,
+    /// Clear colors set with `DrawCommand::SetClearColor`, None being the main camera
+    clear_colors: HashMap<Option<camera::CameraId>, Color>,
+}
+
+/// Draws prepared for the frame and the working buffers used to prepare them, cleared rather
+/// than reallocated each time draws are prepared
+#[derive(Default)]
+pub(crate) struct FrameArena {
+    prepared: PreparedDraws,
+    /// Entities in submitted order, before sorting
+    unsorted_entities: Vec<EntityDrawInstruction>,
+    /// Sort key and scissor of each unsorted entity
+    sort_keys: Vec<(DrawSortKey, Option<ScissorRect>)>,
+    /// Indices of the unsorted entities in draw order
+    order: Vec<usize>,
+    entity_count_by_size: HashMap<EntityBuffer, u64>,
+    layer_clears: HashMap<(Option<camera::CameraId>, u8), camera::CameraClear>,
+}
+
+impl FrameArena {
+    fn clear(&mut self) {
+        let prepared = &mut self.prepared;
+        prepared.entities.clear();
+        prepared.scissor_changes.clear();
+        prepared.camera_changes.clear();
+        prepared.layer_changes.clear();
+        prepared.cameras.clear();
+        prepared.clear_colors.clear();
+        self.unsorted_entities.clear();
+        self.sort_keys.clear();
+        self.order.clear();
+        self.entity_count_by_size.clear();
+        self.layer_clears.clear();
+    }
+}
+
+/// Attachments for a render pass of prepared draws
+pub(crate) struct PassTarget<'a> {
+    view: &'a wgpu::TextureView,
+    /// Single sampled view the multisampled view is resolved into
+    resolve_target: Option<&'a wgpu::TextureView>,
+    depth_view: &'a wgpu::TextureView,
+    size: PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    clear_color: wgpu::Color,
+    /// Viewport of the main camera
+    viewport: Option<camera::Viewport>,
+    /// Scale from the space scissor rects are specified in to the target
+    scissor_scale: f32,
+}
+
+/// Device, queue and resources for creating meshes, textures and materials, borrowed from
+/// the `State` apart from its camera, input and other per frame state, see `State::graphics`
+pub struct GraphicsContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    /// Format of the surface, which the built-in shaders render to
+    pub format: wgpu::TextureFormat,
+    /// Layout of a material binding a single texture, see `Material::from_graphics`
+    pub texture_bind_group_layout: &'a wgpu::BindGroupLayout,
+    pub shaders: &'a BuildInShaders,
+    pub resources: &'a mut Resources,
+    pub mesh_pool: &'a mut mesh_pool::MeshPool,
+}
+
+/// Access to the frame being rendered, for rendering in addition to the engine's draw commands
+pub struct RenderContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub view: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub size: PhysicalSize<u32>,
+    pub scale_factor: f32,
+    pub resources: &'a Resources,
 }
 
 pub trait Game {
+    /// Request assets to be loaded before init, via `loader.load(path)`, init is only called once
+    /// all requested assets have loaded, with the bytes available from `state.assets`
+    fn load(&mut self, loader: &mut AssetLoader) -> LoadHandle {
+        loader.handle()
+    }
     fn init(&mut self, state: &mut State);
     fn update(&mut self, state: &mut State, elapsed: f32);
     fn render(&mut self, commands: &mut Vec<DrawCommand>);
-    fn resize(&mut self, state: &mut State);
+    /// Called after the window is resized, once `state.camera` has been updated per
+    /// `state.aspect_policy`
+    fn resize(&mut self, _state: &mut State) {}
+    /// Called after the surface is reconfigured with a new format, see
+    /// `State::refresh_surface_format`, to rebuild pipelines created for the previous format
+    fn surface_format_changed(&mut self, _state: &mut State) {}
+    /// Called at the start of the frame after `state.localization` switched language, to
+    /// re-set localized text
+    fn language_changed(&mut self, _state: &mut State) {}
+    /// Called for each window event before the engine processes input
+    /// Return true to consume the event, preventing it from reaching `state.input`
+    fn window_event(&mut self, _state: &mut State, _event: &WindowEvent) -> bool {
+        false
+    }
+    /// Called before the engine's render pass, with the frame's draws prepared, to record
+    /// custom work such as compute passes the scene depends on
+    /// The scene pass clears `view`, so draw to the view in `post_render` instead
+    fn pre_render(&mut self, _context: &mut RenderContext) {}
+    /// Called after the engine's render pass, before the frame is submitted, to record
+    /// custom passes over the frame or readbacks of it
+    fn post_render(&mut self, _context: &mut RenderContext) {}
+}
+
+/// Options used when creating the renderer
+#[derive(Debug, Clone, Default)]
+pub struct RendererSettings {
+    /// Directory to record a wgpu API trace to, requires the `trace` feature on native
+    pub trace_path: Option<std::path::PathBuf>,
+    /// Dynamic resolution, disabled if None
+    pub resolution_scaling: Option<ResolutionScaling>,
+    /// Samples per pixel for multisample anti-aliasing, disabled if None
+    pub msaa_samples: Option<u32>,
+    /// How frames are presented to the window, `AutoNoVsync` if None
+    pub present_mode: Option<wgpu::PresentMode>,
+    /// Frames the GPU may queue ahead of presentation, 1 if None, see `Helia::with_frame_latency`
+    pub frame_latency: Option<u32>,
+}
+
+/// Game made of the closures given to `Helia::run_with`, its data is created by `init`
+struct ClosureGame<T, I, U, R> {
+    data: Option<T>,
+    init: Option<I>,
+    update: U,
+    render: R,
+}
+
+impl<T, I, U, R> Game for ClosureGame<T, I, U, R>
+where
+    I: FnOnce(&mut State) -> T,
+    U: FnMut(&mut T, &mut State, f32),
+    R: FnMut(&T, &mut Vec<DrawCommand>),
+{
+    fn init(&mut self, state: &mut State) {
+        if let Some(init) = self.init.take() {
+            self.data = Some(init(state));
+        }
+    }
+
+    fn update(&mut self, state: &mut State, elapsed: f32) {
+        if let Some(data) = self.data.as_mut() {
+            (self.update)(data, state, elapsed);
+        }
+    }
+
+    fn render(&mut self, commands: &mut Vec<DrawCommand>) {
+        if let Some(data) = self.data.as_ref() {
+            (self.render)(data, commands);
+        }
+    }
 }
 
 pub struct Helia {
     title: String,
     resizable: bool,
+    decorations: bool,
     window_size: PhysicalSize<u32>,
+    settings: RendererSettings,
 }
 
 impl Helia {
@@ -525,7 +2208,9 @@ impl Helia {
         Self {
             title: "Helia".to_string(),
             resizable: false,
+            decorations: true,
             window_size: PhysicalSize::new(960, 540),
+            settings: RendererSettings::default(),
         }
     }
 
@@ -547,6 +2232,66 @@ impl Helia {
         self
     }
 
+    /// Without decorations the window has no title bar or border, add drag and button
+    /// regions to `state.window_chrome` to provide your own
+    pub fn with_decorations(&mut self, decorations: bool) -> &mut Self {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Lower the render resolution when frame time exceeds the budget, see `ResolutionScaling`
+    pub fn with_resolution_scaling(&mut self, scaling: ResolutionScaling) -> &mut Self {
+        self.settings.resolution_scaling = Some(scaling);
+        self
+    }
+
+    /// Smooth edges with multisample anti-aliasing, commonly 4 samples per pixel
+    /// Falls back to the highest count the adapter supports below the requested count
+    pub fn with_msaa(&mut self, samples: u32) -> &mut Self {
+        self.settings.msaa_samples = Some(samples);
+        self
+    }
+
+    /// How frames are presented, e.g. `AutoVsync` to cap the frame rate to the refresh rate,
+    /// unsupported modes fall back to the automatic mode, see also `State::set_vsync`
+    pub fn with_present_mode(&mut self, present_mode: wgpu::PresentMode) -> &mut Self {
+        self.settings.present_mode = Some(present_mode);
+        self
+    }
+
+    /// Maximum frames queued ahead of presentation, 1 minimises input latency, higher values
+    /// smooth out frame time spikes at the cost of latency, clamped to at least 1
+    pub fn with_frame_latency(&mut self, latency: u32) -> &mut Self {
+        self.settings.frame_latency = Some(latency.max(1));
+        self
+    }
+
+    /// Record a wgpu API trace for replaying and debugging, requires the `trace` feature
+    pub fn with_trace_path<P: Into<std::path::PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.settings.trace_path = Some(path.into());
+        self
+    }
+
+    /// Run with closures rather than a `Game` implementation, for small examples and jam games
+    ///
+    /// `init` creates the game's data once the state is ready, which `update` and `render` are
+    /// then given each frame.
+    pub async fn run_with<T, I, U, R>(&self, init: I, update: U, render: R)
+    where
+        T: 'static,
+        I: FnOnce(&mut State) -> T + 'static,
+        U: FnMut(&mut T, &mut State, f32) + 'static,
+        R: FnMut(&T, &mut Vec<DrawCommand>) + 'static,
+    {
+        let game = ClosureGame {
+            data: None,
+            init: Some(init),
+            update,
+            render,
+        };
+        self.run(Box::new(game)).await
+    }
+
     pub async fn run(&self, game: Box<dyn Game>) {
         cfg_if::cfg_if! {
             if #[cfg(target_arch = "wasm32")] {
@@ -561,9 +2306,43 @@ impl Helia {
         // Consider ControlFlow::Poll and not using about_to_wait in AppHandler 
         // c.f. https://github.com/sotrh/learn-wgpu/issues/549#issuecomment-2570248027
 
-        let mut app = App::new(game, self.title.clone(), self.resizable, self.window_size, &event_loop);
+        let window_attributes = Window::default_attributes()
+            .with_title(self.title.clone())
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations);
+        let mut app = App::new(
+            game,
+            window_attributes,
+            self.window_size,
+            self.settings.clone(),
+            &event_loop,
+        );
         event_loop.run_app(&mut app).ok();
 
         // Consider EventLoopExtWebSys::spawn_app for WASM to avoid exception
     }
+
+    /// Run the game without a window, rendering each frame to an offscreen texture, until
+    /// `State::exit` is called, e.g. for automated tests on machines without a display
+    ///
+    /// Frames run back to back, drive time with `Time::manual_step` for repeatable frames.
+    /// There are no window events, so input is only what the game applies to `state.input`.
+    /// Returns an error if no graphics adapter is available, software adapters such as
+    /// llvmpipe or WARP work.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_headless(&self, game: Box<dyn Game>) -> anyhow::Result<()> {
+        // Several headless runs may share a process, e.g. tests
+        env_logger::builder()
+            .filter(Some("wgpu"), log::LevelFilter::Warn)
+            .filter_level(log::LevelFilter::Info)
+            .try_init()
+            .ok();
+
+        let state = State::new(None, self.window_size, self.settings.clone());
+        let mut state = pollster::block_on(state)?;
+        let mut runner = GameRunner::new(game);
+        runner.start(&mut state);
+        while runner.frame(&mut state) {}
+        Ok(())
+    }
 }
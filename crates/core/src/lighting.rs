@@ -0,0 +1,175 @@
+use bytemuck::Zeroable;
+use glam::*;
+use wgpu::util::DeviceExt;
+
+use crate::{resource_map::ResourceMap, Color};
+
+slotmap::new_key_type! { pub struct LightId; }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Uniform light reaching every surface, the sum of ambient lights is used
+    Ambient,
+    /// Parallel rays travelling in the direction, e.g. the sun
+    Directional { direction: Vec3 },
+    /// Radiates from the position, falling off to nothing at the range
+    Point { position: Vec3, range: f32 },
+}
+
+/// Light used by lit shaders such as `BuildInShaders::lit_textured`, add to `state.resources.lights`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn ambient(color: Color, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Ambient,
+            color,
+            intensity,
+        }
+    }
+
+    pub fn directional(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional {
+                direction: direction.normalize_or_zero(),
+            },
+            color,
+            intensity,
+        }
+    }
+
+    pub fn point(position: Vec3, range: f32, color: Color, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Point { position, range },
+            color,
+            intensity,
+        }
+    }
+
+    fn radiance(&self) -> [f32; 3] {
+        [
+            (self.color.r as f32) * self.intensity,
+            (self.color.g as f32) * self.intensity,
+            (self.color.b as f32) * self.intensity,
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct DirectionalLightRaw {
+    direction: [f32; 3],
+    _padding: f32,
+    color: [f32; 3],
+    _padding2: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointLightRaw {
+    position: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    _padding: f32,
+}
+
+/// Matches `Lights` in `helia/lighting.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightsUniform {
+    ambient: [f32; 3],
+    directional_count: u32,
+    directional: [DirectionalLightRaw; LightBindGroup::MAX_DIRECTIONAL_LIGHTS],
+    point: [PointLightRaw; LightBindGroup::MAX_POINT_LIGHTS],
+    point_count: u32,
+    _padding: [u32; 3],
+}
+
+/// Uniform buffer of the lights in the scene, bound at `@group(3)` for lit shaders
+pub(crate) struct LightBindGroup {
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    buffer: wgpu::Buffer,
+}
+
+impl LightBindGroup {
+    pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+    pub const MAX_POINT_LIGHTS: usize = 16;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("light_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&LightsUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("light_bind_group"),
+        });
+        Self {
+            layout,
+            bind_group,
+            buffer,
+        }
+    }
+
+    /// Write the lights to the buffer, lights beyond the maximum count of each kind are ignored
+    pub fn update(&self, lights: &ResourceMap<LightId, Light>, queue: &wgpu::Queue) {
+        let mut uniform = LightsUniform::zeroed();
+        let mut ambient = Vec3::ZERO;
+        let (mut directional_count, mut point_count) = (0, 0);
+        for light in lights.values() {
+            let color = light.radiance();
+            match light.kind {
+                LightKind::Ambient => ambient += Vec3::from_array(color),
+                LightKind::Directional { direction } => {
+                    if directional_count < Self::MAX_DIRECTIONAL_LIGHTS {
+                        uniform.directional[directional_count] = DirectionalLightRaw {
+                            direction: direction.to_array(),
+                            color,
+                            ..Zeroable::zeroed()
+                        };
+                        directional_count += 1;
+                    }
+                }
+                LightKind::Point { position, range } => {
+                    if point_count < Self::MAX_POINT_LIGHTS {
+                        uniform.point[point_count] = PointLightRaw {
+                            position: position.to_array(),
+                            range,
+                            color,
+                            _padding: 0.0,
+                        };
+                        point_count += 1;
+                    }
+                }
+            }
+        }
+        uniform.ambient = ambient.to_array();
+        uniform.directional_count = directional_count as u32;
+        uniform.point_count = point_count as u32;
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use anyhow::*;
+
+/// Tables of localized strings by key for each language, see `State::localization`
+///
+/// Tables are loaded from a subset of Fluent (FTL) or from JSON:
+///
+/// ```text
+/// # Messages, with attributes looked up as `message.attribute`
+/// menu =
+///     .start = Start Game
+///     .quit = Quit
+/// -brand = Helia
+/// welcome = Welcome to { -brand }, { $name }!
+/// ```
+///
+/// JSON tables are an object of strings, nested objects are flattened into dotted keys, so
+/// `{ "menu": { "start": "Start Game" } }` also defines `menu.start`.
+///
+/// Keys missing from the current language are looked up in the fallback language, then
+/// returned as is so missing strings stand out. Changing the language calls
+/// `Game::language_changed`, text which polls `version` can also re-set itself.
+#[derive(Debug, Default)]
+pub struct Localization {
+    tables: HashMap<String, HashMap<String, String>>,
+    language: Option<String>,
+    fallback: Option<String>,
+    version: u64,
+    language_changed: bool,
+}
+
+impl Localization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the messages of an FTL file to the language's table
+    pub fn load_ftl(&mut self, language: &str, bytes: &[u8]) -> Result<()> {
+        let text = std::str::from_utf8(bytes).context("FTL file is not UTF-8")?;
+        let mut entries: Vec<(String, String)> = Vec::new();
+        let mut message: Option<String> = None;
+        for (index, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let indented = line.starts_with(char::is_whitespace);
+            if indented && !trimmed.starts_with('.') {
+                // Continuation of a multiline value
+                let Some((_, value)) = entries.last_mut() else {
+                    bail!("FTL line {}: value without a message", index + 1);
+                };
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(trimmed);
+                continue;
+            }
+            let (id, value) = trimmed
+                .split_once('=')
+                .ok_or_else(|| anyhow!("FTL line {}: expected `key = value`", index + 1))?;
+            let (id, value) = (id.trim(), value.trim().to_string());
+            if indented {
+                let Some(message) = &message else {
+                    bail!("FTL line {}: attribute without a message", index + 1);
+                };
+                entries.push((format!("{}{}", message, id), value));
+            } else {
+                message = Some(id.to_string());
+                entries.push((id.to_string(), value));
+            }
+        }
+        let table = self.table_mut(language);
+        for (key, value) in entries {
+            // Messages with only attributes have no value of their own
+            if !value.is_empty() || !table.contains_key(&key) {
+                table.insert(key, value);
+            }
+        }
+        self.table_changed(language);
+        Ok(())
+    }
+
+    /// Add the strings of a JSON object to the language's table
+    pub fn load_json(&mut self, language: &str, bytes: &[u8]) -> Result<()> {
+        let value: serde_json::Value = serde_json::from_slice(bytes).context("Invalid JSON")?;
+        let serde_json::Value::Object(object) = value else {
+            bail!("Localization JSON must be an object");
+        };
+        let mut entries = Vec::new();
+        Self::flatten("", &object, &mut entries)?;
+        self.table_mut(language).extend(entries);
+        self.table_changed(language);
+        Ok(())
+    }
+
+    fn flatten(
+        prefix: &str,
+        object: &serde_json::Map<String, serde_json::Value>,
+        entries: &mut Vec<(String, String)>,
+    ) -> Result<()> {
+        for (key, value) in object.iter() {
+            let key = format!("{}{}", prefix, key);
+            match value {
+                serde_json::Value::String(text) => entries.push((key, text.clone())),
+                serde_json::Value::Object(object) => {
+                    Self::flatten(&format!("{}.", key), object, entries)?
+                }
+                _ => bail!("Localization value {} is not a string or object", key),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, language: &str, key: K, value: V) {
+        self.table_mut(language).insert(key.into(), value.into());
+        self.table_changed(language);
+    }
+
+    fn table_mut(&mut self, language: &str) -> &mut HashMap<String, String> {
+        self.tables.entry(language.to_string()).or_default()
+    }
+
+    /// Text looked up from the language's table may have changed
+    fn table_changed(&mut self, language: &str) {
+        if self.language.as_deref() == Some(language) || self.fallback.as_deref() == Some(language)
+        {
+            self.version += 1;
+        }
+    }
+
+    /// Switch language, text is looked up in the new language's table from now on
+    pub fn set_language(&mut self, language: &str) {
+        if self.language.as_deref() == Some(language) {
+            return;
+        }
+        if !self.tables.contains_key(language) {
+            log::warn!("No strings loaded for language {}", language);
+        }
+        self.language = Some(language.to_string());
+        self.version += 1;
+        self.language_changed = true;
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Language to look up keys missing from the current language in
+    pub fn set_fallback(&mut self, language: Option<&str>) {
+        self.fallback = language.map(str::to_string);
+        self.version += 1;
+    }
+
+    /// Languages with a table loaded
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    /// Increases whenever the language changes or strings are loaded for it, text showing
+    /// localized strings should be re-set when this differs from when it was last set
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub(crate) fn take_language_changed(&mut self) -> bool {
+        std::mem::take(&mut self.language_changed)
+    }
+
+    /// String for the key in the current language, None if neither it nor the fallback
+    /// language has one
+    pub fn get(&self, key: &str) -> Option<&str> {
+        [self.language.as_deref(), self.fallback.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|language| self.tables.get(language)?.get(key))
+            .map(String::as_str)
+            .next()
+    }
+
+    /// String for the key in the current language, the key itself if missing
+    pub fn tr<'a>(&'a self, key: &'a str) -> &'a str {
+        self.get(key).unwrap_or(key)
+    }
+
+    /// String for the key with its placeables resolved, `{ $name }` from the arguments and
+    /// `{ -term }` or `{ message }` from the tables
+    pub fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let text = self.tr(key);
+        let mut resolved = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                break;
+            };
+            resolved.push_str(&rest[..start]);
+            let placeable = rest[start + 1..start + length].trim();
+            match placeable.strip_prefix('$') {
+                Some(name) => match args.iter().find(|(arg, _)| *arg == name) {
+                    Some((_, value)) => resolved.push_str(value),
+                    None => resolved.push_str(placeable),
+                },
+                None => resolved.push_str(self.tr(placeable)),
+            }
+            rest = &rest[start + length + 1..];
+        }
+        resolved.push_str(rest);
+        resolved
+    }
+}
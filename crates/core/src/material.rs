@@ -1,11 +1,158 @@
-use crate::{shader::ShaderId, texture::TextureId, State};
+use anyhow::*;
+
+use crate::{
+    shader::{AlphaCutoff, BlendMode, CullMode, DepthBias, PipelineOptions, Shader, ShaderId},
+    texture::{Texture, TextureId},
+    Color, GraphicsContext, Resources, State,
+};
 
 slotmap::new_key_type! { pub struct MaterialId; }
 
+/// Textures the materials of a shader bind at `@group(2)`, each followed by its sampler, see
+/// `ShaderDescriptor::with_texture_layout`
+///
+/// Texture `i` is at `@binding(2 * i)` and its sampler at `@binding(2 * i + 1)`, the material
+/// uniform block, if declared, is at the binding after the last sampler. The built-in shaders
+/// bind a single 2D texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialTextureLayout {
+    textures: Vec<wgpu::TextureViewDimension>,
+}
+
+impl MaterialTextureLayout {
+    pub fn new(textures: &[wgpu::TextureViewDimension]) -> Self {
+        assert!(!textures.is_empty(), "Materials should bind at least one texture");
+        Self {
+            textures: textures.to_vec(),
+        }
+    }
+
+    /// A single 2D texture, as bound by the built-in shaders
+    pub fn single() -> Self {
+        Self::new(&[wgpu::TextureViewDimension::D2])
+    }
+
+    pub fn textures(&self) -> &[wgpu::TextureViewDimension] {
+        &self.textures
+    }
+
+    /// Binding of the material uniform block, see `ShaderDescriptor::with_material_uniforms`
+    pub fn uniforms_binding(&self) -> u32 {
+        2 * self.textures.len() as u32
+    }
+
+    pub fn create_bind_group_layout(
+        &self,
+        device: &wgpu::Device,
+        with_uniforms: bool,
+        label: &str,
+    ) -> wgpu::BindGroupLayout {
+        let mut entries = Vec::with_capacity(2 * self.textures.len() + 1);
+        for (index, view_dimension) in self.textures.iter().enumerate() {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 * index as u32,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: *view_dimension,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 * index as u32 + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                // This should match the filterable field of the texture entry above
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+        if with_uniforms {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: self.uniforms_binding(),
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+        }
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some(label),
+        })
+    }
+
+    /// Check the textures can be bound with the layout, they must match its textures in
+    /// number and dimension, and have a filterable float format
+    ///
+    /// Views aren't inspected, a 2D texture is accepted for an array entry if it was created
+    /// with an array view, e.g. by `Texture::array_from_images`.
+    pub fn validate(&self, textures: &[&Texture]) -> Result<()> {
+        if textures.len() != self.textures.len() {
+            bail!(
+                "Shader binds {} material textures, {} were provided",
+                self.textures.len(),
+                textures.len()
+            );
+        }
+        for (index, (texture, view_dimension)) in textures.iter().zip(&self.textures).enumerate()
+        {
+            let texture = &texture.texture;
+            let layers = texture.depth_or_array_layers();
+            let matches = texture.dimension() == wgpu::TextureDimension::D2
+                && match view_dimension {
+                    wgpu::TextureViewDimension::D2 => layers == 1,
+                    wgpu::TextureViewDimension::D2Array => true,
+                    wgpu::TextureViewDimension::Cube => layers == 6,
+                    wgpu::TextureViewDimension::CubeArray => layers % 6 == 0,
+                    _ => false,
+                };
+            if !matches {
+                bail!(
+                    "Material texture {} is a {:?} texture with {} layers, the shader binds {:?}",
+                    index,
+                    texture.dimension(),
+                    layers,
+                    view_dimension
+                );
+            }
+            let filterable = wgpu::TextureSampleType::Float { filterable: true };
+            if texture.format().sample_type(None, None) != Some(filterable) {
+                bail!(
+                    "Material texture {} has format {:?}, which can't be filtered",
+                    index,
+                    texture.format()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for MaterialTextureLayout {
+    fn default() -> Self {
+        Self::single()
+    }
+}
+
 pub struct Material {
     pub shader: ShaderId,
+    /// The first of `textures`
     pub texture: TextureId,
+    /// Textures bound in the order of the shader's `MaterialTextureLayout`
+    pub textures: Vec<TextureId>,
     pub diffuse_bind_group: wgpu::BindGroup,
+    /// Overrides the shader's pipeline options, materials with different options
+    /// share the shader but render with different pipeline variants
+    pub options: Option<PipelineOptions>,
+    /// Used in place of source alpha when the pipeline options enable `blend_constant`
+    pub blend_constant: Color,
+    /// Bound after the textures at `@group(2)` for shaders which declare material uniforms,
+    /// see `ShaderDescriptor::with_material_uniforms`
+    pub uniforms_buffer: Option<wgpu::Buffer>,
 }
 // todo: we don't want the bind group info in the public types, but that requires us to have
 // an internal representation, as we can't create a bind group until we have the texture,
@@ -27,58 +174,236 @@ pub struct Material {
 // we should investigate this before we attempt to extend our existing scene structure which does track
 // the current bindings, although only at the mesh and material level (where as really it should be per bind group)
 impl Material {
+    /// Material binding a single texture, panics if the shader binds other textures, see
+    /// `with_textures`
     pub fn new(shader: ShaderId, texture: TextureId, state: &State) -> Self {
-        let id = texture;
-        let texture = &state.resources.textures[id];
         // todo: would be nice to provide an overload that takes a enum of BuildInShaders
         // and that we keep track of enum -> ShaderId, that way the user only has to worry about
         // shader ids for shaders they've created
-        let device = &state.device;
+        Self::with_textures(shader, &[texture], state)
+            .expect("Texture should match the shader's texture layout")
+    }
+
+    /// Material binding the textures in the order of the shader's `MaterialTextureLayout`,
+    /// errors if a texture doesn't exist or doesn't match the layout
+    pub fn with_textures(shader: ShaderId, textures: &[TextureId], state: &State) -> Result<Self> {
+        Self::create(
+            shader,
+            textures,
+            &state.device,
+            &state.resources,
+            state.get_texture_bind_group_layout_ref(),
+        )
+    }
+
+    /// As `new`, for code holding a `GraphicsContext` rather than the `State`
+    pub fn from_graphics(shader: ShaderId, texture: TextureId, graphics: &GraphicsContext) -> Self {
+        Self::with_textures_from_graphics(shader, &[texture], graphics)
+            .expect("Texture should match the shader's texture layout")
+    }
+
+    /// As `with_textures`, for code holding a `GraphicsContext` rather than the `State`
+    pub fn with_textures_from_graphics(
+        shader: ShaderId,
+        textures: &[TextureId],
+        graphics: &GraphicsContext,
+    ) -> Result<Self> {
+        Self::create(
+            shader,
+            textures,
+            graphics.device,
+            graphics.resources,
+            graphics.texture_bind_group_layout,
+        )
+    }
+
+    fn create(
+        shader: ShaderId,
+        textures: &[TextureId],
+        device: &wgpu::Device,
+        resources: &Resources,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Self> {
+        let resolved = textures
+            .iter()
+            .map(|id| {
+                resources.textures.get(*id).ok_or_else(|| {
+                    anyhow!("Texture {} does not exist", resources.textures.describe(*id))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let single = MaterialTextureLayout::single();
+        let texture_layout = resources
+            .shaders
+            .get(shader)
+            .map_or(&single, |shader| shader.texture_layout());
+        texture_layout.validate(&resolved)?;
+
+        let material_layout =
+            resources.shaders.get(shader).and_then(|shader| {
+                Some((shader.material_layout()?, shader.material_uniforms_size()))
+            });
+        // Zeroed until written with `set_uniforms`
+        // Materials have no id until inserted, so are labelled with their textures
+        let textures_label = textures
+            .iter()
+            .map(|id| resources.textures.describe(*id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let uniforms_buffer = material_layout.filter(|(_, size)| *size > 0).map(|(_, size)| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&format!("Material Uniform Buffer [{}]", textures_label)),
+                size: size.next_multiple_of(16) as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        let mut entries = Vec::with_capacity(2 * resolved.len() + 1);
+        for (index, texture) in resolved.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 * index as u32,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 * index as u32 + 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
+        entries.extend(uniforms_buffer.as_ref().map(|buffer| wgpu::BindGroupEntry {
+            binding: texture_layout.uniforms_binding(),
+            resource: buffer.as_entire_binding(),
+        }));
         let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: state.get_texture_bind_group_layout_ref(),
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
+            layout: material_layout.map_or(texture_bind_group_layout, |(layout, _)| layout),
+            entries: &entries,
+            label: Some(&format!("Material Bind Group [{}]", textures_label)),
         });
-        Self {
+        Ok(Self {
             shader,
-            texture: id,
+            texture: textures[0],
+            textures: textures.to_vec(),
             diffuse_bind_group,
-        }
+            options: None,
+            blend_constant: Color::WHITE,
+            uniforms_buffer,
+        })
+    }
+
+    /// Set the material's uniform block, e.g. an outline color or dissolve amount shared by
+    /// every entity using the material
+    pub fn with_uniforms<T: bytemuck::Pod>(self, uniforms: &T, state: &State) -> Self {
+        self.set_uniforms(uniforms, state);
+        self
+    }
+
+    /// Write the material's uniform block, the struct must match the size declared with
+    /// `ShaderDescriptor::with_material_uniforms`
+    pub fn set_uniforms<T: bytemuck::Pod>(&self, uniforms: &T, state: &State) {
+        let buffer = self
+            .uniforms_buffer
+            .as_ref()
+            .expect("Material uniforms require a shader which declares them");
+        let shader = &state.resources.shaders[self.shader];
+        assert_eq!(
+            std::mem::size_of::<T>(),
+            shader.material_uniforms_size(),
+            "Material uniforms should match the size declared by the shader"
+        );
+        state
+            .queue
+            .write_buffer(buffer, 0, bytemuck::bytes_of(uniforms));
+    }
+
+    pub fn has_uniforms(&self) -> bool {
+        self.uniforms_buffer.is_some()
+    }
+
+    pub fn with_options(mut self, options: PipelineOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Override just the cull mode, keeping the shader's other options
+    pub fn with_cull_mode(self, cull_mode: CullMode, state: &State) -> Self {
+        let options = self.pipeline_options(&state.resources.shaders[self.shader]);
+        self.with_options(options.with_cull_mode(cull_mode))
+    }
+
+    /// Render both sides of faces, shorthand for `CullMode::None`
+    pub fn double_sided(self, state: &State) -> Self {
+        self.with_cull_mode(CullMode::None, state)
+    }
+
+    /// Override just the depth bias, keeping the shader's other options
+    pub fn with_depth_bias(self, depth_bias: DepthBias, state: &State) -> Self {
+        let options = self.pipeline_options(&state.resources.shaders[self.shader]);
+        self.with_options(options.with_depth_bias(depth_bias))
+    }
+
+    /// Override just the blend mode, keeping the shader's other options, blended materials
+    /// don't write depth and are depth sorted
+    pub fn with_blend_mode(self, blend_mode: BlendMode, state: &State) -> Self {
+        let options = self.pipeline_options(&state.resources.shaders[self.shader]);
+        self.with_options(options.with_blend_mode(blend_mode))
+    }
+
+    /// Render additively, keeping the shader's other options, e.g. for glow sprites
+    pub fn additive(self, state: &State) -> Self {
+        self.with_blend_mode(BlendMode::Additive, state)
+    }
+
+    /// Render opaque with depth writes, discarding fragments with alpha below the cutoff, so
+    /// cutout sprites occlude correctly without being depth sorted, see `AlphaCutoff`
+    pub fn alpha_cutoff(self, cutoff: AlphaCutoff, state: &State) -> Self {
+        let mut options = self.pipeline_options(&state.resources.shaders[self.shader]);
+        options.blend_mode = BlendMode::Opaque;
+        options.blend_constant = false;
+        options.depth_write = true;
+        options.alpha_cutoff = Some(cutoff);
+        self.with_options(options)
+    }
+
+    /// Weight the material's color by the constant when blending rather than by its alpha
+    pub fn with_blend_constant(mut self, blend_constant: Color, state: &State) -> Self {
+        let mut options = self.pipeline_options(&state.resources.shaders[self.shader]);
+        options.blend_constant = true;
+        self.blend_constant = blend_constant;
+        self.with_options(options)
+    }
+
+    /// Options to render with, the material's override or else the shader's defaults
+    pub fn pipeline_options(&self, shader: &Shader) -> PipelineOptions {
+        self.options.unwrap_or(shader.options)
+    }
+
+    /// Blended materials need to be depth sorted
+    pub fn requires_ordering(&self, shader: &Shader) -> bool {
+        self.options
+            .map_or(shader.requires_ordering, |options| options.requires_ordering())
     }
 
     pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         // todo: probably want to expose filtering at some point
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    // This should match the filterable field of the
-                    // corresponding Texture entry above.
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-            label: Some("texture_bind_group_layout"),
-        })
+        MaterialTextureLayout::single().create_bind_group_layout(
+            device,
+            false,
+            "texture_bind_group_layout",
+        )
+    }
+
+    /// Texture bind group layout for 2D texture arrays, used by the sprite array shader
+    pub fn create_array_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let layout = MaterialTextureLayout::new(&[wgpu::TextureViewDimension::D2Array]);
+        layout.create_bind_group_layout(device, false, "texture_array_bind_group_layout")
+    }
+
+    /// Texture bind group layout with a uniform buffer at binding 2, used by shaders which
+    /// declare material uniforms
+    pub fn create_uniforms_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        MaterialTextureLayout::single().create_bind_group_layout(
+            device,
+            true,
+            "material_uniforms_bind_group_layout",
+        )
     }
 }
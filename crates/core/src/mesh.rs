@@ -1,50 +1,435 @@
+use std::{ops::Range, sync::Arc};
+
+use anyhow::*;
 use wgpu::util::DeviceExt;
 use wgpu::Buffer;
 
-use crate::shader::Vertex;
+use crate::{bounds::Aabb, mesh_pool::PoolAllocation, shader::Vertex};
 
 slotmap::new_key_type! { pub struct MeshId; }
 
+/// Vertex and index buffers, shared by the meshes in a page of a `MeshPool`
+#[derive(Clone)]
+pub struct MeshBuffers {
+    pub vertex: Arc<Buffer>,
+    pub index: Arc<Buffer>,
+}
+
+impl MeshBuffers {
+    /// Whether these are the same buffers, so drawing from them needs no rebinding
+    pub fn ptr_eq(&self, other: &MeshBuffers) -> bool {
+        Arc::ptr_eq(&self.vertex, &other.vertex) && Arc::ptr_eq(&self.index, &other.index)
+    }
+}
+
+/// Vertex indices of a mesh, 32 bit indices allow more than 65536 vertices
+#[derive(Debug, Clone, Copy)]
+pub enum Indices<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl Indices<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn format(&self) -> wgpu::IndexFormat {
+        match self {
+            Indices::U16(_) => wgpu::IndexFormat::Uint16,
+            Indices::U32(_) => wgpu::IndexFormat::Uint32,
+        }
+    }
+
+    fn get(&self, index: usize) -> usize {
+        match self {
+            Indices::U16(indices) => indices[index] as usize,
+            Indices::U32(indices) => indices[index] as usize,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+
+    fn validate(&self, vertex_count: usize) -> Result<()> {
+        let out_of_range = (0..self.len())
+            .map(|i| self.get(i))
+            .find(|i| *i >= vertex_count);
+        if let Some(index) = out_of_range {
+            bail!(
+                "Index {} is out of range of {} vertices",
+                index,
+                vertex_count
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Attribute of `Vertex`, stored as 32 bit floats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshAttribute {
+    /// 3 floats, required
+    Position,
+    /// 2 floats, zero if omitted
+    TexCoords,
+    /// 3 floats, computed from the triangles if omitted
+    Normal,
+}
+
+impl MeshAttribute {
+    fn component_count(&self) -> usize {
+        match self {
+            MeshAttribute::Position | MeshAttribute::Normal => 3,
+            MeshAttribute::TexCoords => 2,
+        }
+    }
+}
+
+/// Where an attribute is found within each vertex of interleaved data, see
+/// `Mesh::from_interleaved`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeDescriptor {
+    pub attribute: MeshAttribute,
+    /// Offset in bytes from the start of the vertex
+    pub offset: usize,
+}
+
+impl AttributeDescriptor {
+    pub fn new(attribute: MeshAttribute, offset: usize) -> Self {
+        Self { attribute, offset }
+    }
+
+    /// Attributes of data laid out as `Vertex`, which is uploaded without conversion
+    pub fn vertex_layout() -> [AttributeDescriptor; 3] {
+        [
+            Self::new(MeshAttribute::Position, 0),
+            Self::new(MeshAttribute::TexCoords, 12),
+            Self::new(MeshAttribute::Normal, 20),
+        ]
+    }
+}
+
 pub struct Mesh {
-    pub vertex_buffer: Buffer,
-    pub index_buffer: Buffer,
+    pub buffers: MeshBuffers,
+    /// Offset of the mesh's vertices in the vertex buffer, added to each index
+    pub base_vertex: i32,
+    /// Offset of the mesh's indices in the index buffer
+    pub first_index: u32,
     pub index_count: u32,
+    pub index_format: wgpu::IndexFormat,
+    /// Bounds of the vertex positions in model space, used for frustum culling and picking
+    /// unless overridden per entity with `RenderProperties::bounds`
+    pub bounds: Aabb,
+    /// Returned to the pool when the mesh is dropped
+    allocation: Option<PoolAllocation>,
 }
 
 impl Mesh {
     pub fn new(vertices: &[Vertex], indices: &[u16], device: &wgpu::Device) -> Self {
+        Self::with_label(vertices, indices, device, None)
+    }
+
+    /// Label is used to name the buffers in GPU captures and validation errors
+    pub fn with_label(
+        vertices: &[Vertex],
+        indices: &[u16],
+        device: &wgpu::Device,
+        label: Option<&str>,
+    ) -> Self {
+        Self::with_indices(vertices, Indices::U16(indices), device, label)
+    }
+
+    /// Mesh with 16 or 32 bit indices, see `with_label`
+    pub fn with_indices(
+        vertices: &[Vertex],
+        indices: Indices,
+        device: &wgpu::Device,
+        label: Option<&str>,
+    ) -> Self {
+        Self::upload(
+            bytemuck::cast_slice(vertices),
+            Self::bounds(vertices),
+            indices,
+            device,
+            label,
+        )
+    }
+
+    /// Mesh from vertex data interleaved with the given stride in bytes, such as a glTF
+    /// buffer view, attributes other than those of `Vertex` are skipped
+    ///
+    /// Data already laid out as `Vertex`, see `AttributeDescriptor::vertex_layout`, is
+    /// uploaded as is. Returns an error if there is no position attribute, an attribute
+    /// doesn't fit in the stride, or an index is out of range.
+    pub fn from_interleaved(
+        data: &[u8],
+        stride: usize,
+        attributes: &[AttributeDescriptor],
+        indices: Indices,
+        device: &wgpu::Device,
+    ) -> Result<Self> {
+        let find = |attribute| {
+            attributes
+                .iter()
+                .find(|descriptor| descriptor.attribute == attribute)
+                .map(|descriptor| descriptor.offset)
+        };
+        let position = find(MeshAttribute::Position)
+            .ok_or_else(|| anyhow!("Interleaved vertex data has no position attribute"))?;
+        if let Some(descriptor) = attributes.iter().find(|descriptor| {
+            descriptor.offset + 4 * descriptor.attribute.component_count() > stride
+        }) {
+            bail!(
+                "{:?} attribute at offset {} does not fit within a stride of {}",
+                descriptor.attribute,
+                descriptor.offset,
+                stride
+            );
+        }
+        // The last vertex need only extend to the end of its attributes
+        let vertex_end = attributes
+            .iter()
+            .map(|descriptor| descriptor.offset + 4 * descriptor.attribute.component_count())
+            .max()
+            .unwrap_or(0);
+        let vertex_count = (data.len() + stride).saturating_sub(vertex_end) / stride;
+        indices.validate(vertex_count)?;
+
+        let read = |vertex: usize, offset: usize, count: usize| -> &[u8] {
+            let start = vertex * stride + offset;
+            &data[start..start + 4 * count]
+        };
+        let read_vec3 = |vertex: usize, offset: usize| {
+            glam::Vec3::from(bytemuck::pod_read_unaligned::<[f32; 3]>(read(
+                vertex, offset, 3,
+            )))
+        };
+        let positions = (0..vertex_count)
+            .map(|i| read_vec3(i, position))
+            .collect::<Vec<_>>();
+        let bounds = Aabb::from_points(positions.iter().copied());
+
+        let vertex_size = std::mem::size_of::<Vertex>();
+        let matches_vertex = AttributeDescriptor::vertex_layout()
+            .iter()
+            .all(|descriptor| attributes.contains(descriptor));
+        if stride == vertex_size && matches_vertex && data.len() >= vertex_count * vertex_size {
+            let data = &data[..vertex_count * vertex_size];
+            return Ok(Self::upload(data, bounds, indices, device, None));
+        }
+
+        let normals = match find(MeshAttribute::Normal) {
+            Some(offset) => (0..vertex_count).map(|i| read_vec3(i, offset)).collect(),
+            None => Self::normals_from_indices(&positions, indices),
+        };
+        let uv_offset = find(MeshAttribute::TexCoords);
+        let vertices = (0..vertex_count)
+            .map(|i| Vertex {
+                position: positions[i].to_array(),
+                tex_coords: uv_offset.map_or([0.0; 2], |offset| {
+                    bytemuck::pod_read_unaligned(read(i, offset, 2))
+                }),
+                normal: normals[i].to_array(),
+            })
+            .collect::<Vec<_>>();
+        Ok(Self::upload(
+            bytemuck::cast_slice(&vertices),
+            bounds,
+            indices,
+            device,
+            None,
+        ))
+    }
+
+    /// Mesh from separate attribute arrays with 16 or 32 bit indices, missing texture
+    /// coordinates are zero and missing normals are computed from the triangles
+    ///
+    /// Returns an error if the arrays differ in length or an index is out of range.
+    pub fn from_planar(
+        positions: &[[f32; 3]],
+        uvs: Option<&[[f32; 2]]>,
+        normals: Option<&[[f32; 3]]>,
+        indices: Indices,
+        device: &wgpu::Device,
+    ) -> Result<Self> {
+        let vertex_count = positions.len();
+        if uvs.is_some_and(|uvs| uvs.len() != vertex_count)
+            || normals.is_some_and(|normals| normals.len() != vertex_count)
+        {
+            bail!("Attribute arrays should all have {} elements", vertex_count);
+        }
+        indices.validate(vertex_count)?;
+
+        let computed_normals = match normals {
+            Some(_) => Vec::new(),
+            None => {
+                let positions = positions
+                    .iter()
+                    .map(|p| glam::Vec3::from(*p))
+                    .collect::<Vec<_>>();
+                Self::normals_from_indices(&positions, indices)
+            }
+        };
+        let vertices = (0..vertex_count)
+            .map(|i| Vertex {
+                position: positions[i],
+                tex_coords: uvs.map_or([0.0; 2], |uvs| uvs[i]),
+                normal: normals
+                    .map_or_else(|| computed_normals[i].to_array(), |normals| normals[i]),
+            })
+            .collect::<Vec<_>>();
+        Ok(Self::with_indices(&vertices, indices, device, None))
+    }
+
+    fn upload(
+        vertex_data: &[u8],
+        bounds: Aabb,
+        indices: Indices,
+        device: &wgpu::Device,
+        label: Option<&str>,
+    ) -> Self {
+        let vertex_label = label.map(|label| format!("{} Vertex Buffer", label));
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
+            label: Some(vertex_label.as_deref().unwrap_or("Vertex Buffer")),
+            contents: vertex_data,
             usage: wgpu::BufferUsages::VERTEX,
         });
 
+        let index_label = label.map(|label| format!("{} Index Buffer", label));
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(indices),
+            label: Some(index_label.as_deref().unwrap_or("Index Buffer")),
+            contents: indices.bytes(),
             usage: wgpu::BufferUsages::INDEX,
         });
         Self {
-            vertex_buffer,
-            index_buffer,
+            buffers: MeshBuffers {
+                vertex: Arc::new(vertex_buffer),
+                index: Arc::new(index_buffer),
+            },
+            base_vertex: 0,
+            first_index: 0,
             index_count: indices.len() as u32,
+            index_format: indices.format(),
+            bounds,
+            allocation: None,
+        }
+    }
+
+    /// Mesh whose data has been written to a page of a `MeshPool`
+    pub(crate) fn from_pool(
+        vertices: &[Vertex],
+        index_count: u32,
+        buffers: MeshBuffers,
+        base_vertex: i32,
+        first_index: u32,
+        allocation: PoolAllocation,
+    ) -> Self {
+        Self {
+            buffers,
+            base_vertex,
+            first_index,
+            index_count,
+            index_format: wgpu::IndexFormat::Uint16,
+            bounds: Self::bounds(vertices),
+            allocation: Some(allocation),
         }
     }
 
+    /// Recompute the bounds from the vertices, e.g. after the mesh's vertex data has been
+    /// changed on the GPU, which the mesh can't read back
+    pub fn recompute_bounds(&mut self, vertices: &[Vertex]) {
+        self.bounds = Self::bounds(vertices);
+    }
+
+    fn bounds(vertices: &[Vertex]) -> Aabb {
+        Aabb::from_points(
+            vertices
+                .iter()
+                .map(|vertex| glam::Vec3::from(vertex.position)),
+        )
+    }
+
+    /// Whether the mesh shares buffers from a `MeshPool` rather than having its own
+    pub fn is_pooled(&self) -> bool {
+        self.allocation.is_some()
+    }
+
+    /// Range of the index buffer to draw
+    pub fn index_range(&self) -> Range<u32> {
+        self.first_index..self.first_index + self.index_count
+    }
+
+    /// Normals are computed from the triangles, see `compute_normals`
     pub fn from_arrays(
         positions: &[glam::Vec3],
         uvs: &[glam::Vec2],
         indicies: &[u16],
         device: &wgpu::Device,
     ) -> Self {
+        let normals = Self::compute_normals(positions, indicies);
+        Self::from_arrays_with_normals(positions, uvs, &normals, indicies, device)
+    }
+
+    pub fn from_arrays_with_normals(
+        positions: &[glam::Vec3],
+        uvs: &[glam::Vec2],
+        normals: &[glam::Vec3],
+        indicies: &[u16],
+        device: &wgpu::Device,
+    ) -> Self {
+        let vertices = Self::vertices_from_arrays(positions, uvs, normals);
+        Mesh::new(vertices.as_slice(), indicies, &device)
+    }
+
+    pub fn vertices_from_arrays(
+        positions: &[glam::Vec3],
+        uvs: &[glam::Vec2],
+        normals: &[glam::Vec3],
+    ) -> Vec<Vertex> {
         let mut vertices = Vec::new();
         for i in 0..positions.len() {
             vertices.push(Vertex {
                 position: positions[i].to_array(),
                 tex_coords: uvs[i].to_array(),
+                normal: normals[i].to_array(),
             });
         }
-        Mesh::new(vertices.as_slice(), indicies, &device)
+        vertices
+    }
+
+    /// Smooth vertex normals from counter-clockwise triangles, each vertex's normal is the
+    /// area weighted average of the faces using it, so give hard edges separate vertices
+    /// Vertices not used by any triangle face +z
+    pub fn compute_normals(positions: &[glam::Vec3], indicies: &[u16]) -> Vec<glam::Vec3> {
+        Self::normals_from_indices(positions, Indices::U16(indicies))
+    }
+
+    fn normals_from_indices(positions: &[glam::Vec3], indices: Indices) -> Vec<glam::Vec3> {
+        let mut normals = vec![glam::Vec3::ZERO; positions.len()];
+        for triangle in 0..indices.len() / 3 {
+            let [a, b, c] = [0, 1, 2].map(|i| indices.get(3 * triangle + i));
+            // Length of the cross product is twice the triangle's area, weighting the sum
+            let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            normals[a] += normal;
+            normals[b] += normal;
+            normals[c] += normal;
+        }
+        normals
+            .into_iter()
+            .map(|normal| normal.try_normalize().unwrap_or(glam::Vec3::Z))
+            .collect()
     }
     // todo: generic on Vertex type
 }
@@ -0,0 +1,277 @@
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    mesh::{Mesh, MeshBuffers},
+    shader::Vertex,
+};
+
+/// First fit allocator of ranges within a page, freed ranges are merged with their neighbours
+#[derive(Debug)]
+struct FreeList {
+    /// Sorted and non-adjacent
+    free: Vec<Range<u32>>,
+}
+
+impl FreeList {
+    fn new(capacity: u32) -> Self {
+        Self {
+            free: std::iter::once(0..capacity).collect(),
+        }
+    }
+
+    fn allocate(&mut self, length: u32) -> Option<Range<u32>> {
+        let index = self
+            .free
+            .iter()
+            .position(|range| range.end - range.start >= length)?;
+        let range = &mut self.free[index];
+        let allocated = range.start..range.start + length;
+        range.start += length;
+        if range.start == range.end {
+            self.free.remove(index);
+        }
+        Some(allocated)
+    }
+
+    fn free(&mut self, range: Range<u32>) {
+        let index = self.free.partition_point(|free| free.end <= range.start);
+        self.free.insert(index, range);
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free.remove(index + 1).end;
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free.remove(index).end;
+        }
+    }
+
+    fn free_count(&self) -> u32 {
+        self.free.iter().map(|range| range.end - range.start).sum()
+    }
+}
+
+#[derive(Debug)]
+struct PageRanges {
+    vertices: FreeList,
+    indices: FreeList,
+}
+
+/// Ranges of a pooled mesh, returned to the page when the mesh is dropped
+#[derive(Debug)]
+pub(crate) struct PoolAllocation {
+    vertices: Range<u32>,
+    indices: Range<u32>,
+    page: Arc<Mutex<PageRanges>>,
+}
+
+impl Drop for PoolAllocation {
+    fn drop(&mut self) {
+        if let Ok(mut page) = self.page.lock() {
+            page.vertices.free(self.vertices.clone());
+            page.indices.free(self.indices.clone());
+        }
+    }
+}
+
+struct Page {
+    buffers: MeshBuffers,
+    ranges: Arc<Mutex<PageRanges>>,
+}
+
+/// Shared vertex and index buffers meshes are sub-allocated from, rather than each mesh
+/// creating its own, see `State::mesh_pool`
+///
+/// Meshes in the same page draw without rebinding buffers. Pages are created as needed,
+/// meshes larger than a page get buffers of their own.
+pub struct MeshPool {
+    vertices_per_page: u32,
+    indices_per_page: u32,
+    /// When false indices are offset into the page on upload and meshes draw with a base
+    /// vertex of zero, see `with_base_vertex`
+    base_vertex: bool,
+    pages: Vec<Page>,
+}
+
+impl Default for MeshPool {
+    fn default() -> Self {
+        Self::new(65536, 3 * 65536)
+    }
+}
+
+impl MeshPool {
+    pub fn new(vertices_per_page: u32, indices_per_page: u32) -> Self {
+        Self {
+            vertices_per_page,
+            // Index ranges are allocated in pairs to keep writes 4 byte aligned
+            indices_per_page: indices_per_page.next_multiple_of(2),
+            base_vertex: true,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Whether the adapter supports drawing with a base vertex, which WebGL doesn't, see
+    /// `wgpu::DownlevelFlags::BASE_VERTEX`
+    ///
+    /// Without it pages are limited to the vertices a 16 bit index can address.
+    pub fn with_base_vertex(mut self, supported: bool) -> Self {
+        self.base_vertex = supported;
+        if !supported {
+            self.vertices_per_page = self.vertices_per_page.min(u16::MAX as u32 + 1);
+        }
+        self
+    }
+
+    /// Create a mesh in the pool, see `Mesh::new`
+    pub fn create(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Mesh {
+        let vertex_count = vertices.len() as u32;
+        let index_count = (indices.len() as u32).next_multiple_of(2);
+        if vertex_count == 0
+            || vertex_count > self.vertices_per_page
+            || index_count > self.indices_per_page
+        {
+            return Mesh::new(vertices, indices, device);
+        }
+
+        let allocation = self
+            .pages
+            .iter()
+            .enumerate()
+            .find_map(|(index, page)| {
+                let mut ranges = page.ranges.lock().ok()?;
+                let vertices = ranges.vertices.allocate(vertex_count)?;
+                match ranges.indices.allocate(index_count) {
+                    Some(indices) => Some((index, vertices, indices)),
+                    None => {
+                        ranges.vertices.free(vertices);
+                        None
+                    }
+                }
+            })
+            .or_else(|| {
+                self.pages.push(self.create_page(device));
+                let mut ranges = self.pages.last()?.ranges.lock().ok()?;
+                let vertices = ranges.vertices.allocate(vertex_count)?;
+                let indices = ranges.indices.allocate(index_count)?;
+                Some((self.pages.len() - 1, vertices, indices))
+            });
+        let Some((page_index, vertex_range, index_range)) = allocation else {
+            return Mesh::new(vertices, indices, device);
+        };
+
+        let page = &self.pages[page_index];
+        let vertex_stride = std::mem::size_of::<Vertex>() as wgpu::BufferAddress;
+        queue.write_buffer(
+            &page.buffers.vertex,
+            vertex_range.start as wgpu::BufferAddress * vertex_stride,
+            bytemuck::cast_slice(vertices),
+        );
+        let index_stride = std::mem::size_of::<u16>() as wgpu::BufferAddress;
+        let mut padded_indices = if self.base_vertex {
+            indices.to_vec()
+        } else {
+            // Fits as the page's vertices are limited to the range of u16
+            let offset = vertex_range.start as u16;
+            indices.iter().map(|index| index + offset).collect()
+        };
+        padded_indices.resize(index_count as usize, 0);
+        queue.write_buffer(
+            &page.buffers.index,
+            index_range.start as wgpu::BufferAddress * index_stride,
+            bytemuck::cast_slice(&padded_indices),
+        );
+
+        Mesh::from_pool(
+            vertices,
+            indices.len() as u32,
+            page.buffers.clone(),
+            if self.base_vertex {
+                vertex_range.start as i32
+            } else {
+                0
+            },
+            index_range.start,
+            PoolAllocation {
+                vertices: vertex_range,
+                indices: index_range,
+                page: page.ranges.clone(),
+            },
+        )
+    }
+
+    /// Create a mesh in the pool from separate arrays, see `Mesh::from_arrays`
+    pub fn create_from_arrays(
+        &mut self,
+        positions: &[glam::Vec3],
+        uvs: &[glam::Vec2],
+        indices: &[u16],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Mesh {
+        let normals = Mesh::compute_normals(positions, indices);
+        let vertices = Mesh::vertices_from_arrays(positions, uvs, &normals);
+        self.create(&vertices, indices, device, queue)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Vertices in use across all pages
+    pub fn allocated_vertices(&self) -> u32 {
+        self.pages
+            .iter()
+            .filter_map(|page| page.ranges.lock().ok())
+            .map(|ranges| self.vertices_per_page - ranges.vertices.free_count())
+            .sum()
+    }
+
+    /// Release pages with no meshes allocated in them
+    pub fn trim(&mut self) {
+        let (vertices_per_page, indices_per_page) = (self.vertices_per_page, self.indices_per_page);
+        self.pages.retain(|page| {
+            page.ranges.lock().is_ok_and(|ranges| {
+                ranges.vertices.free_count() < vertices_per_page
+                    || ranges.indices.free_count() < indices_per_page
+            })
+        });
+    }
+
+    fn create_page(&self, device: &wgpu::Device) -> Page {
+        let buffer = |label, size, usage| {
+            Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: usage | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }))
+        };
+        let vertex_size = std::mem::size_of::<Vertex>() as wgpu::BufferAddress;
+        let index_size = std::mem::size_of::<u16>() as wgpu::BufferAddress;
+        Page {
+            buffers: MeshBuffers {
+                vertex: buffer(
+                    "Mesh Pool Vertex Buffer",
+                    self.vertices_per_page as wgpu::BufferAddress * vertex_size,
+                    wgpu::BufferUsages::VERTEX,
+                ),
+                index: buffer(
+                    "Mesh Pool Index Buffer",
+                    self.indices_per_page as wgpu::BufferAddress * index_size,
+                    wgpu::BufferUsages::INDEX,
+                ),
+            },
+            ranges: Arc::new(Mutex::new(PageRanges {
+                vertices: FreeList::new(self.vertices_per_page),
+                indices: FreeList::new(self.indices_per_page),
+            })),
+        }
+    }
+}
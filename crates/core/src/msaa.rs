@@ -0,0 +1,88 @@
+use winit::dpi::PhysicalSize;
+
+use crate::texture::Texture;
+
+struct MsaaTarget {
+    color: Texture,
+    depth: Texture,
+    size: PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+}
+
+/// Multisampled color and depth attachments the scene is drawn to before being resolved
+/// into the frame, see `Helia::with_msaa`
+pub(crate) struct Multisampling {
+    pub sample_count: u32,
+    target: Option<MsaaTarget>,
+}
+
+impl Multisampling {
+    pub fn new(sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            target: None,
+        }
+    }
+
+    /// Highest supported sample count not above the requested count, as support for counts
+    /// other than 1 and 4 depends on the adapter and format
+    pub fn supported_sample_count(
+        adapter: &wgpu::Adapter,
+        format: wgpu::TextureFormat,
+        requested: u32,
+    ) -> u32 {
+        let color = adapter.get_texture_format_features(format).flags;
+        let depth = adapter
+            .get_texture_format_features(Texture::DEPTH_FORMAT)
+            .flags;
+        [16, 8, 4, 2]
+            .into_iter()
+            .filter(|count| *count <= requested)
+            .find(|count| {
+                color.sample_count_supported(*count) && depth.sample_count_supported(*count)
+            })
+            .unwrap_or(1)
+    }
+
+    /// Recreate the attachments if the size or format of the frame has changed
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) {
+        if self
+            .target
+            .as_ref()
+            .is_some_and(|target| target.size == size && target.format == format)
+        {
+            return;
+        }
+        self.target = Some(MsaaTarget {
+            color: Texture::create_render_target_with_samples(
+                device,
+                size.width,
+                size.height,
+                format,
+                self.sample_count,
+                "MSAA Color Target",
+            ),
+            depth: Texture::create_depth_texture_with_samples(
+                device,
+                size.width,
+                size.height,
+                self.sample_count,
+                "MSAA Depth Target",
+            ),
+            size,
+            format,
+        });
+    }
+
+    /// Color and depth views, available once prepared
+    pub fn views(&self) -> Option<(&wgpu::TextureView, &wgpu::TextureView)> {
+        self.target
+            .as_ref()
+            .map(|target| (&target.color.view, &target.depth.view))
+    }
+}
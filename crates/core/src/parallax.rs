@@ -0,0 +1,131 @@
+use glam::*;
+
+use crate::{
+    camera::Camera,
+    entity::RenderProperties,
+    material::MaterialId,
+    mesh::MeshId,
+    Color, DrawCommand,
+};
+
+/// Background layer which scrolls relative to an orthographic camera
+///
+/// The mesh should be a centered unit quad, for layers which repeat the material's texture
+/// needs a repeating sampler, see `Texture::with_address_mode`.
+#[derive(Debug, Clone, Copy)]
+pub struct ParallaxLayer {
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    /// World size of one repeat of the texture
+    pub tile_size: Vec2,
+    /// How much the layer moves with the world, 0.0 stays fixed on screen (infinitely far away),
+    /// 1.0 moves with the world, values between give the parallax effect
+    pub scroll_factor: Vec2,
+    /// Position of the center of a tile when the camera is at the origin
+    pub offset: Vec2,
+    /// Tile the texture to fill the view along each axis, otherwise a single tile is drawn
+    pub repeat: BVec2,
+    pub depth: f32,
+    pub color: Color,
+}
+
+impl ParallaxLayer {
+    pub fn new(mesh: MeshId, material: MaterialId, tile_size: Vec2) -> Self {
+        Self {
+            mesh,
+            material,
+            tile_size,
+            scroll_factor: Vec2::ZERO,
+            offset: Vec2::ZERO,
+            repeat: BVec2::TRUE,
+            depth: 0.0,
+            color: Color::WHITE,
+        }
+    }
+
+    pub fn with_scroll_factor(mut self, scroll_factor: Vec2) -> Self {
+        self.scroll_factor = scroll_factor;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat_x: bool, repeat_y: bool) -> Self {
+        self.repeat = BVec2::new(repeat_x, repeat_y);
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, camera: &Camera, commands: &mut Vec<DrawCommand>) {
+        let center = camera.target.truncate();
+        let view_min = center + Vec2::new(camera.size.left, camera.size.bottom);
+        let view_max = center + Vec2::new(camera.size.right, camera.size.top);
+        let anchor = center * (Vec2::ONE - self.scroll_factor) + self.offset;
+
+        // Texture coordinates are relative to the tile centered on the anchor, v runs downwards
+        let (x, width, u_offset, u_scale) = if self.repeat.x {
+            let width = view_max.x - view_min.x;
+            let u_offset = (view_min.x - anchor.x) / self.tile_size.x + 0.5;
+            (center.x, width, u_offset, width / self.tile_size.x)
+        } else {
+            (anchor.x, self.tile_size.x, 0.0, 1.0)
+        };
+        let (y, height, v_offset, v_scale) = if self.repeat.y {
+            let height = view_max.y - view_min.y;
+            let v_offset = -(view_max.y - anchor.y) / self.tile_size.y + 0.5;
+            (center.y, height, v_offset, height / self.tile_size.y)
+        } else {
+            (anchor.y, self.tile_size.y, 0.0, 1.0)
+        };
+
+        let matrix = Mat4::from_scale_rotation_translation(
+            Vec3::new(width, height, 1.0),
+            Quat::IDENTITY,
+            Vec3::new(x, y, self.depth),
+        );
+        commands.push(DrawCommand::Draw(
+            self.mesh,
+            self.material,
+            RenderProperties::builder()
+                .with_matrix(matrix)
+                .with_uv_offset_scale(Vec2::new(u_offset, v_offset), Vec2::new(u_scale, v_scale))
+                .with_color(self.color)
+                .build(),
+        ));
+    }
+}
+
+/// Parallax layers rendered back to front in the order added
+#[derive(Debug, Clone, Default)]
+pub struct ParallaxBackground {
+    pub layers: Vec<ParallaxLayer>,
+}
+
+impl ParallaxBackground {
+    pub fn new() -> Self {
+        Self { layers: Vec::new() }
+    }
+
+    pub fn add_layer(&mut self, layer: ParallaxLayer) -> &mut Self {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn render(&self, camera: &Camera, commands: &mut Vec<DrawCommand>) {
+        for layer in self.layers.iter() {
+            layer.render(camera, commands);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use glam::*;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize_or_zero(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + distance * self.direction
+    }
+
+    /// Distance along the ray to the plane, if the ray hits the plane in front of its origin
+    pub fn intersect_plane(&self, point: Vec3, normal: Vec3) -> Option<f32> {
+        let denominator = normal.dot(self.direction);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        let distance = (point - self.origin).dot(normal) / denominator;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    /// Distance along the ray to the axis aligned box, using the slab method
+    pub fn intersect_aabb(&self, min: Vec3, max: Vec3) -> Option<f32> {
+        let inverse_direction = self.direction.recip();
+        let t1 = (min - self.origin) * inverse_direction;
+        let t2 = (max - self.origin) * inverse_direction;
+        let t_min = t1.min(t2).max_element();
+        let t_max = t1.max(t2).min_element();
+        if t_max >= t_min.max(0.0) {
+            Some(t_min.max(0.0))
+        } else {
+            None
+        }
+    }
+
+    /// Parameter along the line (point + t * direction) of the point closest to the ray
+    /// Returns None if the line is parallel to the ray
+    pub fn closest_point_on_line(&self, point: Vec3, direction: Vec3) -> Option<f32> {
+        let direction = direction.normalize_or_zero();
+        let w = point - self.origin;
+        let b = direction.dot(self.direction);
+        let denominator = 1.0 - b * b;
+        if denominator.abs() < 1e-6 {
+            return None;
+        }
+        let d = direction.dot(w);
+        let e = self.direction.dot(w);
+        Some((b * e - d) / denominator)
+    }
+}
@@ -0,0 +1,125 @@
+use slotmap::SecondaryMap;
+
+use crate::{
+    entity::RenderProperties,
+    prefab::PrefabId,
+    scene::Scene,
+    transform::Transform,
+    transform_hierarchy::TransformId,
+};
+
+/// Reuses prefab instances for short lived entities (bullets, particles, pickups)
+///
+/// Released instances are hidden rather than removed from the scene and are handed out
+/// again by `spawn`, avoiding insert/remove churn in the scene's slotmaps.
+pub struct Pool {
+    prefab: PrefabId,
+    active: Vec<TransformId>,
+    active_index: SecondaryMap<TransformId, usize>,
+    free: Vec<TransformId>,
+}
+
+impl Pool {
+    pub fn new(prefab: PrefabId) -> Self {
+        Self {
+            prefab,
+            active: Vec::new(),
+            active_index: SecondaryMap::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Create a pool with hidden instances already added to the scene
+    pub fn with_capacity(scene: &mut Scene, prefab: PrefabId, capacity: usize) -> Self {
+        let mut pool = Self::new(prefab);
+        pool.reserve(scene, capacity);
+        pool
+    }
+
+    pub fn prefab(&self) -> PrefabId {
+        self.prefab
+    }
+
+    /// Ensure at least `additional` free instances exist
+    pub fn reserve(&mut self, scene: &mut Scene, additional: usize) {
+        let required = additional.saturating_sub(self.free.len());
+        let ids = scene.add_instance_batch(
+            self.prefab,
+            (0..required).map(|_| (Transform::default(), RenderProperties::default())),
+        );
+        for id in ids {
+            scene.set_visible(id, false);
+            self.free.push(id);
+        }
+    }
+
+    /// Show a free instance with the transform and properties, adding a new instance if none are free
+    pub fn spawn(
+        &mut self,
+        scene: &mut Scene,
+        transform: Transform,
+        properties: RenderProperties,
+    ) -> TransformId {
+        let id = loop {
+            match self.free.pop() {
+                // Instances removed from the scene directly are dropped from the pool
+                Some(id) if scene.hierarchy.contains(id) => {
+                    scene.hierarchy.set_transform(id, transform);
+                    scene.get_mut(id).properties = properties;
+                    scene.set_visible(id, true);
+                    break id;
+                }
+                Some(_) => continue,
+                None => break scene.add_instance(self.prefab, transform, properties),
+            }
+        };
+        self.active_index.insert(id, self.active.len());
+        self.active.push(id);
+        id
+    }
+
+    /// Hide the instance and return it to the pool, returns false if it was not active in this pool
+    pub fn release(&mut self, scene: &mut Scene, id: TransformId) -> bool {
+        let Some(index) = self.active_index.remove(id) else {
+            return false;
+        };
+        self.active.swap_remove(index);
+        if let Some(moved) = self.active.get(index) {
+            self.active_index.insert(*moved, index);
+        }
+        if scene.hierarchy.contains(id) {
+            scene.set_visible(id, false);
+            self.free.push(id);
+        }
+        true
+    }
+
+    pub fn release_all(&mut self, scene: &mut Scene) {
+        while let Some(id) = self.active.last().copied() {
+            self.release(scene, id);
+        }
+    }
+
+    pub fn is_active(&self, id: TransformId) -> bool {
+        self.active_index.contains_key(id)
+    }
+
+    /// Currently spawned instances, in no particular order
+    pub fn active(&self) -> &[TransformId] {
+        &self.active
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.active.len()
+    }
+
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Remove every instance owned by the pool from the scene
+    pub fn clear(&mut self, scene: &mut Scene) {
+        scene.remove_batch(self.active.drain(..).chain(self.free.drain(..)));
+        self.active_index.clear();
+    }
+}
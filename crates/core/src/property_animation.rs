@@ -0,0 +1,501 @@
+use glam::{Mat4, Vec2, Vec3};
+use slotmap::{SecondaryMap, SlotMap};
+
+use crate::{
+    entity::RenderProperties, entity_effects::EntityEffect, transform_hierarchy::TransformId, Color,
+};
+
+slotmap::new_key_type! { pub struct AnimationId; }
+
+/// Values which can be blended between keyframes
+pub trait Interpolate: Copy {
+    fn interpolate(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(from: Self, to: Self, t: f32) -> Self {
+        from + (to - from) * t
+    }
+}
+
+/// Booleans can't be blended, so hold until the next keyframe
+impl Interpolate for bool {
+    fn interpolate(from: Self, to: Self, t: f32) -> Self {
+        if t < 1.0 {
+            from
+        } else {
+            to
+        }
+    }
+}
+
+impl Interpolate for Vec2 {
+    fn interpolate(from: Self, to: Self, t: f32) -> Self {
+        from.lerp(to, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(from: Self, to: Self, t: f32) -> Self {
+        let t = t as f64;
+        Color {
+            r: from.r + (to.r - from.r) * t,
+            g: from.g + (to.g - from.g) * t,
+            b: from.b + (to.b - from.b) * t,
+            a: from.a + (to.a - from.a) * t,
+        }
+    }
+}
+
+/// How a keyframe transitions to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Hold the value until the next keyframe, for flashing and blinking
+    Step,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::Step => 0.0,
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe<T> {
+    /// Seconds from the start of the animation
+    pub time: f32,
+    pub value: T,
+    /// Transition from this keyframe to the next
+    pub easing: Easing,
+}
+
+/// Keyframes for a single property, kept sorted by time
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new() -> Self {
+        Self {
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn with_key(self, time: f32, value: T) -> Self {
+        self.with_eased_key(time, value, Easing::Linear)
+    }
+
+    pub fn with_eased_key(mut self, time: f32, value: T, easing: Easing) -> Self {
+        self.insert(Keyframe {
+            time,
+            value,
+            easing,
+        });
+        self
+    }
+
+    pub fn insert(&mut self, keyframe: Keyframe<T>) {
+        let index = self
+            .keyframes
+            .partition_point(|existing| existing.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Value at the time, holding the first and last values outside the keyframes
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        match (next.checked_sub(1), self.keyframes.get(next)) {
+            (None, next) => next.map(|keyframe| keyframe.value),
+            (Some(previous), None) => Some(self.keyframes[previous].value),
+            (Some(previous), Some(next)) => {
+                let previous = &self.keyframes[previous];
+                let t = (time - previous.time) / (next.time - previous.time);
+                Some(T::interpolate(
+                    previous.value,
+                    next.value,
+                    previous.easing.apply(t),
+                ))
+            }
+        }
+    }
+}
+
+impl<T: Interpolate> Default for Track<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keyframed render properties of an entity, e.g. a flashing tint on a pickup
+///
+/// Properties without a track are left untouched when applied.
+#[derive(Debug, Clone, Default)]
+pub struct PropertyAnimation {
+    pub tint: Option<Track<Color>>,
+    pub uv_offset: Option<Track<Vec2>>,
+    pub outline_color: Option<Track<Color>>,
+    pub outline_thickness: Option<Track<f32>>,
+    /// Blend the color, after any tint, toward the color by the track's amount from 0.0 to 1.0
+    pub color_blend: Option<(Color, Track<f32>)>,
+    /// Uniform scale applied on top of the world matrix
+    pub scale: Option<Track<f32>>,
+    /// Hide the entity while false
+    pub visible: Option<Track<bool>>,
+    pub looping: bool,
+}
+
+impl PropertyAnimation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tint(mut self, track: Track<Color>) -> Self {
+        self.tint = Some(track);
+        self
+    }
+
+    pub fn with_uv_offset(mut self, track: Track<Vec2>) -> Self {
+        self.uv_offset = Some(track);
+        self
+    }
+
+    pub fn with_outline_color(mut self, track: Track<Color>) -> Self {
+        self.outline_color = Some(track);
+        self
+    }
+
+    pub fn with_outline_thickness(mut self, track: Track<f32>) -> Self {
+        self.outline_thickness = Some(track);
+        self
+    }
+
+    pub fn with_color_blend(mut self, color: Color, track: Track<f32>) -> Self {
+        self.color_blend = Some((color, track));
+        self
+    }
+
+    pub fn with_scale(mut self, track: Track<f32>) -> Self {
+        self.scale = Some(track);
+        self
+    }
+
+    pub fn with_visible(mut self, track: Track<bool>) -> Self {
+        self.visible = Some(track);
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Time of the last keyframe across all tracks
+    pub fn duration(&self) -> f32 {
+        [
+            self.tint.as_ref().map(Track::duration),
+            self.uv_offset.as_ref().map(Track::duration),
+            self.outline_color.as_ref().map(Track::duration),
+            self.outline_thickness.as_ref().map(Track::duration),
+            self.color_blend.as_ref().map(|(_, track)| track.duration()),
+            self.scale.as_ref().map(Track::duration),
+            self.visible.as_ref().map(Track::duration),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0.0, f32::max)
+    }
+
+    /// Write the animated properties at the time, returns false if the entity is hidden
+    pub fn apply(&self, time: f32, properties: &mut RenderProperties) -> bool {
+        if let Some(color) = self.tint.as_ref().and_then(|track| track.sample(time)) {
+            properties.color = color;
+        }
+        if let Some((color, amount)) = self
+            .color_blend
+            .as_ref()
+            .and_then(|(color, track)| Some((*color, track.sample(time)?)))
+        {
+            properties.color = Color::interpolate(properties.color, color, amount);
+        }
+        if let Some(uv_offset) = self.uv_offset.as_ref().and_then(|track| track.sample(time)) {
+            properties.uv_offset = uv_offset;
+        }
+        if let Some(color) = self
+            .outline_color
+            .as_ref()
+            .and_then(|track| track.sample(time))
+        {
+            properties.outline_color = color;
+        }
+        if let Some(thickness) = self
+            .outline_thickness
+            .as_ref()
+            .and_then(|track| track.sample(time))
+        {
+            properties.outline_thickness = thickness;
+        }
+        if let Some(scale) = self.scale.as_ref().and_then(|track| track.sample(time)) {
+            properties.world_matrix *= Mat4::from_scale(Vec3::splat(scale));
+        }
+        self.visible
+            .as_ref()
+            .and_then(|track| track.sample(time))
+            .unwrap_or(true)
+    }
+}
+
+/// Plays a `PropertyAnimation`, advance with `update` then `apply` to the properties each frame
+///
+/// Animations on scene entities are played by `Scene::animations` instead.
+#[derive(Debug, Clone)]
+pub struct PropertyAnimator {
+    pub animation: PropertyAnimation,
+    pub speed: f32,
+    time: f32,
+    playing: bool,
+}
+
+impl PropertyAnimator {
+    pub fn new(animation: PropertyAnimation) -> Self {
+        Self {
+            animation,
+            speed: 1.0,
+            time: 0.0,
+            playing: true,
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.playing = true;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Advance by the elapsed time, returns true when a non-looping animation finishes
+    pub fn update(&mut self, elapsed: f32) -> bool {
+        if !self.playing {
+            return false;
+        }
+        let duration = self.animation.duration();
+        self.time += elapsed * self.speed;
+        if self.animation.looping {
+            if duration > 0.0 {
+                self.time = self.time.rem_euclid(duration);
+            }
+            false
+        } else if self.time >= duration {
+            self.time = duration;
+            self.playing = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns false if the animation hides the entity
+    pub fn apply(&self, properties: &mut RenderProperties) -> bool {
+        self.animation.apply(self.time, properties)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PlayingAnimation {
+    entity: TransformId,
+    animator: PropertyAnimator,
+    /// Seconds until the animation stops itself
+    remaining: Option<f32>,
+}
+
+/// Property animations playing on scene entities, advanced with the frame's elapsed game time
+/// by `Scene::update` and applied to the draws of `Scene::render`, see `Scene::animations`
+///
+/// Built in effects such as blinking and flashing are played with `start_effect`.
+///
+/// Animations leave the entity's own properties untouched, stopping one restores how the
+/// entity looked before. Non-looping animations hold their last values once finished until
+/// stopped, play them with `play_for` to stop them after a duration instead. Animations on an
+/// entity are applied in the order they were played.
+#[derive(Debug, Clone, Default)]
+pub struct PropertyAnimations {
+    animations: SlotMap<AnimationId, PlayingAnimation>,
+    by_entity: SecondaryMap<TransformId, Vec<AnimationId>>,
+}
+
+impl PropertyAnimations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Play an animation on the entity until stopped
+    pub fn play(&mut self, entity: TransformId, animation: PropertyAnimation) -> AnimationId {
+        self.insert(entity, animation, None)
+    }
+
+    /// Play an animation on the entity which stops itself after the duration in seconds
+    pub fn play_for(
+        &mut self,
+        entity: TransformId,
+        animation: PropertyAnimation,
+        duration: f32,
+    ) -> AnimationId {
+        self.insert(entity, animation, Some(duration))
+    }
+
+    /// Play a built in effect on the entity, looping effects run until stopped while `Flash`
+    /// stops itself once faded
+    pub fn start_effect(&mut self, entity: TransformId, effect: EntityEffect) -> AnimationId {
+        let animation = effect.animation();
+        if animation.looping {
+            self.play(entity, animation)
+        } else {
+            let duration = animation.duration();
+            self.play_for(entity, animation, duration)
+        }
+    }
+
+    /// Play a built in effect on the entity which stops itself after the duration in seconds,
+    /// e.g. blinking while briefly invulnerable
+    pub fn start_effect_for(
+        &mut self,
+        entity: TransformId,
+        effect: EntityEffect,
+        duration: f32,
+    ) -> AnimationId {
+        self.play_for(entity, effect.animation(), duration)
+    }
+
+    fn insert(
+        &mut self,
+        entity: TransformId,
+        animation: PropertyAnimation,
+        remaining: Option<f32>,
+    ) -> AnimationId {
+        let id = self.animations.insert(PlayingAnimation {
+            entity,
+            animator: PropertyAnimator::new(animation),
+            remaining,
+        });
+        match self.by_entity.get_mut(entity) {
+            Some(ids) => ids.push(id),
+            None => {
+                self.by_entity.insert(entity, vec![id]);
+            }
+        }
+        id
+    }
+
+    /// Returns false if the animation had already stopped
+    pub fn stop(&mut self, id: AnimationId) -> bool {
+        let Some(animation) = self.animations.remove(id) else {
+            return false;
+        };
+        if let Some(ids) = self.by_entity.get_mut(animation.entity) {
+            ids.retain(|other| *other != id);
+            if ids.is_empty() {
+                self.by_entity.remove(animation.entity);
+            }
+        }
+        true
+    }
+
+    /// Stop every animation on the entity
+    pub fn stop_all(&mut self, entity: TransformId) {
+        for id in self.by_entity.remove(entity).unwrap_or_default() {
+            self.animations.remove(id);
+        }
+    }
+
+    /// Animator of a playing animation, to pause, restart or change its speed
+    pub fn get(&self, id: AnimationId) -> Option<&PropertyAnimator> {
+        self.animations.get(id).map(|animation| &animation.animator)
+    }
+
+    pub fn get_mut(&mut self, id: AnimationId) -> Option<&mut PropertyAnimator> {
+        self.animations
+            .get_mut(id)
+            .map(|animation| &mut animation.animator)
+    }
+
+    pub fn is_active(&self, id: AnimationId) -> bool {
+        self.animations.contains_key(id)
+    }
+
+    /// Whether the entity has any animations playing
+    pub fn has_animations(&self, entity: TransformId) -> bool {
+        self.by_entity.contains_key(entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.animations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.animations.clear();
+        self.by_entity.clear();
+    }
+
+    /// Advance animations by the elapsed seconds, stopping those played for a duration which
+    /// has passed, called by `Scene::update`
+    pub fn advance(&mut self, elapsed: f32) {
+        let mut expired = Vec::new();
+        for (id, animation) in self.animations.iter_mut() {
+            animation.animator.update(elapsed);
+            if let Some(remaining) = animation.remaining.as_mut() {
+                *remaining -= elapsed;
+                if *remaining <= 0.0 {
+                    expired.push(id);
+                }
+            }
+        }
+        for id in expired {
+            self.stop(id);
+        }
+    }
+
+    /// Write the entity's animations to its properties, returns false if any hide it
+    pub fn apply(&self, entity: TransformId, properties: &mut RenderProperties) -> bool {
+        let mut visible = true;
+        for id in self.by_entity.get(entity).into_iter().flatten() {
+            visible &= self.animations[*id].animator.apply(properties);
+        }
+        visible
+    }
+}
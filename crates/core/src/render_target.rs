@@ -0,0 +1,130 @@
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    camera::Camera,
+    draw_report::DrawReport,
+    frustum::Frustum,
+    texture::{Texture, TextureId},
+    DrawCommand, PassTarget, State,
+};
+
+/// Offscreen color texture with its own depth buffer, for rendering the scene from a second
+/// camera, e.g. a minimap or a security camera screen
+///
+/// The color texture is registered in `state.resources.textures`, so materials can sample it
+/// like any other texture.
+pub struct RenderTarget {
+    pub texture: TextureId,
+    depth_texture: Texture,
+    size: PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+}
+
+impl RenderTarget {
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+}
+
+impl State {
+    /// Create a render target in the surface format, so existing materials can draw into it
+    pub fn create_render_target(&mut self, width: u32, height: u32, label: &str) -> RenderTarget {
+        let format = self.config.format;
+        let texture = Texture::create_render_target(&self.device, width, height, format, label);
+        let depth_texture = Texture::create_depth_texture_with_size(
+            &self.device,
+            width,
+            height,
+            &format!("{label} Depth"),
+        );
+        RenderTarget {
+            texture: self.resources.textures.insert_named(texture, label),
+            depth_texture,
+            size: PhysicalSize::new(width, height),
+            format,
+        }
+    }
+
+    /// Render the draw commands as seen by the camera into the target, cleared to the camera's
+    /// clear color
+    ///
+    /// The commands are submitted immediately, so call this from `Game::update` and the target
+    /// is up to date when the frame is drawn. Scissor rects are relative to the target. A
+    /// material sampling the target must not be drawn into it. Returns the report of the
+    /// commands drawn, this is separate from `draw_report` which only covers the main pass.
+    pub fn render_to_target(
+        &mut self,
+        camera: &Camera,
+        draw_commands: &[DrawCommand],
+        target: &RenderTarget,
+    ) -> DrawReport {
+        let mut report = DrawReport::default();
+        if !self.resources.textures.contains_key(target.texture) {
+            log::warn!(
+                "Render target texture {} has been removed",
+                self.resources.textures.describe(target.texture)
+            );
+            return report;
+        }
+
+        let frustum = self.frustum_culling.then(|| Frustum::from_camera(camera));
+        let mut arena = std::mem::take(&mut self.frame_arena);
+        self.prepare_draws(
+            draw_commands,
+            frustum,
+            camera.cull_mask,
+            target.format,
+            1,
+            &mut report,
+            &mut arena,
+        );
+        let prepared = &arena.prepared;
+        self.bindings.update_cameras(
+            &prepared.cameras,
+            camera,
+            &self.resources.cameras,
+            &self.device,
+            &self.queue,
+        );
+        self.skybox.prepare(
+            &self.device,
+            &self.queue,
+            camera,
+            &self.resources.cameras,
+            prepared.camera_changes.iter().filter_map(|(_, id)| *id),
+            &self.resources.textures,
+            target.format,
+            1,
+        );
+        self.lighting.update(&self.resources.lights, &self.queue);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Target Encoder"),
+            });
+        report.binds = self.encode_draws(
+            &mut encoder,
+            prepared,
+            &PassTarget {
+                view: &self.resources.textures[target.texture].view,
+                resolve_target: None,
+                depth_view: &target.depth_texture.view,
+                size: target.size,
+                format: target.format,
+                sample_count: 1,
+                clear_color: camera.clear_color,
+                viewport: camera.viewport,
+                scissor_scale: 1.0,
+            },
+        );
+        self.frame_arena = arena;
+        // Submitted before the main pass rewrites the entity and camera uniforms
+        self.queue.submit(std::iter::once(encoder.finish()));
+        report
+    }
+}
@@ -0,0 +1,294 @@
+use winit::dpi::PhysicalSize;
+
+use crate::texture::Texture;
+
+// Frames to wait after a change of scale before considering another, gives the
+// average frame time a chance to reflect the new resolution
+const ADJUST_INTERVAL: u32 = 30;
+// Weight of the latest frame in the running average frame time
+const AVERAGE_WEIGHT: f32 = 0.1;
+
+/// Settings for dynamic resolution, the scene is rendered to a scaled down target when
+/// frame time exceeds the budget and then upscaled to the surface
+#[derive(Debug, Clone, Copy)]
+pub struct ResolutionScaling {
+    /// Frame time budget in seconds
+    pub target_frame_time: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Change in scale per adjustment
+    pub step: f32,
+    /// Strength of the sharpening applied when upscaling, 0.0 to disable
+    pub sharpness: f32,
+}
+
+impl Default for ResolutionScaling {
+    fn default() -> Self {
+        Self {
+            target_frame_time: 1.0 / 60.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+            step: 0.1,
+            sharpness: 0.2,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct UpscaleUniforms {
+    texel_size: [f32; 2],
+    sharpness: f32,
+    _padding: f32,
+}
+
+struct ScaledTarget {
+    size: PhysicalSize<u32>,
+    color: Texture,
+    depth: Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+pub(crate) struct AdaptiveResolution {
+    pub settings: ResolutionScaling,
+    scale: f32,
+    average_frame_time: Option<f32>,
+    frames_since_change: u32,
+    format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    target: Option<ScaledTarget>,
+}
+
+impl AdaptiveResolution {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        settings: ResolutionScaling,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("upscale_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Upscale Uniform Buffer"),
+            size: std::mem::size_of::<UpscaleUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pipeline = Self::create_pipeline(device, &bind_group_layout, format);
+
+        Self {
+            scale: settings.max_scale,
+            settings,
+            average_frame_time: None,
+            frames_since_change: 0,
+            format,
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            target: None,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Adjust the scale based on the latest frame time
+    pub fn update(&mut self, frame_time: f32) {
+        let average = match self.average_frame_time {
+            Some(average) => average + AVERAGE_WEIGHT * (frame_time - average),
+            None => frame_time,
+        };
+        self.average_frame_time = Some(average);
+
+        self.frames_since_change += 1;
+        if self.frames_since_change < ADJUST_INTERVAL {
+            return;
+        }
+
+        let settings = &self.settings;
+        let scale = if average > 1.1 * settings.target_frame_time {
+            self.scale - settings.step
+        } else if average < 0.8 * settings.target_frame_time {
+            self.scale + settings.step
+        } else {
+            self.scale
+        };
+        let scale = scale.clamp(settings.min_scale, settings.max_scale);
+        if scale != self.scale {
+            log::info!("Render scale {:.2} -> {:.2}", self.scale, scale);
+            self.scale = scale;
+            self.frames_since_change = 0;
+        }
+    }
+
+    /// Ensure the scaled target matches the current scale, returns false if the scene
+    /// should be rendered directly to the surface instead
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_size: PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) -> bool {
+        if self.scale >= 1.0 {
+            self.target = None;
+            return false;
+        }
+
+        if format != self.format {
+            self.format = format;
+            self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, format);
+            self.target = None;
+        }
+
+        let size = PhysicalSize::new(
+            ((surface_size.width as f32 * self.scale).round() as u32).max(1),
+            ((surface_size.height as f32 * self.scale).round() as u32).max(1),
+        );
+        if self.target.as_ref().is_none_or(|target| target.size != size) {
+            let color = Texture::create_render_target(
+                device,
+                size.width,
+                size.height,
+                format,
+                "scaled_color_texture",
+            );
+            let depth = Texture::create_depth_texture_with_size(
+                device,
+                size.width,
+                size.height,
+                "scaled_depth_texture",
+            );
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("upscale_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&color.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&color.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            self.target = Some(ScaledTarget {
+                size,
+                color,
+                depth,
+                bind_group,
+            });
+        }
+
+        let uniforms = UpscaleUniforms {
+            texel_size: [(size.width as f32).recip(), (size.height as f32).recip()],
+            sharpness: self.settings.sharpness,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        true
+    }
+
+    /// Color and depth views to render the scene to, and their size, valid after `prepare` returns true
+    pub fn target_views(&self) -> Option<(&wgpu::TextureView, &wgpu::TextureView, PhysicalSize<u32>)> {
+        self.target
+            .as_ref()
+            .map(|target| (&target.color.view, &target.depth.view, target.size))
+    }
+
+    /// Draw the scaled target to the view, covering it entirely
+    pub fn upscale(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upscale Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let shader_module = device.create_shader_module(wgpu::include_wgsl!("shaders/upscale.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Upscale Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Upscale Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
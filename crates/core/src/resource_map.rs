@@ -0,0 +1,106 @@
+use std::ops::{Deref, Index, IndexMut};
+
+use slotmap::{Key, SecondaryMap, SlotMap};
+
+/// SlotMap of resources with optional debug names
+///
+/// Derefs to the underlying SlotMap for reading, mutation goes through these methods so
+/// removals also remove names, names are for logs and tooling only and need not be unique.
+pub struct ResourceMap<K: Key, V> {
+    values: SlotMap<K, V>,
+    names: SecondaryMap<K, String>,
+}
+
+impl<K: Key, V> ResourceMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            values: SlotMap::with_key(),
+            names: SecondaryMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: V) -> K {
+        self.values.insert(value)
+    }
+
+    pub fn insert_named<N: Into<String>>(&mut self, value: V, name: N) -> K {
+        let id = self.values.insert(value);
+        self.names.insert(id, name.into());
+        id
+    }
+
+    pub fn set_name<N: Into<String>>(&mut self, id: K, name: N) {
+        if self.values.contains_key(id) {
+            self.names.insert(id, name.into());
+        }
+    }
+
+    pub fn get_name(&self, id: K) -> Option<&str> {
+        self.names.get(id).map(|name| name.as_str())
+    }
+
+    /// Name if set, otherwise the debug representation of the id, for use in logs
+    pub fn describe(&self, id: K) -> String {
+        match self.get_name(id) {
+            Some(name) => format!("'{}' ({:?})", name, id),
+            None => format!("{:?}", id),
+        }
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<K> {
+        self.names
+            .iter()
+            .find(|(_, entry)| entry.as_str() == name)
+            .map(|(id, _)| id)
+    }
+
+    pub fn get_mut(&mut self, id: K) -> Option<&mut V> {
+        self.values.get_mut(id)
+    }
+
+    pub fn iter_mut(&mut self) -> slotmap::basic::IterMut<'_, K, V> {
+        self.values.iter_mut()
+    }
+
+    pub fn values_mut(&mut self) -> slotmap::basic::ValuesMut<'_, K, V> {
+        self.values.values_mut()
+    }
+
+    pub fn remove(&mut self, id: K) -> Option<V> {
+        self.names.remove(id);
+        self.values.remove(id)
+    }
+
+    pub fn clear(&mut self) {
+        self.names.clear();
+        self.values.clear();
+    }
+}
+
+impl<K: Key, V> Default for ResourceMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Key, V> Deref for ResourceMap<K, V> {
+    type Target = SlotMap<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl<K: Key, V> Index<K> for ResourceMap<K, V> {
+    type Output = V;
+
+    fn index(&self, id: K) -> &V {
+        &self.values[id]
+    }
+}
+
+impl<K: Key, V> IndexMut<K> for ResourceMap<K, V> {
+    fn index_mut(&mut self, id: K) -> &mut V {
+        &mut self.values[id]
+    }
+}
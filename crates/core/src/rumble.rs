@@ -0,0 +1,123 @@
+/// Vibration of a gamepad's two rumble motors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumbleEffect {
+    /// Strength of the heavy, low frequency motor from 0.0 to 1.0
+    pub low_frequency: f32,
+    /// Strength of the light, high frequency motor from 0.0 to 1.0
+    pub high_frequency: f32,
+    /// In seconds
+    pub duration: f32,
+}
+
+impl RumbleEffect {
+    pub fn new(low_frequency: f32, high_frequency: f32, duration: f32) -> Self {
+        Self {
+            low_frequency: low_frequency.clamp(0.0, 1.0),
+            high_frequency: high_frequency.clamp(0.0, 1.0),
+            duration: duration.max(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+enum RumbleRequest {
+    Play(usize, RumbleEffect),
+    Stop(usize),
+}
+
+/// Haptic feedback for gamepads, requests are sent to the backend at the end of the frame
+///
+/// Gamepads are identified by their index as reported by the platform. Only the web backend
+/// currently supports rumble, using the Gamepad API's vibration actuator where the browser
+/// provides one, elsewhere requests are ignored, check `is_supported`.
+#[derive(Debug, Default)]
+pub struct Rumble {
+    requests: Vec<RumbleRequest>,
+}
+
+impl Rumble {
+    /// Play the effect on the gamepad, replacing any effect already playing
+    pub fn play(&mut self, gamepad: usize, effect: RumbleEffect) {
+        self.requests.push(RumbleRequest::Play(gamepad, effect));
+    }
+
+    pub fn stop(&mut self, gamepad: usize) {
+        self.requests.push(RumbleRequest::Stop(gamepad));
+    }
+
+    /// Whether the platform has a rumble backend, individual gamepads may still lack motors
+    pub fn is_supported() -> bool {
+        cfg!(target_arch = "wasm32")
+    }
+
+    pub(crate) fn flush(&mut self) {
+        for request in self.requests.drain(..) {
+            Self::send(request);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send(_request: RumbleRequest) {}
+
+    #[cfg(target_arch = "wasm32")]
+    fn send(request: RumbleRequest) {
+        let gamepad = match request {
+            RumbleRequest::Play(gamepad, _) | RumbleRequest::Stop(gamepad) => gamepad,
+        };
+        let result = Self::actuator(gamepad).and_then(|actuator| match request {
+            RumbleRequest::Play(_, effect) => {
+                let params = js_sys::Object::new();
+                for (key, value) in [
+                    ("duration", (effect.duration * 1000.0) as f64),
+                    ("startDelay", 0.0),
+                    ("strongMagnitude", effect.low_frequency as f64),
+                    ("weakMagnitude", effect.high_frequency as f64),
+                ] {
+                    js_sys::Reflect::set(&params, &key.into(), &value.into())?;
+                }
+                Self::call(
+                    &actuator,
+                    "playEffect",
+                    &["dual-rumble".into(), params.into()],
+                )
+            }
+            RumbleRequest::Stop(_) => Self::call(&actuator, "reset", &[]),
+        });
+        if let Err(error) = result {
+            log::debug!("Unable to rumble gamepad {}: {:?}", gamepad, error);
+        }
+    }
+
+    /// Vibration actuator of the gamepad, accessed dynamically as `playEffect` is not yet
+    /// part of web-sys's stable API
+    #[cfg(target_arch = "wasm32")]
+    fn actuator(gamepad: usize) -> Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue> {
+        let window = web_sys::window().ok_or("No window available")?;
+        let navigator = js_sys::Reflect::get(&window, &"navigator".into())?;
+        let gamepads = Self::call(&navigator, "getGamepads", &[])?;
+        let gamepad = js_sys::Reflect::get_u32(&gamepads, gamepad as u32)?;
+        if gamepad.is_null() || gamepad.is_undefined() {
+            return Err("No gamepad connected at index".into());
+        }
+        let actuator = js_sys::Reflect::get(&gamepad, &"vibrationActuator".into())?;
+        if actuator.is_null() || actuator.is_undefined() {
+            return Err("Gamepad has no vibration actuator".into());
+        }
+        Ok(actuator)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn call(
+        target: &wasm_bindgen::JsValue,
+        method: &str,
+        args: &[wasm_bindgen::JsValue],
+    ) -> Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue> {
+        use wasm_bindgen::JsCast;
+
+        let function: js_sys::Function =
+            js_sys::Reflect::get(target, &method.into())?.dyn_into()?;
+        let args = args.iter().collect::<js_sys::Array>();
+        js_sys::Reflect::apply(&function, target, &args)
+    }
+}
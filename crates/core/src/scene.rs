@@ -1,22 +1,38 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use crate::assets::AssetLoader;
+use crate::bounds::Aabb;
 use crate::camera::Camera;
+use crate::draw_report::MAX_LOGGED_DROPS;
 use crate::entity::*;
+use crate::frustum::Frustum;
 use crate::material::*;
 use crate::mesh::*;
+use crate::picking::Ray;
 use crate::prefab::*;
+use crate::property_animation::PropertyAnimations;
+use crate::scene_file::{SceneFile, SceneLoad};
+use crate::scene_validation::{SceneIssue, SceneReport};
+use crate::shader::ShaderId;
+use crate::snapshot::*;
+use crate::time::Time;
 use crate::transform::Transform;
 use crate::transform_hierarchy::TransformId;
 use crate::transform_hierarchy::TransformHierarchy;
 use crate::DrawCommand;
 use crate::Resources;
-use slotmap::SecondaryMap;
+use glam::{Mat4, Vec3};
 use slotmap::DenseSlotMap;
+use slotmap::Key;
+use slotmap::SecondaryMap;
 
 // ^^ should probably consider a prelude, although I do prefer this to throwing everything in the prelude
 
+#[derive(Debug, Clone, Copy)]
 pub struct SceneEntity {
-    pub visible: bool,
+    /// Local visibility, the entity is only rendered if all its ancestors in the hierarchy are also visible
+    /// Private so changes go through `Scene::set_visible`, keeping the visibility cache valid
+    visible: bool,
     pub mesh: MeshId,
     pub material: MaterialId,
     pub properties: RenderProperties,
@@ -31,14 +47,50 @@ impl SceneEntity {
             properties,
         }
     }
+
+    /// Local visibility, see `Scene::set_visible`
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+}
+
+/// Where an entity's id is stored for rendering, either the scene's render objects or a prefab's instances
+#[derive(Debug, Clone, Copy)]
+struct Membership {
+    prefab: Option<PrefabId>,
+    index: usize,
 }
 
 pub struct Scene {
     pub prefabs: DenseSlotMap<PrefabId, Prefab>,
     pub hierarchy: TransformHierarchy,
+    /// Leave entities outside the view of the camera passed to `update` out of the scene graph,
+    /// only enable if the scene is drawn with that camera alone, off by default as the
+    /// renderer already culls draws per camera, see `State::frustum_culling`
+    pub frustum_culling: bool,
+    /// Property animations and effects playing on entities, applied when rendered
+    pub animations: PropertyAnimations,
+    culled_count: usize,
     entities: SecondaryMap<TransformId, SceneEntity>,
     render_objects: Vec<TransformId>,
     scene_graph: Vec<TransformId>,
+    // Removal replaces ids with null 'tombstones' rather than shifting the vecs, preserving
+    // render order, these are compacted out at the start of the next update
+    membership: SecondaryMap<TransformId, Membership>,
+    requires_compaction: bool,
+    names: SecondaryMap<TransformId, String>,
+    name_lookup: HashMap<String, TransformId>,
+    tags: SecondaryMap<TransformId, HashSet<String>>,
+    tag_lookup: HashMap<String, Vec<TransformId>>,
+    node_visibility: SecondaryMap<TransformId, bool>,
+    effective_visibility: SecondaryMap<TransformId, bool>,
+    visibility_dirty: bool,
+    visibility_version: u64,
+    /// World matrices at the end of the previous and latest fixed ticks, see `update_interpolated`
+    tick_matrices: SecondaryMap<TransformId, [Mat4; 2]>,
+    interpolated_tick: Option<u64>,
+    /// Materials already logged as missing or using a missing shader
+    logged_invalid_materials: HashSet<MaterialId>,
 }
 
 impl Scene {
@@ -48,7 +100,23 @@ impl Scene {
             render_objects: Vec::new(),
             entities: SecondaryMap::new(),
             hierarchy: TransformHierarchy::new(),
+            frustum_culling: false,
+            animations: PropertyAnimations::new(),
+            culled_count: 0,
             scene_graph: Vec::new(),
+            membership: SecondaryMap::new(),
+            requires_compaction: false,
+            names: SecondaryMap::new(),
+            name_lookup: HashMap::new(),
+            tags: SecondaryMap::new(),
+            tag_lookup: HashMap::new(),
+            node_visibility: SecondaryMap::new(),
+            effective_visibility: SecondaryMap::new(),
+            visibility_dirty: true,
+            visibility_version: 0,
+            tick_matrices: SecondaryMap::new(),
+            interpolated_tick: None,
+            logged_invalid_materials: HashSet::new(),
         }
     }
 
@@ -71,12 +139,12 @@ impl Scene {
         transform: Transform,
         properties: RenderProperties,
     ) -> TransformId {
-        let prefab = self.prefabs.get_mut(prefab_id).unwrap();
+        let prefab = &self.prefabs[prefab_id];
+        let entity = SceneEntity::new(prefab.mesh, prefab.material, properties);
         let id = self
             .hierarchy
             .insert(transform, None);
-        self.entities.insert(id, SceneEntity::new(prefab.mesh, prefab.material, properties));
-        prefab.instances.push(id);
+        self.insert_entity(id, entity, Some(prefab_id));
         id
     }
 
@@ -90,27 +158,147 @@ impl Scene {
         let id = self
             .hierarchy
             .insert(transform, None);
-        self.entities.insert(id, SceneEntity::new(mesh, material, properties));
-        self.render_objects.push(id);
+        self.insert_entity(id, SceneEntity::new(mesh, material, properties), None);
         id
     }
 
-    pub fn remove(&mut self, id: TransformId) {
-        if let Some(index) = self.render_objects.iter().position(|x| *x == id) {
-            self.render_objects.remove(index);
-            self.hierarchy.remove(id);
-            self.entities.remove(id);
+    fn insert_entity(&mut self, id: TransformId, entity: SceneEntity, prefab_id: Option<PrefabId>) {
+        self.entities.insert(id, entity);
+        let prefab = prefab_id.and_then(|prefab_id| self.prefabs.get_mut(prefab_id));
+        let membership = match prefab {
+            Some(prefab) => {
+                prefab.instances.push(id);
+                Membership { prefab: prefab_id, index: prefab.instances.len() - 1 }
+            }
+            None => {
+                self.render_objects.push(id);
+                Membership { prefab: None, index: self.render_objects.len() - 1 }
+            }
+        };
+        self.membership.insert(id, membership);
+    }
+
+    /// Add many entities at once, reserving capacity up front, returns the ids in the order provided
+    pub fn add_batch<I>(&mut self, entities: I) -> Vec<TransformId>
+    where
+        I: IntoIterator<Item = (MeshId, MaterialId, Transform, RenderProperties)>,
+    {
+        let entities = entities.into_iter().collect::<Vec<_>>();
+        self.reserve_entities(entities.len(), None);
+        let transforms = entities.iter().map(|(_, _, transform, _)| *transform);
+        let ids = self.hierarchy.insert_batch(transforms, None);
+        for (id, (mesh, material, _, properties)) in ids.iter().zip(entities) {
+            self.insert_entity(*id, SceneEntity::new(mesh, material, properties), None);
         }
+        ids
     }
 
-    pub fn remove_instance(&mut self, prefab_id: PrefabId, id: TransformId) {
-        if let Some(prefab) = self.prefabs.get_mut(prefab_id) {
-            if let Some(index) = prefab.instances.iter().position(|x| *x == id) {
-                prefab.instances.remove(index);
-                self.entities.remove(id);
+    /// Add many instances of a prefab at once, reserving capacity up front, returns the ids in the order provided
+    pub fn add_instance_batch<I>(&mut self, prefab_id: PrefabId, instances: I) -> Vec<TransformId>
+    where
+        I: IntoIterator<Item = (Transform, RenderProperties)>,
+    {
+        let instances = instances.into_iter().collect::<Vec<_>>();
+        self.reserve_entities(instances.len(), Some(prefab_id));
+        let prefab = &self.prefabs[prefab_id];
+        let (mesh, material) = (prefab.mesh, prefab.material);
+        let transforms = instances.iter().map(|(transform, _)| *transform);
+        let ids = self.hierarchy.insert_batch(transforms, None);
+        for (id, (_, properties)) in ids.iter().zip(instances) {
+            self.insert_entity(*id, SceneEntity::new(mesh, material, properties), Some(prefab_id));
+        }
+        ids
+    }
+
+    /// Reserve capacity in the hierarchy and every map keyed by entity for more entities
+    fn reserve_entities(&mut self, additional: usize, prefab_id: Option<PrefabId>) {
+        self.hierarchy.reserve(additional);
+        let capacity = self.hierarchy.capacity();
+        self.entities.set_capacity(capacity);
+        self.membership.set_capacity(capacity);
+        match prefab_id.and_then(|prefab_id| self.prefabs.get_mut(prefab_id)) {
+            Some(prefab) => prefab.instances.reserve(additional),
+            None => self.render_objects.reserve(additional),
+        }
+    }
+
+    /// Remove a node, any entity attached to it, and all its descendants from the scene
+    /// Works for both individual entities and prefab instances, returns false if the id was not in the scene
+    pub fn remove(&mut self, id: TransformId) -> bool {
+        self.remove_batch([id]) > 0
+    }
+
+    /// Remove many nodes and their descendants, returns the number of provided ids which were removed
+    pub fn remove_batch<I: IntoIterator<Item = TransformId>>(&mut self, ids: I) -> usize {
+        let mut unique = HashSet::new();
+        let roots = ids
+            .into_iter()
+            .filter(|id| self.hierarchy.contains(*id) && unique.insert(*id))
+            .collect::<Vec<_>>();
+
+        let mut subtree = HashSet::new();
+        let mut pending = roots.clone();
+        while let Some(id) = pending.pop() {
+            if subtree.insert(id) {
+                pending.extend_from_slice(self.hierarchy.get_children(id));
+            }
+        }
+
+        for id in subtree {
+            if let Some(membership) = self.membership.remove(id) {
+                let ids = match membership.prefab {
+                    Some(prefab_id) => self.prefabs.get_mut(prefab_id).map(|prefab| &mut prefab.instances),
+                    None => Some(&mut self.render_objects),
+                };
+                if let Some(slot) = ids.and_then(|ids| ids.get_mut(membership.index)) {
+                    *slot = TransformId::null();
+                    self.requires_compaction = true;
+                }
+            }
+            self.entities.remove(id);
+            self.clear_node_metadata(id);
+        }
+
+        let mut removed = 0;
+        for id in roots {
+            // may already have been removed as the descendant of another root
+            if self.hierarchy.contains(id) {
                 self.hierarchy.remove(id);
             }
+            removed += 1;
         }
+        removed
+    }
+
+    /// Remove a prefab instance, prefer `remove` which handles both entities and instances
+    pub fn remove_instance(&mut self, prefab_id: PrefabId, id: TransformId) -> bool {
+        let is_instance = self
+            .membership
+            .get(id)
+            .is_some_and(|membership| membership.prefab == Some(prefab_id));
+        is_instance && self.remove(id)
+    }
+
+    /// Removes tombstones left by removals and updates the stored indices of remaining entities
+    fn compact(&mut self) {
+        if !self.requires_compaction {
+            return;
+        }
+        self.render_objects.retain(|id| !id.is_null());
+        for (index, id) in self.render_objects.iter().enumerate() {
+            if let Some(membership) = self.membership.get_mut(*id) {
+                membership.index = index;
+            }
+        }
+        for prefab in self.prefabs.values_mut() {
+            prefab.instances.retain(|id| !id.is_null());
+            for (index, id) in prefab.instances.iter().enumerate() {
+                if let Some(membership) = self.membership.get_mut(*id) {
+                    membership.index = index;
+                }
+            }
+        }
+        self.requires_compaction = false;
     }
 
     pub fn clear(&mut self) {
@@ -119,6 +307,225 @@ impl Scene {
         self.prefabs.clear();
         self.render_objects.clear();
         self.scene_graph.clear();
+        self.membership.clear();
+        self.requires_compaction = false;
+        self.names.clear();
+        self.name_lookup.clear();
+        self.tags.clear();
+        self.tag_lookup.clear();
+        self.node_visibility.clear();
+        self.effective_visibility.clear();
+        self.visibility_dirty = true;
+        self.tick_matrices.clear();
+        self.interpolated_tick = None;
+        self.logged_invalid_materials.clear();
+        self.animations.clear();
+    }
+
+    /// Set the name of an entity, names are expected to be unique, if another entity
+    /// already has this name `find_by_name` returns this one until it's renamed or removed
+    pub fn set_name<T: Into<String>>(&mut self, id: TransformId, name: T) {
+        if !self.hierarchy.contains(id) {
+            return;
+        }
+        let name = name.into();
+        if let Some(previous) = self.names.insert(id, name.clone()) {
+            self.release_name(id, &previous);
+        }
+        self.name_lookup.insert(name, id);
+    }
+
+    /// Point the lookup for a name no longer held by the id at another holder, if any
+    fn release_name(&mut self, id: TransformId, name: &str) {
+        if self.name_lookup.get(name) == Some(&id) {
+            match self.names.iter().find(|(_, other)| *other == name) {
+                Some((holder, _)) => {
+                    self.name_lookup.insert(name.to_string(), holder);
+                }
+                None => {
+                    self.name_lookup.remove(name);
+                }
+            }
+        }
+    }
+
+    pub fn get_name(&self, id: TransformId) -> Option<&str> {
+        self.names.get(id).map(|name| name.as_str())
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<TransformId> {
+        self.name_lookup
+            .get(name)
+            .copied()
+            .filter(|id| self.hierarchy.contains(*id))
+    }
+
+    pub fn add_tag<T: Into<String>>(&mut self, id: TransformId, tag: T) {
+        if !self.hierarchy.contains(id) {
+            return;
+        }
+        let tag = tag.into();
+        if let Some(tags) = self.tags.entry(id) {
+            if tags.or_default().insert(tag.clone()) {
+                self.tag_lookup.entry(tag).or_default().push(id);
+            }
+        }
+    }
+
+    pub fn remove_tag(&mut self, id: TransformId, tag: &str) {
+        if let Some(tags) = self.tags.get_mut(id) {
+            if tags.remove(tag) {
+                self.release_tag(id, tag);
+            }
+        }
+    }
+
+    /// Remove the id from the tag's lookup, dropping the tag once no ids hold it
+    fn release_tag(&mut self, id: TransformId, tag: &str) {
+        if let Some(ids) = self.tag_lookup.get_mut(tag) {
+            ids.retain(|x| *x != id);
+            if ids.is_empty() {
+                self.tag_lookup.remove(tag);
+            }
+        }
+    }
+
+    pub fn has_tag(&self, id: TransformId, tag: &str) -> bool {
+        self.tags.get(id).is_some_and(|tags| tags.contains(tag))
+    }
+
+    /// Iterate over the ids of all entities with the provided tag
+    pub fn iter_with_tag<'a>(&'a self, tag: &str) -> impl Iterator<Item = TransformId> + 'a {
+        self.tag_lookup
+            .get(tag)
+            .into_iter()
+            .flat_map(|ids| ids.iter().copied())
+            .filter(|id| self.hierarchy.contains(*id))
+    }
+
+    /// Replace the name and tags of a node, see `State::restore`
+    pub(crate) fn set_node_metadata(&mut self, id: TransformId, name: Option<&str>, tags: &[String]) {
+        if !self.hierarchy.contains(id) {
+            return;
+        }
+        match name {
+            Some(name) => self.set_name(id, name),
+            None => {
+                if let Some(previous) = self.names.remove(id) {
+                    self.release_name(id, &previous);
+                }
+            }
+        }
+        if let Some(previous) = self.tags.remove(id) {
+            for tag in previous.iter() {
+                self.release_tag(id, tag);
+            }
+        }
+        for tag in tags.iter() {
+            self.add_tag(id, tag.as_str());
+        }
+    }
+
+    fn clear_node_metadata(&mut self, id: TransformId) {
+        self.node_visibility.remove(id);
+        self.animations.stop_all(id);
+        if let Some(name) = self.names.remove(id) {
+            self.release_name(id, &name);
+        }
+        if let Some(tags) = self.tags.remove(id) {
+            for tag in tags.iter() {
+                self.release_tag(id, tag);
+            }
+        }
+    }
+
+    /// Parse a scene file and start loading the assets it references, see `SceneLoad::try_swap`
+    /// to replace a scene with it once loaded
+    pub fn load_async(bytes: &[u8], assets: &mut AssetLoader) -> anyhow::Result<SceneLoad> {
+        Ok(SceneLoad::new(SceneFile::parse(bytes)?, assets))
+    }
+
+    /// Capture the current hierarchy and entity state of the scene
+    pub fn snapshot(&self) -> SceneSnapshot {
+        let mut snapshot = SceneSnapshot::default();
+        for id in self.hierarchy.ids() {
+            snapshot.entries.insert(id, SnapshotEntry {
+                transform: self.hierarchy.get_transform(id).unwrap(),
+                parent: self.hierarchy.get_parent(id),
+                entity: self.entities.get(id).copied(),
+                prefab: self.membership.get(id).and_then(|membership| membership.prefab),
+                name: self.names.get(id).cloned(),
+                tags: self
+                    .tags
+                    .get(id)
+                    .map(|tags| tags.iter().cloned().collect())
+                    .unwrap_or_default(),
+            });
+        }
+        snapshot
+    }
+
+    /// Calculate the changes required to return the scene to the provided snapshot
+    pub fn diff(&self, snapshot: &SceneSnapshot) -> SceneDiff {
+        self.snapshot().diff(snapshot)
+    }
+
+    /// Return the scene to the state captured in the snapshot, returning the changes made
+    /// Entities which had been removed since the snapshot are re-added with new ids, see `SceneDiff::remapped`
+    pub fn apply(&mut self, snapshot: &SceneSnapshot) -> SceneDiff {
+        let mut diff = self.diff(snapshot);
+        self.apply_diff(&mut diff);
+        diff
+    }
+
+    /// Apply a set of changes to the scene, populating the diff's remapped ids for any re-added entities
+    pub fn apply_diff(&mut self, diff: &mut SceneDiff) {
+        // Detach reparented nodes first, removing their current parent would otherwise take
+        // them with it, they're attached to their target parent once it exists below
+        for (id, _, parent) in diff.transformed.iter() {
+            let parent = parent.map(|parent| diff.resolve(parent));
+            if self.hierarchy.contains(*id) && self.hierarchy.get_parent(*id) != parent {
+                self.hierarchy.parent(*id, None);
+            }
+        }
+
+        for id in diff.removed.iter() {
+            self.remove(*id);
+        }
+
+        // Parents must exist before their children can be re-added, so keep passing over
+        // the pending entries until no more progress can be made
+        let mut pending = diff.added.iter().collect::<Vec<_>>();
+        while !pending.is_empty() {
+            let count = pending.len();
+            pending.retain(|(id, entry)| {
+                let parent = entry.parent.map(|parent| diff.remapped.get(&parent).copied().unwrap_or(parent));
+                if let Some(parent) = parent {
+                    if !self.hierarchy.contains(parent) {
+                        return true;
+                    }
+                }
+                let new_id = self.hierarchy.insert(entry.transform, parent);
+                if let Some(entity) = entry.entity {
+                    let prefab = entry.prefab.filter(|prefab_id| self.prefabs.contains_key(*prefab_id));
+                    self.insert_entity(new_id, entity, prefab);
+                }
+                diff.remapped.insert(*id, new_id);
+                false
+            });
+            if pending.len() == count {
+                log::warn!("Unable to restore {} snapshot entries with missing parents", count);
+                break;
+            }
+        }
+
+        for (id, transform, parent) in diff.transformed.iter() {
+            let parent = parent.map(|parent| diff.resolve(parent));
+            if self.hierarchy.contains(*id) {
+                self.hierarchy.parent(*id, parent);
+                self.hierarchy.set_transform(*id, *transform);
+            }
+        }
     }
 
     pub fn get(&self, id: TransformId) -> &SceneEntity {
@@ -126,21 +533,264 @@ impl Scene {
     }
 
     // This is misleading because you could update entity.properties.world_matrix but it would have no effect
+    // Visibility can't be changed through this, use `set_visible`
     pub fn get_mut(&mut self, id: TransformId) -> &mut SceneEntity {
         &mut self.entities[id]
     }
 
+    /// Render properties of the entity, None if the id has no entity, the world matrix is
+    /// still overwritten from the hierarchy
+    pub fn get_properties_mut(&mut self, id: TransformId) -> Option<&mut RenderProperties> {
+        self.entities.get_mut(id).map(|entity| &mut entity.properties)
+    }
+
+    /// Set the local visibility of a node, hiding a node hides all of its descendants
+    /// Works for both entities and transform only nodes (e.g. a panel root)
+    pub fn set_visible(&mut self, id: TransformId, visible: bool) {
+        if let Some(entity) = self.entities.get_mut(id) {
+            entity.visible = visible;
+        } else if self.hierarchy.contains(id) {
+            self.node_visibility.insert(id, visible);
+        }
+        self.visibility_dirty = true;
+    }
+
+    pub fn is_locally_visible(&self, id: TransformId) -> bool {
+        if let Some(entity) = self.entities.get(id) {
+            entity.visible
+        } else {
+            self.node_visibility.get(id).copied().unwrap_or(true)
+        }
+    }
+
+    /// Is the node and all of its ancestors visible
+    pub fn is_visible(&self, id: TransformId) -> bool {
+        if !self.visibility_dirty && self.visibility_version == self.hierarchy.structure_version() {
+            if let Some(visible) = self.effective_visibility.get(id) {
+                return *visible;
+            }
+        }
+        let mut current = Some(id);
+        let mut depth = 0;
+        while let Some(id) = current {
+            if !self.is_locally_visible(id) {
+                return false;
+            }
+            current = self.hierarchy.get_parent(id);
+            depth += 1;
+            if depth > self.hierarchy.len() {
+                log::warn!("Cyclical transform hierarchy detected checking visibility of {id:?}");
+                break;
+            }
+        }
+        true
+    }
+
+    /// Nearest visible entity hit by the ray which passes the filter, with the distance to the hit
+    ///
+    /// Each entity is tested against the same local space box, the default for primitives
+    /// is a unit box from -0.5 to 0.5, see `pick_bounds` to test against each entity's bounds.
+    pub fn pick<F>(&self, ray: &Ray, local_min: Vec3, local_max: Vec3, filter: F) -> Option<(TransformId, f32)>
+    where
+        F: Fn(TransformId) -> bool,
+    {
+        let local_bounds = Aabb::new(local_min, local_max);
+        self.pick_nearest(ray, |id| filter(id).then_some(local_bounds))
+    }
+
+    /// As `pick`, testing each entity against its `RenderProperties::local_bounds`, entities
+    /// whose mesh doesn't exist are skipped
+    pub fn pick_bounds<F>(&self, ray: &Ray, resources: &Resources, filter: F) -> Option<(TransformId, f32)>
+    where
+        F: Fn(TransformId) -> bool,
+    {
+        self.pick_nearest(ray, |id| {
+            let entity = &self.entities[id];
+            let mesh = resources.meshes.get(entity.mesh)?;
+            filter(id).then(|| entity.properties.local_bounds(mesh))
+        })
+    }
+
+    /// World space bounds of the entity as of the last `update`, None if its mesh doesn't
+    /// exist, e.g. to outline it with `DebugDraw::aabb`
+    pub fn world_bounds(&self, id: TransformId, resources: &Resources) -> Option<Aabb> {
+        let entity = self.entities.get(id)?;
+        let mesh = resources.meshes.get(entity.mesh)?;
+        Some(entity.properties.world_bounds(mesh))
+    }
+
+    fn pick_nearest<F>(&self, ray: &Ray, local_bounds: F) -> Option<(TransformId, f32)>
+    where
+        F: Fn(TransformId) -> Option<Aabb>,
+    {
+        let mut nearest: Option<(TransformId, f32)> = None;
+        for id in self.entities.keys() {
+            if !self.is_visible(id) {
+                continue;
+            }
+            let Some(bounds) = local_bounds(id) else {
+                continue;
+            };
+            let Some(matrix) = self.hierarchy.get_world_matrix(id) else {
+                continue;
+            };
+            if matrix.determinant().abs() < f32::EPSILON {
+                continue;
+            }
+            let inverse = matrix.inverse();
+            let local_ray = Ray::new(
+                inverse.transform_point3(ray.origin),
+                inverse.transform_vector3(ray.direction),
+            );
+            let Some(local_distance) = local_ray.intersect_aabb(bounds.min, bounds.max) else {
+                continue;
+            };
+            // Distances aren't preserved by scaled transforms, so measure in world space
+            let hit = matrix.transform_point3(local_ray.at(local_distance));
+            let distance = hit.distance(ray.origin);
+            if nearest.is_none_or(|(_, nearest)| distance < nearest) {
+                nearest = Some((id, distance));
+            }
+        }
+        nearest
+    }
+
+    /// Rebuild the effective visibility cache if local visibility or the hierarchy has changed
+    fn update_visibility(&mut self) {
+        let version = self.hierarchy.structure_version();
+        if !self.visibility_dirty && self.visibility_version == version {
+            return;
+        }
+        self.effective_visibility.clear();
+        let mut pending = self
+            .hierarchy
+            .roots()
+            .map(|id| (id, true))
+            .collect::<Vec<_>>();
+        while let Some((id, parent_visible)) = pending.pop() {
+            let visible = parent_visible && self.is_locally_visible(id);
+            if self.effective_visibility.insert(id, visible).is_some() {
+                log::warn!("Cyclical transform hierarchy detected {id:?} already visited");
+                continue;
+            }
+            for child in self.hierarchy.get_children(id) {
+                pending.push((*child, visible));
+            }
+        }
+        self.visibility_dirty = false;
+        self.visibility_version = version;
+    }
+
     /// Updates entity world matrices from hierarchy
+    /// Advances animations by the frame's elapsed game time, see `Game::update`
     /// Builds ordered scene graph, including ordering based on camera depth for alpha blended objects
     pub fn update(
         &mut self,
         camera: &Camera,
-        resources: &Resources
+        resources: &Resources,
+        elapsed: f32,
+    ) {
+        self.update_with_tick_alpha(camera, resources, elapsed, None);
+    }
+
+    /// As `update`, but entities are drawn blended between where they were at the end of the
+    /// previous and latest fixed ticks by `Time::tick_alpha`, so movement simulated in fixed
+    /// ticks looks smooth at any frame rate
+    ///
+    /// Rendering lags a tick behind the simulation. Call each frame after the frame's ticks,
+    /// when several ticks run in one frame the blend spans all of them.
+    pub fn update_interpolated(&mut self, camera: &Camera, resources: &Resources, time: &Time) {
+        let ticked = self.interpolated_tick.replace(time.tick()) != Some(time.tick());
+        if ticked {
+            let entities = &self.entities;
+            self.tick_matrices.retain(|id, _| entities.contains_key(id));
+        }
+        for id in self.entities.keys() {
+            let current = self.hierarchy.get_world_matrix(id).unwrap();
+            match self.tick_matrices.get_mut(id) {
+                Some([previous, latest]) => {
+                    if ticked {
+                        *previous = *latest;
+                    }
+                    *latest = current;
+                }
+                None => {
+                    self.tick_matrices.insert(id, [current; 2]);
+                }
+            }
+        }
+        self.update_with_tick_alpha(camera, resources, time.elapsed, Some(time.tick_alpha()));
+    }
+
+    /// Draw the entity where it is now until the next tick rather than blending from its
+    /// previous position, e.g. after teleporting it
+    pub fn reset_interpolation(&mut self, id: TransformId) {
+        if let (Some(matrices), Some(current)) =
+            (self.tick_matrices.get_mut(id), self.hierarchy.get_world_matrix(id))
+        {
+            *matrices = [current; 2];
+        }
+    }
+
+    fn update_with_tick_alpha(
+        &mut self,
+        camera: &Camera,
+        resources: &Resources,
+        elapsed: f32,
+        tick_alpha: Option<f32>,
     ) {
+        self.compact();
+        self.animations.advance(elapsed);
+
         // Update Entity World Matrix From Hierarchy
         for (id, entity) in self.entities.iter_mut() {
-            entity.properties.world_matrix = self.hierarchy.get_world_matrix(id).unwrap();
+            let world_matrix = self.hierarchy.get_world_matrix(id).unwrap();
+            entity.properties.world_matrix = match (tick_alpha, self.tick_matrices.get(id)) {
+                (Some(alpha), Some([previous, _])) if *previous != world_matrix => {
+                    Transform::from(*previous)
+                        .lerp(&Transform::from(world_matrix), alpha)
+                        .into()
+                }
+                _ => world_matrix,
+            };
         }
+        self.update_visibility();
+        let effective_visibility = &self.effective_visibility;
+        let is_visible = |id: &TransformId| effective_visibility.get(*id).copied().unwrap_or(true);
+        let frustum = self.frustum_culling.then(|| Frustum::from_camera(camera));
+        let entity_map = &self.entities;
+        // Entities with missing meshes are kept, so the renderer reports them
+        let is_in_view = |id: &TransformId| {
+            let entity = &entity_map[*id];
+            match (&frustum, resources.meshes.get(entity.mesh)) {
+                (Some(frustum), Some(mesh)) => {
+                    frustum.intersects_aabb(&entity.properties.world_bounds(mesh))
+                }
+                _ => true,
+            }
+        };
+        let mut culled_count = 0;
+        let logged_invalid_materials = &mut self.logged_invalid_materials;
+        // Entities with a missing material or shader are kept unordered, the renderer drops
+        // them and reports why in the draw report
+        let mut draw_group = |material_id: MaterialId| {
+            let shader_id = resources.materials.get(material_id).map(|material| material.shader);
+            let group = shader_id.and_then(|shader_id| {
+                let shader = resources.shaders.get(shader_id)?;
+                Some((shader_id, resources.materials[material_id].requires_ordering(shader)))
+            });
+            if group.is_none()
+                && logged_invalid_materials.len() < MAX_LOGGED_DROPS
+                && logged_invalid_materials.insert(material_id)
+            {
+                log::warn!(
+                    "Scene entities use material {:?} which {}",
+                    material_id,
+                    if shader_id.is_some() { "has a missing shader" } else { "does not exist" }
+                );
+            }
+            group.unwrap_or((ShaderId::default(), false))
+        };
 
         // Build list of entities by shader so we can know how many entities will need to rendered per shader
         // also allows us to add to the scene graph grouped by shader, to minimise rebinds during render pass
@@ -149,32 +799,33 @@ impl Scene {
         for (id, entity) in self
             .render_objects
             .iter()
+            .filter(|id| is_visible(id))
             .map(|id| (id, &self.entities[*id]))
-            .filter(|(_, entity)| entity.visible)
         {
-            let material = &resources.materials[entity.material];
-            if !entities_by_shader.contains_key(&material.shader) {
-                entities_by_shader.insert(material.shader, Vec::new());
+            if !is_in_view(id) {
+                culled_count += 1;
+                continue;
             }
             entities_by_shader
-                .get_mut(&material.shader)
-                .unwrap()
+                .entry(draw_group(entity.material))
+                .or_insert_with(Vec::new)
                 .push(*id);
         }
 
         for prefab in self.prefabs.values() {
-            let material = &resources.materials[prefab.material];
-            if !entities_by_shader.contains_key(&material.shader) {
-                entities_by_shader.insert(material.shader, Vec::new());
-            }
-            
-            let entities = &mut entities_by_shader.get_mut(&material.shader).unwrap();
+            let entities = entities_by_shader
+                .entry(draw_group(prefab.material))
+                .or_insert_with(Vec::new);
             for id in prefab
                 .instances
                 .iter()
-                .filter(|id| self.entities[**id].visible)
+                .filter(|id| is_visible(id))
             {
-                entities.push(*id);
+                if is_in_view(id) {
+                    entities.push(*id);
+                } else {
+                    culled_count += 1;
+                }
             }
         }
         // todo: remove the straight get_mut unwraps?
@@ -183,9 +834,8 @@ impl Scene {
         let mut alpha_entities = Vec::new();
         self.scene_graph.clear();
 
-        for (shader_id, entities) in entities_by_shader.iter_mut() {
-            let shader = &resources.shaders[*shader_id];
-            if shader.requires_ordering {
+        for ((_, requires_ordering), entities) in entities_by_shader.iter_mut() {
+            if *requires_ordering {
                 alpha_entities.append(entities);
             } else {
                 self.scene_graph.append(entities);
@@ -211,11 +861,83 @@ impl Scene {
             a_z.total_cmp(&b_z)
         });
         self.scene_graph.append(&mut alpha_entities);
+        self.culled_count = culled_count;
+    }
+
+    /// Count the scene's entities and check them against the resources, reporting
+    /// dangling mesh, material and texture ids, hierarchy nodes whose parent is gone, and
+    /// prefab instances whose material no longer matches their prefab
+    pub fn validate(&self, resources: &Resources) -> SceneReport {
+        let mut report = SceneReport {
+            prefabs: self.prefabs.len(),
+            nodes: self.hierarchy.len(),
+            ..Default::default()
+        };
+        let mut checked_materials = HashSet::new();
+        for (id, entity) in self.entities.iter() {
+            let prefab_id = self.membership.get(id).and_then(|membership| membership.prefab);
+            match prefab_id {
+                Some(_) => report.prefab_instances += 1,
+                None => report.entities += 1,
+            }
+            if !self.hierarchy.contains(id) {
+                report.issues.push(SceneIssue::DetachedEntity(id));
+            }
+            if resources.meshes.get(entity.mesh).is_none() {
+                report.issues.push(SceneIssue::MissingMesh(id, entity.mesh));
+            }
+            match resources.materials.get(entity.material) {
+                Some(material) if checked_materials.insert(entity.material) => {
+                    for texture in material.textures.iter() {
+                        if resources.textures.get(*texture).is_none() {
+                            report
+                                .issues
+                                .push(SceneIssue::MissingTexture(entity.material, *texture));
+                        }
+                    }
+                }
+                Some(_) => {}
+                None => report.issues.push(SceneIssue::MissingMaterial(id, entity.material)),
+            }
+            let Some(prefab_id) = prefab_id else {
+                continue;
+            };
+            if let Some(prefab) = self.prefabs.get(prefab_id) {
+                if prefab.material != entity.material {
+                    report.issues.push(SceneIssue::DivergentInstance {
+                        prefab: prefab_id,
+                        id,
+                        material: entity.material,
+                        prefab_material: prefab.material,
+                    });
+                }
+            }
+        }
+        for id in self.hierarchy.ids() {
+            let parent = self.hierarchy.get_parent(id);
+            if parent.is_some_and(|parent| !self.hierarchy.contains(parent)) {
+                report.issues.push(SceneIssue::OrphanedNode(id));
+            }
+        }
+        report
+    }
+
+    /// Entities left out of the scene graph by frustum culling in the last update
+    pub fn culled_count(&self) -> usize {
+        self.culled_count
     }
 
     pub fn render(&mut self, draw_commands: &mut Vec<DrawCommand>) {
-        for entity in self.scene_graph.iter().map(|id| &self.entities[*id]) {
-            draw_commands.push(DrawCommand::Draw(entity.mesh, entity.material, entity.properties));
+        // filter_map as entities may have been removed since the last update
+        for (id, entity) in self
+            .scene_graph
+            .iter()
+            .filter_map(|id| self.entities.get(*id).map(|entity| (*id, entity)))
+        {
+            let mut properties = entity.properties;
+            if self.animations.apply(id, &mut properties) {
+                draw_commands.push(DrawCommand::Draw(entity.mesh, entity.material, properties));
+            }
         }
     }
 }
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use anyhow::*;
+use glam::*;
+
+use crate::{
+    assets::{AssetLoader, LoadHandle},
+    entity::RenderProperties,
+    material::{Material, MaterialId},
+    mesh::MeshId,
+    scene::Scene,
+    shader::ShaderId,
+    texture::Texture,
+    transform::Transform,
+    transform_hierarchy::TransformId,
+    State,
+};
+
+/// Turns the bytes of assets a scene file references into resources, see `SceneLoad`
+pub trait SceneAssets {
+    fn mesh(&mut self, path: &str, bytes: &[u8], state: &mut State) -> Result<MeshId>;
+    fn material(&mut self, path: &str, bytes: &[u8], state: &mut State) -> Result<MaterialId>;
+}
+
+/// Resolves material paths as images, textured with the shader, for scenes whose meshes
+/// are all named resources created by the game
+pub struct TextureMaterials {
+    pub shader: ShaderId,
+}
+
+impl SceneAssets for TextureMaterials {
+    fn mesh(&mut self, path: &str, _bytes: &[u8], _state: &mut State) -> Result<MeshId> {
+        bail!("No mesh format to load {} with", path)
+    }
+
+    fn material(&mut self, path: &str, bytes: &[u8], state: &mut State) -> Result<MaterialId> {
+        let texture = Texture::from_bytes_with_label(&state.device, &state.queue, bytes, path)?;
+        let texture = state.resources.textures.insert(texture);
+        let material = Material::new(self.shader, texture, state);
+        Ok(state.resources.materials.insert_named(material, path))
+    }
+}
+
+/// Where a mesh or material of a scene file comes from
+#[derive(Debug, Clone, PartialEq)]
+enum AssetSource {
+    /// Loaded through the asset loader and resolved by `SceneAssets`
+    Path(String),
+    /// Already in the resources under the name
+    Named(String),
+}
+
+#[derive(Debug, Clone)]
+struct EntityEntry {
+    mesh: String,
+    material: String,
+    transform: Transform,
+    color: Option<wgpu::Color>,
+    name: Option<String>,
+    parent: Option<String>,
+    tags: Vec<String>,
+}
+
+/// Scene described in text, one declaration per line, `#` starts a comment
+///
+/// ```text
+/// mesh crate assets/crate.mesh
+/// mesh quad @quad
+/// material wood assets/wood.png
+/// entity crate wood position 0 1 0 scale 2 2 2 name box
+/// entity quad wood position 0 0 1 rotation 0 0 0 1 color 1 0 0 1 parent box tag floor
+/// ```
+///
+/// Mesh and material paths are loaded through the `AssetLoader`, `@` names refer to
+/// resources which already exist. Entity rotations are quaternions, parents must be named
+/// on an earlier line.
+#[derive(Debug, Clone, Default)]
+pub struct SceneFile {
+    meshes: Vec<(String, AssetSource)>,
+    materials: Vec<(String, AssetSource)>,
+    entities: Vec<EntityEntry>,
+}
+
+impl SceneFile {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(bytes).context("Scene file is not UTF-8")?;
+        let mut file = SceneFile::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            file.parse_line(keyword, &mut tokens)
+                .with_context(|| format!("Scene file line {}", index + 1))?;
+        }
+        Ok(file)
+    }
+
+    fn parse_line<'a>(
+        &mut self,
+        keyword: &str,
+        tokens: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<()> {
+        match keyword {
+            "mesh" | "material" => {
+                let name = Self::token(tokens, "name")?.to_string();
+                let source = match Self::token(tokens, "path")? {
+                    path if path.starts_with('@') => AssetSource::Named(path[1..].to_string()),
+                    path => AssetSource::Path(path.to_string()),
+                };
+                let declared = if keyword == "mesh" {
+                    &mut self.meshes
+                } else {
+                    &mut self.materials
+                };
+                if declared.iter().any(|(existing, _)| *existing == name) {
+                    bail!("Duplicate {} {}", keyword, name);
+                }
+                declared.push((name, source));
+            }
+            "entity" => {
+                let mesh = Self::token(tokens, "mesh")?.to_string();
+                let material = Self::token(tokens, "material")?.to_string();
+                if !self.meshes.iter().any(|(name, _)| *name == mesh) {
+                    bail!("Undeclared mesh {}", mesh);
+                }
+                if !self.materials.iter().any(|(name, _)| *name == material) {
+                    bail!("Undeclared material {}", material);
+                }
+                let mut entity = EntityEntry {
+                    mesh,
+                    material,
+                    transform: Transform::default(),
+                    color: None,
+                    name: None,
+                    parent: None,
+                    tags: Vec::new(),
+                };
+                while let Some(key) = tokens.next() {
+                    match key {
+                        "position" => {
+                            entity.transform.position = Vec3::from(Self::floats(tokens, key)?)
+                        }
+                        "rotation" => {
+                            entity.transform.rotation =
+                                Quat::from_array(Self::floats(tokens, key)?).normalize()
+                        }
+                        "scale" => entity.transform.scale = Vec3::from(Self::floats(tokens, key)?),
+                        "color" => {
+                            let [r, g, b, a] = Self::floats(tokens, key)?.map(f64::from);
+                            entity.color = Some(wgpu::Color { r, g, b, a });
+                        }
+                        "name" => entity.name = Some(Self::token(tokens, key)?.to_string()),
+                        "parent" => {
+                            let parent = Self::token(tokens, key)?;
+                            let declared = self
+                                .entities
+                                .iter()
+                                .any(|other| other.name.as_deref() == Some(parent));
+                            if !declared {
+                                bail!("Parent {} is not named on an earlier line", parent);
+                            }
+                            entity.parent = Some(parent.to_string());
+                        }
+                        "tag" => entity.tags.push(Self::token(tokens, key)?.to_string()),
+                        _ => bail!("Unknown entity property {}", key),
+                    }
+                }
+                self.entities.push(entity);
+            }
+            _ => bail!("Unknown declaration {}", keyword),
+        }
+        Ok(())
+    }
+
+    fn token<'a>(tokens: &mut impl Iterator<Item = &'a str>, what: &str) -> Result<&'a str> {
+        tokens.next().ok_or_else(|| anyhow!("Missing {}", what))
+    }
+
+    fn floats<'a, const N: usize>(
+        tokens: &mut impl Iterator<Item = &'a str>,
+        key: &str,
+    ) -> Result<[f32; N]> {
+        let mut values = [0.0; N];
+        for value in values.iter_mut() {
+            let token = Self::token(tokens, key)?;
+            *value = token
+                .parse()
+                .with_context(|| format!("Invalid {} value {}", key, token))?;
+        }
+        Ok(values)
+    }
+
+    /// Paths to request from the asset loader
+    fn paths(&self) -> impl Iterator<Item = &str> {
+        self.meshes
+            .iter()
+            .chain(self.materials.iter())
+            .filter_map(|(_, source)| match source {
+                AssetSource::Path(path) => Some(path.as_str()),
+                AssetSource::Named(_) => None,
+            })
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+}
+
+/// Scene file whose assets are loading, see `Scene::load_async`
+///
+/// Poll `try_swap` each frame, the current scene is left untouched until every asset has
+/// loaded and resolved, then replaced in one step, so the old level stays playable until
+/// the new one is ready.
+pub struct SceneLoad {
+    file: SceneFile,
+    handle: LoadHandle,
+}
+
+impl SceneLoad {
+    pub(crate) fn new(file: SceneFile, assets: &mut AssetLoader) -> Self {
+        Self {
+            handle: assets.load_all(file.paths()),
+            file,
+        }
+    }
+
+    /// Fraction of the scene's assets loaded, resolving takes place in `try_swap` once
+    /// this reaches 1.0
+    pub fn progress(&self, assets: &AssetLoader) -> f32 {
+        assets.progress(&self.handle)
+    }
+
+    pub fn file(&self) -> &SceneFile {
+        &self.file
+    }
+
+    /// Receive loaded assets and once all are ready, resolve them and replace the scene,
+    /// returns true when swapped, errors if an asset failed to load or resolve
+    ///
+    /// Resources created from assets before an error are left in place.
+    pub fn try_swap(
+        &self,
+        scene: &mut Scene,
+        assets: &mut AssetLoader,
+        resolver: &mut impl SceneAssets,
+        state: &mut State,
+    ) -> Result<bool> {
+        assets.poll();
+        if !assets.is_complete(&self.handle) {
+            return Ok(false);
+        }
+        let mut loaded = Scene::new();
+        loaded.frustum_culling = scene.frustum_culling;
+        self.populate(&mut loaded, assets, resolver, state)?;
+        *scene = loaded;
+        Ok(true)
+    }
+
+    fn populate(
+        &self,
+        scene: &mut Scene,
+        assets: &mut AssetLoader,
+        resolver: &mut impl SceneAssets,
+        state: &mut State,
+    ) -> Result<()> {
+        let mut meshes = HashMap::new();
+        for (name, source) in self.file.meshes.iter() {
+            let id = match source {
+                AssetSource::Named(id_name) => state.resources.meshes.find_by_name(id_name),
+                AssetSource::Path(path) => {
+                    let bytes = Self::bytes(assets, path)?;
+                    Some(resolver.mesh(path, &bytes, state)?)
+                }
+            };
+            meshes.insert(
+                name.as_str(),
+                id.ok_or_else(|| anyhow!("No mesh {}", name))?,
+            );
+        }
+        let mut materials = HashMap::new();
+        for (name, source) in self.file.materials.iter() {
+            let id = match source {
+                AssetSource::Named(id_name) => state.resources.materials.find_by_name(id_name),
+                AssetSource::Path(path) => {
+                    let bytes = Self::bytes(assets, path)?;
+                    Some(resolver.material(path, &bytes, state)?)
+                }
+            };
+            let id = id.ok_or_else(|| anyhow!("No material {}", name))?;
+            materials.insert(name.as_str(), id);
+        }
+
+        let mut named: HashMap<&str, TransformId> = HashMap::new();
+        for entity in self.file.entities.iter() {
+            let mut properties = RenderProperties::default();
+            if let Some(color) = entity.color {
+                properties.color = color;
+            }
+            let id = scene.add(
+                meshes[entity.mesh.as_str()],
+                materials[entity.material.as_str()],
+                entity.transform,
+                properties,
+            );
+            if let Some(parent) = &entity.parent {
+                scene.hierarchy.parent(id, Some(named[parent.as_str()]));
+            }
+            if let Some(name) = &entity.name {
+                scene.set_name(id, name);
+                named.insert(name, id);
+            }
+            for tag in entity.tags.iter() {
+                scene.add_tag(id, tag);
+            }
+        }
+        Ok(())
+    }
+
+    fn bytes(assets: &AssetLoader, path: &str) -> Result<Vec<u8>> {
+        if let Some(error) = assets.get_error(path) {
+            bail!("Failed to load {}: {}", path, error);
+        }
+        // Copied rather than taken, so scenes sharing assets can load concurrently
+        assets
+            .get(path)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| anyhow!("Asset {} was not loaded", path))
+    }
+}
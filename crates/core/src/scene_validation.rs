@@ -0,0 +1,79 @@
+use crate::{
+    material::MaterialId, mesh::MeshId, prefab::PrefabId, texture::TextureId,
+    transform_hierarchy::TransformId,
+};
+
+/// Problem found in a scene by `Scene::validate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SceneIssue {
+    MissingMesh(TransformId, MeshId),
+    MissingMaterial(TransformId, MaterialId),
+    /// The material exists but references a texture which has been removed, reported once
+    /// per material
+    MissingTexture(MaterialId, TextureId),
+    /// Hierarchy node whose parent no longer exists, it keeps the world matrix it had when
+    /// the parent was removed
+    OrphanedNode(TransformId),
+    /// Entity whose hierarchy node has been removed, it is never given a world matrix
+    DetachedEntity(TransformId),
+    /// Prefab instance whose material was changed after it was added, the scene groups
+    /// instances by the prefab's material so it may be drawn out of order
+    DivergentInstance {
+        prefab: PrefabId,
+        id: TransformId,
+        material: MaterialId,
+        prefab_material: MaterialId,
+    },
+}
+
+impl std::fmt::Display for SceneIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneIssue::MissingMesh(id, mesh) => {
+                write!(f, "mesh {:?} of entity {:?} does not exist", mesh, id)
+            }
+            SceneIssue::MissingMaterial(id, material) => {
+                write!(f, "material {:?} of entity {:?} does not exist", material, id)
+            }
+            SceneIssue::MissingTexture(material, texture) => write!(
+                f,
+                "texture {:?} used by material {:?} does not exist",
+                texture, material
+            ),
+            SceneIssue::OrphanedNode(id) => {
+                write!(f, "parent of hierarchy node {:?} does not exist", id)
+            }
+            SceneIssue::DetachedEntity(id) => {
+                write!(f, "hierarchy node of entity {:?} does not exist", id)
+            }
+            SceneIssue::DivergentInstance {
+                prefab,
+                id,
+                material,
+                prefab_material,
+            } => write!(
+                f,
+                "instance {:?} of prefab {:?} uses material {:?} rather than {:?}",
+                id, prefab, material, prefab_material
+            ),
+        }
+    }
+}
+
+/// Counts of what a scene holds and any problems found, see `Scene::validate`
+#[derive(Debug, Clone, Default)]
+pub struct SceneReport {
+    /// Entities added individually, excluding prefab instances
+    pub entities: usize,
+    pub prefabs: usize,
+    pub prefab_instances: usize,
+    /// Hierarchy nodes, including those without an entity
+    pub nodes: usize,
+    pub issues: Vec<SceneIssue>,
+}
+
+impl SceneReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
@@ -0,0 +1,109 @@
+use winit::dpi::PhysicalSize;
+
+/// Frame read back from the GPU, see `State::request_screenshot`
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed 8 bit RGBA rows, top row first
+    pub rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Formats which can be read back, others are skipped with a warning
+    pub fn supports_format(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Copy the texture into a buffer for reading once the encoder has been submitted
+    pub(crate) fn encode_copy(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> PendingScreenshot {
+        let size = PhysicalSize::new(texture.width(), texture.height());
+        let padded_row = (4 * size.width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Buffer"),
+            size: (padded_row * size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(size.height),
+                },
+            },
+            texture.size(),
+        );
+        PendingScreenshot {
+            buffer,
+            size,
+            padded_row,
+            bgra: matches!(
+                texture.format(),
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+        }
+    }
+}
+
+/// Screenshot copy recorded but not yet read
+pub(crate) struct PendingScreenshot {
+    buffer: wgpu::Buffer,
+    size: PhysicalSize<u32>,
+    padded_row: u32,
+    bgra: bool,
+}
+
+impl PendingScreenshot {
+    /// Wait for the copy to complete and read the pixels, blocking until the GPU is done
+    /// Returns None on the web, where buffers can only be mapped asynchronously
+    pub fn read(self, device: &wgpu::Device) -> Option<Screenshot> {
+        if cfg!(target_arch = "wasm32") {
+            log::warn!("Screenshots are not supported on the web");
+            return None;
+        }
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if let Err(error) = receiver.recv().ok()? {
+            log::warn!("Unable to read screenshot: {}", error);
+            return None;
+        }
+
+        let row = 4 * self.size.width as usize;
+        let mut rgba = Vec::with_capacity(row * self.size.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for padded in data.chunks(self.padded_row as usize) {
+                rgba.extend_from_slice(&padded[..row]);
+            }
+        }
+        self.buffer.unmap();
+        if self.bgra {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Some(Screenshot {
+            width: self.size.width,
+            height: self.size.height,
+            rgba,
+        })
+    }
+}
@@ -1,9 +1,16 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
 use glam::*;
 use wgpu::PipelineCompilationOptions;
 
 use crate::{
-    camera::CameraBindGroup,
-    entity::{EntityBindGroup, EntityDrawInstruction, RenderProperties},
+    bindings::SharedBindings,
+    entity::{EntityBuffer, EntityDrawInstruction, RenderProperties},
+    material::{Material, MaterialTextureLayout},
+    shader_descriptor::EntityUniformLayout,
     texture,
 };
 
@@ -13,9 +20,13 @@ use crate::{
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
 }
 
 impl Vertex {
+    /// Normal along +z, facing the default camera as sprites do
+    pub const FORWARD_NORMAL: [f32; 3] = [0.0, 0.0, 1.0];
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
@@ -31,6 +42,11 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
             ],
         }
     }
@@ -64,6 +80,102 @@ impl EntityUniforms {
     }
 }
 
+/// Entity uniforms for the sprite outline shader
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OutlineEntityUniforms {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    pub uv_offset: [f32; 2],
+    pub uv_scale: [f32; 2],
+    pub outline_color: [f32; 4],
+    pub outline_thickness: f32,
+    _padding: [f32; 3],
+}
+
+impl OutlineEntityUniforms {
+    pub fn write_bytes(instance: &RenderProperties, bytes: &mut Vec<u8>) {
+        let data = OutlineEntityUniforms {
+            model: instance.world_matrix.to_cols_array_2d(),
+            color: [
+                instance.color.r as f32,
+                instance.color.g as f32,
+                instance.color.b as f32,
+                instance.color.a as f32,
+            ],
+            uv_offset: instance.uv_offset.to_array(),
+            uv_scale: instance.uv_scale.to_array(),
+            outline_color: [
+                instance.outline_color.r as f32,
+                instance.outline_color.g as f32,
+                instance.outline_color.b as f32,
+                instance.outline_color.a as f32,
+            ],
+            outline_thickness: instance.outline_thickness,
+            _padding: [0.0; 3],
+        };
+        bytes.clear();
+        bytes.extend_from_slice(bytemuck::bytes_of(&data));
+    }
+}
+
+/// Material uniforms for the signed distance field text shader, see `BuildInShaders::sdf_text`
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SdfTextUniforms {
+    pub glow_color: [f32; 4],
+    /// Glow drawn beyond the outline, in normalized distance, 0.0 for no glow
+    pub glow_radius: f32,
+    _padding: [f32; 3],
+}
+
+impl SdfTextUniforms {
+    pub fn new(glow_color: wgpu::Color, glow_radius: f32) -> Self {
+        Self {
+            glow_color: [
+                glow_color.r as f32,
+                glow_color.g as f32,
+                glow_color.b as f32,
+                glow_color.a as f32,
+            ],
+            glow_radius,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Entity uniforms for the sprite array shader
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ArrayEntityUniforms {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    pub uv_offset: [f32; 2],
+    pub uv_scale: [f32; 2],
+    pub texture_layer: u32,
+    _padding: [u32; 3],
+}
+
+impl ArrayEntityUniforms {
+    pub fn write_bytes(instance: &RenderProperties, bytes: &mut Vec<u8>) {
+        let data = ArrayEntityUniforms {
+            model: instance.world_matrix.to_cols_array_2d(),
+            color: [
+                instance.color.r as f32,
+                instance.color.g as f32,
+                instance.color.b as f32,
+                instance.color.a as f32,
+            ],
+            uv_offset: instance.uv_offset.to_array(),
+            uv_scale: instance.uv_scale.to_array(),
+            texture_layer: instance.texture_layer,
+            _padding: [0; 3],
+        };
+        bytes.clear();
+        bytes.extend_from_slice(bytemuck::bytes_of(&data));
+    }
+}
+
 pub struct Instance {
     pub position: Vec3,
     pub rotation: Quat,
@@ -122,74 +234,666 @@ impl InstanceRaw {
 
 slotmap::new_key_type! { pub struct ShaderId; }
 
+/// Which faces are discarded when rasterizing, based on counter-clockwise front faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CullMode {
+    /// Double-sided, e.g. foliage planes
+    None,
+    /// Only back faces render, e.g. inside-out skyboxes
+    Front,
+    #[default]
+    Back,
+}
+
+impl CullMode {
+    pub fn face(&self) -> Option<wgpu::Face> {
+        match self {
+            CullMode::None => None,
+            CullMode::Front => Some(wgpu::Face::Front),
+            CullMode::Back => Some(wgpu::Face::Back),
+        }
+    }
+}
+
+/// Offsets the depth of rasterized fragments, use to stop coplanar surfaces such as decals
+/// and highlight quads z-fighting with the surface beneath them
+///
+/// With the default `Less` depth compare negative values pull the surface towards the camera.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DepthBias {
+    /// Constant offset in units of the smallest resolvable depth difference
+    pub constant: i32,
+    /// Offset scaled by the slope of the polygon relative to the camera
+    pub slope_scale: f32,
+    /// Maximum absolute offset, zero for no clamping
+    pub clamp: f32,
+}
+
+impl DepthBias {
+    pub fn new(constant: i32, slope_scale: f32) -> Self {
+        Self {
+            constant,
+            slope_scale,
+            clamp: 0.0,
+        }
+    }
+
+    fn state(&self) -> wgpu::DepthBiasState {
+        wgpu::DepthBiasState {
+            constant: self.constant,
+            slope_scale: self.slope_scale,
+            clamp: self.clamp,
+        }
+    }
+}
+
+// Compared bitwise so DepthBias can be part of a PipelineKey
+impl PartialEq for DepthBias {
+    fn eq(&self, other: &Self) -> bool {
+        self.constant == other.constant
+            && self.slope_scale.to_bits() == other.slope_scale.to_bits()
+            && self.clamp.to_bits() == other.clamp.to_bits()
+    }
+}
+
+impl Eq for DepthBias {}
+
+impl Hash for DepthBias {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.constant.hash(state);
+        self.slope_scale.to_bits().hash(state);
+        self.clamp.to_bits().hash(state);
+    }
+}
+
+/// Alpha below which fragments are discarded, for foliage and fence sprites which should
+/// write depth and occlude correctly without being alpha blended and depth sorted
+///
+/// Shaders used with a cutoff must declare `override ALPHA_CUTOFF: f32 = 0.0;` and discard
+/// fragments below it, as the built-in unlit textured shader does.
+#[derive(Debug, Clone, Copy)]
+pub struct AlphaCutoff(pub f32);
+
+impl AlphaCutoff {
+    /// Name of the pipeline overridable constant the cutoff is passed to shaders as
+    pub const CONSTANT: &'static str = "ALPHA_CUTOFF";
+}
+
+impl Default for AlphaCutoff {
+    fn default() -> Self {
+        Self(0.5)
+    }
+}
+
+// Compared bitwise so AlphaCutoff can be part of a PipelineKey
+impl PartialEq for AlphaCutoff {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for AlphaCutoff {}
+
+impl Hash for AlphaCutoff {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+/// How fragment colors are combined with the color already in the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// Replaces the existing color
+    #[default]
+    Opaque,
+    /// Blended over the existing color by alpha
+    Alpha,
+    /// Blended over by alpha for colors already multiplied by their alpha, avoids dark
+    /// fringes around filtered sprite edges
+    Premultiplied,
+    /// Added to the existing color weighted by alpha, for glows, lasers and particles
+    Additive,
+    /// Multiplies the existing color, for shadows and tints, white leaves it unchanged
+    Multiply,
+}
+
+impl BlendMode {
+    /// Blended geometry doesn't write depth and is drawn after opaque geometry, depth sorted
+    pub fn is_blended(&self) -> bool {
+        *self != BlendMode::Opaque
+    }
+}
+
+/// Fixed function state baked into a render pipeline
+///
+/// Shaders have default options, materials may override them, each distinct combination
+/// (along with the target format) results in a pipeline variant cached on the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineOptions {
+    pub blend_mode: BlendMode,
+    /// Weight the source color by the material's blend constant rather than its alpha,
+    /// opaque materials are blended over as with `BlendMode::Alpha`
+    pub blend_constant: bool,
+    pub depth_compare: wgpu::CompareFunction,
+    pub depth_write: bool,
+    pub cull_mode: CullMode,
+    pub depth_bias: DepthBias,
+    /// Discard fragments below the cutoff, see `AlphaCutoff`
+    pub alpha_cutoff: Option<AlphaCutoff>,
+}
+
+impl PipelineOptions {
+    /// Blended modes don't write depth
+    pub fn new(blend_mode: BlendMode, depth_compare: wgpu::CompareFunction) -> Self {
+        Self {
+            blend_mode,
+            blend_constant: false,
+            depth_compare,
+            depth_write: !blend_mode.is_blended(),
+            cull_mode: CullMode::Back,
+            depth_bias: DepthBias::default(),
+            alpha_cutoff: None,
+        }
+    }
+
+    /// Opaque rendering with depth writes, discarding fragments below the cutoff
+    pub fn masked(cutoff: AlphaCutoff, depth_compare: wgpu::CompareFunction) -> Self {
+        Self {
+            alpha_cutoff: Some(cutoff),
+            ..Self::new(BlendMode::Opaque, depth_compare)
+        }
+    }
+
+    /// Additive blending, as with alpha blending this doesn't write depth
+    pub fn additive(depth_compare: wgpu::CompareFunction) -> Self {
+        Self::new(BlendMode::Additive, depth_compare)
+    }
+
+    /// Blended geometry has to be drawn after opaque geometry, sorted by depth
+    pub fn requires_ordering(&self) -> bool {
+        self.blend_mode.is_blended()
+    }
+
+    /// Use the blend mode, writing depth only if it is opaque
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self.depth_write = !blend_mode.is_blended();
+        self
+    }
+
+    fn blend_state(&self) -> wgpu::BlendState {
+        let (source, inverse_source) = if self.blend_constant {
+            (wgpu::BlendFactor::Constant, wgpu::BlendFactor::OneMinusConstant)
+        } else {
+            (wgpu::BlendFactor::SrcAlpha, wgpu::BlendFactor::OneMinusSrcAlpha)
+        };
+        let component = |src_factor, dst_factor| wgpu::BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        };
+        // Additive and multiply leave the destination alpha as is
+        let keep_alpha = component(wgpu::BlendFactor::Zero, wgpu::BlendFactor::One);
+        match self.blend_mode {
+            BlendMode::Opaque if !self.blend_constant => wgpu::BlendState::REPLACE,
+            BlendMode::Opaque | BlendMode::Alpha => wgpu::BlendState {
+                color: component(source, inverse_source),
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            BlendMode::Premultiplied => {
+                let source = if self.blend_constant {
+                    source
+                } else {
+                    wgpu::BlendFactor::One
+                };
+                wgpu::BlendState {
+                    color: component(source, inverse_source),
+                    alpha: wgpu::BlendComponent::OVER,
+                }
+            }
+            BlendMode::Additive => wgpu::BlendState {
+                color: component(source, wgpu::BlendFactor::One),
+                alpha: keep_alpha,
+            },
+            BlendMode::Multiply => wgpu::BlendState {
+                color: component(wgpu::BlendFactor::Dst, wgpu::BlendFactor::Zero),
+                alpha: keep_alpha,
+            },
+        }
+    }
+
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn with_depth_bias(mut self, depth_bias: DepthBias) -> Self {
+        self.depth_bias = depth_bias;
+        self
+    }
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self::new(BlendMode::Opaque, wgpu::CompareFunction::Less)
+    }
+}
+
+/// How the renderer draws meshes, for inspecting geometry, see `State::set_debug_view`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DebugView {
+    #[default]
+    Normal,
+    /// Triangle edges only, requires `wgpu::Features::POLYGON_MODE_LINE`
+    Wireframe,
+    /// Every fragment drawn adds to the target's color, so pixels drawn many times show
+    /// brighter, depth testing is disabled so hidden surfaces count too
+    Overdraw,
+}
+
+/// Identifies a pipeline variant of a shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub format: wgpu::TextureFormat,
+    /// Samples per pixel of the target, greater than 1 when multisampling
+    pub sample_count: u32,
+    pub options: PipelineOptions,
+    pub view: DebugView,
+}
+
+/// How per-entity render properties are converted to the bytes of the entity uniform
+enum UniformWriter {
+    Delegate(fn(instance: &RenderProperties, bytes: &mut Vec<u8>)),
+    Layout(EntityUniformLayout),
+}
+
+impl UniformWriter {
+    fn write(&self, instance: &RenderProperties, bytes: &mut Vec<u8>) {
+        match self {
+            UniformWriter::Delegate(delegate) => delegate(instance, bytes),
+            UniformWriter::Layout(layout) => layout.write_bytes(instance, bytes),
+        }
+    }
+}
+
+/// Layout of `@group(2)`, either the shared texture layout or one owned by the shader
+enum MaterialLayout<'a> {
+    Shared(&'a wgpu::BindGroupLayout),
+    Owned(wgpu::BindGroupLayout),
+}
+
+/// Pipelines for a shader module, binding the camera and entity uniforms from the renderer's
+/// `SharedBindings`
 pub struct Shader {
-    pub render_pipeline: wgpu::RenderPipeline,
-    pub camera_bind_group: CameraBindGroup,
-    pub entity_bind_group: EntityBindGroup,
-    // ^^ these last two should be shared between shaders where possible
+    /// Options used by materials which don't specify their own
+    pub options: PipelineOptions,
     pub requires_ordering: bool,
-    bytes_delegate: fn(instance: &RenderProperties, bytes: &mut Vec<u8>),
+    /// Binds the scene's lights at `@group(3)`, see `helia/lighting.wgsl`
+    pub lit: bool,
+    /// Label of the shader module, used for the pipelines and debug groups
+    label: String,
+    shader_module: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    uniform_writer: UniformWriter,
+    /// Shaders with the same entity uniform size and binding type share a buffer, see
+    /// `SharedBindings`
+    entity_buffer: EntityBuffer,
+    /// Layout of `@group(2)` when materials carry a uniform block, see `Material::with_uniforms`,
+    /// or bind other than a single 2D texture
+    material_layout: Option<wgpu::BindGroupLayout>,
+    texture_layout: MaterialTextureLayout,
+    material_uniforms_size: usize,
     bytes_buffer: Vec<u8>,
-    next_offset: u64,
 }
 
 impl Shader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
+        bindings: &mut SharedBindings,
         module_descriptor: wgpu::ShaderModuleDescriptor,
         texture_format: wgpu::TextureFormat,
         texture_bind_group_layout: &wgpu::BindGroupLayout,
-        alpha_blending: bool, // todo: enum, cause also pre-multiplied
+        blend_mode: BlendMode,
+        depth_compare: wgpu::CompareFunction,
         entity_uniforms_size: usize,
         to_bytes_delegate: fn(instance: &RenderProperties, bytes: &mut Vec<u8>),
     ) -> Self {
-        let camera_bind_group = CameraBindGroup::new(device);
-        // Much of what's in camera.rs w.r.t. CameraBindGroup is dependent on shader implementation
-        // Note: this bind group can and arguably should be shared between shaders, however waiting
-        // for a use case
+        Self::with_options(
+            device,
+            bindings,
+            module_descriptor,
+            texture_format,
+            texture_bind_group_layout,
+            PipelineOptions::new(blend_mode, depth_compare),
+            entity_uniforms_size,
+            to_bytes_delegate,
+        )
+    }
 
-        let entity_bind_group = EntityBindGroup::new(entity_uniforms_size, &device);
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_options(
+        device: &wgpu::Device,
+        bindings: &mut SharedBindings,
+        module_descriptor: wgpu::ShaderModuleDescriptor,
+        texture_format: wgpu::TextureFormat,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        options: PipelineOptions,
+        entity_uniforms_size: usize,
+        to_bytes_delegate: fn(instance: &RenderProperties, bytes: &mut Vec<u8>),
+    ) -> Self {
+        Self::create(
+            device,
+            bindings,
+            module_descriptor,
+            texture_format,
+            MaterialLayout::Shared(texture_bind_group_layout),
+            MaterialTextureLayout::single(),
+            options,
+            None,
+            entity_uniforms_size,
+            UniformWriter::Delegate(to_bytes_delegate),
+            0,
+        )
+    }
+
+    /// Shader whose materials bind a 2D texture array rather than a texture, see
+    /// `Texture::array_from_images`
+    pub(crate) fn with_texture_array(
+        device: &wgpu::Device,
+        bindings: &mut SharedBindings,
+        module_descriptor: wgpu::ShaderModuleDescriptor,
+        texture_format: wgpu::TextureFormat,
+        options: PipelineOptions,
+        entity_uniforms_size: usize,
+        to_bytes_delegate: fn(instance: &RenderProperties, bytes: &mut Vec<u8>),
+    ) -> Self {
+        Self::create(
+            device,
+            bindings,
+            module_descriptor,
+            texture_format,
+            MaterialLayout::Owned(Material::create_array_bind_group_layout(device)),
+            MaterialTextureLayout::new(&[wgpu::TextureViewDimension::D2Array]),
+            options,
+            None,
+            entity_uniforms_size,
+            UniformWriter::Delegate(to_bytes_delegate),
+            0,
+        )
+    }
+
+    /// Shader whose entity uniform is written according to a declared layout rather than
+    /// a delegate, see `ShaderDescriptor`, lit shaders bind the light bind group layout and
+    /// a non-zero material uniforms size adds a uniform buffer to the material bind group
+    /// after the textures of the texture layout
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn with_layout(
+        device: &wgpu::Device,
+        bindings: &mut SharedBindings,
+        module_descriptor: wgpu::ShaderModuleDescriptor,
+        texture_format: wgpu::TextureFormat,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        light_bind_group_layout: Option<&wgpu::BindGroupLayout>,
+        options: PipelineOptions,
+        uniform_layout: EntityUniformLayout,
+        texture_layout: MaterialTextureLayout,
+        material_uniforms_size: usize,
+    ) -> Self {
+        let material_layout =
+            if material_uniforms_size > 0 || texture_layout != MaterialTextureLayout::single() {
+                MaterialLayout::Owned(texture_layout.create_bind_group_layout(
+                    device,
+                    material_uniforms_size > 0,
+                    "material_bind_group_layout",
+                ))
+            } else {
+                MaterialLayout::Shared(texture_bind_group_layout)
+            };
+        Self::create(
+            device,
+            bindings,
+            module_descriptor,
+            texture_format,
+            material_layout,
+            texture_layout,
+            options,
+            light_bind_group_layout,
+            uniform_layout.size(),
+            UniformWriter::Layout(uniform_layout),
+            material_uniforms_size,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create(
+        device: &wgpu::Device,
+        bindings: &mut SharedBindings,
+        module_descriptor: wgpu::ShaderModuleDescriptor,
+        texture_format: wgpu::TextureFormat,
+        material_layout: MaterialLayout,
+        texture_layout: MaterialTextureLayout,
+        options: PipelineOptions,
+        light_bind_group_layout: Option<&wgpu::BindGroupLayout>,
+        entity_uniforms_size: usize,
+        uniform_writer: UniformWriter,
+        material_uniforms_size: usize,
+    ) -> Self {
+        let entity_buffer = EntityBuffer {
+            uniforms_size: entity_uniforms_size,
+            storage: Self::reads_entity_storage(&module_descriptor, entity_uniforms_size),
+        };
+        // Creates the entity buffer for the uniform size if no other shader uses it
+        bindings.entity_layout(entity_buffer, device);
         // Entity Bind Group is specific on shader implementation (the fact it's an individual uniform
-        // in binding 0) and it's bound per entity, but this is extremely general, it is also depednent
-        // upon the size of the uniforms for the specific shader, however we anticipate it may still be
-        // sharable. We may also want to consider splitting between more universal (model matrix) properties
-        // and material specific elements (color, uvs etc) to encourage reuse if we get to the point of sharing
+        // in binding 0) and it's bound per entity, it is also dependent upon the size of the
+        // uniforms for the specific shader, so is shared between shaders of the same size.
+        // We may also want to consider splitting between more universal (model matrix) properties
+        // and material specific elements (color, uvs etc) to encourage further reuse
 
         // bind group layouts order has to match the @group declarations in the shader
+        let entity_layout = &bindings
+            .entity_bind_group(entity_buffer)
+            .expect("Entity bind group created above")
+            .layout;
+        let mut bind_group_layouts = vec![
+            bindings.camera_layout(),
+            entity_layout,
+            match &material_layout {
+                MaterialLayout::Shared(layout) => layout,
+                MaterialLayout::Owned(layout) => layout,
+            },
+        ];
+        bind_group_layouts.extend(light_bind_group_layout);
+        let label = module_descriptor.label.unwrap_or("Shader").to_string();
         let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[
-                &camera_bind_group.layout,
-                &entity_bind_group.layout,
-                texture_bind_group_layout,
-            ],
+            label: Some(&format!("{} Pipeline Layout", label)),
+            bind_group_layouts: &bind_group_layouts,
             push_constant_ranges: &[],
         });
         // You could conceivably share pipeline layouts between shaders with similar bind group requirements
 
-        let blend_state = if alpha_blending {
-            Some(wgpu::BlendState::ALPHA_BLENDING)
-        } else {
-            Some(wgpu::BlendState::REPLACE)
+        let shader_module = device.create_shader_module(module_descriptor);
+
+        let mut shader = Self {
+            options,
+            requires_ordering: options.requires_ordering(),
+            lit: light_bind_group_layout.is_some(),
+            label,
+            shader_module,
+            layout,
+            pipelines: HashMap::new(),
+            uniform_writer,
+            entity_buffer,
+            material_layout: match material_layout {
+                MaterialLayout::Shared(_) => None,
+                MaterialLayout::Owned(layout) => Some(layout),
+            },
+            texture_layout,
+            material_uniforms_size,
+            bytes_buffer: Vec::new(),
+        };
+        // Create the default variant up front so most shaders never build one mid-frame
+        shader.prepare_pipeline(
+            device,
+            PipelineKey {
+                format: texture_format,
+                sample_count: 1,
+                options,
+                view: DebugView::Normal,
+            },
+        );
+        shader
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Size in bytes of the entity uniforms
+    pub fn entity_uniforms_size(&self) -> usize {
+        self.entity_buffer.uniforms_size
+    }
+
+    /// Identifies the shared entity bind group used
+    pub fn entity_buffer(&self) -> EntityBuffer {
+        self.entity_buffer
+    }
+
+    /// Whether the shader declares its entities as a storage array at `@group(1) @binding(0)`,
+    /// as `helia/entity_instance.wgsl` does where storage buffers are supported, rather than
+    /// a uniform
+    ///
+    /// Entities are packed at the uniform size, which must be the array's stride.
+    fn reads_entity_storage(
+        descriptor: &wgpu::ShaderModuleDescriptor,
+        entity_uniforms_size: usize,
+    ) -> bool {
+        let wgpu::ShaderSource::Wgsl(source) = &descriptor.source else {
+            return false;
+        };
+        // Invalid source is reported by wgpu when the module is created
+        let Ok(module) = naga::front::wgsl::parse_str(source) else {
+            return false;
         };
+        let stride = module.global_variables.iter().find_map(|(_, variable)| {
+            let binding = variable.binding.as_ref()?;
+            let storage = matches!(variable.space, naga::AddressSpace::Storage { .. });
+            match module.types[variable.ty].inner {
+                naga::TypeInner::Array { stride, .. }
+                    if storage && binding.group == 1 && binding.binding == 0 =>
+                {
+                    Some(stride)
+                }
+                _ => None,
+            }
+        });
+        if let Some(stride) = stride {
+            assert_eq!(
+                stride as usize, entity_uniforms_size,
+                "Entity storage array stride should match the entity uniforms size"
+            );
+        }
+        stride.is_some()
+    }
 
-        let shader_module = device.create_shader_module(module_descriptor);
-        // there is a pipeline per shader, determines how many buffers you send!
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&layout),
+    /// Layout materials of the shader create their bind group with, if it differs from the
+    /// shared texture bind group layout
+    pub fn material_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        self.material_layout.as_ref()
+    }
+
+    /// Textures materials of the shader bind, see `Material::with_textures`
+    pub fn texture_layout(&self) -> &MaterialTextureLayout {
+        &self.texture_layout
+    }
+
+    /// Size in bytes of the material uniform block, zero if materials don't carry one
+    pub fn material_uniforms_size(&self) -> usize {
+        self.material_uniforms_size
+    }
+
+    /// Creates the pipeline variant for the key if it isn't already cached
+    pub fn prepare_pipeline(&mut self, device: &wgpu::Device, key: PipelineKey) {
+        if !self.pipelines.contains_key(&key) {
+            let pipeline = self.create_pipeline(device, key);
+            self.pipelines.insert(key, pipeline);
+        }
+    }
+
+    /// Cached pipeline variant, see `prepare_pipeline`
+    pub fn get_pipeline(&self, key: &PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(key)
+    }
+
+    pub fn pipeline_count(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    /// Drop all cached variants, e.g. after the surface format has changed
+    pub fn clear_pipelines(&mut self) {
+        self.pipelines.clear();
+    }
+
+    fn create_pipeline(&self, device: &wgpu::Device, key: PipelineKey) -> wgpu::RenderPipeline {
+        let options = key.options;
+        let mut blend_state = Some(options.blend_state());
+        let mut constants = HashMap::new();
+        if let Some(cutoff) = options.alpha_cutoff {
+            constants.insert(AlphaCutoff::CONSTANT.to_string(), cutoff.0 as f64);
+        }
+        let mut depth_write_enabled = options.depth_write;
+        let mut depth_compare = options.depth_compare;
+        let mut polygon_mode = wgpu::PolygonMode::Fill;
+
+        // Overdraw replaces the fragment stage, keeping the shader's vertex transform
+        let overdraw_module;
+        let mut fragment_module = &self.shader_module;
+        match key.view {
+            DebugView::Normal => {}
+            DebugView::Wireframe => polygon_mode = wgpu::PolygonMode::Line,
+            DebugView::Overdraw => {
+                overdraw_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("Overdraw Shader"),
+                    source: wgpu::ShaderSource::Wgsl(
+                        include_str!("shaders/overdraw.wgsl").into(),
+                    ),
+                });
+                fragment_module = &overdraw_module;
+                constants.clear();
+                blend_state = Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::OVER,
+                });
+                depth_write_enabled = false;
+                depth_compare = wgpu::CompareFunction::Always;
+            }
+        }
+
+        // there is a pipeline per shader variant, determines how many buffers you send!
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{} Pipeline", self.label)),
+            layout: Some(&self.layout),
             vertex: wgpu::VertexState {
-                module: &shader_module,
+                module: &self.shader_module,
                 entry_point: None,
                 compilation_options: PipelineCompilationOptions::default(),
                 buffers: &[Vertex::desc()], //, InstanceRaw::desc() for particle systems
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader_module,
+                module: fragment_module,
                 entry_point: None,
-                compilation_options: PipelineCompilationOptions::default(),
+                compilation_options: PipelineCompilationOptions {
+                    constants: &constants,
+                    ..Default::default()
+                },
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: texture_format,
+                    format: key.format,
                     blend: blend_state,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -198,9 +902,9 @@ impl Shader {
                 topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
+                cull_mode: options.cull_mode.face(),
+                // Line requires Features::POLYGON_MODE_LINE, see `DebugView::Wireframe`
+                polygon_mode,
                 // Requires Features::DEPTH_CLIP_CONTROL
                 unclipped_depth: false,
                 // Requires Features::CONSERVATIVE_RASTERIZATION
@@ -209,49 +913,37 @@ impl Shader {
             depth_stencil: Some(wgpu::DepthStencilState {
                 // Could arguably be None for 2D
                 format: texture::Texture::DEPTH_FORMAT,
-                depth_write_enabled: !alpha_blending,
-                depth_compare: wgpu::CompareFunction::Less,
+                depth_write_enabled,
+                depth_compare,
                 stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
+                bias: options.depth_bias.state(),
             }),
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: key.sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
             cache: None,
-        });
-
-        Self {
-            render_pipeline,
-            camera_bind_group,
-            entity_bind_group,
-            requires_ordering: alpha_blending,
-            bytes_delegate: to_bytes_delegate,
-            bytes_buffer: Vec::new(),
-            next_offset: 0,
-        }
+        })
     }
 
-    pub fn reset_offset(&mut self) {
-        self.next_offset = 0;
-    }
-
-    pub fn write_entity_uniforms(&mut self, entity: &mut EntityDrawInstruction, queue: &wgpu::Queue) {
-        // previously the writing to the queue as done as part of the delegate,
-        // which avoided the use of a Vec just for returning uniform data per entity
-        // however this formulation has 'cleaner' separation of responsibility. We should probably
-        // profile this to see if there is significant performance impact and consider reverting
-        // to the delegate doing the queue write to avoid the unnecessary shuffling with Vec.
+    /// Write the entity's uniforms to the next free slot of the shared buffer for this shader's
+    /// uniform size, the buffer must have capacity, see `SharedBindings::reset_entities`
+    ///
+    /// Uniforms are uploaded for all entities at once by `SharedBindings::flush_entities`.
+    pub fn write_entity_uniforms(
+        &mut self,
+        entity: &mut EntityDrawInstruction,
+        bindings: &mut SharedBindings,
+    ) {
+        // The delegate writes into a Vec rather than straight into the shared buffer's
+        // staging data, so it needs no access to the bindings.
         // The use of a delegates is to avoid requiring type information when storing the shader.
-        entity.uniform_offset = self.next_offset * self.entity_bind_group.alignment;
-        self.next_offset += 1;
-        (self.bytes_delegate)(&entity.instance, &mut self.bytes_buffer);
-        queue.write_buffer(
-            &self.entity_bind_group.buffer,
-            entity.uniform_offset as wgpu::BufferAddress,
-            self.bytes_buffer.as_slice(),
-        );
+        self.uniform_writer
+            .write(&entity.instance, &mut self.bytes_buffer);
+        entity.uniform_offset =
+            bindings.write_entity_uniforms(self.entity_buffer, &self.bytes_buffer);
+
     }
 }
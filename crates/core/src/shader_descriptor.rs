@@ -0,0 +1,302 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use anyhow::*;
+
+use crate::{
+    entity::RenderProperties,
+    material::MaterialTextureLayout,
+    shader::{BlendMode, PipelineOptions, Shader, ShaderId},
+    shader_library::validate_wgsl,
+    State,
+};
+
+/// Render property written to a member of the entity uniform struct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniformField {
+    /// `mat4x4<f32>`, the world matrix
+    Model,
+    /// `vec4<f32>`
+    Color,
+    /// `vec2<f32>`
+    UvOffset,
+    /// `vec2<f32>`
+    UvScale,
+    /// `vec4<f32>`
+    OutlineColor,
+    /// `f32`, in texels
+    OutlineThickness,
+    /// `u32`, layer of the material's texture array
+    TextureLayer,
+}
+
+impl UniformField {
+    /// Alignment and size under WGSL's uniform address space layout rules
+    fn align_size(&self) -> (usize, usize) {
+        match self {
+            UniformField::Model => (16, 64),
+            UniformField::Color | UniformField::OutlineColor => (16, 16),
+            UniformField::UvOffset | UniformField::UvScale => (8, 8),
+            UniformField::OutlineThickness | UniformField::TextureLayer => (4, 4),
+        }
+    }
+
+    fn wgsl_type(&self) -> &'static str {
+        match self {
+            UniformField::Model => "mat4x4<f32>",
+            UniformField::Color | UniformField::OutlineColor => "vec4<f32>",
+            UniformField::UvOffset | UniformField::UvScale => "vec2<f32>",
+            UniformField::OutlineThickness => "f32",
+            UniformField::TextureLayer => "u32",
+        }
+    }
+
+    fn wgsl_name(&self) -> &'static str {
+        match self {
+            UniformField::Model => "world",
+            UniformField::Color => "color",
+            UniformField::UvOffset => "uv_offset",
+            UniformField::UvScale => "uv_scale",
+            UniformField::OutlineColor => "outline_color",
+            UniformField::OutlineThickness => "outline_thickness",
+            UniformField::TextureLayer => "texture_layer",
+        }
+    }
+
+    fn write(&self, instance: &RenderProperties, bytes: &mut Vec<u8>) {
+        let color = |color: wgpu::Color| {
+            [
+                color.r as f32,
+                color.g as f32,
+                color.b as f32,
+                color.a as f32,
+            ]
+        };
+        match self {
+            UniformField::Model => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&instance.world_matrix.to_cols_array()))
+            }
+            UniformField::Color => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&color(instance.color)))
+            }
+            UniformField::UvOffset => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&instance.uv_offset.to_array()))
+            }
+            UniformField::UvScale => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&instance.uv_scale.to_array()))
+            }
+            UniformField::OutlineColor => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&color(instance.outline_color)))
+            }
+            UniformField::OutlineThickness => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&instance.outline_thickness))
+            }
+            UniformField::TextureLayer => {
+                bytes.extend_from_slice(bytemuck::bytes_of(&instance.texture_layer))
+            }
+        }
+    }
+}
+
+/// Members of the `@group(1) @binding(0)` entity uniform struct of a shader, in declaration order
+///
+/// Offsets and padding follow WGSL's layout rules, so the struct in the shader only needs
+/// its members declared in the same order, see `wgsl_struct`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityUniformLayout {
+    fields: Vec<UniformField>,
+}
+
+impl EntityUniformLayout {
+    pub fn new(fields: &[UniformField]) -> Self {
+        Self {
+            fields: fields.to_vec(),
+        }
+    }
+
+    /// Layout of the `Entity` struct in `helia/entity.wgsl`, used by the built-in shaders
+    pub fn standard() -> Self {
+        Self::new(&[
+            UniformField::Model,
+            UniformField::Color,
+            UniformField::UvOffset,
+            UniformField::UvScale,
+        ])
+    }
+
+    pub fn fields(&self) -> &[UniformField] {
+        &self.fields
+    }
+
+    /// Size in bytes, rounded up to 16 as required of uniform structs
+    pub fn size(&self) -> usize {
+        let end = self.fields.iter().fold(0usize, |offset, field| {
+            let (align, size) = field.align_size();
+            offset.next_multiple_of(align) + size
+        });
+        end.next_multiple_of(16).max(16)
+    }
+
+    /// WGSL declaration of the struct with the given name, for use in shader source
+    pub fn wgsl_struct(&self, name: &str) -> String {
+        let mut output = format!("struct {} {{\n", name);
+        for field in self.fields.iter() {
+            output.push_str(&format!(
+                "    {}: {},\n",
+                field.wgsl_name(),
+                field.wgsl_type()
+            ));
+        }
+        output.push_str("};\n");
+        output
+    }
+
+    pub(crate) fn write_bytes(&self, instance: &RenderProperties, bytes: &mut Vec<u8>) {
+        bytes.clear();
+        for field in self.fields.iter() {
+            let (align, _) = field.align_size();
+            bytes.resize(bytes.len().next_multiple_of(align), 0);
+            field.write(instance, bytes);
+        }
+        bytes.resize(self.size(), 0);
+    }
+}
+
+impl Default for EntityUniformLayout {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum WgslSource {
+    Source(String),
+    File(PathBuf),
+}
+
+/// Describes a user-defined shader, build it to get a `ShaderId` for use with `Material::new`
+///
+/// Source is composed with `state.shader_library`, so it may `#include` the built-in modules,
+/// and is validated before the shader is created. Shaders use the same bind groups as the
+/// built-in shaders: the camera at `@group(0)` (see `helia/camera.wgsl`), the entity uniform
+/// at `@group(1)` laid out as declared, and the material textures and samplers at `@group(2)`,
+/// a single texture and sampler unless declared with `with_texture_layout`, followed by the
+/// material's uniform block if declared.
+/// Vertices have a position at location 0 and texture coordinates at location 1.
+#[derive(Debug, Clone)]
+pub struct ShaderDescriptor {
+    label: String,
+    source: WgslSource,
+    uniform_layout: EntityUniformLayout,
+    options: PipelineOptions,
+    lit: bool,
+    texture_layout: MaterialTextureLayout,
+    material_uniforms_size: usize,
+}
+
+impl ShaderDescriptor {
+    pub fn from_source<L: Into<String>, S: Into<String>>(label: L, source: S) -> Self {
+        Self {
+            label: label.into(),
+            source: WgslSource::Source(source.into()),
+            uniform_layout: EntityUniformLayout::standard(),
+            options: PipelineOptions::default(),
+            lit: false,
+            texture_layout: MaterialTextureLayout::single(),
+            material_uniforms_size: 0,
+        }
+    }
+
+    /// Source is read from the file on `build`, the path is used as the label
+    pub fn from_file<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        Self {
+            label: path.display().to_string(),
+            source: WgslSource::File(path),
+            uniform_layout: EntityUniformLayout::standard(),
+            options: PipelineOptions::default(),
+            lit: false,
+            texture_layout: MaterialTextureLayout::single(),
+            material_uniforms_size: 0,
+        }
+    }
+
+    pub fn with_uniform_layout(mut self, layout: EntityUniformLayout) -> Self {
+        self.uniform_layout = layout;
+        self
+    }
+
+    /// Bind the scene's lights at `@group(3)`, include `helia/lighting.wgsl` to use them
+    pub fn with_lighting(mut self) -> Self {
+        self.lit = true;
+        self
+    }
+
+    /// Textures materials bind at `@group(2)`, e.g. a diffuse and a normal map, create
+    /// materials with `Material::with_textures`
+    pub fn with_texture_layout(mut self, layout: MaterialTextureLayout) -> Self {
+        self.texture_layout = layout;
+        self
+    }
+
+    /// Materials carry a uniform block of the type, set with `Material::with_uniforms`, which
+    /// the shader declares as a struct of the same layout at `@group(2)`, at the binding after
+    /// the last texture sampler, `@binding(2)` for a single texture
+    pub fn with_material_uniforms<T: bytemuck::Pod>(mut self) -> Self {
+        self.material_uniforms_size = std::mem::size_of::<T>();
+        self
+    }
+
+    /// Pipeline options used by materials which don't specify their own
+    pub fn with_options(mut self, options: PipelineOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Blend with the existing color, blended modes don't write depth
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.options = self.options.with_blend_mode(blend_mode);
+        self
+    }
+
+    /// Alpha blend over the existing color without writing depth
+    pub fn with_alpha_blending(self) -> Self {
+        self.with_blend_mode(BlendMode::Alpha)
+    }
+
+    /// Add to the existing color without writing depth
+    pub fn with_additive_blending(self) -> Self {
+        self.with_blend_mode(BlendMode::Additive)
+    }
+
+    /// Read and validate the source and create the shader, returns an error describing the
+    /// problem if the source can't be read, an include is unresolved or the WGSL is invalid
+    pub fn build(self, state: &mut State) -> Result<ShaderId> {
+        let source = match &self.source {
+            WgslSource::Source(source) => Cow::Borrowed(source.as_str()),
+            WgslSource::File(path) => Cow::Owned(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Unable to read shader {}", path.display()))?,
+            ),
+        };
+        let source = state.shader_library.compose(&source)?;
+        validate_wgsl(&self.label, &source)?;
+
+        let format = state.surface_format();
+        let shader = Shader::with_layout(
+            &state.device,
+            &mut state.bindings,
+            wgpu::ShaderModuleDescriptor {
+                label: Some(&self.label),
+                source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+            },
+            format,
+            &state.texture_bind_group_layout,
+            self.lit.then_some(&state.lighting.layout),
+            self.options,
+            self.uniform_layout,
+            self.texture_layout,
+            self.material_uniforms_size,
+        );
+        Ok(state.resources.shaders.insert_named(shader, self.label))
+    }
+}
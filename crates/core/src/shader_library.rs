@@ -0,0 +1,163 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use anyhow::*;
+
+/// Named WGSL modules which shader source can pull in with `#include "name"`
+///
+/// Includes are resolved recursively and each module is only included once per shader,
+/// so modules can include their own dependencies without producing duplicate declarations.
+/// Built-in modules are registered under the `helia/` prefix.
+pub struct ShaderLibrary {
+    modules: HashMap<String, String>,
+}
+
+impl ShaderLibrary {
+    pub fn new() -> Self {
+        Self {
+            modules: HashMap::new(),
+        }
+    }
+
+    /// Library containing the modules used by the built-in shaders
+    pub fn with_builtins() -> Self {
+        let mut library = Self::new();
+        library.add_module(
+            "helia/camera.wgsl",
+            include_str!("shaders/include/camera.wgsl"),
+        );
+        library.add_module(
+            "helia/entity.wgsl",
+            include_str!("shaders/include/entity.wgsl"),
+        );
+        library.add_module(
+            "helia/entity_instance.wgsl",
+            include_str!("shaders/include/entity_instance.wgsl"),
+        );
+        library.add_module(
+            "helia/color.wgsl",
+            include_str!("shaders/include/color.wgsl"),
+        );
+        library.add_module(
+            "helia/lighting.wgsl",
+            include_str!("shaders/include/lighting.wgsl"),
+        );
+        library
+    }
+
+    /// Have `helia/entity_instance.wgsl` read entities from a storage buffer indexed by the
+    /// instance index rather than a uniform, done by the renderer when the device supports
+    /// storage buffers
+    ///
+    /// Shaders including it must call `load_entity` with `@builtin(instance_index)`.
+    pub fn enable_entity_storage(&mut self) {
+        self.add_module(
+            "helia/entity_instance.wgsl",
+            include_str!("shaders/include/entity_instance_storage.wgsl"),
+        );
+    }
+
+    /// Register a module, replacing any existing module with the same name
+    pub fn add_module<N: Into<String>, S: Into<String>>(&mut self, name: N, source: S) {
+        self.modules.insert(name.into(), source.into());
+    }
+
+    pub fn contains_module(&self, name: &str) -> bool {
+        self.modules.contains_key(name)
+    }
+
+    /// Resolve all includes in the source, returning the combined WGSL
+    pub fn compose(&self, source: &str) -> Result<String> {
+        let mut output = String::with_capacity(source.len());
+        let mut included = HashSet::new();
+        self.compose_into(source, "<source>", &mut included, &mut output)?;
+        Ok(output)
+    }
+
+    /// Compose and validate the source and wrap it in a descriptor suitable for `Shader::new`
+    pub fn descriptor(&self, label: &'static str, source: &str) -> Result<wgpu::ShaderModuleDescriptor<'static>> {
+        let source = self.compose(source)?;
+        validate_wgsl(label, &source)?;
+        Ok(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        })
+    }
+
+    fn compose_into<'a>(
+        &'a self,
+        source: &'a str,
+        source_name: &str,
+        included: &mut HashSet<&'a str>,
+        output: &mut String,
+    ) -> Result<()> {
+        for (index, line) in source.lines().enumerate() {
+            let Some(directive) = line.trim().strip_prefix("#include") else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+
+            let name = directive
+                .trim()
+                .strip_prefix('"')
+                .and_then(|name| name.strip_suffix('"'))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "{}:{}: malformed include, expected #include \"name\"",
+                        source_name,
+                        index + 1
+                    )
+                })?;
+            let (name, module) = self.modules.get_key_value(name).ok_or_else(|| {
+                anyhow!(
+                    "{}:{}: unknown shader module \"{}\"",
+                    source_name,
+                    index + 1,
+                    name
+                )
+            })?;
+            if included.insert(name.as_str()) {
+                self.compose_into(module, name, included, output)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse and validate WGSL, returning naga's diagnostics as the error
+///
+/// The error includes the line, column and offending snippet of the source, rather than the
+/// panic wgpu raises on an invalid shader module. The name is used as the path in the output.
+pub fn validate_wgsl(name: &str, source: &str) -> Result<()> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|error| {
+        anyhow!(
+            "Failed to parse shader {}\n{}",
+            name,
+            error.emit_to_string_with_path(source, name)
+        )
+    })?;
+    // Device capabilities are checked by wgpu when the module is created,
+    // this is only concerned with errors in the source itself
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|error| {
+        anyhow!(
+            "Failed to validate shader {}\n{}",
+            name,
+            error.emit_to_string_with_path(source, name)
+        )
+    })?;
+    Ok(())
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
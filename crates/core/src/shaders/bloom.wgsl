@@ -0,0 +1,86 @@
+struct Bloom {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _padding: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// Read by every pass, the scene for the prefilter, otherwise a level of the blur chain
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> u_bloom: Bloom;
+// Only read by the composite pass
+@group(0) @binding(3)
+var t_scene: texture_2d<f32>;
+
+// Single triangle covering the screen, no vertex buffer required
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// Four bilinear taps a texel from the centre, averaging a 4x4 block of the source
+fn box_sample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    var color = textureSample(t_source, s_source, uv + vec2<f32>(-texel.x, -texel.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(texel.x, -texel.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-texel.x, texel.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(texel.x, texel.y)).rgb;
+    return 0.25 * color;
+}
+
+// Keep the part of the color above the threshold, easing in over the knee
+fn bright(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    let knee = u_bloom.knee;
+    let soft = clamp(brightness - u_bloom.threshold + knee, 0.0, 2.0 * knee);
+    let curve = soft * soft / (4.0 * knee + 0.00001);
+    let contribution = max(curve, brightness - u_bloom.threshold) / max(brightness, 0.00001);
+    return color * contribution;
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(bright(box_sample(in.tex_coords)), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(box_sample(in.tex_coords), 1.0);
+}
+
+// 3x3 tent filter over the smaller level, added to the level above
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    let uv = in.tex_coords;
+    var color = 4.0 * textureSample(t_source, s_source, uv).rgb;
+    color += 2.0 * textureSample(t_source, s_source, uv + vec2<f32>(texel.x, 0.0)).rgb;
+    color += 2.0 * textureSample(t_source, s_source, uv - vec2<f32>(texel.x, 0.0)).rgb;
+    color += 2.0 * textureSample(t_source, s_source, uv + vec2<f32>(0.0, texel.y)).rgb;
+    color += 2.0 * textureSample(t_source, s_source, uv - vec2<f32>(0.0, texel.y)).rgb;
+    color += textureSample(t_source, s_source, uv + texel).rgb;
+    color += textureSample(t_source, s_source, uv - texel).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(texel.x, -texel.y)).rgb;
+    color += textureSample(t_source, s_source, uv + vec2<f32>(-texel.x, texel.y)).rgb;
+    return vec4<f32>(color / 16.0, 1.0);
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(t_scene, s_source, in.tex_coords);
+    let glow = textureSample(t_source, s_source, in.tex_coords).rgb;
+    return vec4<f32>(scene.rgb + u_bloom.intensity * glow, scene.a);
+}
@@ -0,0 +1,53 @@
+#include "helia/color.wgsl"
+
+struct ColorGrading {
+    intensity: f32,
+    lut_size: f32,
+    // Non-zero when the target is an sRGB format, so sampled colors are linear
+    // and need encoding before the lookup, LUTs are authored against sRGB values
+    srgb_target: u32,
+    _padding: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+@group(0) @binding(2)
+var t_lut: texture_3d<f32>;
+@group(0) @binding(3)
+var s_lut: sampler;
+@group(0) @binding(4)
+var<uniform> u_grading: ColorGrading;
+
+// Single triangle covering the screen, no vertex buffer required
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(t_scene, s_scene, in.tex_coords);
+    var color = clamp(scene.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (u_grading.srgb_target != 0u) {
+        color = linear_to_srgb(color);
+    }
+    // Remap so 0 and 1 land on the centres of the first and last texels
+    let size = u_grading.lut_size;
+    let coords = color * ((size - 1.0) / size) + 0.5 / size;
+    var graded = textureSample(t_lut, s_lut, coords).rgb;
+    if (u_grading.srgb_target != 0u) {
+        graded = srgb_to_linear(graded);
+    }
+    return vec4<f32>(mix(scene.rgb, graded, u_grading.intensity), scene.a);
+}
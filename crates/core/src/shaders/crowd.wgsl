@@ -0,0 +1,81 @@
+#include "helia/camera.wgsl"
+
+struct Animation {
+    // Offset of the mesh in its vertex buffer, included in vertex_index
+    base_vertex: u32,
+    vertex_count: u32,
+    frame_count: u32,
+    // Texels per row of the baked positions
+    width: u32,
+};
+
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+    @location(10) frame: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> u_animation: Animation;
+@group(1) @binding(1)
+var t_positions: texture_2d<f32>;
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+// Position of the vertex in the frame, frames are stored one after another, vertex by vertex
+fn baked_position(vertex: u32, frame: u32) -> vec3<f32> {
+    let texel = frame * u_animation.vertex_count + vertex;
+    let coords = vec2<u32>(texel % u_animation.width, texel / u_animation.width);
+    return textureLoad(t_positions, coords, 0).xyz;
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let world = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let vertex = model.index - u_animation.base_vertex;
+    var position = model.position;
+    if (u_animation.frame_count > 0u && vertex < u_animation.vertex_count) {
+        // Blend between the frames either side of fractional frames
+        let last = f32(u_animation.frame_count - 1u);
+        let frame = clamp(instance.frame, 0.0, last);
+        let current = u32(floor(frame));
+        let next = min(current + 1u, u_animation.frame_count - 1u);
+        position = mix(
+            baked_position(vertex, current),
+            baked_position(vertex, next),
+            fract(frame),
+        );
+    }
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.color = instance.color;
+    out.clip_position = u_camera.view_proj * world * vec4<f32>(position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    if (color.a <= 0.0) {
+        discard;
+    }
+    return color;
+}
@@ -0,0 +1,6 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_camera: CameraUniform;
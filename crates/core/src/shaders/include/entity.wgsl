@@ -0,0 +1,10 @@
+struct Entity {
+    world: mat4x4<f32>,
+    color: vec4<f32>,
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+};
+
+@group(1)
+@binding(0)
+var<uniform> u_entity: Entity;
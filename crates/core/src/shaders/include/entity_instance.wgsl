@@ -0,0 +1,7 @@
+#include "helia/entity.wgsl"
+
+// Entity of the draw, a uniform bound at an offset per entity on devices without storage
+// buffers, see entity_instance_storage.wgsl
+fn load_entity(instance_index: u32) -> Entity {
+    return u_entity;
+}
@@ -0,0 +1,16 @@
+// Replaces entity_instance.wgsl where storage buffers are supported, entities are bound
+// once and drawn with their index as the instance index
+struct Entity {
+    world: mat4x4<f32>,
+    color: vec4<f32>,
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+};
+
+@group(1)
+@binding(0)
+var<storage, read> u_entities: array<Entity>;
+
+fn load_entity(instance_index: u32) -> Entity {
+    return u_entities[instance_index];
+}
@@ -0,0 +1,39 @@
+struct DirectionalLight {
+    // Direction the light travels
+    direction: vec3<f32>,
+    color: vec3<f32>,
+};
+
+struct PointLight {
+    position: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+};
+
+struct Lights {
+    ambient: vec3<f32>,
+    directional_count: u32,
+    directional: array<DirectionalLight, 4>,
+    point: array<PointLight, 16>,
+    point_count: u32,
+};
+
+@group(3) @binding(0)
+var<uniform> u_lights: Lights;
+
+// Diffuse light reaching a surface at the world position with the given unit normal
+fn diffuse_lighting(world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var light = u_lights.ambient;
+    for (var i = 0u; i < u_lights.directional_count; i++) {
+        let directional = u_lights.directional[i];
+        light += directional.color * max(dot(normal, -directional.direction), 0.0);
+    }
+    for (var i = 0u; i < u_lights.point_count; i++) {
+        let point = u_lights.point[i];
+        let offset = point.position - world_position;
+        let distance = length(offset);
+        let falloff = clamp(1.0 - distance / point.range, 0.0, 1.0);
+        light += point.color * max(dot(normal, offset / max(distance, 0.0001)), 0.0) * falloff * falloff;
+    }
+    return light;
+}
@@ -0,0 +1,50 @@
+#include "helia/camera.wgsl"
+#include "helia/entity.wgsl"
+#include "helia/lighting.wgsl"
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+};
+
+// Set on pipelines for alpha cutoff materials, fragments with lower alpha are discarded
+override ALPHA_CUTOFF: f32 = 0.0;
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let world_position = u_entity.world * vec4<f32>(model.position, 1.0);
+    out.tex_coords = model.tex_coords * u_entity.uv_scale + u_entity.uv_offset;
+    out.world_position = world_position.xyz;
+    // Assumes uniform scale, non-uniform scale would need the inverse transpose
+    let world = u_entity.world;
+    out.world_normal = mat3x3<f32>(world[0].xyz, world[1].xyz, world[2].xyz) * model.normal;
+    out.clip_position = u_camera.view_proj * world_position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(in.world_normal);
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * u_entity.color;
+    if (color.a < ALPHA_CUTOFF) {
+        discard;
+    }
+    return vec4<f32>(color.rgb * diffuse_lighting(in.world_position, normal), color.a);
+}
@@ -0,0 +1,5 @@
+// Added to the target by each fragment drawn, see `DebugView::Overdraw`
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 0.04, 0.02, 1.0);
+}
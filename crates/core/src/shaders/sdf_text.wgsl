@@ -0,0 +1,87 @@
+#include "helia/camera.wgsl"
+
+struct SdfEntity {
+    world: mat4x4<f32>,
+    color: vec4<f32>,
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+    outline_color: vec4<f32>,
+    // Width in normalized distance, 0.5 being the field's full spread
+    outline_thickness: f32,
+};
+
+@group(1)
+@binding(0)
+var<uniform> u_entity: SdfEntity;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+// Shared by all the text using the material
+struct SdfMaterial {
+    glow_color: vec4<f32>,
+    // In normalized distance as the outline thickness
+    glow_radius: f32,
+};
+
+@group(2) @binding(2)
+var<uniform> u_material: SdfMaterial;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords * u_entity.uv_scale + u_entity.uv_offset;
+    out.clip_position = u_camera.view_proj * u_entity.world * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// Single channel fields store the same distance in each channel, so the median
+// reads both them and multi-channel fields
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    return max(min(a, b), min(max(a, b), c));
+}
+
+// Straight alpha source over destination
+fn over(src: vec4<f32>, dst: vec4<f32>) -> vec4<f32> {
+    let alpha = src.a + dst.a * (1.0 - src.a);
+    if (alpha <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    let rgb = (src.rgb * src.a + dst.rgb * dst.a * (1.0 - src.a)) / alpha;
+    return vec4<f32>(rgb, alpha);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let field = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // Positive inside the glyph
+    let distance = median(field.r, field.g, field.b) - 0.5;
+    // Anti-alias over a screen pixel whatever the scale
+    let width = max(fwidth(distance), 0.0001);
+
+    let fill = smoothstep(-width, width, distance);
+    let outline_edge = -max(u_entity.outline_thickness, 0.0);
+    let outline = smoothstep(outline_edge - width, outline_edge + width, distance);
+    let glow_edge = outline_edge - max(u_material.glow_radius, 0.0);
+    let glow = select(0.0, smoothstep(glow_edge, outline_edge, distance), u_material.glow_radius > 0.0);
+
+    var color = u_material.glow_color * vec4<f32>(1.0, 1.0, 1.0, glow);
+    if (u_entity.outline_thickness > 0.0) {
+        color = over(u_entity.outline_color * vec4<f32>(1.0, 1.0, 1.0, outline), color);
+    }
+    return over(u_entity.color * vec4<f32>(1.0, 1.0, 1.0, fill), color);
+}
@@ -0,0 +1,36 @@
+struct Skybox {
+    // Inverse of the camera's view projection without its translation
+    inverse_view_proj: mat4x4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_skybox: Skybox;
+@group(0) @binding(1)
+var t_skybox: texture_cube<f32>;
+@group(0) @binding(2)
+var s_skybox: sampler;
+
+// Single triangle covering the screen at the far plane, no vertex buffer required
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let near = u_skybox.inverse_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far = u_skybox.inverse_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = far.xyz / far.w - near.xyz / near.w;
+    // Cubemaps are sampled left handed, flip z so the front face is ahead of the default camera
+    return textureSample(t_skybox, s_skybox, vec3<f32>(direction.xy, -direction.z));
+}
@@ -0,0 +1,50 @@
+#include "helia/camera.wgsl"
+
+struct ArrayEntity {
+    world: mat4x4<f32>,
+    color: vec4<f32>,
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+    texture_layer: u32,
+};
+
+@group(1)
+@binding(0)
+var<uniform> u_entity: ArrayEntity;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// Set on pipelines for alpha cutoff materials, fragments with lower alpha are discarded
+override ALPHA_CUTOFF: f32 = 0.0;
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords * u_entity.uv_scale + u_entity.uv_offset;
+    out.clip_position = u_camera.view_proj * u_entity.world * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords, u_entity.texture_layer) * u_entity.color;
+    if (color.a < ALPHA_CUTOFF) {
+        discard;
+    }
+    return color;
+}
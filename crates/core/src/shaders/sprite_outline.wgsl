@@ -0,0 +1,81 @@
+#include "helia/camera.wgsl"
+
+struct OutlineEntity {
+    world: mat4x4<f32>,
+    color: vec4<f32>,
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+    outline_color: vec4<f32>,
+    // In texels of the sprite texture, 0.0 disables the outline
+    outline_thickness: f32,
+};
+
+@group(1)
+@binding(0)
+var<uniform> u_entity: OutlineEntity;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords * u_entity.uv_scale + u_entity.uv_offset;
+    out.clip_position = u_camera.view_proj * u_entity.world * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// Alpha of the neighbour, treating anything outside the sprite's region of the
+// texture as transparent so atlas neighbours don't bleed into the outline
+fn neighbour_alpha(uv: vec2<f32>, region_min: vec2<f32>, region_max: vec2<f32>) -> f32 {
+    let alpha = textureSampleLevel(t_diffuse, s_diffuse, uv, 0.0).a;
+    let inside = all(uv >= region_min) && all(uv <= region_max);
+    return select(0.0, alpha, inside);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let sprite = textureSample(t_diffuse, s_diffuse, in.tex_coords) * u_entity.color;
+    if (u_entity.outline_thickness <= 0.0) {
+        return sprite;
+    }
+
+    let offset = u_entity.outline_thickness / vec2<f32>(textureDimensions(t_diffuse));
+    let region_end = u_entity.uv_offset + u_entity.uv_scale;
+    let region_min = min(u_entity.uv_offset, region_end);
+    let region_max = max(u_entity.uv_offset, region_end);
+    // Sample the eight directions, diagonals scaled to keep the outline round
+    let diagonal = 0.7071 * offset;
+    var alpha = 0.0;
+    alpha = max(alpha, neighbour_alpha(in.tex_coords + vec2<f32>(offset.x, 0.0), region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords - vec2<f32>(offset.x, 0.0), region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords + vec2<f32>(0.0, offset.y), region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords - vec2<f32>(0.0, offset.y), region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords + diagonal, region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords - diagonal, region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords + vec2<f32>(diagonal.x, -diagonal.y), region_min, region_max));
+    alpha = max(alpha, neighbour_alpha(in.tex_coords + vec2<f32>(-diagonal.x, diagonal.y), region_min, region_max));
+
+    // Outline sits behind the sprite, showing only where the sprite is transparent
+    let outline = u_entity.outline_color * vec4<f32>(1.0, 1.0, 1.0, alpha);
+    let out_alpha = sprite.a + outline.a * (1.0 - sprite.a);
+    if (out_alpha <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    let rgb = (sprite.rgb * sprite.a + outline.rgb * outline.a * (1.0 - sprite.a)) / out_alpha;
+    return vec4<f32>(rgb, out_alpha);
+}
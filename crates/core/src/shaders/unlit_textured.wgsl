@@ -1,6 +1,5 @@
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
+#include "helia/camera.wgsl"
+#include "helia/entity_instance.wgsl"
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
@@ -10,21 +9,11 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
 };
 
-struct Entity {
-    world: mat4x4<f32>,
-    color: vec4<f32>,
-    uv_offset: vec2<f32>,
-    uv_scale: vec2<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> u_camera: CameraUniform;
-
-@group(1)
-@binding(0)
-var<uniform> u_entity: Entity;
+// Set on pipelines for alpha cutoff materials, fragments with lower alpha are discarded
+override ALPHA_CUTOFF: f32 = 0.0;
 
 @group(2) @binding(0)
 var t_diffuse: texture_2d<f32>;
@@ -35,14 +24,21 @@ var s_diffuse: sampler;
 @vertex
 fn vs_main(
     model: VertexInput,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
+    let entity = load_entity(instance_index);
     var out: VertexOutput;
-    out.tex_coords = model.tex_coords * u_entity.uv_scale + u_entity.uv_offset;
-    out.clip_position = u_camera.view_proj * u_entity.world * vec4<f32>(model.position, 1.0);
+    out.tex_coords = model.tex_coords * entity.uv_scale + entity.uv_offset;
+    out.color = entity.color;
+    out.clip_position = u_camera.view_proj * entity.world * vec4<f32>(model.position, 1.0);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * u_entity.color;
-}
\ No newline at end of file
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    if (color.a < ALPHA_CUTOFF) {
+        discard;
+    }
+    return color;
+}
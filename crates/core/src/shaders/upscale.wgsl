@@ -0,0 +1,44 @@
+struct Upscale {
+    texel_size: vec2<f32>,
+    sharpness: f32,
+    _padding: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+@group(0) @binding(2)
+var<uniform> u_upscale: Upscale;
+
+// Single triangle covering the screen, no vertex buffer required
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let center = textureSample(t_scene, s_scene, in.tex_coords);
+    if (u_upscale.sharpness <= 0.0) {
+        return center;
+    }
+    // Unsharp mask over the direct neighbours to recover some of the detail lost to filtering
+    let offset = u_upscale.texel_size;
+    let north = textureSample(t_scene, s_scene, in.tex_coords - vec2<f32>(0.0, offset.y)).rgb;
+    let south = textureSample(t_scene, s_scene, in.tex_coords + vec2<f32>(0.0, offset.y)).rgb;
+    let east = textureSample(t_scene, s_scene, in.tex_coords + vec2<f32>(offset.x, 0.0)).rgb;
+    let west = textureSample(t_scene, s_scene, in.tex_coords - vec2<f32>(offset.x, 0.0)).rgb;
+    let k = u_upscale.sharpness;
+    let sharpened = center.rgb * (1.0 + 4.0 * k) - k * (north + south + east + west);
+    return vec4<f32>(clamp(sharpened, vec3<f32>(0.0), vec3<f32>(1.0)), center.a);
+}
@@ -0,0 +1,224 @@
+use glam::*;
+
+use crate::{
+    camera::{Camera, CameraBindGroup},
+    shader_library::ShaderLibrary,
+    Color, RenderContext,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShapeVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+impl ShapeVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ShapeVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Immediate mode flat colored 2D shapes, for prototyping without creating meshes,
+/// materials or textures
+///
+/// Shapes are in the xy plane, batched into a single draw each frame, alpha blended in the
+/// order they were added over the scene with the main camera, then cleared. They are drawn
+/// before `State::debug` lines.
+pub struct ShapeDraw {
+    vertices: Vec<ShapeVertex>,
+    format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    camera_bind_group: CameraBindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+}
+
+impl ShapeDraw {
+    const INITIAL_VERTEX_CAPACITY: usize = 1024;
+    const CIRCLE_SEGMENTS: usize = 32;
+
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let camera_bind_group = CameraBindGroup::new(device);
+        let pipeline = Self::create_pipeline(device, library, &camera_bind_group.layout, format);
+        Self {
+            vertices: Vec::new(),
+            format,
+            pipeline,
+            camera_bind_group,
+            vertex_buffer: Self::create_vertex_buffer(device, Self::INITIAL_VERTEX_CAPACITY),
+            vertex_capacity: Self::INITIAL_VERTEX_CAPACITY,
+        }
+    }
+
+    fn triangle(&mut self, points: [Vec2; 3], color: Color) {
+        let color = [
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ];
+        self.vertices.extend(points.map(|point| ShapeVertex {
+            position: point.extend(0.0).to_array(),
+            color,
+        }));
+    }
+
+    /// Filled rect centered on the point
+    pub fn draw_rect(&mut self, center: Vec2, size: Vec2, color: Color) {
+        let half = size / 2.0;
+        let (min, max) = (center - half, center + half);
+        self.draw_polygon(
+            &[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)],
+            color,
+        );
+    }
+
+    pub fn draw_circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        let point = |i: usize| {
+            let angle = i as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + radius * Vec2::from_angle(angle)
+        };
+        for i in 0..Self::CIRCLE_SEGMENTS {
+            self.triangle([center, point(i), point(i + 1)], color);
+        }
+    }
+
+    /// Line of the given thickness in world units, with square ends
+    pub fn draw_line(&mut self, from: Vec2, to: Vec2, thickness: f32, color: Color) {
+        let Some(direction) = (to - from).try_normalize() else {
+            return;
+        };
+        let offset = direction.perp() * thickness / 2.0;
+        self.draw_polygon(
+            &[from - offset, to - offset, to + offset, from + offset],
+            color,
+        );
+    }
+
+    /// Filled convex polygon, points in order around its edge
+    pub fn draw_polygon(&mut self, points: &[Vec2], color: Color) {
+        for i in 1..points.len().saturating_sub(1) {
+            self.triangle([points[0], points[i], points[i + 1]], color);
+        }
+    }
+
+    /// Number of triangles queued for the next frame
+    pub fn triangle_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Draw and clear the queued shapes
+    pub(crate) fn render(
+        &mut self,
+        context: &mut RenderContext,
+        camera: &Camera,
+        library: &ShaderLibrary,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let (device, queue, format) = (context.device, context.queue, context.format);
+        if format != self.format {
+            self.format = format;
+            self.pipeline =
+                Self::create_pipeline(device, library, &self.camera_bind_group.layout, format);
+        }
+        if self.vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.vertex_capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.camera_bind_group.update(camera, queue);
+
+        {
+            let mut render_pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Shapes Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: context.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    ..Default::default()
+                });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..self.vertices.len() as u32, 0..1);
+        }
+        self.vertices.clear();
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shapes Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<ShapeVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        library: &ShaderLibrary,
+        camera_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        // Vertex colored with the camera's view projection, as for debug lines
+        let descriptor = library
+            .descriptor("debug_lines.wgsl", include_str!("shaders/debug_lines.wgsl"))
+            .expect("Built-in shader includes should resolve");
+        let shader_module = device.create_shader_module(descriptor);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shapes Pipeline Layout"),
+            bind_group_layouts: &[camera_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shapes Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[ShapeVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // Not culled, so shapes given in either winding are drawn
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use glam::*;
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    camera::{Camera, CameraId},
+    resource_map::ResourceMap,
+    shader_library::ShaderLibrary,
+    texture::{Texture, TextureId},
+};
+
+/// Resample an equirectangular panorama into the six faces of a cubemap, in the order
+/// expected by `Texture::cubemap_from_images`
+///
+/// The centre of the panorama becomes the front face, ahead of the default camera.
+pub fn faces_from_equirect(source: &RgbaImage, face_size: u32) -> Vec<RgbaImage> {
+    (0..6)
+        .map(|face| {
+            RgbaImage::from_fn(face_size, face_size, |x, y| {
+                let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                // Back to the right handed world the shader flips z out of
+                let direction = face_direction(face, s, t).normalize() * Vec3::new(1.0, 1.0, -1.0);
+                let u = 0.5 + direction.x.atan2(-direction.z) / std::f32::consts::TAU;
+                let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / std::f32::consts::PI;
+                sample_bilinear(source, u, v)
+            })
+        })
+        .collect()
+}
+
+/// Direction through the texel at `s`, `t` in -1 to 1 of the cubemap face, per the
+/// layer order and orientation of cube textures
+fn face_direction(face: usize, s: f32, t: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    }
+}
+
+/// Sample at normalized coordinates, wrapping horizontally around the panorama
+fn sample_bilinear(source: &RgbaImage, u: f32, v: f32) -> Rgba<u8> {
+    let (width, height) = source.dimensions();
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let column = |x: f32| (x as i64).rem_euclid(width as i64) as u32;
+    let row = |y: f32| (y as u32).min(height - 1);
+    let texel = |x: f32, y: f32| Vec4::from(source.get_pixel(column(x), row(y)).0.map(f32::from));
+    let top = texel(x0, y0).lerp(texel(x0 + 1.0, y0), fx);
+    let bottom = texel(x0, y0 + 1.0).lerp(texel(x0 + 1.0, y0 + 1.0), fx);
+    Rgba(
+        top.lerp(bottom, fy)
+            .round()
+            .to_array()
+            .map(|value| value as u8),
+    )
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyboxUniforms {
+    inverse_view_proj: [[f32; 4]; 4],
+}
+
+impl SkyboxUniforms {
+    fn new(camera: &Camera) -> Self {
+        // Without translation the sky stays infinitely far away as the camera moves
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(camera.build_view_matrix()));
+        Self {
+            inverse_view_proj: (camera.build_projection_matrix() * rotation)
+                .inverse()
+                .to_cols_array_2d(),
+        }
+    }
+}
+
+struct SkyboxBinding {
+    texture: TextureId,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Draws the cubemap of a camera's `skybox` behind everything else, at the start of each
+/// pass which clears color for the camera
+pub(crate) struct Skybox {
+    layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader_module: wgpu::ShaderModule,
+    /// Keyed by target format and sample count
+    pipelines: HashMap<(wgpu::TextureFormat, u32), wgpu::RenderPipeline>,
+    /// Keyed by camera, None being the main camera
+    bindings: HashMap<Option<CameraId>, SkyboxBinding>,
+}
+
+impl Skybox {
+    pub fn new(device: &wgpu::Device, library: &ShaderLibrary) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("skybox_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let descriptor = library
+            .descriptor("skybox.wgsl", include_str!("shaders/skybox.wgsl"))
+            .expect("Built-in shader includes should resolve");
+        Self {
+            layout,
+            pipeline_layout,
+            shader_module: device.create_shader_module(descriptor),
+            pipelines: HashMap::new(),
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Update the bindings of the main camera and the cameras draws switch to, and create
+    /// the pipeline for the target
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        main_camera: &Camera,
+        cameras: &ResourceMap<CameraId, Camera>,
+        used: impl Iterator<Item = CameraId>,
+        textures: &ResourceMap<TextureId, Texture>,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.prepare_camera(device, queue, None, main_camera, textures);
+        for id in used {
+            if let Some(camera) = cameras.get(id) {
+                self.prepare_camera(device, queue, Some(id), camera, textures);
+            }
+        }
+        if !self.bindings.is_empty() && !self.pipelines.contains_key(&(format, sample_count)) {
+            let pipeline = self.create_pipeline(device, format, sample_count);
+            self.pipelines.insert((format, sample_count), pipeline);
+        }
+    }
+
+    /// Update the binding of the camera, None being the main camera, cameras without a skybox
+    /// or whose cubemap is missing have none
+    fn prepare_camera(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: Option<CameraId>,
+        camera: &Camera,
+        textures: &ResourceMap<TextureId, Texture>,
+    ) {
+        let Some((texture_id, texture)) = camera
+            .skybox
+            .and_then(|texture_id| Some((texture_id, textures.get(texture_id)?)))
+        else {
+            self.bindings.remove(&id);
+            return;
+        };
+        let uniforms = SkyboxUniforms::new(camera);
+        match self.bindings.get(&id) {
+            Some(binding) if binding.texture == texture_id => {
+                queue.write_buffer(&binding.buffer, 0, bytemuck::cast_slice(&[uniforms]));
+            }
+            _ => {
+                let binding = self.create_binding(device, texture_id, texture, uniforms);
+                self.bindings.insert(id, binding);
+            }
+        }
+    }
+
+    /// Draw the camera's skybox if it has one, replacing the bound pipeline
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        id: Option<CameraId>,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        let (Some(binding), Some(pipeline)) = (
+            self.bindings.get(&id),
+            self.pipelines.get(&(format, sample_count)),
+        ) else {
+            return;
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &binding.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_binding(
+        &self,
+        device: &wgpu::Device,
+        texture_id: TextureId,
+        texture: &Texture,
+        uniforms: SkyboxUniforms,
+    ) -> SkyboxBinding {
+        use wgpu::util::DeviceExt;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skybox Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skybox_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        });
+        SkyboxBinding {
+            texture: texture_id,
+            buffer,
+            bind_group,
+        }
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox Pipeline"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader_module,
+                entry_point: None,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Drawn first without writing depth, so the scene always draws over it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use slotmap::SecondaryMap;
+
+use crate::prefab::PrefabId;
+use crate::scene::SceneEntity;
+use crate::transform::Transform;
+use crate::transform_hierarchy::TransformId;
+
+// Snapshots are intended as the building block for undo / redo in editor tooling
+// they capture the hierarchy and the entities attached to it, but not resources
+// or prefab definitions, which are assumed to outlive any individual edit
+
+/// Captured state of a single hierarchy node and its entity, if it has one
+#[derive(Debug, Clone)]
+pub struct SnapshotEntry {
+    pub transform: Transform,
+    pub parent: Option<TransformId>,
+    pub entity: Option<SceneEntity>,
+    pub prefab: Option<PrefabId>,
+    /// See `Scene::set_name`
+    pub name: Option<String>,
+    /// See `Scene::add_tag`
+    pub tags: Vec<String>,
+}
+
+/// Point in time capture of a scene's hierarchy and entities
+#[derive(Debug, Clone, Default)]
+pub struct SceneSnapshot {
+    pub(crate) entries: SecondaryMap<TransformId, SnapshotEntry>,
+}
+
+impl SceneSnapshot {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: TransformId) -> Option<&SnapshotEntry> {
+        self.entries.get(id)
+    }
+
+    /// Calculates the changes required to go from this snapshot to the target snapshot
+    pub fn diff(&self, target: &SceneSnapshot) -> SceneDiff {
+        let mut diff = SceneDiff::default();
+        for (id, entry) in target.entries.iter() {
+            match self.entries.get(id) {
+                Some(current) => {
+                    if current.transform != entry.transform || current.parent != entry.parent {
+                        diff.transformed.push((id, entry.transform, entry.parent));
+                    }
+                }
+                None => diff.added.push((id, entry.clone())),
+            }
+        }
+        for id in self.entries.keys() {
+            if !target.entries.contains_key(id) {
+                diff.removed.push(id);
+            }
+        }
+        diff
+    }
+}
+
+/// Compact set of changes between two scene states
+///
+/// Ids in `added` refer to the ids at the time the snapshot was taken, slotmap keys can not
+/// be reused once removed, so re-added entities are given new ids which are recorded in `remapped`
+#[derive(Debug, Clone, Default)]
+pub struct SceneDiff {
+    pub added: Vec<(TransformId, SnapshotEntry)>,
+    pub removed: Vec<TransformId>,
+    pub transformed: Vec<(TransformId, Transform, Option<TransformId>)>,
+    pub remapped: HashMap<TransformId, TransformId>,
+}
+
+impl SceneDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.transformed.is_empty()
+    }
+
+    /// Resolve an id from a snapshot to its current id in the scene
+    pub fn resolve(&self, id: TransformId) -> TransformId {
+        self.remapped.get(&id).copied().unwrap_or(id)
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+use crate::draw_report::BindCounts;
+
+/// GPU time between two points of a frame, e.g. drawing the scene's draw commands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassTime {
+    pub label: &'static str,
+    /// Duration in seconds
+    pub time: f32,
+}
+
+/// Timings and counts of the last rendered frame, for perf overlays, see `State::stats`
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Real time in seconds since the previous frame
+    pub frame_time: f32,
+    /// CPU time in seconds spent in `State::render`, including waiting for the surface
+    pub render_time: f32,
+    /// Entities drawn after culling
+    pub entities: usize,
+    /// Draw calls for the frame's draw commands
+    pub draw_calls: usize,
+    pub binds: BindCounts,
+    /// GPU time of each pass, empty if the device has no timestamp queries
+    ///
+    /// Results are read back asynchronously, so are a few frames old.
+    pub gpu_passes: Vec<PassTime>,
+}
+
+impl Stats {
+    /// Total GPU time in seconds of the passes
+    pub fn gpu_time(&self) -> f32 {
+        self.gpu_passes.iter().map(|pass| pass.time).sum()
+    }
+}
+
+/// Writes timestamps between passes and reads them back once the GPU has finished with them
+///
+/// Timestamps aren't written while a previous frame's results are being read back.
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    /// Label of the pass ending at each timestamp after the first
+    labels: Vec<&'static str>,
+    /// Labels of the timestamps being read back
+    pending: Option<Vec<&'static str>>,
+    /// Outcome of mapping the readback buffer, see `MAP_PENDING`
+    map_state: Arc<AtomicU8>,
+}
+
+impl GpuTimer {
+    const MAX_TIMESTAMPS: u32 = 8;
+    const MAP_PENDING: u8 = 0;
+    const MAP_SUCCEEDED: u8 = 1;
+    const MAP_FAILED: u8 = 2;
+    pub(crate) const FEATURES: wgpu::Features =
+        wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+    /// None if the device doesn't support timestamps between passes
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(Self::FEATURES) {
+            return None;
+        }
+        let size = (Self::MAX_TIMESTAMPS as usize * std::mem::size_of::<u64>()) as u64;
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Stats Timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: Self::MAX_TIMESTAMPS,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Stats Timestamp Resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Stats Timestamp Readback"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            labels: Vec::new(),
+            pending: None,
+            map_state: Arc::new(AtomicU8::new(Self::MAP_PENDING)),
+        })
+    }
+
+    /// Write the timestamp the frame's first pass is timed from
+    pub(crate) fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.pending.is_none() {
+            self.labels.clear();
+            self.mark(encoder, "");
+        }
+    }
+
+    /// Write a timestamp ending the pass with the label
+    pub(crate) fn mark(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        let index = self.labels.len() as u32;
+        if self.pending.is_none() && index < Self::MAX_TIMESTAMPS {
+            encoder.write_timestamp(&self.query_set, index);
+            self.labels.push(label);
+        }
+    }
+
+    /// Copy the frame's timestamps to the readback buffer
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let count = self.labels.len() as u32;
+        if self.pending.is_some() || count < 2 {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, size);
+    }
+
+    /// Start reading back the timestamps once the frame has been submitted
+    pub(crate) fn submitted(&mut self) {
+        if self.pending.is_some() || self.labels.len() < 2 {
+            return;
+        }
+        let map_state = self.map_state.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let state = match result {
+                    Ok(()) => Self::MAP_SUCCEEDED,
+                    Err(_) => Self::MAP_FAILED,
+                };
+                map_state.store(state, Ordering::Release);
+            });
+        self.pending = Some(std::mem::take(&mut self.labels));
+    }
+
+    /// Time of each pass once a frame's timestamps have been read back
+    pub(crate) fn collect(&mut self, device: &wgpu::Device) -> Option<Vec<PassTime>> {
+        self.pending.as_ref()?;
+        device.poll(wgpu::Maintain::Poll);
+        match self.map_state.swap(Self::MAP_PENDING, Ordering::Acquire) {
+            Self::MAP_PENDING => return None,
+            Self::MAP_FAILED => {
+                // Try again with the next frame's timestamps
+                self.pending = None;
+                return None;
+            }
+            _ => {}
+        }
+        let labels = self.pending.take()?;
+        let times = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            timestamps[..labels.len()]
+                .windows(2)
+                .zip(labels.iter().skip(1))
+                .map(|(pair, label)| PassTime {
+                    label,
+                    time: pair[1].saturating_sub(pair[0]) as f32 * self.period / 1e9,
+                })
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        Some(times)
+    }
+}
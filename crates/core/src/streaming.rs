@@ -0,0 +1,261 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use glam::*;
+
+use crate::{
+    material::MaterialId, mesh::MeshId, scene::Scene, tasks::TaskHandle, texture::TextureId,
+    transform_hierarchy::TransformId, State,
+};
+
+/// Future loading a chunk's data, run on a background task
+#[cfg(not(target_arch = "wasm32"))]
+pub type ChunkFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+/// Future loading a chunk's data, run on a background task
+#[cfg(target_arch = "wasm32")]
+pub type ChunkFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// Position of a chunk in the grid on the xz plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkCoord {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Chunk containing the position
+    pub fn from_position(position: Vec3, chunk_size: f32) -> Self {
+        Self {
+            x: (position.x / chunk_size).floor() as i32,
+            z: (position.z / chunk_size).floor() as i32,
+        }
+    }
+
+    /// World position of the chunk's minimum corner
+    pub fn origin(&self, chunk_size: f32) -> Vec3 {
+        Vec3::new(self.x as f32, 0.0, self.z as f32) * chunk_size
+    }
+
+    /// Number of chunks between this and the other chunk, counting diagonals as one
+    pub fn distance(&self, other: ChunkCoord) -> u32 {
+        self.x.abs_diff(other.x).max(self.z.abs_diff(other.z))
+    }
+}
+
+/// Everything created for a chunk, removed when the chunk unloads
+#[derive(Debug, Clone, Default)]
+pub struct ChunkContents {
+    /// Removed from the scene along with their descendants
+    pub entities: Vec<TransformId>,
+    pub materials: Vec<MaterialId>,
+    pub meshes: Vec<MeshId>,
+    pub textures: Vec<TextureId>,
+}
+
+/// Game specific loading of chunks, see `ChunkStreamer`
+pub trait ChunkSource {
+    type Data: Send + 'static;
+
+    /// Load the chunk's data in the background, e.g. reading a file or generating terrain,
+    /// this must not touch the GPU, which only happens in `instantiate`
+    fn load(&mut self, coord: ChunkCoord) -> ChunkFuture<Self::Data>;
+
+    /// Create the chunk's resources and entities once its data has loaded, returning
+    /// everything created so it can be removed when the chunk unloads
+    fn instantiate(
+        &mut self,
+        coord: ChunkCoord,
+        data: Self::Data,
+        scene: &mut Scene,
+        state: &mut State,
+    ) -> ChunkContents;
+
+    /// Called before the chunk's contents are removed, e.g. to save changes made to it
+    fn unloading(
+        &mut self,
+        _coord: ChunkCoord,
+        _contents: &ChunkContents,
+        _scene: &mut Scene,
+        _state: &mut State,
+    ) {
+    }
+}
+
+enum Chunk<T> {
+    Loading(TaskHandle<T>),
+    Loaded(ChunkContents),
+    /// The load task ended without data, e.g. it panicked, not retried until unloaded
+    Failed,
+}
+
+/// Loads and unloads chunks of a large world around a focus point, e.g. the player
+///
+/// Chunks within the load radius of the focus are loaded using `State::tasks`, nearest
+/// first, and instantiated on the main thread when their data arrives. Chunks further than
+/// the unload radius are removed, the gap between the radii stops chunks on the boundary
+/// loading and unloading repeatedly. Call `update` each frame from `Game::update`.
+///
+/// A chunk whose load fails, e.g. as the future panicked, frees its pending slot and isn't
+/// loaded again until it has gone beyond the unload radius, see `is_failed`.
+pub struct ChunkStreamer<S: ChunkSource> {
+    pub source: S,
+    chunk_size: f32,
+    load_radius: u32,
+    unload_radius: u32,
+    /// Most chunks loading at once, limits the background work queued by fast movement
+    pub max_pending: usize,
+    chunks: HashMap<ChunkCoord, Chunk<S::Data>>,
+}
+
+impl<S: ChunkSource> ChunkStreamer<S> {
+    /// Radii are in chunks, the unload radius is at least one more than the load radius
+    pub fn new(source: S, chunk_size: f32, load_radius: u32, unload_radius: u32) -> Self {
+        Self {
+            source,
+            chunk_size,
+            load_radius,
+            unload_radius: unload_radius.max(load_radius + 1),
+            max_pending: 4,
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self
+    }
+
+    pub fn chunk_size(&self) -> f32 {
+        self.chunk_size
+    }
+
+    pub fn chunk_at(&self, position: Vec3) -> ChunkCoord {
+        ChunkCoord::from_position(position, self.chunk_size)
+    }
+
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        matches!(self.chunks.get(&coord), Some(Chunk::Loaded(_)))
+    }
+
+    /// Whether the chunk's load failed, see `ChunkStreamer`
+    pub fn is_failed(&self, coord: ChunkCoord) -> bool {
+        matches!(self.chunks.get(&coord), Some(Chunk::Failed))
+    }
+
+    pub fn contents(&self, coord: ChunkCoord) -> Option<&ChunkContents> {
+        match self.chunks.get(&coord) {
+            Some(Chunk::Loaded(contents)) => Some(contents),
+            _ => None,
+        }
+    }
+
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = ChunkCoord> + '_ {
+        self.chunks.iter().filter_map(|(coord, chunk)| match chunk {
+            Chunk::Loaded(_) => Some(*coord),
+            Chunk::Loading(_) | Chunk::Failed => None,
+        })
+    }
+
+    /// Number of chunks whose data is still loading
+    pub fn pending_count(&self) -> usize {
+        self.chunks
+            .values()
+            .filter(|chunk| matches!(chunk, Chunk::Loading(_)))
+            .count()
+    }
+
+    /// Instantiate chunks which have finished loading, unload distant chunks and start
+    /// loading nearby ones
+    pub fn update(&mut self, focus: Vec3, scene: &mut Scene, state: &mut State) {
+        let center = self.chunk_at(focus);
+
+        let finished = self
+            .chunks
+            .iter()
+            .filter_map(|(coord, chunk)| match chunk {
+                Chunk::Loading(handle) if state.tasks.is_finished(handle) => {
+                    Some((*coord, *handle))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (coord, handle) in finished {
+            match state.tasks.take(&handle) {
+                Some(data) => {
+                    let contents = self.source.instantiate(coord, data, scene, state);
+                    self.chunks.insert(coord, Chunk::Loaded(contents));
+                }
+                None => {
+                    log::warn!("Chunk {:?} failed to load", coord);
+                    self.chunks.insert(coord, Chunk::Failed);
+                }
+            }
+        }
+
+        let distant = self
+            .chunks
+            .keys()
+            .filter(|coord| coord.distance(center) > self.unload_radius)
+            .copied()
+            .collect::<Vec<_>>();
+        for coord in distant {
+            self.unload(coord, scene, state);
+        }
+
+        let available = self.max_pending.saturating_sub(self.pending_count());
+        if available == 0 {
+            return;
+        }
+        let radius = self.load_radius as i32;
+        let mut missing = (-radius..=radius)
+            .flat_map(|x| (-radius..=radius).map(move |z| (x, z)))
+            .map(|(x, z)| ChunkCoord::new(center.x + x, center.z + z))
+            .filter(|coord| !self.chunks.contains_key(coord))
+            .collect::<Vec<_>>();
+        missing.sort_by_key(|coord| {
+            let (x, z) = (coord.x - center.x, coord.z - center.z);
+            x * x + z * z
+        });
+        for coord in missing.into_iter().take(available) {
+            let handle = state.tasks.spawn(self.source.load(coord));
+            self.chunks.insert(coord, Chunk::Loading(handle));
+        }
+    }
+
+    /// Remove the chunk's contents, or abandon its load
+    pub fn unload(&mut self, coord: ChunkCoord, scene: &mut Scene, state: &mut State) {
+        match self.chunks.remove(&coord) {
+            Some(Chunk::Loading(handle)) => state.tasks.forget(handle),
+            Some(Chunk::Loaded(contents)) => {
+                self.source.unloading(coord, &contents, scene, state);
+                Self::remove_contents(contents, scene, state);
+            }
+            Some(Chunk::Failed) | None => {}
+        }
+    }
+
+    /// Unload every chunk, e.g. when leaving the world
+    pub fn clear(&mut self, scene: &mut Scene, state: &mut State) {
+        let coords = self.chunks.keys().copied().collect::<Vec<_>>();
+        for coord in coords {
+            self.unload(coord, scene, state);
+        }
+    }
+
+    fn remove_contents(contents: ChunkContents, scene: &mut Scene, state: &mut State) {
+        scene.remove_batch(contents.entities);
+        let resources = &mut state.resources;
+        for id in contents.materials {
+            resources.materials.remove(id);
+        }
+        for id in contents.meshes {
+            resources.meshes.remove(id);
+        }
+        for id in contents.textures {
+            resources.textures.remove(id);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use winit::window::Window;
+
+/// Progress shown on the app's taskbar button or dock icon, with the states Windows'
+/// taskbar distinguishes, progress is from 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TaskbarProgress {
+    #[default]
+    None,
+    /// Busy for an unknown duration
+    Indeterminate,
+    Normal(f32),
+    Paused(f32),
+    Error(f32),
+}
+
+impl TaskbarProgress {
+    /// Fraction complete, None if not shown or indeterminate
+    pub fn fraction(&self) -> Option<f32> {
+        match self {
+            TaskbarProgress::None | TaskbarProgress::Indeterminate => None,
+            TaskbarProgress::Normal(progress)
+            | TaskbarProgress::Paused(progress)
+            | TaskbarProgress::Error(progress) => Some(progress.clamp(0.0, 1.0)),
+        }
+    }
+}
+
+/// Shows taskbar progress and badges for a platform, see `Taskbar::set_backend`
+///
+/// Calls are made from the main thread at the end of the frame, only when the progress or
+/// badge has changed.
+pub trait TaskbarBackend {
+    fn set_progress(&mut self, window: &Window, progress: TaskbarProgress);
+    /// Short text such as a count on the dock icon, None to remove it
+    fn set_badge(&mut self, window: &Window, badge: Option<&str>);
+}
+
+/// Appends progress and the badge to the window title, for platforms without a native backend
+#[derive(Debug, Default)]
+pub struct TitleTaskbar {
+    /// Title before any progress was shown, captured on first use
+    title: Option<String>,
+    progress: TaskbarProgress,
+    badge: Option<String>,
+}
+
+impl TitleTaskbar {
+    fn apply(&mut self, window: &Window) {
+        let title = self.title.get_or_insert_with(|| window.title());
+        let mut decorated = title.clone();
+        match self.progress {
+            TaskbarProgress::None => {}
+            TaskbarProgress::Indeterminate => decorated.push_str(" (working)"),
+            progress => {
+                let percent = (progress.fraction().unwrap_or(0.0) * 100.0).round();
+                let state = match progress {
+                    TaskbarProgress::Paused(_) => " paused",
+                    TaskbarProgress::Error(_) => " failed",
+                    _ => "",
+                };
+                decorated.push_str(&format!(" ({}%{})", percent, state));
+            }
+        }
+        if let Some(badge) = &self.badge {
+            decorated.push_str(&format!(" [{}]", badge));
+        }
+        window.set_title(&decorated);
+    }
+}
+
+impl TaskbarBackend for TitleTaskbar {
+    fn set_progress(&mut self, window: &Window, progress: TaskbarProgress) {
+        self.progress = progress;
+        self.apply(window);
+    }
+
+    fn set_badge(&mut self, window: &Window, badge: Option<&str>) {
+        self.badge = badge.map(str::to_string);
+        self.apply(window);
+    }
+}
+
+/// Progress and badge on the app's taskbar button or dock icon, e.g. for long asset bakes in
+/// tools, changes are sent to the backend at the end of the frame
+///
+/// winit has no taskbar API, so there is no backend by default and changes are only
+/// recorded, check `is_supported`. Provide a platform backend, such as one using Windows'
+/// `ITaskbarList3` or macOS' `NSDockTile`, or `TitleTaskbar`, with `set_backend`.
+#[derive(Default)]
+pub struct Taskbar {
+    progress: TaskbarProgress,
+    badge: Option<String>,
+    progress_changed: bool,
+    badge_changed: bool,
+    backend: Option<Box<dyn TaskbarBackend>>,
+}
+
+impl Taskbar {
+    pub fn set_progress(&mut self, progress: TaskbarProgress) {
+        self.progress_changed |= progress != self.progress;
+        self.progress = progress;
+    }
+
+    pub fn clear_progress(&mut self) {
+        self.set_progress(TaskbarProgress::None);
+    }
+
+    pub fn progress(&self) -> TaskbarProgress {
+        self.progress
+    }
+
+    pub fn set_badge<S: Into<String>>(&mut self, badge: Option<S>) {
+        let badge = badge.map(Into::into);
+        self.badge_changed |= badge != self.badge;
+        self.badge = badge;
+    }
+
+    pub fn badge(&self) -> Option<&str> {
+        self.badge.as_deref()
+    }
+
+    /// Replace the backend, the current progress and badge are sent to it next frame
+    pub fn set_backend(&mut self, backend: Option<Box<dyn TaskbarBackend>>) {
+        self.backend = backend;
+        self.progress_changed = true;
+        self.badge_changed = true;
+    }
+
+    /// Whether a backend is set to show progress and badges
+    pub fn is_supported(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    pub(crate) fn flush(&mut self, window: &Window) {
+        let Some(backend) = &mut self.backend else {
+            return;
+        };
+        if std::mem::take(&mut self.progress_changed) {
+            backend.set_progress(window, self.progress);
+        }
+        if std::mem::take(&mut self.badge_changed) {
+            backend.set_badge(window, self.badge.as_deref());
+        }
+    }
+}
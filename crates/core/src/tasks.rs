@@ -0,0 +1,251 @@
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    future::Future,
+    marker::PhantomData,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+// On native futures are run to completion on a small pool of worker threads, on the web they
+// are spawned as micro-tasks on the main thread. Either way results are sent back over a
+// channel and received when the engine polls at the start of each frame. Tasks which end
+// without a result, e.g. by panicking, send None so they don't stay pending forever.
+
+#[cfg(not(target_arch = "wasm32"))]
+type TaskOutput = Box<dyn Any + Send>;
+#[cfg(target_arch = "wasm32")]
+type TaskOutput = Box<dyn Any>;
+type TaskMessage = (u64, Option<TaskOutput>);
+
+/// Identifies a spawned task and the type of its result
+#[derive(Debug)]
+pub struct TaskHandle<T> {
+    id: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TaskHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TaskHandle<T> {}
+
+pub struct Tasks {
+    next_id: u64,
+    pending: HashSet<u64>,
+    completed: HashMap<u64, TaskOutput>,
+    /// Tasks which ended without a result, until taken or forgotten
+    failed: HashSet<u64>,
+    sender: Sender<TaskMessage>,
+    receiver: Receiver<TaskMessage>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pool: Option<native::ThreadPool>,
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            next_id: 0,
+            pending: HashSet::new(),
+            completed: HashMap::new(),
+            failed: HashSet::new(),
+            sender,
+            receiver,
+            #[cfg(not(target_arch = "wasm32"))]
+            pool: None,
+        }
+    }
+
+    /// Run the future in the background, take the result with `take` once `is_finished`
+    ///
+    /// Futures which wait on IO occupy a worker thread on native, so keep the number of
+    /// those in flight small.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn<F>(&mut self, future: F) -> TaskHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let completion = self.start();
+        let id = completion.id;
+        self.pool
+            .get_or_insert_with(native::ThreadPool::new)
+            .execute(Box::new(move || {
+                let output = pollster::block_on(future);
+                completion.send(Box::new(output));
+            }));
+        Self::handle(id)
+    }
+
+    /// Run the future in the background, take the result with `take` once `is_finished`
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn<F>(&mut self, future: F) -> TaskHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let completion = self.start();
+        let id = completion.id;
+        wasm_bindgen_futures::spawn_local(async move {
+            let output = future.await;
+            completion.send(Box::new(output));
+        });
+        Self::handle(id)
+    }
+
+    /// Receive the results of any tasks which have completed
+    pub fn poll(&mut self) {
+        while let Ok((id, output)) = self.receiver.try_recv() {
+            // Results of tasks which were forgotten are dropped
+            if self.pending.remove(&id) {
+                match output {
+                    Some(output) => {
+                        self.completed.insert(id, output);
+                    }
+                    None => {
+                        self.failed.insert(id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// True once the task has completed, or failed without a result, see `is_failed`
+    pub fn is_finished<T>(&self, handle: &TaskHandle<T>) -> bool {
+        self.completed.contains_key(&handle.id) || self.failed.contains(&handle.id)
+    }
+
+    /// Whether the task ended without a result, e.g. its future panicked, `take` returns None
+    pub fn is_failed<T>(&self, handle: &TaskHandle<T>) -> bool {
+        self.failed.contains(&handle.id)
+    }
+
+    /// True until the result has been taken or the task forgotten
+    pub fn is_pending<T>(&self, handle: &TaskHandle<T>) -> bool {
+        self.pending.contains(&handle.id) || self.is_finished(handle)
+    }
+
+    /// Take the result of a finished task, None if it's still running, already taken or
+    /// failed, which also clears the failure
+    pub fn take<T: 'static>(&mut self, handle: &TaskHandle<T>) -> Option<T> {
+        self.failed.remove(&handle.id);
+        let output = self.completed.remove(&handle.id)?;
+        // The handle's type parameter matches the spawned future's output
+        output.downcast::<T>().ok().map(|output| *output)
+    }
+
+    /// Discard the result of the task when it completes, the task itself still runs to completion
+    pub fn forget<T>(&mut self, handle: TaskHandle<T>) {
+        self.pending.remove(&handle.id);
+        self.completed.remove(&handle.id);
+        self.failed.remove(&handle.id);
+    }
+
+    /// Number of tasks running or awaiting `take`
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.completed.len() + self.failed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn start(&mut self) -> Completion {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id);
+        Completion {
+            id,
+            sender: Some(self.sender.clone()),
+        }
+    }
+
+    fn handle<T>(id: u64) -> TaskHandle<T> {
+        TaskHandle {
+            id,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Sends a task's result, or None if dropped without one, e.g. when its future panics
+struct Completion {
+    id: u64,
+    sender: Option<Sender<TaskMessage>>,
+}
+
+impl Completion {
+    fn send(mut self, output: TaskOutput) {
+        if let Some(sender) = self.sender.take() {
+            sender.send((self.id, Some(output))).ok();
+        }
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            sender.send((self.id, None)).ok();
+        }
+    }
+}
+
+impl Default for Tasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::sync::{
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    };
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    // Worker threads are started on first use and exit when the pool is dropped
+    pub struct ThreadPool {
+        sender: Sender<Job>,
+    }
+
+    impl ThreadPool {
+        const MAX_THREADS: usize = 4;
+
+        pub fn new() -> Self {
+            let (sender, receiver) = channel::<Job>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            let threads = std::thread::available_parallelism()
+                .map_or(1, |count| count.get().saturating_sub(1))
+                .clamp(1, Self::MAX_THREADS);
+            for index in 0..threads {
+                let receiver = receiver.clone();
+                std::thread::Builder::new()
+                    .name(format!("helia-task-{index}"))
+                    .spawn(move || loop {
+                        let job = match receiver.lock() {
+                            Ok(receiver) => receiver.recv(),
+                            Err(_) => return,
+                        };
+                        match job {
+                            // A panicking task reports it failed, and the worker carries on
+                            Ok(job) => {
+                                std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)).ok();
+                            }
+                            Err(_) => return,
+                        }
+                    })
+                    .expect("Failed to spawn task thread");
+            }
+            Self { sender }
+        }
+
+        pub fn execute(&self, job: Job) {
+            self.sender.send(job).ok();
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use anyhow::*;
 use image::GenericImageView;
 
+use crate::{
+    distance_field::{self, DistanceFieldSettings},
+    ktx2::Ktx2,
+    mipmaps, skybox,
+};
+
 slotmap::new_key_type! { pub struct TextureId; }
 
 pub struct Texture {
@@ -15,11 +21,448 @@ impl Texture {
         Self::from_image(device, queue, &img, None)
     }
 
+    /// Label is used to name the texture in GPU captures and validation errors
+    pub fn from_bytes_with_label(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_image(device, queue, &img, Some(label))
+    }
+
+    /// Without generating mipmaps, e.g. for atlases whose cells would bleed into each other
+    /// in smaller levels, or textures only drawn at or above their size
+    pub fn from_bytes_without_mipmaps(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_image_without_mipmaps(device, queue, &img, label)
+    }
+
+    /// Replace the sampler with one using the address mode, e.g. `Repeat` for tiling textures
+    /// Must be called before creating materials using the texture
+    pub fn with_address_mode(mut self, device: &wgpu::Device, address_mode: wgpu::AddressMode) -> Self {
+        let mipmapped = self.texture.mip_level_count() > 1;
+        self.sampler = Self::create_sampler(device, address_mode, wgpu::FilterMode::Nearest, mipmapped);
+        self
+    }
+
+    /// Trilinear filtered when minified if the texture has mipmaps
+    fn create_sampler(
+        device: &wgpu::Device,
+        address_mode: wgpu::AddressMode,
+        filter: wgpu::FilterMode,
+        mipmapped: bool,
+    ) -> wgpu::Sampler {
+        let min_filter = if mipmapped { wgpu::FilterMode::Linear } else { filter };
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: filter,
+            min_filter,
+            mipmap_filter: min_filter,
+            ..Default::default()
+        })
+    }
+
+    /// Create a 1x1 texture of a single color, useful for flat colored meshes which are tinted by entity color
+    pub fn from_color(device: &wgpu::Device, queue: &wgpu::Queue, color: [u8; 4]) -> Result<Self> {
+        let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+        Self::from_image(device, queue, &img, None)
+    }
+
+    /// Load a signed distance field atlas, e.g. from an external generator, single channel
+    /// fields are read from the color channels, multi-channel fields from red, green and blue
+    pub fn from_distance_field_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_distance_field_image(device, queue, &img, None)
+    }
+
+    /// Generate a signed distance field atlas from a bitmap atlas, such as a font, for text
+    /// which stays sharp when scaled, see `distance_field::generate`
+    pub fn generate_distance_field(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        settings: &DistanceFieldSettings,
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        let field = distance_field::generate(&img.to_rgba8(), settings)?;
+        Self::from_distance_field_image(device, queue, &image::DynamicImage::ImageRgba8(field), None)
+    }
+
+    /// Distances are stored linearly and filtered, interpolating between texels is what
+    /// keeps edges smooth when magnified
+    pub fn from_distance_field_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_image_with_format(
+            device,
+            queue,
+            img,
+            label,
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::FilterMode::Linear,
+            false,
+        )
+    }
+
+    /// Load a cubemap for `Camera::skybox` from six square images of the same size, in the
+    /// order right, left, top, bottom, front and back, the front being ahead of the default
+    /// camera looking down -z
+    pub fn cubemap_from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: [&[u8]; 6],
+    ) -> Result<Self> {
+        let faces = faces
+            .iter()
+            .map(|bytes| Ok(image::load_from_memory(bytes)?.to_rgba8()))
+            .collect::<Result<Vec<_>>>()?;
+        Self::cubemap_from_images(device, queue, &faces, None)
+    }
+
+    /// Load a cubemap for `Camera::skybox` from an equirectangular panorama, resampled to
+    /// faces of the size in pixels, see `skybox::faces_from_equirect`
+    pub fn cubemap_from_equirect_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        face_size: u32,
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?.to_rgba8();
+        let faces = skybox::faces_from_equirect(&img, face_size.max(1));
+        Self::cubemap_from_images(device, queue, &faces, None)
+    }
+
+    /// Faces are in the order of `cubemap_from_bytes`, the texture is sampled with linear
+    /// filtering so can't be used by materials
+    pub fn cubemap_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::RgbaImage],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        if faces.len() != 6 {
+            bail!("A cubemap needs 6 faces, {} were provided", faces.len());
+        }
+        let face_size = faces[0].width();
+        if face_size == 0
+            || faces
+                .iter()
+                .any(|face| face.width() != face_size || face.height() != face_size)
+        {
+            bail!("Cubemap faces must be square and of the same size");
+        }
+
+        let size = wgpu::Extent3d {
+            width: face_size,
+            height: face_size,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for (layer, face) in faces.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                },
+                face,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * face_size),
+                    rows_per_image: Some(face_size),
+                },
+                wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..size
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// Load a 2D texture array from images of the same size, one layer per image in order,
+    /// for use with `BuildInShaders::sprite_array`
+    pub fn array_from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[&[u8]],
+    ) -> Result<Self> {
+        let layers = layers
+            .iter()
+            .map(|bytes| Ok(image::load_from_memory(bytes)?.to_rgba8()))
+            .collect::<Result<Vec<_>>>()?;
+        Self::array_from_images(device, queue, &layers, None)
+    }
+
+    /// Layers are nearest filtered without mipmaps, select one per entity with
+    /// `RenderPropertiesBuilder::with_texture_layer`
+    pub fn array_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[image::RgbaImage],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let Some(first) = layers.first() else {
+            bail!("A texture array needs at least one layer");
+        };
+        let (width, height) = first.dimensions();
+        if width == 0
+            || height == 0
+            || layers
+                .iter()
+                .any(|layer| layer.dimensions() != (width, height))
+        {
+            bail!("Texture array layers must be of the same non-zero size");
+        }
+        let max_layers = device.limits().max_texture_array_layers;
+        if layers.len() > max_layers as usize {
+            bail!(
+                "A texture array can have at most {} layers, {} were provided",
+                max_layers,
+                layers.len()
+            );
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers.len() as u32,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for (index, layer) in layers.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: index as u32,
+                    },
+                },
+                layer,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..size
+                },
+            );
+        }
+
+        // Explicit, as a single layer array would otherwise get a D2 view
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let sampler = Self::create_sampler(
+            device,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::FilterMode::Nearest,
+            false,
+        );
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// Load a KTX2 texture, keeping BC, ETC2 or ASTC data compressed in VRAM when the device
+    /// supports it, otherwise decompressing BC1 to BC5 to RGBA8, e.g. on WebGL2 without the
+    /// S3TC extension, see `Ktx2`
+    pub fn from_ktx2_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let ktx2 = Ktx2::parse(bytes)?;
+        let format = ktx2.format();
+        if !device.features().contains(ktx2.required_features()) {
+            let format = if format.is_srgb() {
+                wgpu::TextureFormat::Rgba8UnormSrgb
+            } else {
+                wgpu::TextureFormat::Rgba8Unorm
+            };
+            let img = image::DynamicImage::ImageRgba8(ktx2.decompress()?);
+            return Self::from_image_with_format(
+                device,
+                queue,
+                &img,
+                label,
+                format,
+                wgpu::FilterMode::Nearest,
+                true,
+            );
+        }
+
+        let size = wgpu::Extent3d {
+            width: ktx2.width(),
+            height: ktx2.height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: ktx2.level_count(),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format.block_copy_size(None).unwrap_or(4);
+        for level in 0..ktx2.level_count() {
+            // Levels smaller than a block are still stored as whole blocks
+            let level_size = size
+                .mip_level_size(level, wgpu::TextureDimension::D2)
+                .physical_size(format);
+            let bytes_per_row = level_size.width / block_width * block_size;
+            let rows = level_size.height / block_height;
+            let data = ktx2.level(level).unwrap_or_default();
+            if data.len() < (bytes_per_row * rows) as usize {
+                bail!("KTX2 level {} is truncated", level);
+            }
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(rows),
+                },
+                level_size,
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = Self::create_sampler(
+            device,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::FilterMode::Nearest,
+            ktx2.level_count() > 1,
+        );
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// Generates a full mip chain on the GPU, see `from_image_without_mipmaps` to opt out
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_image_with_format(
+            device,
+            queue,
+            img,
+            label,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::FilterMode::Nearest,
+            true,
+        )
+    }
+
+    pub fn from_image_without_mipmaps(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_image_with_format(
+            device,
+            queue,
+            img,
+            label,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::FilterMode::Nearest,
+            false,
+        )
+    }
+
+    fn from_image_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        format: wgpu::TextureFormat,
+        filter: wgpu::FilterMode,
+        mipmaps: bool,
     ) -> Result<Self> {
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
@@ -29,14 +472,21 @@ impl Texture {
             height: dimensions.1,
             depth_or_array_layers: 1,
         };
+        let (mip_level_count, mut usage) = if mipmaps {
+            let count = mipmaps::mip_level_count(dimensions.0, dimensions.1);
+            (count, wgpu::TextureUsages::RENDER_ATTACHMENT)
+        } else {
+            (1, wgpu::TextureUsages::empty())
+        };
+        usage |= wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage,
             view_formats: &[],
         });
 
@@ -56,16 +506,11 @@ impl Texture {
             size,
         );
 
+        mipmaps::generate(device, queue, &texture);
+
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler =
+            Self::create_sampler(device, wgpu::AddressMode::ClampToEdge, filter, mip_level_count > 1);
 
         Ok(Self {
             texture,
@@ -80,10 +525,30 @@ impl Texture {
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         label: &str,
+    ) -> Self {
+        Self::create_depth_texture_with_size(device, config.width, config.height, label)
+    }
+
+    pub fn create_depth_texture_with_size(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        label: &str,
+    ) -> Self {
+        Self::create_depth_texture_with_samples(device, width, height, 1, label)
+    }
+
+    /// Depth texture to pair with a multisampled color target
+    pub fn create_depth_texture_with_samples(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        label: &str,
     ) -> Self {
         let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
 
@@ -91,7 +556,7 @@ impl Texture {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -122,4 +587,57 @@ impl Texture {
             sampler,
         }
     }
+
+    /// Color texture which can be rendered to and then sampled, e.g. for upscaling or post processing
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        Self::create_render_target_with_samples(device, width, height, format, 1, label)
+    }
+
+    /// Color target with multiple samples per pixel, for rendering with MSAA and resolving into
+    /// a single sampled texture
+    pub fn create_render_target_with_samples(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
 }
@@ -1,5 +1,29 @@
 use instant::Instant;
 
+/// How `Time::update` handles long frames, e.g. after a stall, a breakpoint or the window
+/// being dragged, so simulations aren't given one huge step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CatchUpPolicy {
+    /// Give the whole frame time however long
+    Unbounded,
+    /// Limit the frame time in seconds, the rest is dropped so game time runs slower than
+    /// real time while frames are long
+    Clamp { max_frame_time: f32 },
+    /// Limit the frame time in seconds, carrying the rest over to following frames so game
+    /// time catches up with real time in steps of at most this length
+    CatchUp { max_frame_time: f32 },
+    /// Limit the fixed ticks completed in a frame, the time of any more is dropped
+    FixedSteps { max_steps: u32 },
+}
+
+impl Default for CatchUpPolicy {
+    fn default() -> Self {
+        Self::Clamp {
+            max_frame_time: 0.25,
+        }
+    }
+}
+
 pub struct Time {
     /// total elapsed game time since startup
     pub total_elapsed: f32,
@@ -7,13 +31,23 @@ pub struct Time {
     pub elapsed: f32,
     /// speed at which time updates relative to wall clock time
     pub time_scale: f32,
-    /// the maximum considered length of a frame
-    /// game time will run slower if this is exceeded
-    pub max_frame_time_ms: Option<u32>,
+    /// handling of frames too long to simulate in one step, see `CatchUpPolicy`
+    pub catch_up: CatchUpPolicy,
     /// total elapsed time since startup
     pub total_elapsed_real_time: f32,
     /// real time elapsed in last frame
     pub elapsed_real_time: f32,
+    /// length of a fixed tick of game time in seconds, see `ticks_this_frame`
+    pub fixed_time_step: f32,
+    /// frame time used by each update in place of the measured wall clock time,
+    /// for deterministic replays and tests, time scale still applies
+    pub manual_step: Option<f32>,
+    frame: u64,
+    tick: u64,
+    ticks_this_frame: u32,
+    tick_accumulator: f32,
+    /// real time carried over by `CatchUpPolicy::CatchUp`
+    behind: f32,
     last_update_time: Instant,
     real_time_instant: Instant,
 }
@@ -21,28 +55,137 @@ pub struct Time {
 impl Time {
     pub fn update(&mut self) -> f32 {
         let elapsed = self.last_update_time.elapsed();
+        self.last_update_time = Instant::now();
 
         self.elapsed_real_time = elapsed.as_secs_f32();
         self.total_elapsed_real_time = self.real_time_instant.elapsed().as_secs_f32();
 
-        self.elapsed = elapsed.as_secs_f32() * self.time_scale;
-        if let Some(max_ms) = self.max_frame_time_ms {
-            if elapsed.as_millis() > max_ms as u128 {
-                self.elapsed = max_ms as f32 / 1000.0 * self.time_scale
+        let frame_time = match self.manual_step {
+            Some(step) => step,
+            None => self.limit_frame_time(elapsed.as_secs_f32()),
+        };
+        self.advance(frame_time)
+    }
+
+    /// Apply the catch up policy's limit to the measured length of a frame
+    fn limit_frame_time(&mut self, frame_time: f32) -> f32 {
+        match self.catch_up {
+            CatchUpPolicy::Clamp { max_frame_time } => frame_time.min(max_frame_time),
+            CatchUpPolicy::CatchUp { max_frame_time } => {
+                let owed = frame_time + self.behind;
+                let frame_time = owed.min(max_frame_time);
+                self.behind = owed - frame_time;
+                frame_time
             }
+            CatchUpPolicy::Unbounded | CatchUpPolicy::FixedSteps { .. } => frame_time,
+        }
+    }
+
+    /// Advance game time by a frame of the provided length in seconds (before time scale),
+    /// counting the frame and any fixed ticks it completes, up to the limit of
+    /// `CatchUpPolicy::FixedSteps`
+    /// Called by `update`, call directly to drive time externally, e.g. stepping a test
+    pub fn advance(&mut self, frame_time: f32) -> f32 {
+        self.elapsed = frame_time * self.time_scale;
+        self.frame += 1;
+
+        self.ticks_this_frame = 0;
+        if self.fixed_time_step > 0.0 {
+            self.tick_accumulator += self.elapsed;
+            while self.tick_accumulator >= self.fixed_time_step {
+                self.tick_accumulator -= self.fixed_time_step;
+                self.ticks_this_frame += 1;
+            }
+            if let CatchUpPolicy::FixedSteps { max_steps } = self.catch_up {
+                if self.ticks_this_frame > max_steps {
+                    let dropped = self.ticks_this_frame - max_steps;
+                    self.elapsed -= dropped as f32 * self.fixed_time_step;
+                    self.ticks_this_frame = max_steps;
+                }
+            }
+            self.tick += self.ticks_this_frame as u64;
         }
         self.total_elapsed += self.elapsed;
-        self.last_update_time = Instant::now();
 
         self.elapsed
     }
 
+    /// Real time in seconds not yet given to the game by `CatchUpPolicy::CatchUp`
+    pub fn time_behind(&self) -> f32 {
+        self.behind
+    }
+
+    /// Number of updates since startup
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Number of fixed ticks completed since startup
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Number of fixed ticks completed by the last update, run fixed step simulation this
+    /// many times in `Game::update`
+    pub fn ticks_this_frame(&self) -> u32 {
+        self.ticks_this_frame
+    }
+
+    /// Fraction of the next fixed tick elapsed, for interpolating rendered state between ticks
+    pub fn tick_alpha(&self) -> f32 {
+        if self.fixed_time_step > 0.0 {
+            self.tick_accumulator / self.fixed_time_step
+        } else {
+            0.0
+        }
+    }
+
+    /// Capture game time and the frame and tick counters, see `State::checkpoint`
+    pub(crate) fn snapshot(&self) -> TimeSnapshot {
+        TimeSnapshot {
+            total_elapsed: self.total_elapsed,
+            time_scale: self.time_scale,
+            frame: self.frame,
+            tick: self.tick,
+            ticks_this_frame: self.ticks_this_frame,
+            tick_accumulator: self.tick_accumulator,
+            behind: self.behind,
+        }
+    }
+
+    /// Return game time and the counters to a snapshot, real time is unaffected
+    pub(crate) fn restore(&mut self, snapshot: &TimeSnapshot) {
+        self.total_elapsed = snapshot.total_elapsed;
+        self.time_scale = snapshot.time_scale;
+        self.frame = snapshot.frame;
+        self.tick = snapshot.tick;
+        self.ticks_this_frame = snapshot.ticks_this_frame;
+        self.tick_accumulator = snapshot.tick_accumulator;
+        self.behind = snapshot.behind;
+    }
+
+    /// Reset the elapsed game time and any partial tick, the frame and tick counters keep
+    /// increasing
     pub fn reset(&mut self) {
         self.total_elapsed = 0.0;
+        self.tick_accumulator = 0.0;
+        self.behind = 0.0;
         self.real_time_instant = Instant::now();
     }
 }
 
+/// Game time state of `Time`, restored together so ticks stay in step with game time
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TimeSnapshot {
+    total_elapsed: f32,
+    time_scale: f32,
+    frame: u64,
+    tick: u64,
+    ticks_this_frame: u32,
+    tick_accumulator: f32,
+    behind: f32,
+}
+
 impl Default for Time {
     fn default() -> Self {
         Self {
@@ -51,7 +194,14 @@ impl Default for Time {
             time_scale: 1.0,
             total_elapsed_real_time: 0.0,
             elapsed_real_time: 0.0,
-            max_frame_time_ms: None,
+            catch_up: CatchUpPolicy::default(),
+            fixed_time_step: 1.0 / 60.0,
+            manual_step: None,
+            frame: 0,
+            tick: 0,
+            ticks_this_frame: 0,
+            tick_accumulator: 0.0,
+            behind: 0.0,
             last_update_time: Instant::now(),
             real_time_instant: Instant::now(),
         }
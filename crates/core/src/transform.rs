@@ -1,6 +1,6 @@
 use glam::*;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Transform {
     pub position: Vec3,
     pub rotation: Quat,
@@ -61,6 +61,99 @@ impl Transform {
     pub fn to_local_matrix(&self) -> Mat4 {
         (*self).into()
     }
+
+    /// Blend towards the other transform, positions and scales linearly and rotations along
+    /// the shortest arc, t of 0.0 is this transform and 1.0 the other
+    pub fn lerp(&self, other: &Transform, t: f32) -> Self {
+        Self {
+            position: self.position.lerp(other.position, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+
+    /// Copy with the position rounded to the nearest multiple of the grid size on each axis,
+    /// axes with a grid size of zero are left as is
+    pub fn snapped(&self, grid_size: Vec3) -> Self {
+        Self {
+            position: GridSnap::new(grid_size).snap_position(self.position),
+            ..*self
+        }
+    }
+}
+
+/// Snapping applied to transform positions and optionally rotations, for tile based placement
+/// and editor tooling, see `TransformHierarchy::set_snapping`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSnap {
+    /// Cell size per axis, axes with a size of zero are not snapped
+    pub cell_size: Vec3,
+    /// Position of a cell corner, for grids not aligned with the origin
+    pub origin: Vec3,
+    /// Snap rotations to multiples of this angle in radians around each axis
+    pub rotation_step: Option<f32>,
+}
+
+impl GridSnap {
+    pub fn new(cell_size: Vec3) -> Self {
+        Self {
+            cell_size,
+            origin: Vec3::ZERO,
+            rotation_step: None,
+        }
+    }
+
+    /// Snap in the xz plane leaving height free, for tiles placed on the ground
+    pub fn ground(cell_size: f32) -> Self {
+        Self::new(Vec3::new(cell_size, 0.0, cell_size))
+    }
+
+    pub fn with_origin(mut self, origin: Vec3) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_rotation_step(mut self, radians: f32) -> Self {
+        self.rotation_step = Some(radians);
+        self
+    }
+
+    pub fn snap_position(&self, position: Vec3) -> Vec3 {
+        let snap_axis = |value: f32, origin: f32, size: f32| {
+            if size > 0.0 {
+                origin + ((value - origin) / size).round() * size
+            } else {
+                value
+            }
+        };
+        Vec3::new(
+            snap_axis(position.x, self.origin.x, self.cell_size.x),
+            snap_axis(position.y, self.origin.y, self.cell_size.y),
+            snap_axis(position.z, self.origin.z, self.cell_size.z),
+        )
+    }
+
+    pub fn snap_rotation(&self, rotation: Quat) -> Quat {
+        let Some(step) = self.rotation_step.filter(|step| *step > 0.0) else {
+            return rotation;
+        };
+        let (yaw, pitch, roll) = rotation.to_euler(EulerRot::YXZ);
+        let snap_angle = |angle: f32| (angle / step).round() * step;
+        Quat::from_euler(
+            EulerRot::YXZ,
+            snap_angle(yaw),
+            snap_angle(pitch),
+            snap_angle(roll),
+        )
+    }
+
+    pub fn apply(&self, transform: Transform) -> Transform {
+        Transform {
+            position: self.snap_position(transform.position),
+            rotation: self.snap_rotation(transform.rotation),
+            scale: transform.scale,
+        }
+    }
 }
 
 impl From<Mat4> for Transform {
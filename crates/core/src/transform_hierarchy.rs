@@ -3,7 +3,7 @@ use std::{collections::HashSet, hash::Hash};
 use glam::{Mat4, Quat, Vec3};
 use slotmap::{self, SecondaryMap, SlotMap};
 
-use crate::transform::Transform;
+use crate::transform::{GridSnap, Transform};
 
 slotmap::new_key_type! { pub struct TransformId; }
 
@@ -18,6 +18,8 @@ pub struct TransformHierarchy {
     hierarchy: SlotMap<TransformId, HierarchyNode>,
     transforms: SecondaryMap<TransformId, Transform>,
     world_matrices: SecondaryMap<TransformId, Mat4>,
+    structure_version: u64,
+    snapping: Option<GridSnap>,
 }
 
 impl TransformHierarchy {
@@ -25,7 +27,9 @@ impl TransformHierarchy {
         Self {
             hierarchy: SlotMap::with_key(),
             transforms: SecondaryMap::new(),
-            world_matrices: SecondaryMap::new()
+            world_matrices: SecondaryMap::new(),
+            structure_version: 0,
+            snapping: None,
         }
     }
 
@@ -33,19 +37,88 @@ impl TransformHierarchy {
         self.hierarchy.clear();
         self.transforms.clear();
         self.world_matrices.clear();
+        self.structure_version += 1;
+    }
+
+    /// Incremented whenever nodes are added, removed or reparented, allows users
+    /// to cache values derived from the hierarchy structure
+    pub fn structure_version(&self) -> u64 {
+        self.structure_version
+    }
+
+    /// Snap local transforms as they are inserted or set, positions snap in parent space
+    /// NOTE: Existing transforms are not snapped until they are next set
+    pub fn set_snapping(&mut self, snapping: Option<GridSnap>) {
+        self.snapping = snapping;
+    }
+
+    pub fn snapping(&self) -> Option<GridSnap> {
+        self.snapping
     }
 
     pub fn insert(&mut self, transform: Transform, parent: Option<TransformId>) -> TransformId {
+        let hierarchy_id = self.insert_node(transform, parent);
+        self.structure_version += 1;
+        hierarchy_id
+    }
+
+    /// Insert many transforms under the same parent, reserving capacity up front and
+    /// incrementing the structure version once, returns the ids in the order provided
+    pub fn insert_batch<I>(
+        &mut self,
+        transforms: I,
+        parent: Option<TransformId>,
+    ) -> Vec<TransformId>
+    where
+        I: IntoIterator<Item = Transform>,
+    {
+        let transforms = transforms.into_iter();
+        let (lower, upper) = transforms.size_hint();
+        let count = upper.unwrap_or(lower);
+        self.reserve(count);
+        if let Some(parent_node) = parent.and_then(|parent| self.hierarchy.get_mut(parent)) {
+            parent_node.children.reserve(count);
+        }
+        let ids = transforms
+            .map(|transform| self.insert_node(transform, parent))
+            .collect::<Vec<_>>();
+        if !ids.is_empty() {
+            self.structure_version += 1;
+        }
+        ids
+    }
+
+    fn insert_node(&mut self, transform: Transform, parent: Option<TransformId>) -> TransformId {
+        let transform = self.snap(transform);
         let node = HierarchyNode { parent: parent, children: Vec::new() };
         let hierarchy_id = self.hierarchy.insert(node);
+        // A new node can't already be a child, so skip the check `attach_parent` makes
+        if let Some(parent_node) = parent.and_then(|parent| self.hierarchy.get_mut(parent)) {
+            parent_node.children.push(hierarchy_id);
+        }
         self.transforms.insert(hierarchy_id, transform);
         self.world_matrices.insert(hierarchy_id, self.get_parent_matrix(parent) * transform.to_local_matrix());
         hierarchy_id
     }
 
+    /// Reserve capacity for at least `additional` more transforms
+    pub fn reserve(&mut self, additional: usize) {
+        self.hierarchy.reserve(additional);
+        let capacity = self.hierarchy.capacity();
+        self.transforms.set_capacity(capacity);
+        self.world_matrices.set_capacity(capacity);
+    }
+
+    /// Number of transforms which can be held without reallocating, maps keyed by
+    /// `TransformId` can be given the same capacity before inserting many nodes
+    pub fn capacity(&self) -> usize {
+        self.hierarchy.capacity()
+    }
+
     /// Remove a transform and all it's descendants from the hierarchy
     pub fn remove(&mut self, id: TransformId) {
         self.deattach_parent(id);
+        self.structure_version += 1;
         if let Some(node) = self.hierarchy.get(id) {
             if node.children.is_empty() {
                 self.hierarchy.remove(id);
@@ -80,16 +153,54 @@ impl TransformHierarchy {
             if let Some(node) = self.hierarchy.get_mut(id) {
                 node.parent = parent;
             }
+            self.attach_parent(id, parent);
+            self.structure_version += 1;
             self.set_transform(id, self.transforms[id]);
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.hierarchy.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hierarchy.is_empty()
+    }
+
+    pub fn contains(&self, id: TransformId) -> bool {
+        self.hierarchy.contains_key(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = TransformId> + '_ {
+        self.hierarchy.keys()
+    }
+
+    pub fn get_parent(&self, id: TransformId) -> Option<TransformId> {
+        self.hierarchy.get(id).and_then(|node| node.parent)
+    }
+
+    pub fn get_children(&self, id: TransformId) -> &[TransformId] {
+        self.hierarchy
+            .get(id)
+            .map(|node| node.children.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Iterate over nodes which have no parent
+    pub fn roots(&self) -> impl Iterator<Item = TransformId> + '_ {
+        self.hierarchy
+            .iter()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(id, _)| id)
+    }
+
     pub fn get_transform(&self, id: TransformId) -> Option<Transform> {
         self.transforms.get(id).copied()
     }
 
     /// Set transform and update relevant hierarchy world matrices
     pub fn set_transform(&mut self, id: TransformId, transform: Transform) {
+        let transform = self.snap(transform);
         self.transforms[id] = transform;
         if let Some(node) = self.hierarchy.get(id) {
             let world_matrix = self.get_parent_matrix(node.parent) * transform.to_local_matrix();
@@ -119,6 +230,13 @@ impl TransformHierarchy {
         self.get_world_matrix(id).and_then(|matrix| Some(matrix.to_scale_rotation_translation()))
     }
 
+    fn snap(&self, transform: Transform) -> Transform {
+        match self.snapping {
+            Some(snapping) => snapping.apply(transform),
+            None => transform,
+        }
+    }
+
     fn deattach_parent(&mut self, id: TransformId) {
         if let Some(parent_node) = self.hierarchy.get(id)
             .and_then(|node| node.parent)
@@ -129,6 +247,14 @@ impl TransformHierarchy {
         }
     }
 
+    fn attach_parent(&mut self, id: TransformId, parent: Option<TransformId>) {
+        if let Some(parent_node) = parent.and_then(|parent| self.hierarchy.get_mut(parent)) {
+            if !parent_node.children.contains(&id) {
+                parent_node.children.push(id);
+            }
+        }
+    }
+
     fn get_parent_matrix(&self, parent: Option<TransformId>) -> Mat4 {
         if let Some(id) = parent {
             self.world_matrices.get(id).copied().unwrap_or(Mat4::IDENTITY)
@@ -0,0 +1,70 @@
+use glam::Vec2;
+use winit::window::Window;
+
+use crate::ScissorRect;
+
+/// What pressing the left mouse button over a chrome region does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromeAction {
+    /// Move the window, e.g. a custom title bar
+    Drag,
+    Minimize,
+    ToggleMaximize,
+    /// Exit, as with closing the window
+    Close,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChromeRegion {
+    pub rect: ScissorRect,
+    pub action: ChromeAction,
+}
+
+/// Hit areas for custom title bars on undecorated windows, see `Helia::with_decorations`
+///
+/// Presses over a region are handled by the engine rather than reaching `state.input`, the
+/// game is responsible for drawing the title bar and buttons. Regions added later take
+/// precedence, so add buttons after a drag region spanning the title bar. Regions are in
+/// physical pixels, update them in `Game::resize` if they depend on the window size.
+#[derive(Debug, Clone, Default)]
+pub struct WindowChrome {
+    regions: Vec<ChromeRegion>,
+}
+
+impl WindowChrome {
+    pub fn add(&mut self, rect: ScissorRect, action: ChromeAction) {
+        self.regions.push(ChromeRegion { rect, action });
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    pub fn regions(&self) -> &[ChromeRegion] {
+        &self.regions
+    }
+
+    /// Action of the topmost region containing the position, e.g. to highlight hovered buttons
+    pub fn hit_test(&self, position: Vec2) -> Option<ChromeAction> {
+        self.regions
+            .iter()
+            .rev()
+            .find(|region| region.rect.contains(position))
+            .map(|region| region.action)
+    }
+
+    /// Apply the action to the window, returns true if the app should exit
+    pub(crate) fn perform(action: ChromeAction, window: &Window) -> bool {
+        match action {
+            ChromeAction::Drag => {
+                if let Err(error) = window.drag_window() {
+                    log::warn!("Unable to drag window: {}", error);
+                }
+            }
+            ChromeAction::Minimize => window.set_minimized(true),
+            ChromeAction::ToggleMaximize => window.set_maximized(!window.is_maximized()),
+            ChromeAction::Close => return true,
+        }
+        false
+    }
+}
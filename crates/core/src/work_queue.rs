@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+
+use instant::Instant;
+use slotmap::{new_key_type, SlotMap};
+
+use crate::State;
+
+new_key_type! { pub struct WorkId; }
+
+/// Returned by each step of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkStatus {
+    /// More steps remain, the task will be run again
+    Pending,
+    Done,
+}
+
+type Task = Box<dyn FnMut(&mut State) -> WorkStatus>;
+
+/// Incremental tasks (chunk meshing, pathfinding, decompression) run a step at a time
+/// within a per frame time budget, to spread long operations over several frames
+///
+/// Tasks take turns, each running a single step before the next task gets a turn, so keep
+/// steps short. At least one step runs each frame regardless of the budget so work always
+/// progresses. The queue on `State` is run by the engine after `Game::update`.
+pub struct WorkQueue {
+    /// Time in seconds to spend on tasks each frame
+    pub frame_budget: f32,
+    // A task is taken out of its slot while it runs, so it can push to or cancel on the queue
+    tasks: SlotMap<WorkId, Option<Task>>,
+    order: VecDeque<WorkId>,
+}
+
+impl WorkQueue {
+    pub fn new(frame_budget: f32) -> Self {
+        Self {
+            frame_budget,
+            tasks: SlotMap::with_key(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Add a task, it is called with the engine state until it returns `WorkStatus::Done`
+    pub fn push<F>(&mut self, task: F) -> WorkId
+    where
+        F: FnMut(&mut State) -> WorkStatus + 'static,
+    {
+        let id = self.tasks.insert(Some(Box::new(task)));
+        self.order.push_back(id);
+        id
+    }
+
+    /// Remove a task before it completes, returns false if it had already finished
+    pub fn cancel(&mut self, id: WorkId) -> bool {
+        // Stale ids left in the order are skipped when reached
+        self.tasks.remove(id).is_some()
+    }
+
+    pub fn is_pending(&self, id: WorkId) -> bool {
+        self.tasks.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+        self.order.clear();
+    }
+}
+
+impl Default for WorkQueue {
+    fn default() -> Self {
+        // A small slice of a 60fps frame
+        Self::new(0.002)
+    }
+}
+
+impl State {
+    /// Run steps of the queued tasks until the frame budget is spent or no tasks remain,
+    /// returns the number of steps run
+    pub(crate) fn run_work_queue(&mut self) -> usize {
+        let start = Instant::now();
+        let mut steps = 0;
+        while let Some(id) = self.work_queue.order.pop_front() {
+            let Some(mut task) = self.work_queue.tasks.get_mut(id).and_then(Option::take) else {
+                continue;
+            };
+            steps += 1;
+            let status = task(self);
+            match (status, self.work_queue.tasks.get_mut(id)) {
+                (WorkStatus::Pending, Some(slot)) => {
+                    *slot = Some(task);
+                    self.work_queue.order.push_back(id);
+                }
+                // Cancelled by the task itself
+                (WorkStatus::Pending, None) => {}
+                (WorkStatus::Done, _) => {
+                    self.work_queue.tasks.remove(id);
+                }
+            }
+            if start.elapsed().as_secs_f32() >= self.work_queue.frame_budget {
+                break;
+            }
+        }
+        steps
+    }
+}
@@ -0,0 +1,309 @@
+use core::{
+    camera::Camera,
+    entity::RenderProperties,
+    input::{InputState, MouseButton},
+    material::{Material, MaterialId},
+    mesh::MeshId,
+    picking::Ray,
+    texture::Texture,
+    transform::Transform,
+    transform_hierarchy::{TransformHierarchy, TransformId},
+    Color, DrawCommand, State,
+};
+use glam::*;
+use primitives::cube;
+use winit::dpi::PhysicalSize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn direction(&self) -> Vec3 {
+        match self {
+            GizmoAxis::X => Vec3::X,
+            GizmoAxis::Y => Vec3::Y,
+            GizmoAxis::Z => Vec3::Z,
+        }
+    }
+
+    /// Basis vectors for the plane perpendicular to the axis
+    fn plane_basis(&self) -> (Vec3, Vec3) {
+        match self {
+            GizmoAxis::X => (Vec3::Y, Vec3::Z),
+            GizmoAxis::Y => (Vec3::Z, Vec3::X),
+            GizmoAxis::Z => (Vec3::X, Vec3::Y),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            GizmoAxis::X => Color::RED,
+            GizmoAxis::Y => Color::GREEN,
+            GizmoAxis::Z => Color::BLUE,
+        }
+    }
+}
+
+const HIGHLIGHT_COLOR: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 0.0,
+    a: 1.0,
+};
+
+struct GizmoDrag {
+    axis: GizmoAxis,
+    start_transform: Transform,
+    start_world_position: Vec3,
+    start_value: f32,
+}
+
+/// Manipulation handles for the transform of a target in a TransformHierarchy
+///
+/// Handles are aligned to world axes, scaling a rotated target scales its local axis closest
+/// to the handle's axis. Call `update` each frame with the current input
+/// to pick handles and apply mouse drags, and `render` after the scene so the handles
+/// are drawn over the top of it.
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub target: Option<TransformId>,
+    /// Length of the handles in world units
+    pub size: f32,
+    /// Thickness of the handles relative to their length
+    pub thickness: f32,
+    mesh: MeshId,
+    material: MaterialId,
+    hovered: Option<GizmoAxis>,
+    drag: Option<GizmoDrag>,
+}
+
+impl Gizmo {
+    pub fn new(state: &mut State) -> Self {
+        let mesh = cube::centered_mesh(state);
+        let mesh = state.resources.meshes.insert(mesh);
+        let texture = Texture::from_color(&state.device, &state.queue, [255; 4]).unwrap();
+        let texture = state.resources.textures.insert(texture);
+        let material = Material::new(state.shaders.overlay, texture, state);
+        let material = state.resources.materials.insert(material);
+
+        Self {
+            mode: GizmoMode::Translate,
+            target: None,
+            size: 1.0,
+            thickness: 0.05,
+            mesh,
+            material,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn hovered(&self) -> Option<GizmoAxis> {
+        self.hovered
+    }
+
+    /// Picks handles under the mouse and converts left mouse drags into transform edits
+    /// Returns true if the target's transform was changed
+    pub fn update(
+        &mut self,
+        hierarchy: &mut TransformHierarchy,
+        camera: &Camera,
+        input: &InputState,
+        size: PhysicalSize<u32>,
+    ) -> bool {
+        let Some(target) = self.target.filter(|id| hierarchy.contains(*id)) else {
+            self.hovered = None;
+            self.drag = None;
+            return false;
+        };
+
+        let origin = Self::world_position(hierarchy, target);
+        let mouse_position = Vec2::new(
+            input.mouse_position.x as f32,
+            input.mouse_position.y as f32,
+        );
+        let ray = camera.screen_to_ray(mouse_position, size);
+
+        if let Some(drag) = &self.drag {
+            if !input.mouse_button_pressed(MouseButton::Left) {
+                self.drag = None;
+                return false;
+            }
+            if let Some(value) = self.drag_value(drag.axis, drag.start_world_position, &ray) {
+                let parent_matrix = hierarchy
+                    .get_parent(target)
+                    .and_then(|parent| hierarchy.get_world_matrix(parent))
+                    .unwrap_or(Mat4::IDENTITY);
+                let transform = self.apply_drag(drag, value - drag.start_value, parent_matrix);
+                hierarchy.set_transform(target, transform);
+                return true;
+            }
+            return false;
+        }
+
+        self.hovered = self.pick(origin, &ray);
+        if input.mouse_button_down(MouseButton::Left) {
+            if let Some(axis) = self.hovered {
+                if let Some(start_value) = self.drag_value(axis, origin, &ray) {
+                    self.drag = Some(GizmoDrag {
+                        axis,
+                        start_transform: hierarchy.get_transform(target).unwrap(),
+                        start_world_position: origin,
+                        start_value,
+                    });
+                }
+            }
+        }
+        false
+    }
+
+    pub fn render(&self, hierarchy: &TransformHierarchy, commands: &mut Vec<DrawCommand>) {
+        let Some(target) = self.target.filter(|id| hierarchy.contains(*id)) else {
+            return;
+        };
+        let origin = Self::world_position(hierarchy, target);
+        let active_axis = self.drag.as_ref().map(|drag| drag.axis).or(self.hovered);
+
+        for axis in GizmoAxis::ALL {
+            let color = if active_axis == Some(axis) {
+                HIGHLIGHT_COLOR
+            } else {
+                axis.color()
+            };
+            let (min, max) = self.handle_bounds(origin, axis, 1.0);
+            self.push_box(0.5 * (min + max), max - min, color, commands);
+
+            match self.mode {
+                GizmoMode::Scale => {
+                    let tip = origin + self.size * axis.direction();
+                    self.push_box(tip, Vec3::splat(3.0 * self.thickness * self.size), color, commands);
+                }
+                GizmoMode::Rotate => {
+                    // Mark the plane of rotation with short spokes either side of the tip
+                    let (u, _) = axis.plane_basis();
+                    let tip = origin + self.size * axis.direction();
+                    let spoke = (0.5 * self.size) * u + self.thickness * self.size * (Vec3::ONE - u);
+                    self.push_box(tip, spoke, color, commands);
+                }
+                GizmoMode::Translate => {}
+            }
+        }
+        self.push_box(origin, Vec3::splat(2.0 * self.thickness * self.size), Color::WHITE, commands);
+    }
+
+    fn push_box(&self, center: Vec3, extents: Vec3, color: Color, commands: &mut Vec<DrawCommand>) {
+        let transform = Transform::from_position_scale(center, extents);
+        commands.push(DrawCommand::Draw(
+            self.mesh,
+            self.material,
+            RenderProperties::builder()
+                .with_matrix(transform.into())
+                .with_color(color)
+                .build(),
+        ));
+    }
+
+    fn world_position(hierarchy: &TransformHierarchy, id: TransformId) -> Vec3 {
+        hierarchy
+            .get_world_matrix(id)
+            .map(|matrix| matrix.transform_point3(Vec3::ZERO))
+            .unwrap_or(Vec3::ZERO)
+    }
+
+    fn handle_bounds(&self, origin: Vec3, axis: GizmoAxis, thickness_scale: f32) -> (Vec3, Vec3) {
+        let direction = axis.direction();
+        let half_thickness = 0.5 * thickness_scale * self.thickness * self.size;
+        let min = origin - half_thickness * (Vec3::ONE - direction);
+        let max = origin + self.size * direction + half_thickness * (Vec3::ONE - direction);
+        (min, max)
+    }
+
+    fn pick(&self, origin: Vec3, ray: &Ray) -> Option<GizmoAxis> {
+        let mut closest: Option<(GizmoAxis, f32)> = None;
+        for axis in GizmoAxis::ALL {
+            if self.mode != GizmoMode::Rotate && ray.direction.dot(axis.direction()).abs() > 0.99 {
+                // Can't meaningfully drag along an axis pointing into the screen (e.g. Z in 2D)
+                continue;
+            }
+            // Pick with generous bounds, thin handles are hard to hit
+            let (min, max) = self.handle_bounds(origin, axis, 3.0);
+            if let Some(distance) = ray.intersect_aabb(min, max) {
+                if closest.is_none_or(|(_, closest_distance)| distance < closest_distance) {
+                    closest = Some((axis, distance));
+                }
+            }
+        }
+        closest.map(|(axis, _)| axis)
+    }
+
+    /// The scalar value being manipulated for the current mode, distance along the axis
+    /// for translate and scale, angle around the axis for rotate
+    fn drag_value(&self, axis: GizmoAxis, origin: Vec3, ray: &Ray) -> Option<f32> {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                ray.closest_point_on_line(origin, axis.direction())
+            }
+            GizmoMode::Rotate => {
+                let distance = ray.intersect_plane(origin, axis.direction())?;
+                let offset = ray.at(distance) - origin;
+                let (u, v) = axis.plane_basis();
+                Some(offset.dot(v).atan2(offset.dot(u)))
+            }
+        }
+    }
+
+    fn apply_drag(&self, drag: &GizmoDrag, delta: f32, parent_matrix: Mat4) -> Transform {
+        let mut transform = drag.start_transform;
+        let inverse_parent = parent_matrix.inverse();
+        let direction = drag.axis.direction();
+        match self.mode {
+            GizmoMode::Translate => {
+                transform.position += inverse_parent.transform_vector3(delta * direction);
+            }
+            GizmoMode::Scale => {
+                // Scale is local, so scale the target's axis closest to the world axis dragged
+                let (_, world_rotation, _) = (parent_matrix * transform.to_local_matrix())
+                    .to_scale_rotation_translation();
+                let local_direction = (world_rotation.inverse() * direction).abs();
+                let index = if local_direction.x >= local_direction.y
+                    && local_direction.x >= local_direction.z
+                {
+                    0
+                } else if local_direction.y >= local_direction.z {
+                    1
+                } else {
+                    2
+                };
+                let factor = (1.0 + delta / self.size).max(0.01);
+                transform.scale[index] *= factor;
+            }
+            GizmoMode::Rotate => {
+                // wrap so crossing the atan2 discontinuity doesn't flip the rotation
+                let angle = (delta + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+                    - std::f32::consts::PI;
+                let local_axis = inverse_parent.transform_vector3(direction).normalize_or_zero();
+                if local_axis != Vec3::ZERO {
+                    transform.rotation = Quat::from_axis_angle(local_axis, angle) * transform.rotation;
+                }
+            }
+        }
+        transform
+    }
+}
@@ -0,0 +1,3 @@
+pub mod gizmo;
+
+pub use gizmo::*;
@@ -0,0 +1,113 @@
+use core::{RenderContext, State};
+use winit::{event::WindowEvent, window::Window};
+
+pub use egui;
+
+/// Renders egui over the top of the frame
+///
+/// Forward window events from `Game::window_event`, build the ui with `run` during update
+/// and draw it from `Game::post_render`. Requires a window, so can't be used when running
+/// headless.
+pub struct EguiIntegration {
+    context: egui::Context,
+    winit_state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    output: Option<egui::FullOutput>,
+}
+
+impl EguiIntegration {
+    pub fn new(state: &State) -> Self {
+        let context = egui::Context::default();
+        let winit_state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window(state),
+            Some(state.scale_factor()),
+            None,
+            Some(state.device.limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(&state.device, state.surface_format(), None, 1, false);
+        Self {
+            context,
+            winit_state,
+            renderer,
+            output: None,
+        }
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    /// Feed a window event to egui, returns true if egui consumed it
+    pub fn handle_event(&mut self, state: &State, event: &WindowEvent) -> bool {
+        self.winit_state.on_window_event(window(state), event).consumed
+    }
+
+    /// Build this frame's ui
+    pub fn run(&mut self, state: &State, build_ui: impl FnMut(&egui::Context)) {
+        let input = self.winit_state.take_egui_input(window(state));
+        let output = self.context.run(input, build_ui);
+        self.winit_state
+            .handle_platform_output(window(state), output.platform_output.clone());
+        self.output = Some(output);
+    }
+
+    /// Draw the ui built by the last call to `run`
+    pub fn render(&mut self, context: &mut RenderContext) {
+        let Some(output) = self.output.take() else {
+            return;
+        };
+
+        let pixels_per_point = output.pixels_per_point;
+        let paint_jobs = self.context.tessellate(output.shapes, pixels_per_point);
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [context.size.width, context.size.height],
+            pixels_per_point,
+        };
+
+        for (id, image_delta) in output.textures_delta.set.iter() {
+            self.renderer
+                .update_texture(context.device, context.queue, *id, image_delta);
+        }
+        let command_buffers = self.renderer.update_buffers(
+            context.device,
+            context.queue,
+            context.encoder,
+            &paint_jobs,
+            &screen_descriptor,
+        );
+        context.queue.submit(command_buffers);
+
+        {
+            let render_pass = context.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            self.renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &paint_jobs,
+                &screen_descriptor,
+            );
+        }
+
+        for id in output.textures_delta.free.iter() {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+fn window(state: &State) -> &Window {
+    state
+        .window
+        .as_deref()
+        .expect("egui requires a window, it can't be used when running headless")
+}
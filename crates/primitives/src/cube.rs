@@ -0,0 +1,80 @@
+use core::{mesh::Mesh, *};
+use glam::*;
+
+const CUBE_POSITIONS: &[Vec3] = &[
+    // Front face
+    Vec3::new(-0.5, -0.5, 0.5),
+    Vec3::new(0.5, -0.5, 0.5),
+    Vec3::new(0.5, 0.5, 0.5),
+    Vec3::new(-0.5, 0.5, 0.5),
+    // Back face
+    Vec3::new(-0.5, -0.5, -0.5),
+    Vec3::new(-0.5, 0.5, -0.5),
+    Vec3::new(0.5, 0.5, -0.5),
+    Vec3::new(0.5, -0.5, -0.5),
+    // Top face
+    Vec3::new(-0.5, 0.5, -0.5),
+    Vec3::new(-0.5, 0.5, 0.5),
+    Vec3::new(0.5, 0.5, 0.5),
+    Vec3::new(0.5, 0.5, -0.5),
+    // Bottom face
+    Vec3::new(-0.5, -0.5, -0.5),
+    Vec3::new(0.5, -0.5, -0.5),
+    Vec3::new(0.5, -0.5, 0.5),
+    Vec3::new(-0.5, -0.5, 0.5),
+    // Right face
+    Vec3::new(0.5, -0.5, -0.5),
+    Vec3::new(0.5, 0.5, -0.5),
+    Vec3::new(0.5, 0.5, 0.5),
+    Vec3::new(0.5, -0.5, 0.5),
+    // Left face
+    Vec3::new(-0.5, -0.5, -0.5),
+    Vec3::new(-0.5, -0.5, 0.5),
+    Vec3::new(-0.5, 0.5, 0.5),
+    Vec3::new(-0.5, 0.5, -0.5),
+];
+const CUBE_UVS: &[Vec2] = &[
+    Vec2::new(0.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(0.0, 0.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+];
+const CUBE_INDICES: &[u16] = &[
+    0, 1, 2, 0, 2, 3, // Front face
+    4, 5, 6, 4, 6, 7, // Back face
+    8, 9, 10, 8, 10, 11, // Top face
+    12, 13, 14, 12, 14, 15, // Bottom face
+    16, 17, 18, 16, 18, 19, // Right face
+    20, 21, 22, 20, 22, 23, // Left face
+];
+
+/// Unit cube centered on the origin
+pub fn centered_mesh(state: &mut State) -> Mesh {
+    state.mesh_pool.create_from_arrays(
+        CUBE_POSITIONS,
+        CUBE_UVS,
+        CUBE_INDICES,
+        &state.device,
+        &state.queue,
+    )
+}
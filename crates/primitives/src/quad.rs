@@ -16,7 +16,13 @@ const QUAD_UVS: &[Vec2] = &[
 const QUAD_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
 
 pub fn centered_mesh(state: &mut State) -> Mesh {
-    Mesh::from_arrays(QUAD_POSITIONS, QUAD_UVS, QUAD_INDICES, &state.device)
+    state.mesh_pool.create_from_arrays(
+        QUAD_POSITIONS,
+        QUAD_UVS,
+        QUAD_INDICES,
+        &state.device,
+        &state.queue,
+    )
 }
 
 pub fn centred_mesh_with_offset_scale(
@@ -25,11 +31,12 @@ pub fn centred_mesh_with_offset_scale(
     offset: Vec2,
     state: &mut State,
 ) -> Mesh {
-    Mesh::from_arrays(
+    state.mesh_pool.create_from_arrays(
         &positions_with_offset_scale(width, height, offset).as_slice(),
         QUAD_UVS,
         QUAD_INDICES,
         &state.device,
+        &state.queue,
     )
 }
 
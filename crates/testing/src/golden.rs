@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::*;
+use core::screenshot::Screenshot;
+
+/// How far a capture may differ from its golden image and still match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference in any channel for pixels to be considered equal, absorbs
+    /// rounding differences between GPUs and drivers
+    pub channel: u8,
+    /// Fraction of pixels which may differ by more than the channel tolerance
+    pub max_differing: f32,
+}
+
+impl Tolerance {
+    pub const EXACT: Tolerance = Tolerance {
+        channel: 0,
+        max_differing: 0.0,
+    };
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            max_differing: 0.001,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Matched,
+    /// The capture differs, the actual and diff images are written next to the golden image
+    Mismatched {
+        differing: usize,
+        total: usize,
+        max_difference: u8,
+    },
+    /// Dimensions differ from the golden image
+    SizeMismatched {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// No golden image exists, the capture is written next to where it should be, rerun with
+    /// `HELIA_UPDATE_GOLDENS` set to save captures as golden images
+    MissingGolden,
+    /// Goldens are being updated, so the capture was saved as the golden image
+    GoldenWritten,
+    /// The frame could not be captured, e.g. the surface doesn't support copying
+    NotCaptured,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        matches!(self, Outcome::Matched | Outcome::GoldenWritten)
+    }
+}
+
+/// Environment variable which, when set, overwrites golden images with the captures
+pub const UPDATE_GOLDENS_VAR: &str = "HELIA_UPDATE_GOLDENS";
+
+/// Whether `UPDATE_GOLDENS_VAR` is set
+pub fn updating_goldens() -> bool {
+    std::env::var_os(UPDATE_GOLDENS_VAR).is_some()
+}
+
+/// Compare the capture against the golden image at the path, or overwrite the golden image
+/// with it if `UPDATE_GOLDENS_VAR` is set
+pub fn compare(capture: &Screenshot, golden: &Path, tolerance: Tolerance) -> Result<Outcome> {
+    compare_or_update(capture, golden, tolerance, updating_goldens())
+}
+
+/// Compare the capture against the golden image at the path, or overwrite the golden image
+/// with it if `update` is set
+pub fn compare_or_update(
+    capture: &Screenshot,
+    golden: &Path,
+    tolerance: Tolerance,
+    update: bool,
+) -> Result<Outcome> {
+    if update {
+        save(capture, golden)?;
+        return Ok(Outcome::GoldenWritten);
+    }
+    if !golden.exists() {
+        save(capture, &sibling(golden, "actual"))?;
+        return Ok(Outcome::MissingGolden);
+    }
+
+    let expected = image::open(golden)
+        .with_context(|| format!("Unable to read golden image {}", golden.display()))?
+        .into_rgba8();
+    if expected.dimensions() != (capture.width, capture.height) {
+        save(capture, &sibling(golden, "actual"))?;
+        return Ok(Outcome::SizeMismatched {
+            expected: expected.dimensions(),
+            actual: (capture.width, capture.height),
+        });
+    }
+
+    let mut differing = 0;
+    let mut max_difference = 0;
+    let mut diff = Vec::with_capacity(capture.rgba.len());
+    for (actual, expected) in capture
+        .rgba
+        .chunks_exact(4)
+        .zip(expected.as_raw().chunks_exact(4))
+    {
+        let difference = actual
+            .iter()
+            .zip(expected)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > tolerance.channel {
+            differing += 1;
+            diff.extend_from_slice(&[255, 0, 255, 255]);
+        } else {
+            // Faded copy of the expected image for context
+            diff.extend(expected[..3].iter().map(|channel| channel / 4));
+            diff.push(255);
+        }
+    }
+
+    let total = (capture.width * capture.height) as usize;
+    if differing as f32 <= tolerance.max_differing * total as f32 {
+        return Ok(Outcome::Matched);
+    }
+    save(capture, &sibling(golden, "actual"))?;
+    save(
+        &Screenshot {
+            width: capture.width,
+            height: capture.height,
+            rgba: diff,
+        },
+        &sibling(golden, "diff"),
+    )?;
+    Ok(Outcome::Mismatched {
+        differing,
+        total,
+        max_difference,
+    })
+}
+
+fn save(capture: &Screenshot, path: &Path) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    image::save_buffer(
+        path,
+        &capture.rgba,
+        capture.width,
+        capture.height,
+        image::ExtendedColorType::Rgba8,
+    )
+    .with_context(|| format!("Unable to write {}", path.display()))
+}
+
+/// e.g. `title_60.png` to `title_60.actual.png`
+fn sibling(golden: &Path, suffix: &str) -> PathBuf {
+    golden.with_extension(format!("{}.png", suffix))
+}
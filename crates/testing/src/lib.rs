@@ -0,0 +1,290 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use core::{
+    assets::{AssetLoader, LoadHandle},
+    input::{KeyCode, MouseButton},
+    DrawCommand, Game, Helia, RenderContext, State,
+};
+use glam::Vec2;
+use winit::{dpi::PhysicalPosition, event::WindowEvent};
+
+pub mod golden;
+
+pub use golden::{Outcome, Tolerance};
+
+/// Input applied at the start of a frame, before `Game::update`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptedInput {
+    KeyDown(KeyCode),
+    KeyUp(KeyCode),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    /// Cursor position in physical pixels
+    MouseMove(Vec2),
+}
+
+impl ScriptedInput {
+    fn apply(&self, state: &mut State) {
+        match *self {
+            ScriptedInput::KeyDown(key) => state.input.press_key(key),
+            ScriptedInput::KeyUp(key) => state.input.release_key(key),
+            ScriptedInput::MouseDown(button) => state.input.press_mouse_button(button),
+            ScriptedInput::MouseUp(button) => state.input.release_mouse_button(button),
+            ScriptedInput::MouseMove(position) => state
+                .input
+                .move_mouse(PhysicalPosition::new(position.x as f64, position.y as f64)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameResult {
+    pub frame: u64,
+    pub golden: PathBuf,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub name: String,
+    pub results: Vec<FrameResult>,
+    /// Errors reading or writing images
+    pub errors: Vec<String>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.errors.is_empty() && self.results.iter().all(|result| result.outcome.passed())
+    }
+
+    /// Panic with a summary of the failures, for use in tests
+    pub fn assert_passed(&self) {
+        if self.passed() {
+            return;
+        }
+        let mut summary = format!("Screenshot test {} failed", self.name);
+        for result in self
+            .results
+            .iter()
+            .filter(|result| !result.outcome.passed())
+        {
+            summary.push_str(&format!(
+                "\n  frame {} ({}): {:?}",
+                result.frame,
+                result.golden.display(),
+                result.outcome
+            ));
+        }
+        for error in self.errors.iter() {
+            summary.push_str(&format!("\n  {}", error));
+        }
+        panic!("{}", summary);
+    }
+}
+
+/// Runs a game for a fixed number of frames with scripted input, capturing frames and
+/// comparing them against golden images, to catch rendering regressions
+///
+/// Each update advances time by a fixed step, so captures are frame accurate regardless of
+/// machine speed. A missing golden image fails the test, run with the `HELIA_UPDATE_GOLDENS`
+/// environment variable set to write the captures as golden images, then review and commit
+/// them.
+///
+/// The game runs headless, rendering offscreen, so no display is needed and tests can run
+/// under `cargo test`, but a graphics adapter is, software adapters such as llvmpipe work.
+pub struct ScreenshotTest {
+    name: String,
+    size: (u32, u32),
+    frames: u64,
+    step: f32,
+    inputs: Vec<(u64, ScriptedInput)>,
+    captures: Vec<u64>,
+    golden_dir: PathBuf,
+    tolerance: Tolerance,
+}
+
+impl ScreenshotTest {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Self {
+            name: name.into(),
+            size: (320, 240),
+            frames: 1,
+            step: 1.0 / 60.0,
+            inputs: Vec::new(),
+            captures: Vec::new(),
+            golden_dir: PathBuf::from("tests/goldens"),
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Number of frames to run, extended to include the last capture
+    pub fn with_frames(mut self, frames: u64) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Game time each frame advances by in seconds
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Apply the input at the start of the frame, frames are counted from 0
+    pub fn with_input(mut self, frame: u64, input: ScriptedInput) -> Self {
+        self.inputs.push((frame, input));
+        self
+    }
+
+    /// Capture the frame rendered after the given frame's update
+    pub fn capture(mut self, frame: u64) -> Self {
+        self.captures.push(frame);
+        self
+    }
+
+    pub fn with_golden_dir<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.golden_dir = directory.into();
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Run the game to completion and compare the captures
+    pub fn run(self, game: Box<dyn Game>) -> TestReport {
+        let (width, height) = self.size;
+        let report = Rc::new(RefCell::new(TestReport {
+            name: self.name.clone(),
+            ..Default::default()
+        }));
+        let last_capture = self.captures.iter().max().map_or(0, |frame| frame + 1);
+        let scripted = ScriptedGame {
+            game,
+            frame: 0,
+            frames: self.frames.max(last_capture),
+            pending_capture: None,
+            test: self,
+            report: report.clone(),
+        };
+
+        let mut helia = Helia::new();
+        helia.with_size(width, height);
+        let result = helia.run_headless(Box::new(scripted));
+
+        let mut report = report.borrow().clone();
+        if let Err(error) = result {
+            report
+                .errors
+                .push(format!("Unable to run game: {:#}", error));
+        }
+        report
+    }
+}
+
+/// Wraps the game under test, applying input and collecting captures
+struct ScriptedGame {
+    game: Box<dyn Game>,
+    test: ScreenshotTest,
+    frame: u64,
+    frames: u64,
+    /// Frame whose capture was requested and is read at the start of the next update
+    pending_capture: Option<u64>,
+    report: Rc<RefCell<TestReport>>,
+}
+
+impl ScriptedGame {
+    fn collect_capture(&mut self, state: &mut State) {
+        let Some(frame) = self.pending_capture.take() else {
+            return;
+        };
+        let golden = self
+            .test
+            .golden_dir
+            .join(format!("{}_{}.png", self.test.name, frame));
+        let mut report = self.report.borrow_mut();
+        let outcome = match state.take_screenshot() {
+            Some(screenshot) => match golden::compare(&screenshot, &golden, self.test.tolerance) {
+                Ok(outcome) => outcome,
+                Err(error) => {
+                    report.errors.push(format!("{:#}", error));
+                    return;
+                }
+            },
+            None => Outcome::NotCaptured,
+        };
+        log::info!("{} frame {}: {:?}", self.test.name, frame, outcome);
+        report.results.push(FrameResult {
+            frame,
+            golden,
+            outcome,
+        });
+    }
+}
+
+impl Game for ScriptedGame {
+    fn load(&mut self, loader: &mut AssetLoader) -> LoadHandle {
+        self.game.load(loader)
+    }
+
+    fn init(&mut self, state: &mut State) {
+        state.time.manual_step = Some(self.test.step);
+        self.game.init(state);
+    }
+
+    fn update(&mut self, state: &mut State, elapsed: f32) {
+        self.collect_capture(state);
+        if self.frame >= self.frames {
+            state.exit();
+            return;
+        }
+
+        for (_, input) in self
+            .test
+            .inputs
+            .iter()
+            .filter(|(frame, _)| *frame == self.frame)
+        {
+            input.apply(state);
+        }
+        self.game.update(state, elapsed);
+        if self.test.captures.contains(&self.frame) {
+            state.request_screenshot();
+            self.pending_capture = Some(self.frame);
+        }
+        self.frame += 1;
+    }
+
+    fn render(&mut self, commands: &mut Vec<DrawCommand>) {
+        self.game.render(commands);
+    }
+
+    fn resize(&mut self, state: &mut State) {
+        self.game.resize(state);
+    }
+
+    fn surface_format_changed(&mut self, state: &mut State) {
+        self.game.surface_format_changed(state);
+    }
+
+    fn language_changed(&mut self, state: &mut State) {
+        self.game.language_changed(state);
+    }
+
+    fn window_event(&mut self, state: &mut State, event: &WindowEvent) -> bool {
+        self.game.window_event(state, event)
+    }
+
+    fn pre_render(&mut self, context: &mut RenderContext) {
+        self.game.pre_render(context);
+    }
+
+    fn post_render(&mut self, context: &mut RenderContext) {
+        self.game.post_render(context);
+    }
+}
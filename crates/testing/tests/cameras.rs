@@ -0,0 +1,114 @@
+use std::{cell::RefCell, rc::Rc};
+
+use core::{
+    camera::{Camera, CameraId, Viewport},
+    draw_report::DrawReport,
+    entity::RenderProperties,
+    material::Material,
+    mesh::Mesh,
+    scene::Scene,
+    texture::Texture,
+    transform::Transform,
+    Color, DrawCommand, Game, State,
+};
+use glam::{Quat, Vec2, Vec3};
+use testing::ScreenshotTest;
+
+const QUAD_POSITIONS: &[Vec3] = &[
+    Vec3::new(-0.5, -0.5, 0.0),
+    Vec3::new(0.5, -0.5, 0.0),
+    Vec3::new(0.5, 0.5, 0.0),
+    Vec3::new(-0.5, 0.5, 0.0),
+];
+const QUAD_UVS: &[Vec2] = &[
+    Vec2::new(0.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+];
+const QUAD_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+/// A scene drawn by the main camera looking forward on the left and a second camera looking
+/// backward on the right, with a quad in front of each
+struct OppositeCameras {
+    scene: Scene,
+    back_camera: Option<CameraId>,
+    report: Rc<RefCell<Option<DrawReport>>>,
+}
+
+fn camera(direction: Vec3, viewport: Viewport) -> Camera {
+    Camera {
+        eye: Vec3::ZERO,
+        target: direction,
+        aspect_ratio: 0.5 * 64.0 / 48.0,
+        viewport: Some(viewport),
+        ..Default::default()
+    }
+}
+
+impl Game for OppositeCameras {
+    fn init(&mut self, state: &mut State) {
+        state.camera = camera(Vec3::NEG_Z, Viewport::new(0.0, 0.0, 0.5, 1.0));
+        self.back_camera = Some(
+            state
+                .resources
+                .cameras
+                .insert(camera(Vec3::Z, Viewport::new(0.5, 0.0, 0.5, 1.0))),
+        );
+
+        let texture = Texture::from_color(&state.device, &state.queue, [255; 4]).unwrap();
+        let texture = state.resources.textures.insert(texture);
+        let material = Material::new(state.shaders.sprite, texture, state);
+        let material = state.resources.materials.insert(material);
+        let mesh = Mesh::from_arrays(QUAD_POSITIONS, QUAD_UVS, QUAD_INDICES, &state.device);
+        let mesh = state.resources.meshes.insert(mesh);
+
+        for (position, color) in [(-3.0 * Vec3::Z, Color::RED), (3.0 * Vec3::Z, Color::BLUE)] {
+            let mut transform = Transform::from_position(position);
+            // Face the camera looking at the quad
+            transform.rotation = Quat::from_rotation_arc(Vec3::Z, -position.normalize());
+            let mut properties = RenderProperties::from_transform(transform);
+            properties.color = color;
+            self.scene.add(mesh, material, transform, properties);
+        }
+    }
+
+    fn update(&mut self, state: &mut State, elapsed: f32) {
+        *self.report.borrow_mut() = Some(state.draw_report().clone());
+        self.scene.update(&state.camera, &state.resources, elapsed);
+    }
+
+    fn render(&mut self, commands: &mut Vec<DrawCommand>) {
+        self.scene.render(commands);
+        if let Some(camera) = self.back_camera {
+            commands.push(DrawCommand::SetCamera(camera));
+            self.scene.render(commands);
+            commands.push(DrawCommand::ClearCamera);
+        }
+    }
+}
+
+#[test]
+fn opposite_cameras_each_draw_their_entity() {
+    let report = Rc::new(RefCell::new(None));
+    ScreenshotTest::new("opposite_cameras")
+        .with_size(64, 48)
+        .with_golden_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/goldens"))
+        .with_frames(3)
+        .capture(1)
+        .run(Box::new(OppositeCameras {
+            scene: Scene::new(),
+            back_camera: None,
+            report: report.clone(),
+        }))
+        .assert_passed();
+
+    // Each camera culls the entity behind it, rather than the scene culling to the main camera
+    let report = report
+        .borrow()
+        .clone()
+        .expect("Frames should have been rendered");
+    assert_eq!(report.submitted, 4);
+    assert_eq!(report.drawn, 2);
+    assert_eq!(report.culled, 2);
+}
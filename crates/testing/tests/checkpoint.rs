@@ -0,0 +1,68 @@
+use std::{cell::RefCell, rc::Rc};
+
+use core::{scene::Scene, transform::Transform, DrawCommand, Game, State};
+use testing::ScreenshotTest;
+
+#[derive(Debug, Default, PartialEq)]
+struct Restored {
+    named: bool,
+    tagged: bool,
+    renamed: Option<String>,
+    later_tag: bool,
+}
+
+/// Checkpoints the scene, edits and removes entities, then restores it
+struct Restore {
+    restored: Rc<RefCell<Restored>>,
+}
+
+impl Game for Restore {
+    fn init(&mut self, state: &mut State) {
+        let mut scene = Scene::new();
+        let removed = scene.hierarchy.insert(Transform::default(), None);
+        let kept = scene.hierarchy.insert(Transform::default(), None);
+        scene.set_name(removed, "player");
+        scene.add_tag(removed, "hero");
+        scene.set_name(kept, "camera");
+        let checkpoint = state.checkpoint(&scene);
+
+        scene.remove(removed);
+        scene.set_name(kept, "renamed");
+        scene.add_tag(kept, "later");
+
+        let diff = state.restore(&checkpoint, &mut scene);
+        let player = diff.resolve(removed);
+        *self.restored.borrow_mut() = Restored {
+            named: scene.find_by_name("player") == Some(player),
+            tagged: scene.iter_with_tag("hero").eq([player]),
+            renamed: scene.get_name(kept).map(str::to_string),
+            later_tag: scene.has_tag(kept, "later"),
+        };
+        state.exit();
+    }
+
+    fn update(&mut self, _state: &mut State, _elapsed: f32) {}
+
+    fn render(&mut self, _commands: &mut Vec<DrawCommand>) {}
+}
+
+#[test]
+fn restoring_a_checkpoint_restores_names_and_tags() {
+    let restored = Rc::new(RefCell::new(Restored::default()));
+    ScreenshotTest::new("checkpoint")
+        .with_frames(1)
+        .run(Box::new(Restore {
+            restored: restored.clone(),
+        }))
+        .assert_passed();
+
+    assert_eq!(
+        *restored.borrow(),
+        Restored {
+            named: true,
+            tagged: true,
+            renamed: Some("camera".to_string()),
+            later_tag: false,
+        }
+    );
+}
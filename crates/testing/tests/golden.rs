@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use core::screenshot::Screenshot;
+use testing::{
+    golden::{compare_or_update, Outcome},
+    Tolerance,
+};
+
+/// Empty directory unique to the test, so tests can run in parallel
+fn directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("helia_golden_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn filled(width: u32, height: u32, rgba: [u8; 4]) -> Screenshot {
+    Screenshot {
+        width,
+        height,
+        rgba: rgba.repeat((width * height) as usize),
+    }
+}
+
+#[test]
+fn update_writes_golden() {
+    let directory = directory("update");
+    let golden = directory.join("frame_0.png");
+    let capture = filled(4, 4, [255, 0, 0, 255]);
+
+    let outcome = compare_or_update(&capture, &golden, Tolerance::EXACT, true).unwrap();
+    assert_eq!(outcome, Outcome::GoldenWritten);
+    assert!(golden.exists());
+
+    let outcome = compare_or_update(&capture, &golden, Tolerance::EXACT, false).unwrap();
+    assert_eq!(outcome, Outcome::Matched);
+}
+
+#[test]
+fn missing_golden_fails() {
+    let directory = directory("missing");
+    let golden = directory.join("frame_0.png");
+    let capture = filled(4, 4, [255, 0, 0, 255]);
+
+    let outcome = compare_or_update(&capture, &golden, Tolerance::EXACT, false).unwrap();
+    assert_eq!(outcome, Outcome::MissingGolden);
+    assert!(!outcome.passed());
+    assert!(!golden.exists());
+    assert!(directory.join("frame_0.actual.png").exists());
+}
+
+#[test]
+fn within_tolerance_matches() {
+    let directory = directory("tolerance");
+    let golden = directory.join("frame_0.png");
+    compare_or_update(
+        &filled(4, 4, [100, 100, 100, 255]),
+        &golden,
+        Tolerance::EXACT,
+        true,
+    )
+    .unwrap();
+
+    let capture = filled(4, 4, [102, 99, 100, 255]);
+    let outcome = compare_or_update(&capture, &golden, Tolerance::default(), false).unwrap();
+    assert_eq!(outcome, Outcome::Matched);
+
+    let outcome = compare_or_update(&capture, &golden, Tolerance::EXACT, false).unwrap();
+    assert_eq!(
+        outcome,
+        Outcome::Mismatched {
+            differing: 16,
+            total: 16,
+            max_difference: 2,
+        }
+    );
+}
+
+#[test]
+fn mismatch_writes_actual_and_diff() {
+    let directory = directory("mismatch");
+    let golden = directory.join("frame_0.png");
+    compare_or_update(
+        &filled(4, 4, [0, 0, 0, 255]),
+        &golden,
+        Tolerance::EXACT,
+        true,
+    )
+    .unwrap();
+
+    let mut capture = filled(4, 4, [0, 0, 0, 255]);
+    capture.rgba[..4].copy_from_slice(&[255, 255, 255, 255]);
+    let outcome = compare_or_update(&capture, &golden, Tolerance::default(), false).unwrap();
+    assert_eq!(
+        outcome,
+        Outcome::Mismatched {
+            differing: 1,
+            total: 16,
+            max_difference: 255,
+        }
+    );
+    assert!(directory.join("frame_0.actual.png").exists());
+
+    let diff = image::open(directory.join("frame_0.diff.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(diff.get_pixel(0, 0).0, [255, 0, 255, 255]);
+    assert_eq!(diff.get_pixel(1, 0).0, [0, 0, 0, 255]);
+}
+
+#[test]
+fn size_mismatch_fails() {
+    let directory = directory("size");
+    let golden = directory.join("frame_0.png");
+    compare_or_update(
+        &filled(4, 4, [0, 0, 0, 255]),
+        &golden,
+        Tolerance::EXACT,
+        true,
+    )
+    .unwrap();
+
+    let outcome = compare_or_update(
+        &filled(8, 4, [0, 0, 0, 255]),
+        &golden,
+        Tolerance::default(),
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        outcome,
+        Outcome::SizeMismatched {
+            expected: (4, 4),
+            actual: (8, 4),
+        }
+    );
+    assert!(directory.join("frame_0.actual.png").exists());
+}
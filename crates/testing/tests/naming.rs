@@ -0,0 +1,34 @@
+use core::{scene::Scene, transform::Transform};
+
+#[test]
+fn naming_a_removed_node_keeps_the_live_name() {
+    let mut scene = Scene::new();
+    let live = scene.hierarchy.insert(Transform::default(), None);
+    let removed = scene.hierarchy.insert(Transform::default(), None);
+    scene.set_name(live, "player");
+    scene.remove(removed);
+
+    scene.set_name(removed, "player");
+    assert_eq!(scene.find_by_name("player"), Some(live));
+}
+
+#[test]
+fn duplicate_names_fall_back_to_another_holder() {
+    let mut scene = Scene::new();
+    let first = scene.hierarchy.insert(Transform::default(), None);
+    let second = scene.hierarchy.insert(Transform::default(), None);
+    let third = scene.hierarchy.insert(Transform::default(), None);
+    scene.set_name(first, "enemy");
+    scene.set_name(second, "enemy");
+    scene.set_name(third, "enemy");
+    assert_eq!(scene.find_by_name("enemy"), Some(third));
+
+    scene.set_name(third, "boss");
+    let holder = scene.find_by_name("enemy");
+    assert!(holder == Some(first) || holder == Some(second));
+
+    scene.remove(holder.unwrap());
+    let remaining = if holder == Some(first) { second } else { first };
+    assert_eq!(scene.find_by_name("enemy"), Some(remaining));
+    assert_eq!(scene.find_by_name("boss"), Some(third));
+}
@@ -0,0 +1,31 @@
+use core::{input::KeyCode, Color, DrawCommand, Game, State};
+use testing::{ScreenshotTest, ScriptedInput};
+
+/// Clears to red, or blue while space is held
+struct ClearColor;
+
+impl Game for ClearColor {
+    fn init(&mut self, _state: &mut State) {}
+
+    fn update(&mut self, state: &mut State, _elapsed: f32) {
+        state.camera.clear_color = if state.input.key_pressed(KeyCode::Space) {
+            Color::BLUE
+        } else {
+            Color::RED
+        };
+    }
+
+    fn render(&mut self, _commands: &mut Vec<DrawCommand>) {}
+}
+
+#[test]
+fn scripted_input_changes_clear_color() {
+    ScreenshotTest::new("clear_color")
+        .with_size(64, 48)
+        .with_golden_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/goldens"))
+        .capture(0)
+        .with_input(1, ScriptedInput::KeyDown(KeyCode::Space))
+        .capture(1)
+        .run(Box::new(ClearColor))
+        .assert_passed();
+}
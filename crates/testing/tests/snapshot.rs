@@ -0,0 +1,34 @@
+use core::{scene::Scene, transform::Transform};
+
+#[test]
+fn applying_a_snapshot_keeps_nodes_reparented_under_removed_nodes() {
+    let mut scene = Scene::new();
+    let y = scene.hierarchy.insert(Transform::default(), None);
+    let before = scene.snapshot();
+
+    let x = scene.hierarchy.insert(Transform::default(), None);
+    scene.hierarchy.parent(y, Some(x));
+
+    let diff = scene.apply(&before);
+    assert_eq!(diff.removed, vec![x]);
+    assert!(scene.hierarchy.contains(y));
+    assert!(!scene.hierarchy.contains(x));
+    assert_eq!(scene.hierarchy.get_parent(y), None);
+    assert_eq!(scene.hierarchy.len(), 1);
+}
+
+#[test]
+fn applying_a_snapshot_swaps_parent_and_child() {
+    let mut scene = Scene::new();
+    let a = scene.hierarchy.insert(Transform::default(), None);
+    let b = scene.hierarchy.insert(Transform::default(), Some(a));
+    let before = scene.snapshot();
+
+    scene.hierarchy.parent(b, None);
+    scene.hierarchy.parent(a, Some(b));
+
+    scene.apply(&before);
+    assert_eq!(scene.hierarchy.get_parent(a), None);
+    assert_eq!(scene.hierarchy.get_parent(b), Some(a));
+    assert_eq!(scene.hierarchy.len(), 2);
+}
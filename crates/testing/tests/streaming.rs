@@ -0,0 +1,96 @@
+use std::{cell::RefCell, rc::Rc};
+
+use core::{
+    scene::Scene,
+    streaming::{ChunkContents, ChunkCoord, ChunkFuture, ChunkSource, ChunkStreamer},
+    DrawCommand, Game, State,
+};
+use glam::Vec3;
+use testing::ScreenshotTest;
+
+/// Chunks in the x = 1 column fail to load by panicking
+struct PanickingSource;
+
+impl ChunkSource for PanickingSource {
+    type Data = ();
+
+    fn load(&mut self, coord: ChunkCoord) -> ChunkFuture<()> {
+        Box::pin(async move {
+            if coord.x == 1 {
+                panic!("Chunk {:?} is corrupt", coord);
+            }
+        })
+    }
+
+    fn instantiate(
+        &mut self,
+        _coord: ChunkCoord,
+        _data: (),
+        _scene: &mut Scene,
+        _state: &mut State,
+    ) -> ChunkContents {
+        ChunkContents::default()
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Progress {
+    loaded: usize,
+    failed: usize,
+    pending: usize,
+}
+
+struct Streaming {
+    scene: Scene,
+    streamer: ChunkStreamer<PanickingSource>,
+    progress: Rc<RefCell<Progress>>,
+}
+
+impl Game for Streaming {
+    fn init(&mut self, _state: &mut State) {}
+
+    fn update(&mut self, state: &mut State, _elapsed: f32) {
+        self.streamer.update(Vec3::ZERO, &mut self.scene, state);
+        let radius = -1..=1;
+        let failed = radius
+            .clone()
+            .flat_map(|x| radius.clone().map(move |z| ChunkCoord::new(x, z)))
+            .filter(|coord| self.streamer.is_failed(*coord))
+            .count();
+        let progress = Progress {
+            loaded: self.streamer.loaded_chunks().count(),
+            failed,
+            pending: self.streamer.pending_count(),
+        };
+        if progress.loaded + progress.failed == 9 {
+            state.exit();
+        }
+        *self.progress.borrow_mut() = progress;
+    }
+
+    fn render(&mut self, _commands: &mut Vec<DrawCommand>) {}
+}
+
+#[test]
+fn failed_chunk_loads_release_their_slots() {
+    let progress = Rc::new(RefCell::new(Progress::default()));
+    // Fewer slots than failing chunks, so streaming stalls if failures keep their slots
+    let streamer = ChunkStreamer::new(PanickingSource, 16.0, 1, 2).with_max_pending(2);
+    ScreenshotTest::new("streaming")
+        .with_frames(10_000)
+        .run(Box::new(Streaming {
+            scene: Scene::new(),
+            streamer,
+            progress: progress.clone(),
+        }))
+        .assert_passed();
+
+    assert_eq!(
+        *progress.borrow(),
+        Progress {
+            loaded: 6,
+            failed: 3,
+            pending: 0,
+        }
+    );
+}
@@ -0,0 +1,27 @@
+use core::{DrawCommand, ScissorRect, State};
+use glam::*;
+
+/// Scissor rect covering a panel, given its center and size in world space, as seen by the
+/// current camera
+pub fn panel_scissor(state: &State, center: Vec3, size: Vec2) -> ScissorRect {
+    let half_size = (0.5 * size).extend(0.0);
+    let corners = [center - half_size, center + half_size];
+    let mut points = corners
+        .iter()
+        .filter_map(|corner| state.camera.world_to_screen(*corner, state.size));
+    match (points.next(), points.next()) {
+        (Some(a), Some(b)) => ScissorRect::from_min_max(a.min(b), a.max(b)),
+        _ => ScissorRect::new(0, 0, 0, 0),
+    }
+}
+
+/// Wraps the draw commands pushed by `render` so they are clipped to the rect
+pub fn render_clipped(
+    rect: ScissorRect,
+    commands: &mut Vec<DrawCommand>,
+    render: impl FnOnce(&mut Vec<DrawCommand>),
+) {
+    commands.push(DrawCommand::SetScissor(rect));
+    render(commands);
+    commands.push(DrawCommand::ClearScissor);
+}
@@ -1,6 +1,10 @@
 use core::atlas::*;
 use std::collections::HashMap;
 
+/// Bitmap fonts blur or pixelate when scaled, for text which stays sharp at any size use a
+/// material with `state.shaders.sdf_text` and a texture from
+/// `Texture::generate_distance_field`, which also allows outlines and, set on the material
+/// with `SdfTextUniforms`, glow
 #[derive(Clone, Debug)]
 pub struct FontAtlas {
     pub atlas: Atlas, // text mesh assumes atlas mesh id a center anchored 1x1 quad
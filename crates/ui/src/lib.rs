@@ -1,6 +1,20 @@
+pub mod clip;
 pub mod font;
+pub mod localized_text;
+pub mod minimap;
+pub mod nameplate;
+pub mod scale;
 pub mod slice_sprite;
+pub mod text_lod;
 pub mod text_mesh;
+pub mod text_selection;
 
+pub use clip::*;
+pub use localized_text::*;
+pub use minimap::*;
+pub use nameplate::*;
+pub use scale::*;
 pub use slice_sprite::*;
+pub use text_lod::*;
 pub use text_mesh::*;
+pub use text_selection::*;
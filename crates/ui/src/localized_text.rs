@@ -0,0 +1,67 @@
+use core::localization::Localization;
+
+use crate::TextMesh;
+
+/// Key of a localized string shown by a `TextMesh`, re-setting the text whenever the
+/// language changes or strings are loaded for it
+///
+/// Call `update` each frame, or from `Game::language_changed`.
+#[derive(Debug, Clone)]
+pub struct LocalizedText {
+    pub key: String,
+    /// Arguments for `{ $name }` placeables, see `Localization::tr_args`
+    args: Vec<(String, String)>,
+    /// Localization version the text was last set at
+    version: Option<u64>,
+}
+
+impl LocalizedText {
+    pub fn new<K: Into<String>>(key: K) -> Self {
+        Self {
+            key: key.into(),
+            args: Vec::new(),
+            version: None,
+        }
+    }
+
+    /// Set an argument, the text is re-set on the next update
+    pub fn set_arg<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        let (name, value) = (name.into(), value.into());
+        match self.args.iter_mut().find(|(arg, _)| *arg == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.args.push((name, value)),
+        }
+        self.version = None;
+    }
+
+    pub fn with_arg<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.set_arg(name, value);
+        self
+    }
+
+    /// Change the key, the text is re-set on the next update
+    pub fn set_key<K: Into<String>>(&mut self, key: K) {
+        self.key = key.into();
+        self.version = None;
+    }
+
+    /// Localized text for the key in the current language
+    pub fn text(&self, localization: &Localization) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        localization.tr_args(&self.key, &args)
+    }
+
+    /// Set the text mesh's text if it's out of date, returns true if it was set
+    pub fn update(&mut self, localization: &Localization, text_mesh: &mut TextMesh) -> bool {
+        if self.version == Some(localization.version()) {
+            return false;
+        }
+        self.version = Some(localization.version());
+        text_mesh.set_text(self.text(localization));
+        true
+    }
+}
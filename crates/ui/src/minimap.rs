@@ -0,0 +1,206 @@
+use core::{
+    camera::{Camera, OrthographicSize, Projection},
+    draw_report::DrawReport,
+    entity::RenderProperties,
+    material::{Material, MaterialId},
+    mesh::MeshId,
+    render_target::RenderTarget,
+    scene::Scene,
+    transform::Transform,
+    Color, DrawCommand, State,
+};
+use glam::*;
+use primitives::quad;
+
+use crate::nameplate::AnchorPlacement;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Marker drawn on the minimap at each entity with the tag, e.g. the player or objectives
+///
+/// The mesh should be a center anchored unit quad and the material use the overlay shader,
+/// so icons draw over the map.
+#[derive(Debug, Clone)]
+pub struct MinimapIcon {
+    pub tag: String,
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    pub color: Color,
+    /// Size in physical pixels
+    pub size: f32,
+}
+
+/// Top-down orthographic view of the scene rendered to a texture and drawn in a screen corner
+///
+/// Each frame call `render` from `Game::update` with the draw commands to show on the map,
+/// then `draw` in `Game::render` to add the map and its icons to the frame.
+pub struct Minimap {
+    /// Looks down the y axis with north (-z) at the top of the map, move with `follow`
+    pub camera: Camera,
+    pub target: RenderTarget,
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    pub corner: MinimapCorner,
+    /// Distance from the screen edges in physical pixels
+    pub margin: f32,
+    /// Size on screen in physical pixels
+    pub size: f32,
+    /// Distance in front of the main camera the map is drawn at, must be beyond the near plane
+    pub depth: f32,
+    pub icons: Vec<MinimapIcon>,
+}
+
+impl Minimap {
+    /// Height above the followed position the camera looks down from
+    const CAMERA_HEIGHT: f32 = 100.0;
+
+    /// Create a minimap showing `extent` world units across, rendered at `resolution` pixels
+    /// square and drawn with the overlay shader
+    pub fn new(resolution: u32, extent: f32, state: &mut State) -> Self {
+        let target = state.create_render_target(resolution, resolution, "Minimap");
+        let mesh = quad::centered_mesh(state);
+        let mesh = state.resources.meshes.insert(mesh);
+        let material = Material::new(state.shaders.overlay, target.texture, state);
+        let material = state.resources.materials.insert(material);
+        let mut camera = Camera {
+            up: Vec3::NEG_Z,
+            near: 0.01,
+            far: 2.0 * Self::CAMERA_HEIGHT,
+            size: OrthographicSize::from_width_height(extent, extent),
+            projection: Projection::Orthographic,
+            ..Camera::default()
+        };
+        Self::look_at(&mut camera, Vec3::ZERO);
+        Self {
+            camera,
+            target,
+            mesh,
+            material,
+            corner: MinimapCorner::TopRight,
+            margin: 16.0,
+            size: resolution as f32,
+            depth: 1.0,
+            icons: Vec::new(),
+        }
+    }
+
+    pub fn with_corner(mut self, corner: MinimapCorner) -> Self {
+        self.corner = corner;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_icon(mut self, icon: MinimapIcon) -> Self {
+        self.icons.push(icon);
+        self
+    }
+
+    /// Center the map on the position, e.g. the player
+    pub fn follow(&mut self, position: Vec3) {
+        Self::look_at(&mut self.camera, position);
+    }
+
+    fn look_at(camera: &mut Camera, position: Vec3) {
+        camera.target = position;
+        camera.eye = position + Self::CAMERA_HEIGHT * Vec3::Y;
+    }
+
+    /// Render the draw commands into the map texture, draws are culled to the map camera, a
+    /// `Scene` drawn to the map must leave `Scene::frustum_culling` disabled
+    pub fn render(&self, draw_commands: &[DrawCommand], state: &mut State) -> DrawReport {
+        state.render_to_target(&self.camera, draw_commands, &self.target)
+    }
+
+    /// Draw the map and the icons of tagged entities within it
+    pub fn draw(&self, scene: &Scene, state: &State, commands: &mut Vec<DrawCommand>) {
+        let center = self.screen_center(state);
+        let Some(placement) = AnchorPlacement::at_screen(state, center, self.depth) else {
+            return;
+        };
+        let size = self.size * placement.pixel_size;
+        commands.push(Self::quad(
+            self.mesh,
+            self.material,
+            &placement,
+            Vec2::splat(size),
+            Color::WHITE,
+        ));
+
+        let resolution = self.target.size();
+        for icon in self.icons.iter() {
+            for id in scene.iter_with_tag(&icon.tag) {
+                let Some(matrix) = scene.hierarchy.get_world_matrix(id) else {
+                    continue;
+                };
+                let Some(map_position) = self
+                    .camera
+                    .world_to_screen(matrix.w_axis.truncate(), resolution)
+                else {
+                    continue;
+                };
+                let uv =
+                    map_position / Vec2::new(resolution.width as f32, resolution.height as f32);
+                if uv.cmplt(Vec2::ZERO).any() || uv.cmpgt(Vec2::ONE).any() {
+                    continue;
+                }
+                let screen_position = center + (uv - 0.5) * self.size;
+                // Slightly nearer than the map so the icon draws over it
+                let Some(placement) =
+                    AnchorPlacement::at_screen(state, screen_position, 0.99 * self.depth)
+                else {
+                    continue;
+                };
+                commands.push(Self::quad(
+                    icon.mesh,
+                    icon.material,
+                    &placement,
+                    Vec2::splat(icon.size * placement.pixel_size),
+                    icon.color,
+                ));
+            }
+        }
+    }
+
+    fn screen_center(&self, state: &State) -> Vec2 {
+        let offset = self.margin + 0.5 * self.size;
+        let (width, height) = (state.size.width as f32, state.size.height as f32);
+        match self.corner {
+            MinimapCorner::TopLeft => Vec2::new(offset, offset),
+            MinimapCorner::TopRight => Vec2::new(width - offset, offset),
+            MinimapCorner::BottomLeft => Vec2::new(offset, height - offset),
+            MinimapCorner::BottomRight => Vec2::new(width - offset, height - offset),
+        }
+    }
+
+    fn quad(
+        mesh: MeshId,
+        material: MaterialId,
+        placement: &AnchorPlacement,
+        size: Vec2,
+        color: Color,
+    ) -> DrawCommand {
+        let transform = Transform::from_position_rotation_scale(
+            placement.world_position,
+            placement.rotation,
+            size.extend(1.0),
+        );
+        DrawCommand::Draw(
+            mesh,
+            material,
+            RenderProperties::builder()
+                .with_matrix(transform.into())
+                .with_color(color)
+                .build(),
+        )
+    }
+}
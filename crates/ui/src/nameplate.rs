@@ -0,0 +1,184 @@
+use core::{
+    camera::{Camera, Projection},
+    entity::RenderProperties,
+    material::MaterialId,
+    mesh::MeshId,
+    transform::Transform,
+    Color, DrawCommand, State,
+};
+use glam::*;
+
+/// Attaches UI (health bars, names) to a point in the world, keeping a constant size on screen
+///
+/// Each frame `place` projects the target with `world_to_screen` and returns where to draw,
+/// or None when the target is behind the camera or off-screen, in which case skip drawing.
+#[derive(Debug, Clone, Copy)]
+pub struct WorldAnchor {
+    /// Offset from the target in world space, e.g. up to above a character's head
+    pub offset: Vec3,
+    /// Offset on screen in physical pixels, positive y is up
+    pub screen_offset: Vec2,
+    /// How far in physical pixels the anchor can be outside the screen before it's hidden,
+    /// so elements don't pop out while partially visible
+    pub margin: f32,
+    /// Distance in front of the camera the element is drawn at, must be beyond the near plane
+    pub depth: f32,
+}
+
+/// Where to draw an anchored element this frame
+#[derive(Debug, Clone, Copy)]
+pub struct AnchorPlacement {
+    /// Physical pixels, origin top left
+    pub screen_position: Vec2,
+    pub world_position: Vec3,
+    /// Faces the camera
+    pub rotation: Quat,
+    /// World units per physical pixel at the world position, scale elements by this to size
+    /// them in pixels
+    pub pixel_size: f32,
+}
+
+impl WorldAnchor {
+    pub fn new(offset: Vec3) -> Self {
+        Self {
+            offset,
+            screen_offset: Vec2::ZERO,
+            margin: 32.0,
+            depth: 1.0,
+        }
+    }
+
+    pub fn with_screen_offset(mut self, screen_offset: Vec2) -> Self {
+        self.screen_offset = screen_offset;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn place(&self, state: &State, target: Vec3) -> Option<AnchorPlacement> {
+        let camera = &state.camera;
+        let size = state.size;
+        let screen_position = camera.world_to_screen(target + self.offset, size)?
+            + Vec2::new(self.screen_offset.x, -self.screen_offset.y);
+
+        let margin = self.margin;
+        if screen_position.x < -margin
+            || screen_position.y < -margin
+            || screen_position.x > size.width as f32 + margin
+            || screen_position.y > size.height as f32 + margin
+        {
+            return None;
+        }
+
+        AnchorPlacement::at_screen(state, screen_position, self.depth)
+    }
+}
+
+impl AnchorPlacement {
+    /// Placement at a screen position (in physical pixels, origin top left) at the distance
+    /// in front of the camera, for elements fixed to the screen such as a minimap in a corner
+    pub fn at_screen(state: &State, screen_position: Vec2, depth: f32) -> Option<Self> {
+        let (camera, size) = (&state.camera, state.size);
+        let forward = (camera.target - camera.eye).normalize_or_zero();
+        let ray = camera.screen_to_ray(screen_position, size);
+        let distance = ray.intersect_plane(camera.eye + depth * forward, -forward)?;
+        let view = Mat4::look_at_rh(camera.eye, camera.target, camera.up);
+        Some(Self {
+            screen_position,
+            world_position: ray.at(distance),
+            rotation: Quat::from_mat4(&view.inverse()),
+            pixel_size: Self::pixel_size(camera, depth, size.height),
+        })
+    }
+
+    /// World units per physical pixel at the distance in front of the camera
+    pub(crate) fn pixel_size(camera: &Camera, depth: f32, height: u32) -> f32 {
+        let view_height = match camera.projection {
+            Projection::Perspective => 2.0 * depth * (0.5 * camera.fov).tan(),
+            Projection::Orthographic => (camera.size.top - camera.size.bottom).abs(),
+        };
+        view_height / height.max(1) as f32
+    }
+}
+
+/// Bar showing a value between 0 and 1, e.g. health, drawn at an anchor placement
+///
+/// The mesh should be a center anchored unit quad and the material untextured or using a
+/// plain white texture, with alpha blending so it draws over the scene.
+#[derive(Debug, Clone, Copy)]
+pub struct Gauge {
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    /// Size in physical pixels
+    pub size: Vec2,
+    pub background: Color,
+    pub fill: Color,
+}
+
+impl Gauge {
+    pub fn new(mesh: MeshId, material: MaterialId, size: Vec2) -> Self {
+        Self {
+            mesh,
+            material,
+            size,
+            background: Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.1,
+                a: 0.8,
+            },
+            fill: Color {
+                r: 0.2,
+                g: 0.8,
+                b: 0.2,
+                a: 1.0,
+            },
+        }
+    }
+
+    pub fn with_colors(mut self, background: Color, fill: Color) -> Self {
+        self.background = background;
+        self.fill = fill;
+        self
+    }
+
+    /// Draw with the fill covering the value's fraction of the bar from the left
+    pub fn render(&self, placement: &AnchorPlacement, value: f32, commands: &mut Vec<DrawCommand>) {
+        let size = self.size * placement.pixel_size;
+        let right = placement.rotation * Vec3::X;
+        // Fill is drawn slightly towards the camera so it doesn't z-fight the background
+        let towards_camera = placement.rotation * Vec3::Z * (0.01 * placement.pixel_size);
+
+        let quad = |center: Vec3, size: Vec2, color: Color| {
+            let transform = Transform::from_position_rotation_scale(
+                center,
+                placement.rotation,
+                size.extend(1.0),
+            );
+            DrawCommand::Draw(
+                self.mesh,
+                self.material,
+                RenderProperties::builder()
+                    .with_matrix(transform.into())
+                    .with_color(color)
+                    .build(),
+            )
+        };
+
+        commands.push(quad(placement.world_position, size, self.background));
+        let fill_width = value.clamp(0.0, 1.0) * size.x;
+        if fill_width > 0.0 {
+            let center =
+                placement.world_position + right * 0.5 * (fill_width - size.x) + towards_camera;
+            commands.push(quad(center, Vec2::new(fill_width, size.y), self.fill));
+        }
+    }
+}
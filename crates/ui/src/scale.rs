@@ -0,0 +1,95 @@
+use core::{camera::OrthographicSize, State};
+use glam::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UiScaleMode {
+    /// Follow the DPI scale factor of the window
+    Auto,
+    /// Fixed number of physical pixels per UI unit
+    Fixed(f32),
+}
+
+/// Central setting for how many physical pixels make up one UI unit
+///
+/// UI sizes (text, nine-slice sprites) are authored in UI units, so using `camera_size`
+/// for the UI camera and passing `factor` to text meshes keeps layouts the same size and
+/// pixel aligned on 1x and 2x displays.
+#[derive(Clone, Copy, Debug)]
+pub struct UiScale {
+    pub mode: UiScaleMode,
+    /// Applied on top of the mode, e.g. 2.0 for upscaled pixel art
+    pub multiplier: f32,
+    /// Round the final factor to a whole number, so texels map to whole pixels
+    pub integer: bool,
+}
+
+impl UiScale {
+    pub fn auto() -> Self {
+        Self {
+            mode: UiScaleMode::Auto,
+            multiplier: 1.0,
+            integer: true,
+        }
+    }
+
+    pub fn fixed(scale: f32) -> Self {
+        Self {
+            mode: UiScaleMode::Fixed(scale),
+            ..Self::auto()
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_integer(mut self, integer: bool) -> Self {
+        self.integer = integer;
+        self
+    }
+
+    /// Physical pixels per UI unit for the current window
+    pub fn factor(&self, state: &State) -> f32 {
+        let base = match self.mode {
+            UiScaleMode::Auto => state.scale_factor(),
+            UiScaleMode::Fixed(scale) => scale,
+        };
+        let factor = base * self.multiplier;
+        if self.integer {
+            factor.round().max(1.0)
+        } else {
+            factor.max(f32::EPSILON)
+        }
+    }
+
+    /// Orthographic size for a camera measuring in UI units
+    pub fn camera_size(&self, state: &State) -> OrthographicSize {
+        OrthographicSize::from_size_scale_factor(state.size, self.factor(state))
+    }
+}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        Self::auto()
+    }
+}
+
+/// Round x and y to the nearest physical pixel given the UI scale factor
+/// A factor of zero disables snapping, e.g. for text in world space
+pub fn snap_to_pixel(position: Vec3, factor: f32) -> Vec3 {
+    if factor <= 0.0 {
+        return position;
+    }
+    Vec3::new(
+        (position.x * factor).round() / factor,
+        (position.y * factor).round() / factor,
+        position.z,
+    )
+}
+
+/// Snap the center of a rect of the given size so that its edges land on physical pixels
+pub fn snap_center_to_pixel(center: Vec3, size: Vec2, factor: f32) -> Vec3 {
+    let half_size = (0.5 * size).extend(0.0);
+    snap_to_pixel(center - half_size, factor) + half_size
+}
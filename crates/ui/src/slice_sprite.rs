@@ -5,6 +5,8 @@ use core::{
 use glam::*;
 use primitives::quad::*;
 
+use crate::scale::snap_center_to_pixel;
+
 #[derive(Clone, Copy, Debug)]
 pub struct SliceConfig {
     pub width: f32,
@@ -60,6 +62,12 @@ impl SliceSpriteMesh {
         let mesh = Self::build_mesh(size, self.config, state);
         state.resources.meshes[self.mesh] = mesh;
     }
+
+    /// Adjusts a center position so the sprite's edges land on physical pixels
+    /// ui_scale is the number of physical pixels per UI unit, see `UiScale::factor`
+    pub fn snap_position(&self, center: Vec3, ui_scale: f32) -> Vec3 {
+        snap_center_to_pixel(center, self.size, ui_scale)
+    }
 }
 
 pub fn build_mesh(
@@ -194,10 +202,11 @@ pub fn build_mesh(
         Vec2::new(right / image_width, bottom / image_height),
     ));
 
-    Mesh::from_arrays(
+    state.mesh_pool.create_from_arrays(
         &positions.as_slice(),
         &uvs.as_slice(),
         &indices.as_slice(),
         &state.device,
+        &state.queue,
     )
 }
@@ -0,0 +1,143 @@
+use core::{camera::Camera, DrawCommand, State};
+use glam::*;
+
+use crate::{font::FontAtlas, text_mesh::*, AnchorPlacement};
+
+/// The same font rasterized at several sizes, so world space text can use the atlas closest
+/// to its size on screen, crisp up close and without aliasing far away
+///
+/// Atlases should share a char map, with tile sizes and custom widths proportional.
+#[derive(Clone, Debug)]
+pub struct FontLods {
+    /// Ordered by tile height, smallest first
+    fonts: Vec<FontAtlas>,
+    /// Fraction the on screen size must pass an atlas's size by before switching, so text
+    /// at a boundary doesn't flicker between atlases
+    pub hysteresis: f32,
+}
+
+impl FontLods {
+    pub fn new(mut fonts: Vec<FontAtlas>) -> Self {
+        assert!(!fonts.is_empty(), "FontLods requires at least one font");
+        fonts.sort_by_key(|font| font.atlas.tile_height);
+        Self {
+            fonts,
+            hysteresis: 0.1,
+        }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis.max(0.0);
+        self
+    }
+
+    pub fn get(&self, index: usize) -> &FontAtlas {
+        &self.fonts[index.min(self.fonts.len() - 1)]
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /// Index of the smallest atlas whose glyphs are at least the height in physical pixels,
+    /// the largest atlas if none are
+    pub fn select(&self, screen_height: f32) -> usize {
+        self.fonts
+            .iter()
+            .position(|font| font.atlas.tile_height as f32 >= screen_height)
+            .unwrap_or(self.fonts.len() - 1)
+    }
+
+    /// As `select`, but keeps the current atlas unless the height is clearly better served
+    /// by another
+    pub fn select_from(&self, current: usize, screen_height: f32) -> usize {
+        let ideal = self.select(screen_height);
+        if ideal > current {
+            self.select(screen_height * (1.0 - self.hysteresis))
+                .max(current)
+        } else if ideal < current {
+            self.select(screen_height * (1.0 + self.hysteresis))
+                .min(current)
+        } else {
+            ideal
+        }
+    }
+}
+
+/// Text in world space which switches between font atlases with its size on screen
+pub struct LodText {
+    text: TextMesh,
+    lods: FontLods,
+    current: usize,
+    /// Line height in world units, constant whichever atlas is used
+    height: f32,
+}
+
+impl LodText {
+    pub fn new(
+        text: String,
+        position: Vec3,
+        lods: FontLods,
+        height: f32,
+        alignment: TextAlignment,
+        vertical_alignment: VerticalAlignment,
+    ) -> Self {
+        let current = lods.len() - 1;
+        let font = lods.get(current).clone();
+        let scale = height / font.atlas.tile_height as f32;
+        Self {
+            text: TextMesh::new(
+                text,
+                position,
+                font,
+                scale,
+                alignment,
+                vertical_alignment,
+            ),
+            lods,
+            current,
+            height,
+        }
+    }
+
+    /// Choose the atlas for the text's size on screen from the camera, call each frame
+    /// before rendering, or whenever the camera or text moves
+    pub fn update(&mut self, camera: &Camera, state: &State) {
+        let forward = (camera.target - camera.eye).normalize_or_zero();
+        let depth = (self.text.get_position() - camera.eye).dot(forward);
+        if depth <= 0.0 {
+            return;
+        }
+        let pixel_size = AnchorPlacement::pixel_size(camera, depth, state.size.height);
+        let screen_height = self.height / pixel_size;
+        let index = self.lods.select_from(self.current, screen_height);
+        if index != self.current {
+            self.current = index;
+            let font = self.lods.get(index).clone();
+            let scale = self.height / font.atlas.tile_height as f32;
+            self.text.set_font(font, scale);
+        }
+    }
+
+    /// Index into the lods of the atlas in use
+    pub fn current_lod(&self) -> usize {
+        self.current
+    }
+
+    pub fn text_mesh(&self) -> &TextMesh {
+        &self.text
+    }
+
+    /// For changing the text or position, changing the font is undone by the next update
+    pub fn text_mesh_mut(&mut self) -> &mut TextMesh {
+        &mut self.text
+    }
+
+    pub fn render(&self, draw_commands: &mut Vec<DrawCommand>) {
+        self.text.render(draw_commands);
+    }
+}
@@ -1,8 +1,9 @@
 use core::transform::Transform;
-use core::{entity::*, DrawCommand};
+use core::{entity::*, Color, DrawCommand};
 use glam::*;
 
 use crate::font::*;
+use crate::scale::snap_center_to_pixel;
 
 #[derive(Clone, Copy, Debug)]
 #[allow(dead_code)]
@@ -27,6 +28,8 @@ pub struct TextMeshBuilder {
     scale: f32,
     alignment: TextAlignment,
     vertical_alignment: VerticalAlignment,
+    ui_scale: f32,
+    outline: (Color, f32),
 }
 
 impl TextMeshBuilder {
@@ -38,18 +41,25 @@ impl TextMeshBuilder {
             scale: 1.0,
             alignment: TextAlignment::Left,
             vertical_alignment: VerticalAlignment::Bottom,
+            ui_scale: 0.0,
+            outline: (Color::WHITE, 0.0),
         }
     }
 
     pub fn build(&self) -> TextMesh {
-        TextMesh::new(
+        let mut text_mesh = TextMesh::new(
             self.text.clone(),
             self.position,
             self.font.clone(),
             self.scale,
             self.alignment,
             self.vertical_alignment,
-        )
+        );
+        if self.ui_scale != 0.0 {
+            text_mesh.set_ui_scale(self.ui_scale);
+        }
+        text_mesh.set_outline(self.outline.0, self.outline.1);
+        text_mesh
     }
 
     #[allow(dead_code)]
@@ -67,6 +77,19 @@ impl TextMeshBuilder {
         self.vertical_alignment = vertical_alignment;
         self
     }
+
+    /// Physical pixels per UI unit, see `UiScale::factor`, glyphs are snapped to whole pixels,
+    /// defaults to zero which disables snapping, e.g. for text in world space
+    pub fn with_ui_scale(&mut self, ui_scale: f32) -> &mut Self {
+        self.ui_scale = ui_scale;
+        self
+    }
+
+    /// Outline around the glyphs, only drawn by fonts using the SDF text shader
+    pub fn with_outline(&mut self, color: Color, thickness: f32) -> &mut Self {
+        self.outline = (color, thickness);
+        self
+    }
 }
 
 pub struct TextMeshElement {
@@ -83,6 +106,9 @@ pub struct TextMesh {
     scale: f32,
     alignment: TextAlignment,
     vertical_alignment: VerticalAlignment,
+    ui_scale: f32,
+    /// Outline color and thickness, see `RenderPropertiesBuilder::with_outline`
+    outline: (Color, f32),
 }
 
 impl TextMesh {
@@ -102,6 +128,9 @@ impl TextMesh {
             scale,
             alignment,
             vertical_alignment,
+            // No pixel snapping until set, see `set_ui_scale`
+            ui_scale: 0.0,
+            outline: (Color::WHITE, 0.0),
         };
         text_mesh.set_text(text);
         text_mesh
@@ -139,6 +168,29 @@ impl TextMesh {
         Vec3::new(x_offset, y_offset, 0.0)
     }
 
+    /// Center of the first glyph, snapped so glyph edges land on physical pixels
+    fn calculate_origin(&self) -> Vec3 {
+        let glyph_size = Vec2::new(
+            self.font.atlas.tile_width as f32,
+            self.font.atlas.tile_height as f32,
+        ) * self.scale;
+        snap_center_to_pixel(
+            self.position + self.calculate_alignment_offset(),
+            glyph_size,
+            self.ui_scale,
+        )
+    }
+
+    pub fn get_ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    /// Update the UI scale factor (e.g. on resize or moving to another monitor) and re-layout
+    pub fn set_ui_scale(&mut self, ui_scale: f32) {
+        self.ui_scale = ui_scale;
+        self.translate(self.position);
+    }
+
     fn get_char_width(char: char, font: &FontAtlas, scale: f32) -> f32 {
         if let Some(custom_widths) = &font.custom_char_widths {
             if let Some(width) = custom_widths.get(&char) {
@@ -148,6 +200,90 @@ impl TextMesh {
         font.atlas.tile_width as f32 * scale
     }
 
+    /// Horizontal advance of the character, zero for characters missing from the font
+    fn get_char_advance(&self, char: char) -> f32 {
+        if self.font.char_map.contains(char) {
+            Self::get_char_width(char, &self.font, self.scale)
+        } else {
+            0.0
+        }
+    }
+
+    /// Number of characters, caret and selection indices are in characters rather than bytes
+    pub fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.font.atlas.tile_height as f32 * self.scale
+    }
+
+    /// Position of the boundary before the character at the index, on the text's center line
+    ///
+    /// Indices past the end of the text give the position after the last character.
+    pub fn caret_position(&self, index: usize) -> Vec3 {
+        let origin = self.calculate_origin();
+        let start = origin.x - 0.5 * self.font.atlas.tile_width as f32 * self.scale;
+        let advance: f32 = self
+            .text
+            .chars()
+            .take(index)
+            .map(|char| self.get_char_advance(char))
+            .sum();
+        Vec3::new(start + advance, origin.y, origin.z)
+    }
+
+    /// Index of the character boundary closest to the position horizontally, the
+    /// position's y is not considered, see `bounds` for hit testing
+    pub fn index_at_position(&self, position: Vec3) -> usize {
+        let mut x = self.caret_position(0).x;
+        for (index, char) in self.text.chars().enumerate() {
+            let advance = self.get_char_advance(char);
+            if position.x < x + 0.5 * advance {
+                return index;
+            }
+            x += advance;
+        }
+        self.char_count()
+    }
+
+    /// Min and max corners of the laid out text
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let start = self.caret_position(0);
+        let end = self.caret_position(self.char_count());
+        let half_height = 0.5 * self.line_height();
+        (
+            Vec2::new(start.x, start.y - half_height),
+            Vec2::new(end.x, end.y + half_height),
+        )
+    }
+
+    /// Outline drawn by fonts using the SDF text shader, thickness is in normalized distance
+    /// where 0.5 is the font's spread, 0.0 for no outline
+    pub fn set_outline(&mut self, color: Color, thickness: f32) {
+        self.outline = (color, thickness);
+        for element in self.elements.iter_mut() {
+            element.instance.outline_color = color;
+            element.instance.outline_thickness = thickness;
+        }
+    }
+
+    pub fn get_font(&self) -> &FontAtlas {
+        &self.font
+    }
+
+    /// Change the font and scale and re-layout, character offsets are reset
+    pub fn set_font(&mut self, font: FontAtlas, scale: f32) {
+        self.font = font;
+        self.scale = scale;
+        self.elements.clear();
+        self.set_text(self.text.clone());
+    }
+
+    pub(crate) fn glyph_mesh(&self) -> core::mesh::MeshId {
+        self.font.atlas.mesh_id
+    }
+
     #[allow(dead_code)]
     pub fn get_position(&self) -> Vec3 {
         self.position
@@ -175,7 +311,7 @@ impl TextMesh {
 
         self.text = text;
 
-        let mut position = self.position + self.calculate_alignment_offset();
+        let mut position = self.calculate_origin();
         let chars = self.text.chars();
         // this is probably terrible practice for anything other than ascii
         for (i, char) in chars.enumerate() {
@@ -187,9 +323,10 @@ impl TextMesh {
                     element.instance.world_matrix = element.transform.to_local_matrix();
                     element.offset = Vec3::ZERO; // reset offset
                 } else {
-                    let (transform, instance) = self.font
+                    let (transform, mut instance) = self.font
                         .atlas
                         .instance_properties(index, position, self.scale);
+                    (instance.outline_color, instance.outline_thickness) = self.outline;
                     self.elements.push(TextMeshElement {
                         transform,
                         instance, 
@@ -209,7 +346,7 @@ impl TextMesh {
             self.set_text(self.text.clone());
             log::warn!("Tried to translate text mesh, but text did not match entity length, use set_text fn to alter text value");
         } else {
-            let mut position = self.position + self.calculate_alignment_offset();
+            let mut position = self.calculate_origin();
             for (i, element) in self.elements.iter_mut().enumerate() {
                 if let Some(char) = self.text.chars().nth(i) {
                     element.transform.position = position + element.offset;
@@ -0,0 +1,104 @@
+use core::{
+    entity::RenderProperties, material::MaterialId, transform::Transform, Color, DrawCommand,
+};
+use glam::*;
+use std::ops::Range;
+
+use crate::text_mesh::TextMesh;
+
+/// Draws a caret and selection highlight for a `TextMesh`, building block for text fields
+///
+/// Uses the text's glyph quad with the provided material, which should be untextured or use
+/// a plain white texture so the colors show as is. The selection is placed behind the text and
+/// the caret in front of it by `depth_offset`, so draw order relative to the text doesn't matter.
+pub struct TextSelectionStyle {
+    pub material: MaterialId,
+    pub caret_color: Color,
+    /// Width of the caret in the same units as the text
+    pub caret_width: f32,
+    pub selection_color: Color,
+    pub depth_offset: f32,
+}
+
+impl TextSelectionStyle {
+    pub fn new(material: MaterialId) -> Self {
+        Self {
+            material,
+            caret_color: Color::WHITE,
+            caret_width: 1.0,
+            selection_color: Color {
+                r: 0.2,
+                g: 0.4,
+                b: 0.9,
+                a: 0.5,
+            },
+            depth_offset: 0.01,
+        }
+    }
+
+    pub fn with_caret(mut self, color: Color, width: f32) -> Self {
+        self.caret_color = color;
+        self.caret_width = width;
+        self
+    }
+
+    pub fn with_selection_color(mut self, color: Color) -> Self {
+        self.selection_color = color;
+        self
+    }
+
+    /// Draw the caret before the character at the index
+    pub fn render_caret(
+        &self,
+        text: &TextMesh,
+        index: usize,
+        draw_commands: &mut Vec<DrawCommand>,
+    ) {
+        let position = text.caret_position(index) + self.depth_offset * Vec3::Z;
+        let size = Vec2::new(self.caret_width, text.line_height());
+        self.render_quad(text, position, size, self.caret_color, draw_commands);
+    }
+
+    /// Draw the highlight behind the characters in the range, the range may be reversed
+    /// as is common when selecting backwards from the caret
+    pub fn render_selection(
+        &self,
+        text: &TextMesh,
+        range: Range<usize>,
+        draw_commands: &mut Vec<DrawCommand>,
+    ) {
+        let (start, end) = (range.start.min(range.end), range.start.max(range.end));
+        if start == end {
+            return;
+        }
+        let start = text.caret_position(start);
+        let end = text.caret_position(end);
+        let width = end.x - start.x;
+        if width <= 0.0 {
+            return;
+        }
+        let center = Vec3::new(start.x + 0.5 * width, start.y, start.z - self.depth_offset);
+        let size = Vec2::new(width, text.line_height());
+        self.render_quad(text, center, size, self.selection_color, draw_commands);
+    }
+
+    fn render_quad(
+        &self,
+        text: &TextMesh,
+        center: Vec3,
+        size: Vec2,
+        color: Color,
+        draw_commands: &mut Vec<DrawCommand>,
+    ) {
+        let transform = Transform::from_position_scale(center, size.extend(1.0));
+        let properties = RenderProperties::builder()
+            .with_matrix(transform.into())
+            .with_color(color)
+            .build();
+        draw_commands.push(DrawCommand::Draw(
+            text.glyph_mesh(),
+            self.material,
+            properties,
+        ));
+    }
+}
@@ -106,6 +106,7 @@ impl Game for GameState {
                 b: 0.3,
                 a: 1.0,
             },
+            ..Default::default()
         };
 
         state.camera = camera;
@@ -140,16 +141,12 @@ impl Game for GameState {
                     * transform.rotation;
             self.scene.hierarchy.set_transform(*id, *transform);
         }
-        self.scene.update(&state.camera, &state.resources);
+        self.scene.update(&state.camera, &state.resources, elapsed);
     }
 
     fn render(&mut self, commands: &mut Vec<DrawCommand>) {
         self.scene.render(commands);
     }
-
-    fn resize(&mut self, state: &mut State) {
-        state.camera.aspect_ratio = state.size.width as f32 / state.size.height as f32;
-    }
 }
 
 pub async fn run() {
@@ -102,6 +102,7 @@ impl Game for GameState {
                 b: 0.3,
                 a: 1.0,
             },
+            ..Default::default()
         };
 
         state.camera = camera;
@@ -137,10 +138,6 @@ impl Game for GameState {
             commands.push(DrawCommand::Draw(mesh_id, material_id, RenderProperties::from_transform(transform)));
         }
     }
-
-    fn resize(&mut self, state: &mut State) {
-        state.camera.aspect_ratio = state.size.width as f32 / state.size.height as f32;
-    }
 }
 
 pub async fn run() {
@@ -24,6 +24,7 @@ impl Game for GameState {
                 b: 0.3,
                 a: 1.0,
             },
+            ..Default::default()
         };
 
         let model = gltf::Gltf::from_slice(include_bytes!("../assets/cube.gltf")).unwrap();
@@ -41,10 +42,6 @@ impl Game for GameState {
     fn render(&mut self, _commands: &mut Vec<DrawCommand>) {
         // If we had something to render then... we'd render it here
     }
-
-    fn resize(&mut self, state: &mut State) {
-        state.camera.aspect_ratio = state.size.width as f32 / state.size.height as f32;
-    }
 }
 
 pub async fn run() {
@@ -24,6 +24,7 @@ impl Game for GameState {
                 b: 0.3,
                 a: 1.0,
             },
+            ..Default::default()
         };
 
         state.camera = camera;
@@ -42,10 +43,6 @@ impl Game for GameState {
     fn render(&mut self, _commands: &mut Vec<DrawCommand>) {
         // We're just changing the clear colour
     }
-
-    fn resize(&mut self, state: &mut State) {
-        state.camera.aspect_ratio = state.size.width as f32 / state.size.height as f32;
-    }
 }
 
 pub async fn run() {
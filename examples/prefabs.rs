@@ -15,22 +15,27 @@ const VERTICES: &[Vertex] = &[
     Vertex {
         position: [-0.0868241, 0.49240386, 0.0],
         tex_coords: [0.4131759, 0.00759614],
+        normal: Vertex::FORWARD_NORMAL,
     }, // A
     Vertex {
         position: [-0.49513406, 0.06958647, 0.0],
         tex_coords: [0.0048659444, 0.43041354],
+        normal: Vertex::FORWARD_NORMAL,
     }, // B
     Vertex {
         position: [-0.21918549, -0.44939706, 0.0],
         tex_coords: [0.28081453, 0.949397],
+        normal: Vertex::FORWARD_NORMAL,
     }, // C
     Vertex {
         position: [0.35966998, -0.3473291, 0.0],
         tex_coords: [0.85967, 0.84732914],
+        normal: Vertex::FORWARD_NORMAL,
     }, // D
     Vertex {
         position: [0.44147372, 0.2347359, 0.0],
         tex_coords: [0.9414737, 0.2652641],
+        normal: Vertex::FORWARD_NORMAL,
     }, // E
 ];
 
@@ -69,6 +74,7 @@ impl Game for GameState {
             },
             projection: camera::Projection::Perspective,
             size: OrthographicSize::default(),
+            ..Default::default()
         };
 
         state.camera = camera;
@@ -131,16 +137,12 @@ impl Game for GameState {
         if let Some(camera_controller) = &self.orbit_camera {
             camera_controller.update_camera(&mut state.camera, &state.input, elapsed);
         }
-        self.scene.update(&state.camera, &state.resources);
+        self.scene.update(&state.camera, &state.resources, elapsed);
     }
 
     fn render(&mut self, commands: &mut Vec<DrawCommand>) {
         self.scene.render(commands);
     }
-
-    fn resize(&mut self, state: &mut State) {
-        state.camera.aspect_ratio = state.size.width as f32 / state.size.height as f32;
-    }
 }
 
 pub async fn run() {
@@ -88,6 +88,7 @@ impl Game for GameState {
             },
             projection: camera::Projection::Orthographic,
             size: OrthographicSize::from_ratio_height(ratio, 1.0),
+            ..Default::default()
         };
 
         state.camera = camera;
@@ -123,11 +124,6 @@ impl Game for GameState {
             commands.push(DrawCommand::Draw(lena.mesh, lena.material, lena.instance));
         }
     }
-
-    fn resize(&mut self, state: &mut State) {
-        let ratio = state.size.width as f32 / state.size.height as f32;
-        state.camera.size = OrthographicSize::from_ratio_height(ratio, 1.0);
-    }
 }
 
 impl GameState {
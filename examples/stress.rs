@@ -0,0 +1,168 @@
+use glam::*;
+use helia::{
+    camera::{Camera, OrthographicSize},
+    entity::*,
+    frame_stats::FrameStats,
+    material::Material,
+    mesh::Mesh,
+    orbit_camera::*,
+    texture::Texture,
+    transform::Transform,
+    transform_hierarchy::TransformId,
+    *,
+};
+
+// 5,000 spinning roots with 9 children each, 50,000 entities in total
+const ROOT_COUNT: usize = 5_000;
+const CHILD_COUNT: usize = 9;
+const ROW_LENGTH: usize = 100;
+const SPACING: f32 = 4.0;
+/// Seconds between frame time reports
+const REPORT_INTERVAL: f32 = 5.0;
+
+const QUAD_POSITIONS: &[Vec3] = &[
+    Vec3::new(-0.5, -0.5, 0.0),
+    Vec3::new(0.5, -0.5, 0.0),
+    Vec3::new(0.5, 0.5, 0.0),
+    Vec3::new(-0.5, 0.5, 0.0),
+];
+const QUAD_UVS: &[Vec2] = &[
+    Vec2::new(0.0, 1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(0.0, 0.0),
+];
+const QUAD_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+pub struct GameState {
+    orbit_camera: OrbitCamera,
+    scene: Scene,
+    roots: Vec<(TransformId, Vec3)>,
+    frame_stats: FrameStats,
+    next_report: f32,
+}
+
+impl Game for GameState {
+    fn init(&mut self, state: &mut State) {
+        let device = &state.device;
+        let queue = &state.queue;
+
+        let extent = Vec3::new(ROW_LENGTH as f32, 0.0, (ROOT_COUNT / ROW_LENGTH) as f32) * SPACING;
+        state.camera = Camera {
+            eye: Vec3::new(0.0, 0.6, 1.0) * extent.x,
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect_ratio: state.size.width as f32 / state.size.height as f32,
+            fov: 60.0 * std::f32::consts::PI / 180.0,
+            near: 0.1,
+            far: 2000.0,
+            projection: camera::Projection::Perspective,
+            size: OrthographicSize::default(),
+            clear_color: Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.1,
+                a: 1.0,
+            },
+            ..Default::default()
+        };
+
+        let texture = Texture::from_color(device, queue, [255, 255, 255, 255]).unwrap();
+        let texture_id = state.resources.textures.insert(texture);
+        let material = Material::new(state.shaders.unlit_textured, texture_id, state);
+        let material_id = state.resources.materials.insert(material);
+        let mesh = Mesh::from_arrays(QUAD_POSITIONS, QUAD_UVS, QUAD_INDICES, &state.device);
+        let mesh_id = state.resources.meshes.insert(mesh);
+
+        self.scene.hierarchy.reserve(ROOT_COUNT * (CHILD_COUNT + 1));
+        for index in 0..ROOT_COUNT {
+            let position = Vec3::new(
+                (index % ROW_LENGTH) as f32,
+                0.0,
+                (index / ROW_LENGTH) as f32,
+            ) * SPACING
+                - 0.5 * extent;
+            let root = self
+                .scene
+                .hierarchy
+                .insert(Transform::from_position(position), None);
+            for child in 0..CHILD_COUNT {
+                let angle = child as f32 / CHILD_COUNT as f32 * std::f32::consts::TAU;
+                let offset = Vec3::new(angle.cos(), 0.0, angle.sin()) * 1.5;
+                let transform = Transform::from_position(offset);
+                let color = Color {
+                    r: 0.5 + 0.5 * angle.cos() as f64,
+                    g: 0.5 + 0.5 * angle.sin() as f64,
+                    b: (index % 7) as f64 / 6.0,
+                    a: 1.0,
+                };
+                let properties = RenderProperties::builder().with_color(color).build();
+                let id = self.scene.add(mesh_id, material_id, transform, properties);
+                self.scene.hierarchy.parent(id, Some(root));
+            }
+            self.roots.push((root, position));
+        }
+        log::info!(
+            "Stress test running with {} entities",
+            ROOT_COUNT * CHILD_COUNT
+        );
+    }
+
+    fn update(&mut self, state: &mut State, elapsed: f32) {
+        self.orbit_camera
+            .update_camera(&mut state.camera, &state.input, elapsed);
+
+        // Rotating every root propagates to all its children each frame
+        let rotation = Quat::from_rotation_y(state.time.total_elapsed);
+        for (id, position) in self.roots.iter() {
+            self.scene
+                .hierarchy
+                .set_transform(*id, Transform::from_position_rotation(*position, rotation));
+        }
+        self.scene.update(&state.camera, &state.resources, elapsed);
+
+        self.frame_stats
+            .record(state.time.elapsed_real_time, state.draw_report());
+        if state.time.total_elapsed_real_time >= self.next_report {
+            self.next_report += REPORT_INTERVAL;
+            log::info!(
+                "Frame time average {:.2}ms, 99th percentile {:.2}ms, max {:.2}ms, {} draws",
+                1000.0 * self.frame_stats.average(),
+                1000.0 * self.frame_stats.percentile(0.99),
+                1000.0 * self.frame_stats.max(),
+                state.draw_report().drawn,
+            );
+        }
+    }
+
+    fn render(&mut self, commands: &mut Vec<DrawCommand>) {
+        self.scene.render(commands);
+    }
+}
+
+pub async fn run() {
+    let game_state = GameState {
+        orbit_camera: OrbitCamera::new(100.0),
+        scene: Scene::new(),
+        roots: Vec::new(),
+        frame_stats: FrameStats::new(600).with_spike_threshold(1.0 / 20.0),
+        next_report: REPORT_INTERVAL,
+    };
+    Helia::new()
+        .with_title("Stress Test")
+        .run(Box::new(game_state))
+        .await;
+}
+
+use scene::Scene;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
+pub async fn start() {
+    run().await;
+}
+
+fn main() {
+    pollster::block_on(run());
+}
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use glam::*;
 use helia::{
     atlas::*,
-    camera::{Camera, OrthographicSize},
+    camera::Camera,
     entity::*,
     material::*,
     mesh::*,
@@ -41,10 +41,9 @@ pub struct GameState {
     text_mesh: Option<TextMesh>,
     slice_mesh: Option<SliceSpriteMesh>,
     sprites: Vec<Sprite>,
+    ui_scale: UiScale,
 }
 
-const PIXEL_RATIO: u32 = 2;
-
 impl Game for GameState {
     fn init(&mut self, state: &mut State) {
         let ratio = state.size.width as f32 / state.size.height as f32;
@@ -63,7 +62,8 @@ impl Game for GameState {
                 a: 1.0,
             },
             projection: camera::Projection::Orthographic,
-            size: OrthographicSize::from_size_scale(state.size, PIXEL_RATIO),
+            size: self.ui_scale.camera_size(state),
+            ..Default::default()
         };
         state.camera = camera;
 
@@ -99,6 +99,7 @@ impl Game for GameState {
             )
             .with_alignment(TextAlignment::Center)
             .with_vertical_alignment(VerticalAlignment::Center)
+            .with_ui_scale(self.ui_scale.factor(state))
             .build();
         self.text_mesh = Some(text_mesh);
 
@@ -169,7 +170,10 @@ impl Game for GameState {
     }
 
     fn resize(&mut self, state: &mut State) {
-        state.camera.size = OrthographicSize::from_size_scale(state.size, PIXEL_RATIO);
+        state.camera.size = self.ui_scale.camera_size(state);
+        if let Some(text_mesh) = &mut self.text_mesh {
+            text_mesh.set_ui_scale(self.ui_scale.factor(state));
+        }
     }
 }
 
@@ -185,6 +189,7 @@ pub async fn run() {
         text_mesh: None,
         slice_mesh: None,
         sprites: Vec::new(),
+        ui_scale: UiScale::auto().with_multiplier(2.0),
     };
     Helia::new().with_resizable(true).run(Box::new(game_state)).await;
 }
@@ -152,6 +152,7 @@ impl BattleState {
                 self.stage = BattleStage::PlayerMove;
             }
         }
+        self.grid.draw_debug(&mut state.debug);
     }
     
     pub fn render(&self, commands: &mut Vec<DrawCommand>) {
@@ -1,6 +1,7 @@
 use glam::*;
 use helia::{
-    material::MaterialId, mesh::MeshId, Color, DrawCommand
+    debug_draw::DebugDraw, isometric::IsometricGrid, material::MaterialId, mesh::MeshId, Color,
+    DrawCommand,
 };
 use std::collections::{HashMap, HashSet, VecDeque};
 
@@ -8,7 +9,7 @@ use crate::{character::Character, sprite::Sprite};
 
 pub struct Grid {
     pub size: IVec2,
-    base_offset: Vec3,
+    layout: IsometricGrid,
     highlights: Vec<GridHighlight>,
     pub occupancy: HashSet<IVec2>,
 }
@@ -22,11 +23,15 @@ pub struct GridHighlight {
 impl Grid {
     pub fn new() -> Self {
         let size = IVec2::new(12, 3);
-        let base_offset = Vec3::new(-400.0, -32.0, 32.0); // dependent on bg sprite currently
+        let layout = IsometricGrid::new(
+            Vec3::new(-400.0, -32.0, 32.0), // dependent on bg sprite currently
+            Vec3::new(64.0, 0.0, 0.0),
+            Vec3::new(32.0, -32.0, 16.0),
+        );
 
         Self {
             size,
-            base_offset,
+            layout,
             highlights: Vec::new(),
             occupancy: HashSet::new(),
         }
@@ -59,9 +64,7 @@ impl Grid {
     }
 
     pub fn get_translation_for_position(&self, grid_position: IVec2) -> Vec3 {
-        let x = grid_position.x as f32;
-        let y = grid_position.y as f32;
-        self.base_offset + Vec3::new(64.0 * x + 32.0 * y, -32.0 * y, 16.0 * y)
+        self.layout.grid_to_world(grid_position)
     }
 
     #[allow(dead_code)]
@@ -137,6 +140,23 @@ impl Grid {
         }
     }
 
+    /// Mark each cell's position, occupied cells in red, and the grid's axes at its origin
+    pub fn draw_debug(&self, debug: &mut DebugDraw) {
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let grid_position = IVec2::new(x, y);
+                let color = if self.occupancy.contains(&grid_position) {
+                    Color::RED
+                } else {
+                    Color::WHITE
+                };
+                debug.circle(self.get_translation_for_position(grid_position), 8.0, color);
+            }
+        }
+        let origin = self.get_translation_for_position(IVec2::ZERO);
+        debug.axes(&Mat4::from_translation(origin), 32.0);
+    }
+
     pub fn render(&self, commands: &mut Vec<DrawCommand>) {
         for highlight in self.highlights.iter() {
             if highlight.visible {
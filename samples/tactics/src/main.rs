@@ -8,8 +8,8 @@ mod utils;
 use battle_state::*;
 use glam::*;
 use helia::{
-    atlas::Atlas, camera::*, material::MaterialId, mesh::MeshId,
-    primitives::*, ui::font::FontAtlas, ui::*, *,
+    atlas::Atlas, camera::*, draw_report::BindCounts, input::KeyCode, material::MaterialId,
+    mesh::MeshId, primitives::*, ui::font::FontAtlas, ui::*, *,
 };
 use std::collections::HashMap;
 
@@ -64,6 +64,8 @@ enum Stage {
 pub struct GameState {
     stage: Stage,
     resources: GameResources,
+    /// Last logged, F2 toggles draw sorting to compare against submission order
+    bind_counts: BindCounts,
 }
 
 impl GameState {
@@ -71,6 +73,25 @@ impl GameState {
         Self {
             stage: Stage::Init,
             resources: GameResources::new(),
+            bind_counts: BindCounts::default(),
+        }
+    }
+
+    fn log_bind_counts(&mut self, state: &mut State) {
+        if state.input.key_down(KeyCode::F2) {
+            state.sort_draws = !state.sort_draws;
+        }
+        let binds = state.draw_report().binds;
+        if binds != self.bind_counts {
+            self.bind_counts = binds;
+            log::info!(
+                "Draw sorting {}: {} binds ({} pipelines, {} materials, {} meshes)",
+                if state.sort_draws { "on" } else { "off" },
+                binds.total(),
+                binds.pipelines,
+                binds.materials,
+                binds.meshes
+            );
         }
     }
 
@@ -123,7 +144,8 @@ impl GameState {
         let char_map = "ABCDEFGHIJKLMNOPQRSTUVabcdefghijklmnopqrstuvWXYZ0123456789_.,!?:; wxyz()[]{}'\"/\\|=-+*<>%".to_string();
 
         let mesh_id = state.resources.meshes.insert(quad_mesh);
-        let material_id = utils::build_material(include_bytes!("../assets/micro-font.png"), state);
+        let material_id =
+            utils::build_material(include_bytes!("../assets/micro-font.png"), &mut state.graphics());
 
         let micro_font = FontAtlas {
             atlas: Atlas {
@@ -139,7 +161,8 @@ impl GameState {
         };
         self.resources.fonts.insert("micro".to_string(), micro_font);
 
-        let material_id = utils::build_material(include_bytes!("../assets/mini-font.png"), state);
+        let material_id =
+            utils::build_material(include_bytes!("../assets/mini-font.png"), &mut state.graphics());
 
         let mut custom_widths = HashMap::new();
         custom_widths.insert(5, "abcdeghknopqstuvxyz.,!?:;=".to_string());
@@ -163,11 +186,11 @@ impl GameState {
 
         self.resources.materials.insert(
             "white-sq".to_string(),
-            utils::build_material(include_bytes!("../assets/white-sq.png"), state),
+            utils::build_material(include_bytes!("../assets/white-sq.png"), &mut state.graphics()),
         );
         self.resources.materials.insert(
             "border".to_string(),
-            utils::build_material(include_bytes!("../assets/border.png"), state),
+            utils::build_material(include_bytes!("../assets/border.png"), &mut state.graphics()),
         );
     }
 }
@@ -187,11 +210,15 @@ impl Game for GameState {
             clear_color: Color::BLACK,
             projection: camera::Projection::Orthographic,
             size: OrthographicSize::from_size_scale(state.size, PIXEL_RATIO),
+            ..Default::default()
         };
 
         self.load_resources(state);
 
         state.camera = camera;
+        state.aspect_policy = AspectPolicy::KeepPixel(PIXEL_RATIO as f32);
+        // F3 toggles the grid gizmos
+        state.debug.enabled = false;
 
         let mut battle_state = BattleState::new(&self.resources, state);
 
@@ -202,6 +229,21 @@ impl Game for GameState {
     }
 
     fn update(&mut self, state: &mut State, elapsed: f32) {
+        self.log_bind_counts(state);
+        if state.input.key_down(KeyCode::F3) {
+            state.debug.enabled = !state.debug.enabled;
+        }
+        if state.input.key_down(KeyCode::F4) {
+            let next = match state.debug_view() {
+                shader::DebugView::Normal => shader::DebugView::Wireframe,
+                shader::DebugView::Wireframe => shader::DebugView::Overdraw,
+                shader::DebugView::Overdraw => shader::DebugView::Normal,
+            };
+            // Skip wireframe where the device can't draw lines
+            if !state.set_debug_view(next) {
+                state.set_debug_view(shader::DebugView::Overdraw);
+            }
+        }
         match &mut self.stage {
             Stage::Init => {}
             Stage::Battle {
@@ -215,10 +257,6 @@ impl Game for GameState {
             state.render(commands);
         }
     }
-
-    fn resize(&mut self, state: &mut State) {
-        state.camera.size = OrthographicSize::from_size_scale(state.size, PIXEL_RATIO);
-    }
 }
 
 pub async fn run() {
@@ -7,11 +7,11 @@ use helia::{
     *,
 };
 
-pub fn build_material(sprite_bytes: &[u8], state: &mut State) -> MaterialId {
-    let texture = Texture::from_bytes(&state.device, &state.queue, sprite_bytes).unwrap();
-    let texture_id = state.resources.textures.insert(texture);
-    let material = Material::new(state.shaders.sprite, texture_id, &state);
-    state.resources.materials.insert(material)
+pub fn build_material(sprite_bytes: &[u8], graphics: &mut GraphicsContext) -> MaterialId {
+    let texture = Texture::from_bytes(graphics.device, graphics.queue, sprite_bytes).unwrap();
+    let texture_id = graphics.resources.textures.insert(texture);
+    let material = Material::from_graphics(graphics.shaders.sprite, texture_id, graphics);
+    graphics.resources.materials.insert(material)
 }
 
 pub fn build_sprite_resources(
@@ -23,6 +23,6 @@ pub fn build_sprite_resources(
 ) -> (MeshId, MaterialId) {
     let quad_mesh = centred_mesh_with_offset_scale(width, height, offset, state);
     let mesh_id = state.resources.meshes.insert(quad_mesh);
-    let material_id = build_material(sprite_bytes, state);
+    let material_id = build_material(sprite_bytes, &mut state.graphics());
     (mesh_id, material_id)
 }
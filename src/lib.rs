@@ -1,3 +1,10 @@
 pub use core::*;
+#[cfg(feature = "editor")]
+pub use editor;
+#[cfg(feature = "primitives")]
 pub use primitives;
+#[cfg(feature = "ui")]
 pub use ui;
+
+#[cfg(feature = "egui")]
+pub use helia_egui as egui;